@@ -0,0 +1,25 @@
+use std::sync::{Mutex, Weak};
+
+use dream_ecs::scene::Scene;
+
+use crate::system::System;
+
+/// Plays the AnimationPlayer components of the scene, runs before transform propagation so that
+/// the animated pose is drawn in the same frame
+#[derive(Default)]
+pub struct AnimationSystem {}
+
+impl System for AnimationSystem {
+    fn update(&mut self, dt: f32, scene: Weak<Mutex<Scene>>) {
+        scene
+            .upgrade()
+            .expect("Unable to upgrade")
+            .lock()
+            .expect("Unable to lock")
+            .update_animation_players(dt);
+    }
+
+    fn runs_in_edit_mode(&self) -> bool {
+        false
+    }
+}
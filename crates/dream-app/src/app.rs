@@ -15,259 +15,98 @@
  *  You should have received a copy of the GNU Affero General Public License
  *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
  **********************************************************************************/
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, Weak};
 
 use winit::dpi::PhysicalPosition;
 use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
 
-use dream_ecs::component::{Bone, Light, LightType, MeshRenderer, SceneCamera, Transform};
+use dream_ecs::component::{
+    Active, Bone, GlobalTransform, Light, LightType, MeshRenderer, MorphWeights, RenderLayers,
+    SceneCamera, Transform,
+};
 use dream_ecs::entity::Entity;
 use dream_ecs::scene::Scene;
+use dream_ecs::serialization::SceneData;
+use dream_ecs::snapshot::SceneSnapshot;
 use dream_math::{pi, Matrix4, UnitQuaternion, Vector2, Vector3};
 use dream_renderer::instance::Instance;
+use dream_renderer::render_storage::EntityKey;
 use dream_renderer::renderer::RendererWgpu;
 use dream_resource::resource_manager::ResourceManager;
 #[cfg(target_arch = "wasm32")]
 pub use wasm_bindgen_rayon::init_thread_pool;
 
+use crate::animation_system::AnimationSystem;
+use crate::asset_errors::report_asset_load_error;
 use crate::input::{
     get_mouse_move, get_mouse_scroll, set_keyboard_state, set_mouse_left_pressed, set_mouse_move,
     set_mouse_right_pressed, set_mouse_scroll,
 };
+use crate::play_mode::is_play_requested;
 use crate::python_script_component_system::PythonScriptComponentSystem;
 use crate::scene_camera_component_system::SceneCameraComponentSystem;
 use crate::system::System;
+use crate::transform_propagation_system::TransformPropagationSystem;
 
 pub struct App {
     pub dt: f32,
     pub component_systems: Vec<Arc<Mutex<dyn System>>>,
     pub resource_manager: ResourceManager,
+    /// Active scene, it is shown in the editor and saved by save_scene
     pub scene: Arc<Mutex<Scene>>,
+    /// Scenes loaded on top of the active scene (e.g. a persistent UI or manager scene)
+    pub additive_scenes: Vec<Arc<Mutex<Scene>>>,
+    /// Snapshots of the scenes that were loaded when play mode started, the active scene first
+    play_snapshots: Option<Vec<(Arc<Mutex<Scene>>, SceneSnapshot)>>,
+}
+
+/// Scene file in the project folder that is opened on startup and written to when saving
+pub const DEFAULT_SCENE_FILE: &str = "main.scene";
+
+pub fn get_default_scene_path() -> PathBuf {
+    dream_fs::fs::get_fs_root().join(DEFAULT_SCENE_FILE)
+}
+
+/// Path of the .scene file with a resource guid
+pub fn get_scene_path(guid: &str, resource_manager: &ResourceManager) -> Option<PathBuf> {
+    resource_manager
+        .get_resource(String::from(guid))
+        .and_then(|resource_handle| resource_handle.upgrade())
+        .map(|resource_handle| resource_handle.get_path())
+}
+
+// set by the editor, the app saves the active scene on its next update
+static SAVE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn request_scene_save() {
+    SAVE_REQUESTED.store(true, Ordering::Relaxed);
 }
 
 impl Default for App {
     fn default() -> App {
         let resource_manager = ResourceManager::default();
-        let scene = Scene::create();
-
-        // populate scene
-        // let entity_handle = scene.lock().expect("Unable to lock scene").create_entity();
-        let dummy_entity =
-            Scene::create_entity(Arc::downgrade(&scene), Default::default(), None, None)
-                .expect("Unable to create dummy entity");
-        let _dummy_entity_child = Scene::create_entity(
-            Arc::downgrade(&scene),
-            Default::default(),
-            Some(dummy_entity),
-            None,
-        )
-        .expect("Unable to create dummy entity");
-        {
-            let scene_camera_entity_handle = Scene::create_entity(
-                Arc::downgrade(&scene),
-                Some("Scene Camera".into()),
-                None,
-                Some(Transform::new(
-                    Vector3::new(-1.87, 1.13, -0.5),
-                    UnitQuaternion::new(Vector3::y() * 4.3),
-                    Vector3::new(1.0, 1.0, 1.0),
-                )),
-            )
-            .expect("Unable to create scene camera entity");
-            let e = Entity::from_handle(scene_camera_entity_handle, Arc::downgrade(&scene));
-            e.add_component(SceneCamera::default());
-        }
-        {
-            let directional_light_entity_handle =
-                Scene::create_entity(Arc::downgrade(&scene), Some("Sun".into()), None, None)
-                    .expect("Unable to create sun entity");
-            // let intensity = 2.2;
-            let intensity = 1.5;
-            Entity::from_handle(directional_light_entity_handle, Arc::downgrade(&scene))
-                .add_component(Light::new(
-                    LightType::DIRECTIONAL,
-                    Vector3::new(
-                        intensity * 255.0 / 255.0,
-                        intensity * 255.0 / 255.0,
-                        intensity * 255.0 / 255.0,
-                    ),
-                    4.0,
-                    Vector3::new(-0.2, -0.4, -0.1),
-                    true,
-                ));
-            // add cube for position visualization (initial shadow map debugging)
-            // MeshRenderer::add_to_entity(
-            //     Arc::downgrade(&scene),
-            //     directional_light_entity_handle,
-            //     &resource_manager,
-            //     "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
-            //     true,
-            //     Default::default(),
-            // );
-            Entity::from_handle(directional_light_entity_handle, Arc::downgrade(&scene))
-                .add_component(Transform::new(
-                    Vector3::new(4.3, 4.2, 2.3),
-                    UnitQuaternion::identity(),
-                    Vector3::new(0.1, 0.1, 0.1),
-                ));
-        }
-        {
-            let cube_entity_handle =
-                Scene::create_entity(Arc::downgrade(&scene), Some("2x Cube".into()), None, None)
-                    .expect("Unable to create cube entity");
-            // add mesh renderer component
-            MeshRenderer::add_to_entity(
-                Arc::downgrade(&scene),
-                cube_entity_handle,
-                &resource_manager,
-                "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
-                true,
-                Default::default(),
-            );
-            Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
-                Transform::new(
-                    Vector3::new(7.4, 2.1, 10.6),
-                    UnitQuaternion::new(Vector3::y() * pi() / 2.0),
-                    Vector3::new(2.0, 2.0, 2.0),
-                ),
-            );
-        }
-        {
-            let cube_entity_handle = Scene::create_entity(
-                Arc::downgrade(&scene),
-                Some("Floating Rectangle".into()),
-                None,
-                None,
-            )
-            .expect("Unable to create cube entity");
-            // add mesh renderer component
-            MeshRenderer::add_to_entity(
-                Arc::downgrade(&scene),
-                cube_entity_handle,
-                &resource_manager,
-                "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
-                true,
-                Default::default(),
-            );
-            Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
-                Transform::new(
-                    Vector3::new(40.0, 10.0, 0.0),
-                    UnitQuaternion::new(Vector3::y() * pi() / 2.0),
-                    Vector3::new(10.0, 1.0, 10.0),
-                ),
-            );
-        }
-        {
-            let cube_entity_handle = Scene::create_entity(
-                Arc::downgrade(&scene),
-                Some("Glowing Cube".into()),
-                None,
-                None,
-            )
-            .expect("Unable to create glowing cube");
-            // add mesh renderer component
-            MeshRenderer::add_to_entity(
-                Arc::downgrade(&scene),
-                cube_entity_handle,
-                &resource_manager,
-                "cfcec28c-ff80-484b-a899-b7575c7454da".into(), // sphere: "7f4860c5-b53d-4c2b-86eb-0c03e3986aee", cube: ""c96dccc5-15f3-441d-b01d-504e52acff36""
-                true,
-                Default::default(),
-            );
-            let intensity = 5.0;
-            Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
-                Light::new(
-                    LightType::POINT,
-                    Vector3::new(
-                        intensity * 50.0 / 255.0,
-                        intensity * 50.0 / 255.0,
-                        intensity * 255.0 / 255.0,
-                    ),
-                    1.5,
-                    Vector3::new(-0.2, -0.4, -0.1),
-                    true,
-                ),
-            );
-            Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
-                Transform::new(
-                    Vector3::new(15.0, 1.5, 0.0),
-                    UnitQuaternion::identity(),
-                    Vector3::new(0.25, 0.25, 0.25),
-                ),
-            );
-        }
-        {
-            let cube_entity_handle =
-                Scene::create_entity(Arc::downgrade(&scene), Some("Floor".into()), None, None)
-                    .expect("Unable to create point light entity");
-            // add mesh renderer component
-            MeshRenderer::add_to_entity(
-                Arc::downgrade(&scene),
-                cube_entity_handle,
-                &resource_manager,
-                "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
-                true,
-                Default::default(),
-            );
-            Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
-                Transform::new(
-                    Vector3::new(0.0, -9.81, 0.0),
-                    UnitQuaternion::identity(),
-                    Vector3::new(100.0, 10.0, 100.0),
-                ),
-            );
-        }
-        {
-            let entity_handle = Scene::create_entity(
-                Arc::downgrade(&scene),
-                Some("Dark Knight".into()),
-                None,
-                None,
-            )
-            .expect("Unable to create entity");
-            // add mesh renderer component
-            MeshRenderer::add_to_entity(
-                Arc::downgrade(&scene),
-                entity_handle,
-                &resource_manager,
-                "7a71a1a6-a2ef-4e84-ad5d-4e3409d5ea87".into(), // 4k: f358ffb3-b766-4839-a93f-30b81ff9c107
-                true,
-                Default::default(),
-            );
-            Entity::from_handle(entity_handle, Arc::downgrade(&scene)).add_component(
-                Transform::new(
-                    Vector3::new(0.7, 0.21, 1.0),
-                    UnitQuaternion::from_euler_angles(-1.7, -2.2, 0.1),
-                    Vector3::new(1.0, 1.0, 1.0),
-                ),
-            );
-        }
-        // {
-        //     let entity_handle =
-        //         Scene::create_entity(Arc::downgrade(&scene), Some("Marisa".into()), None, None)
-        //             .expect("Unable to create entity");
-        //     // add mesh renderer component
-        //     MeshRenderer::add_to_entity(
-        //         Arc::downgrade(&scene),
-        //         entity_handle,
-        //         &resource_manager,
-        //         "8947d8aa-23a1-4fde-88f0-72f9a9250f3c".into(), // marisa broken: 8947d8aa-23a1-4fde-88f0-72f9a9250f3c marisa sketchfab: cebb984f-f669-435b-82fa-cedad7403e54 1k: 7a71a1a6-a2ef-4e84-ad5d-4e3409d5ea87 ; 4k: f358ffb3-b766-4839-a93f-30b81ff9c107
-        //         true,
-        //         Default::default(),
-        //     );
-        //     Entity::from_handle(entity_handle, Arc::downgrade(&scene)).add_component(
-        //         Transform::new(
-        //             Vector3::new(0.7, 0.195, 1.0),
-        //             UnitQuaternion::identity(),
-        //             Vector3::new(1.0, 1.0, 1.0),
-        //         ),
-        //     );
-        // }
+
+        let scene_path = get_default_scene_path();
+        let scene = if dream_fs::fs::exists(scene_path.clone()) {
+            Scene::load(scene_path.clone(), &resource_manager).unwrap_or_else(|err| {
+                log::error!("Unable to load scene, using default scene instead: {}", err);
+                create_default_scene(&resource_manager)
+            })
+        } else {
+            create_default_scene(&resource_manager)
+        };
+        scene.lock().expect("Unable to acquire lock on scene").guid =
+            resource_manager.get_guid_for_path(&scene_path);
 
         // init component systems
         let component_systems = vec![
             Arc::new(Mutex::new(PythonScriptComponentSystem::default())) as Arc<Mutex<dyn System>>,
             Arc::new(Mutex::new(SceneCameraComponentSystem::default())) as Arc<Mutex<dyn System>>,
+            Arc::new(Mutex::new(AnimationSystem::default())) as Arc<Mutex<dyn System>>,
+            // runs last so world transforms include this frame's changes
+            Arc::new(Mutex::new(TransformPropagationSystem::default())) as Arc<Mutex<dyn System>>,
         ];
 
         Self {
@@ -275,6 +114,246 @@ impl Default for App {
             component_systems,
             resource_manager,
             scene,
+            additive_scenes: Vec::new(),
+            play_snapshots: None,
+        }
+    }
+}
+
+fn create_default_scene(resource_manager: &ResourceManager) -> Arc<Mutex<Scene>> {
+    let scene = Scene::create();
+
+    // populate scene
+    // let entity_handle = scene.lock().expect("Unable to lock scene").create_entity();
+    let dummy_entity = Scene::create_entity(Arc::downgrade(&scene), Default::default(), None, None)
+        .expect("Unable to create dummy entity");
+    let _dummy_entity_child = Scene::create_entity(
+        Arc::downgrade(&scene),
+        Default::default(),
+        Some(dummy_entity),
+        None,
+    )
+    .expect("Unable to create dummy entity");
+    {
+        let scene_camera_entity_handle = Scene::create_entity(
+            Arc::downgrade(&scene),
+            Some("Scene Camera".into()),
+            None,
+            Some(Transform::new(
+                Vector3::new(-1.87, 1.13, -0.5),
+                UnitQuaternion::new(Vector3::y() * 4.3),
+                Vector3::new(1.0, 1.0, 1.0),
+            )),
+        )
+        .expect("Unable to create scene camera entity");
+        let e = Entity::from_handle(scene_camera_entity_handle, Arc::downgrade(&scene));
+        e.add_component(SceneCamera::default());
+    }
+    {
+        let directional_light_entity_handle =
+            Scene::create_entity(Arc::downgrade(&scene), Some("Sun".into()), None, None)
+                .expect("Unable to create sun entity");
+        // let intensity = 2.2;
+        let intensity = 1.5;
+        Entity::from_handle(directional_light_entity_handle, Arc::downgrade(&scene)).add_component(
+            Light::new(
+                LightType::DIRECTIONAL,
+                Vector3::new(
+                    intensity * 255.0 / 255.0,
+                    intensity * 255.0 / 255.0,
+                    intensity * 255.0 / 255.0,
+                ),
+                4.0,
+                Vector3::new(-0.2, -0.4, -0.1),
+                true,
+            ),
+        );
+        // add cube for position visualization (initial shadow map debugging)
+        // MeshRenderer::add_to_entity(
+        //     Arc::downgrade(&scene),
+        //     directional_light_entity_handle,
+        //     &resource_manager,
+        //     "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
+        //     true,
+        //     Default::default(),
+        // );
+        Entity::from_handle(directional_light_entity_handle, Arc::downgrade(&scene)).add_component(
+            Transform::new(
+                Vector3::new(4.3, 4.2, 2.3),
+                UnitQuaternion::identity(),
+                Vector3::new(0.1, 0.1, 0.1),
+            ),
+        );
+    }
+    {
+        let cube_entity_handle =
+            Scene::create_entity(Arc::downgrade(&scene), Some("2x Cube".into()), None, None)
+                .expect("Unable to create cube entity");
+        // add mesh renderer component
+        MeshRenderer::add_to_entity(
+            Arc::downgrade(&scene),
+            cube_entity_handle,
+            resource_manager,
+            "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
+            true,
+            Default::default(),
+        );
+        Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
+            Transform::new(
+                Vector3::new(7.4, 2.1, 10.6),
+                UnitQuaternion::new(Vector3::y() * pi() / 2.0),
+                Vector3::new(2.0, 2.0, 2.0),
+            ),
+        );
+    }
+    {
+        let cube_entity_handle = Scene::create_entity(
+            Arc::downgrade(&scene),
+            Some("Floating Rectangle".into()),
+            None,
+            None,
+        )
+        .expect("Unable to create cube entity");
+        // add mesh renderer component
+        MeshRenderer::add_to_entity(
+            Arc::downgrade(&scene),
+            cube_entity_handle,
+            resource_manager,
+            "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
+            true,
+            Default::default(),
+        );
+        Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
+            Transform::new(
+                Vector3::new(40.0, 10.0, 0.0),
+                UnitQuaternion::new(Vector3::y() * pi() / 2.0),
+                Vector3::new(10.0, 1.0, 10.0),
+            ),
+        );
+    }
+    {
+        let cube_entity_handle = Scene::create_entity(
+            Arc::downgrade(&scene),
+            Some("Glowing Cube".into()),
+            None,
+            None,
+        )
+        .expect("Unable to create glowing cube");
+        // add mesh renderer component
+        MeshRenderer::add_to_entity(
+            Arc::downgrade(&scene),
+            cube_entity_handle,
+            resource_manager,
+            "cfcec28c-ff80-484b-a899-b7575c7454da".into(), // sphere: "7f4860c5-b53d-4c2b-86eb-0c03e3986aee", cube: ""c96dccc5-15f3-441d-b01d-504e52acff36""
+            true,
+            Default::default(),
+        );
+        let intensity = 5.0;
+        Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(Light::new(
+            LightType::POINT,
+            Vector3::new(
+                intensity * 50.0 / 255.0,
+                intensity * 50.0 / 255.0,
+                intensity * 255.0 / 255.0,
+            ),
+            1.5,
+            Vector3::new(-0.2, -0.4, -0.1),
+            true,
+        ));
+        Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
+            Transform::new(
+                Vector3::new(15.0, 1.5, 0.0),
+                UnitQuaternion::identity(),
+                Vector3::new(0.25, 0.25, 0.25),
+            ),
+        );
+    }
+    {
+        let cube_entity_handle =
+            Scene::create_entity(Arc::downgrade(&scene), Some("Floor".into()), None, None)
+                .expect("Unable to create point light entity");
+        // add mesh renderer component
+        MeshRenderer::add_to_entity(
+            Arc::downgrade(&scene),
+            cube_entity_handle,
+            resource_manager,
+            "2dcd5e2e-714b-473a-bbdd-98771761cb37".into(),
+            true,
+            Default::default(),
+        );
+        Entity::from_handle(cube_entity_handle, Arc::downgrade(&scene)).add_component(
+            Transform::new(
+                Vector3::new(0.0, -9.81, 0.0),
+                UnitQuaternion::identity(),
+                Vector3::new(100.0, 10.0, 100.0),
+            ),
+        );
+    }
+    {
+        let entity_handle = Scene::create_entity(
+            Arc::downgrade(&scene),
+            Some("Dark Knight".into()),
+            None,
+            None,
+        )
+        .expect("Unable to create entity");
+        // add mesh renderer component
+        MeshRenderer::add_to_entity(
+            Arc::downgrade(&scene),
+            entity_handle,
+            resource_manager,
+            "7a71a1a6-a2ef-4e84-ad5d-4e3409d5ea87".into(), // 4k: f358ffb3-b766-4839-a93f-30b81ff9c107
+            true,
+            Default::default(),
+        );
+        Entity::from_handle(entity_handle, Arc::downgrade(&scene)).add_component(Transform::new(
+            Vector3::new(0.7, 0.21, 1.0),
+            UnitQuaternion::from_euler_angles(-1.7, -2.2, 0.1),
+            Vector3::new(1.0, 1.0, 1.0),
+        ));
+    }
+    // {
+    //     let entity_handle =
+    //         Scene::create_entity(Arc::downgrade(&scene), Some("Marisa".into()), None, None)
+    //             .expect("Unable to create entity");
+    //     // add mesh renderer component
+    //     MeshRenderer::add_to_entity(
+    //         Arc::downgrade(&scene),
+    //         entity_handle,
+    //         &resource_manager,
+    //         "8947d8aa-23a1-4fde-88f0-72f9a9250f3c".into(), // marisa broken: 8947d8aa-23a1-4fde-88f0-72f9a9250f3c marisa sketchfab: cebb984f-f669-435b-82fa-cedad7403e54 1k: 7a71a1a6-a2ef-4e84-ad5d-4e3409d5ea87 ; 4k: f358ffb3-b766-4839-a93f-30b81ff9c107
+    //         true,
+    //         Default::default(),
+    //     );
+    //     Entity::from_handle(entity_handle, Arc::downgrade(&scene)).add_component(
+    //         Transform::new(
+    //             Vector3::new(0.7, 0.195, 1.0),
+    //             UnitQuaternion::identity(),
+    //             Vector3::new(1.0, 1.0, 1.0),
+    //         ),
+    //     );
+    // }
+    scene
+}
+
+/// Load the model of a mesh renderer into the renderer if it is not loaded yet, a model that
+/// fails to load is reported to the editor and drawn as a placeholder
+fn store_mesh_renderer_model(renderer: &mut RendererWgpu, mesh_renderer: &MeshRenderer) {
+    if let Some(resource_handle) = mesh_renderer
+        .resource_handle
+        .as_ref()
+        .and_then(|resource_handle| resource_handle.upgrade())
+    {
+        let resource_key = &resource_handle.key;
+        if !renderer.is_model_stored(resource_key.as_str()) {
+            let model_path = resource_handle.get_path();
+            let model_path = model_path
+                .to_str()
+                .expect("Unable to convert resource path to a string");
+            if let Err(err) = renderer.store_model(Some(resource_key.as_str()), model_path) {
+                log::error!("Unable to load model {}: {}", model_path, err);
+                report_asset_load_error(resource_key.as_str(), err.to_string());
+            }
         }
     }
 }
@@ -282,11 +361,39 @@ impl Default for App {
 impl App {
     pub fn update(&mut self) -> f32 {
         self.dt = 1.0 / 60.0;
-        for i in 0..self.component_systems.len() {
-            self.component_systems[i]
-                .lock()
-                .unwrap()
-                .update(self.dt, Arc::downgrade(&self.scene));
+        if is_play_requested() != self.is_playing() {
+            if self.is_playing() {
+                if let Err(err) = self.stop_play() {
+                    log::error!("Unable to restore scenes after playing: {}", err);
+                }
+            } else {
+                self.start_play();
+            }
+        }
+        if SAVE_REQUESTED.swap(false, Ordering::Relaxed) {
+            if self.is_playing() {
+                log::warn!("Unable to save scene while playing, stop playing first");
+            } else if let Err(err) = self.save_scene() {
+                log::error!("Unable to save scene: {}", err);
+            }
+        }
+        let playing = self.is_playing();
+        for scene in self.get_loaded_scenes() {
+            let destroyed_entities = {
+                let mut scene_mutex_lock = scene.lock().expect("Unable to acquire lock on scene");
+                // make this frame's component events available to the systems
+                scene_mutex_lock.collect_component_events();
+                scene_mutex_lock.take_destroyed_entities()
+            };
+            for i in 0..self.component_systems.len() {
+                let mut component_system = self.component_systems[i].lock().unwrap();
+                for entity_id in &destroyed_entities {
+                    component_system.on_entity_destroyed(Arc::downgrade(&scene), *entity_id);
+                }
+                if playing || component_system.runs_in_edit_mode() {
+                    component_system.update(self.dt, Arc::downgrade(&scene));
+                }
+            }
         }
         // mouse dx and dy should be reset
         set_mouse_move(get_mouse_move() * 0.0);
@@ -296,34 +403,283 @@ impl App {
 
     pub async fn update_async(&mut self) {}
 
-    pub fn draw(&mut self, renderer: &mut RendererWgpu) {
-        renderer.clear();
-        let scene_weak_ref = Arc::downgrade(&self.scene);
-        let root_entity_id: Option<u64> = self
+    pub fn is_playing(&self) -> bool {
+        self.play_snapshots.is_some()
+    }
+
+    /// Snapshot all loaded scenes and start running gameplay systems
+    pub fn start_play(&mut self) {
+        if self.is_playing() {
+            return;
+        }
+        let play_snapshots = self
+            .get_loaded_scenes()
+            .into_iter()
+            .map(|scene| {
+                let snapshot = scene
+                    .lock()
+                    .expect("Unable to acquire lock on scene")
+                    .take_snapshot();
+                (scene, snapshot)
+            })
+            .collect();
+        self.play_snapshots = Some(play_snapshots);
+        for component_system in &self.component_systems {
+            component_system.lock().unwrap().on_play_mode_changed(true);
+        }
+    }
+
+    /// Put every scene back the way it was when play mode started, scenes loaded while playing
+    /// are unloaded and scenes unloaded while playing are loaded again
+    pub fn stop_play(&mut self) -> anyhow::Result<()> {
+        let Some(play_snapshots) = self.play_snapshots.take() else {
+            return Ok(());
+        };
+        let scenes_loaded_while_playing: Vec<Arc<Mutex<Scene>>> = self
+            .get_loaded_scenes()
+            .into_iter()
+            .filter(|scene| {
+                !play_snapshots
+                    .iter()
+                    .any(|(snapshot_scene, _)| Arc::ptr_eq(snapshot_scene, scene))
+            })
+            .collect();
+        for scene in scenes_loaded_while_playing {
+            self.drop_additive_scene(&scene);
+        }
+        // the active scene is the first one of the snapshots, even if another scene took its
+        // place while playing
+        if let Some((scene, _)) = play_snapshots.first() {
+            self.scene = scene.clone();
+        }
+        self.additive_scenes = play_snapshots
+            .iter()
+            .skip(1)
+            .map(|(scene, _)| scene.clone())
+            .collect();
+        // restoring gives the entities new runtime ids, so whatever outlives play mode (e.g. the
+        // selection of the editor) refers to entities by guid
+        for (scene, snapshot) in play_snapshots {
+            scene
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Unable to acquire lock on scene for restoring"))?
+                .restore_snapshot(snapshot, &self.resource_manager)?;
+        }
+        for component_system in &self.component_systems {
+            component_system.lock().unwrap().on_play_mode_changed(false);
+        }
+        Ok(())
+    }
+
+    /// The active scene followed by the additive scenes
+    pub fn get_loaded_scenes(&self) -> Vec<Arc<Mutex<Scene>>> {
+        let mut scenes = vec![self.scene.clone()];
+        scenes.extend(self.additive_scenes.iter().cloned());
+        scenes
+    }
+
+    pub fn get_loaded_scene(&self, guid: &str) -> Option<Arc<Mutex<Scene>>> {
+        self.get_loaded_scenes().into_iter().find(|scene| {
+            scene
+                .lock()
+                .expect("Unable to acquire lock on scene")
+                .guid
+                .as_deref()
+                == Some(guid)
+        })
+    }
+
+    /// Load a .scene file by its resource guid. An additive scene is loaded next to the loaded
+    /// scenes, otherwise it replaces the contents of the active scene and all additive scenes are
+    /// unloaded.
+    pub fn load_scene(&mut self, guid: &str, additive: bool) -> anyhow::Result<Arc<Mutex<Scene>>> {
+        if self.get_loaded_scene(guid).is_some() {
+            return Err(anyhow::anyhow!("Scene {} is already loaded", guid));
+        }
+        let path = get_scene_path(guid, &self.resource_manager)
+            .ok_or_else(|| anyhow::anyhow!("No scene with guid {}", guid))?;
+        let scene = if additive {
+            let scene = Scene::load(path, &self.resource_manager)?;
+            self.additive_scenes.push(scene.clone());
+            scene
+        } else {
+            // parse the file first, so the loaded scenes stay as they are if it is malformed
+            let bytes = dream_fs::fs::read_binary(path, true)
+                .map_err(|err| anyhow::anyhow!("Unable to read scene file: {}", err))?;
+            let yaml =
+                String::from_utf8(bytes).map_err(|_| anyhow::anyhow!("Scene file is not UTF-8"))?;
+            let scene_data = SceneData::from_yaml(&yaml)?;
+            for scene in self.additive_scenes.clone() {
+                self.drop_additive_scene(&scene);
+            }
+            // keep the same scene object, so everything holding on to the active scene sees the
+            // new contents
+            self.scene
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Unable to acquire lock on scene for loading"))?
+                .load_scene_data(&scene_data, &self.resource_manager)?;
+            self.scene.clone()
+        };
+        scene
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Unable to acquire lock on scene for loading"))?
+            .guid = Some(String::from(guid));
+        Ok(scene)
+    }
+
+    /// Unload an additive scene, the active scene can only be replaced by loading another scene
+    pub fn unload_scene(&mut self, guid: &str) -> anyhow::Result<()> {
+        let scene = self
+            .additive_scenes
+            .iter()
+            .find(|scene| {
+                scene
+                    .lock()
+                    .expect("Unable to acquire lock on scene")
+                    .guid
+                    .as_deref()
+                    == Some(guid)
+            })
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No additive scene with guid {} is loaded", guid))?;
+        self.drop_additive_scene(&scene);
+        Ok(())
+    }
+
+    /// Unload a scene that is not the active one anymore, systems drop whatever they keep for its
+    /// entities
+    fn drop_additive_scene(&mut self, scene: &Arc<Mutex<Scene>>) {
+        self.additive_scenes
+            .retain(|additive_scene| !Arc::ptr_eq(additive_scene, scene));
+        let entity_ids = {
+            let mut scene_mutex_lock = scene.lock().expect("Unable to acquire lock on scene");
+            let mut entity_ids = scene_mutex_lock.take_destroyed_entities();
+            if let Some(root_entity_id) = scene_mutex_lock.root_entity_runtime_id {
+                entity_ids.extend(scene_mutex_lock.collect_subtree(root_entity_id));
+            }
+            entity_ids
+        };
+        for component_system in &self.component_systems {
+            let mut component_system = component_system.lock().unwrap();
+            for entity_id in &entity_ids {
+                component_system.on_entity_destroyed(Arc::downgrade(scene), *entity_id);
+            }
+        }
+    }
+
+    /// Move an entity with its descendants from one loaded scene to another, see
+    /// Scene::move_entity_to_scene
+    pub fn move_entity_to_scene(
+        &self,
+        source_scene: &Arc<Mutex<Scene>>,
+        entity_id: u64,
+        target_scene: &Arc<Mutex<Scene>>,
+        new_parent_id: Option<u64>,
+    ) -> anyhow::Result<u64> {
+        Scene::move_entity_to_scene(
+            Arc::downgrade(source_scene),
+            entity_id,
+            Arc::downgrade(target_scene),
+            new_parent_id,
+            &self.resource_manager,
+        )
+    }
+
+    /// Save the active scene to the file it was loaded from
+    pub fn save_scene(&self) -> anyhow::Result<()> {
+        let mut scene_mutex_lock = self
             .scene
             .lock()
-            .expect("Unable to acquire lock on scene")
-            .root_entity_runtime_id;
-        // get children for root entity and render them
-        if let Some(root_entity_id) = root_entity_id {
-            let mut mat: Matrix4<f32> = Matrix4::identity();
-            let mat_from_root_bone: Matrix4<f32> = Matrix4::identity();
-            let root_entity = Entity::from_handle(root_entity_id, scene_weak_ref.clone());
-            if let Some(transform) = root_entity.get_component::<Transform>() {
-                mat = Matrix4::new_translation(&transform.position)
-                    * transform.rotation.to_homogeneous()
-                    * Matrix4::new_nonuniform_scaling(&transform.scale);
+            .map_err(|_| anyhow::anyhow!("Unable to acquire lock on scene for saving"))?;
+        let path = scene_mutex_lock
+            .guid
+            .as_deref()
+            .and_then(|guid| get_scene_path(guid, &self.resource_manager))
+            .unwrap_or_else(get_default_scene_path);
+        scene_mutex_lock.save(path)
+    }
+
+    pub fn draw(&mut self, renderer: &mut RendererWgpu) {
+        renderer.clear();
+        for scene in self.get_loaded_scenes() {
+            draw_scene(renderer, &scene);
+        }
+
+        fn draw_scene(renderer: &mut RendererWgpu, scene: &Arc<Mutex<Scene>>) {
+            let scene_weak_ref = Arc::downgrade(scene);
+            let root_entity_id: Option<u64> = {
+                let mut scene_mutex_lock = scene.lock().expect("Unable to acquire lock on scene");
+                // pick up transforms changed outside of update (e.g. by the editor)
+                scene_mutex_lock.propagate_transforms();
+                // start loading models as soon as a mesh renderer refers to them
+                let mesh_renderer_ids: Vec<u64> = scene_mutex_lock
+                    .added::<MeshRenderer>()
+                    .iter()
+                    .chain(scene_mutex_lock.changed::<MeshRenderer>())
+                    .copied()
+                    .collect();
+                let mut mesh_renderers = scene_mutex_lock.query::<&MeshRenderer>();
+                for entity_id in mesh_renderer_ids {
+                    if let Some(mesh_renderer) = mesh_renderers.get(entity_id) {
+                        store_mesh_renderer_model(renderer, mesh_renderer);
+                    }
+                }
+                drop(mesh_renderers);
+                // nothing is drawn when the root entity is inactive
+                scene_mutex_lock
+                    .root_entity_runtime_id
+                    .filter(|root_entity_id| scene_mutex_lock.is_active_self(*root_entity_id))
+            };
+            // get children for root entity and render them
+            if let Some(root_entity_id) = root_entity_id {
+                let mut mat: Matrix4<f32> = Matrix4::identity();
+                let mat_from_root_bone: Matrix4<f32> = Matrix4::identity();
+                let root_entity = Entity::from_handle(root_entity_id, scene_weak_ref.clone());
+                if let Some(transform) = root_entity.get_component::<Transform>() {
+                    mat = Matrix4::new_translation(&transform.position)
+                        * transform.rotation.to_homogeneous()
+                        * Matrix4::new_nonuniform_scaling(&transform.scale);
+                }
+                let children_ids =
+                    Scene::get_children_for_entity(scene_weak_ref.clone(), root_entity_id);
+                let armature = find_armature(&children_ids, scene_weak_ref.clone(), None);
+                for child_id in children_ids {
+                    draw_entity_and_children(
+                        renderer,
+                        child_id,
+                        scene_weak_ref.clone(),
+                        mat,
+                        mat_from_root_bone,
+                        armature,
+                    );
+                }
             }
-            let children_ids =
-                Scene::get_children_for_entity(scene_weak_ref.clone(), root_entity_id);
-            for child_id in children_ids {
-                draw_entity_and_children(
-                    renderer,
-                    child_id,
-                    scene_weak_ref.clone(),
-                    mat,
-                    mat_from_root_bone,
-                );
+        }
+
+        // skinned meshes are posed by the armature whose root bone is their sibling (or the
+        // sibling of one of their ancestors)
+        fn find_armature(
+            children_ids: &[u64],
+            scene: Weak<Mutex<Scene>>,
+            parent_armature: Option<u64>,
+        ) -> Option<u64> {
+            children_ids
+                .iter()
+                .copied()
+                .find(|child_id| {
+                    Entity::from_handle(*child_id, scene.clone())
+                        .get_component::<Bone>()
+                        .map_or(false, |bone| bone.is_root)
+                })
+                .or(parent_armature)
+        }
+
+        // runtime ids are only unique within a scene, so armatures and morphed meshes of
+        // different scenes are told apart by their scene
+        fn entity_key(scene: &Weak<Mutex<Scene>>, runtime_id: u64) -> EntityKey {
+            EntityKey {
+                scene: scene.as_ptr() as usize,
+                runtime_id,
             }
         }
 
@@ -334,24 +690,34 @@ impl App {
             scene: Weak<Mutex<Scene>>,
             parent_mat: Matrix4<f32>,
             mat_from_root_bone: Matrix4<f32>,
+            armature: Option<u64>,
         ) {
             let entity = Entity::from_handle(entity_id, scene.clone());
+            // an inactive entity hides its whole subtree
+            if entity
+                .get_component::<Active>()
+                .map_or(false, |active| !active.active)
+            {
+                return;
+            }
             let mut mat = Matrix4::identity();
             let mut new_bone_mat = mat_from_root_bone;
 
             if let Some(transform) = entity.get_component::<Transform>() {
-                // TODO: create cache of mat4 that is map of maps
-                // so basically to invalidate caches for all children
-                // all we have to do is remove the element from the map
                 let position = transform.position;
                 let rotation = transform.rotation;
-                let scale = transform.scale;
-                let model_mat = Matrix4::new_translation(&position)
-                    * rotation.to_homogeneous()
-                    * Matrix4::new_nonuniform_scaling(&scale);
-                mat = parent_mat * model_mat;
+                let model_mat = transform.to_matrix();
+                mat = entity
+                    .get_component::<GlobalTransform>()
+                    .map(|global_transform| global_transform.matrix)
+                    .unwrap_or_else(|| parent_mat * model_mat);
+                // cameras and lights without render layers see every layer
+                let layer_mask = entity
+                    .get_component::<RenderLayers>()
+                    .map_or(RenderLayers::ALL, |render_layers| render_layers.mask);
                 if let Some(_scene_camera_component) = entity.get_component::<SceneCamera>() {
                     renderer.set_camera(position.into(), rotation);
+                    renderer.set_camera_layer_mask(layer_mask);
                 }
                 if let Some(light_component) = entity.get_component::<Light>() {
                     let position = Vector3::new(mat.m14, mat.m24, mat.m34);
@@ -362,6 +728,11 @@ impl App {
                         light_component.radius,
                         light_component.direction,
                         light_component.cast_shadow,
+                        (
+                            light_component.inner_cone_angle,
+                            light_component.outer_cone_angle,
+                        ),
+                        layer_mask,
                     );
                 }
                 if let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() {
@@ -374,35 +745,56 @@ impl App {
 
                         if renderer.is_model_stored(resource_key.as_str()) {
                             if let Some(mesh_idx) = mesh_renderer.mesh_idx {
+                                // each entity with morph weights deforms its own copy of the mesh
+                                let mut morph_weights_entity = None;
+                                if let Some(morph_weights) = entity.get_component::<MorphWeights>()
+                                {
+                                    let key = entity_key(&scene, entity_id);
+                                    renderer.set_morph_weights(key, &morph_weights.weights);
+                                    morph_weights_entity = Some(key);
+                                }
                                 renderer.draw_mesh(
                                     model_guid.as_str(),
                                     mesh_idx as i32,
+                                    entity
+                                        .get_component::<RenderLayers>()
+                                        .unwrap_or_default()
+                                        .mask,
+                                    armature.map(|armature| entity_key(&scene, armature)),
+                                    morph_weights_entity,
                                     Instance { mat },
                                 );
                             }
                         } else {
-                            let resource_path = &upgraded_resource_handle.path;
-                            renderer
-                                .store_model(
-                                    Some(resource_key.as_str()),
-                                    resource_path
-                                        .to_str()
-                                        .expect("Unable to convert resource path to a string"),
-                                )
-                                .expect("Unable to store model");
+                            // fallback for mesh renderers created before events were collected
+                            store_mesh_renderer_model(renderer, &mesh_renderer);
                         }
                     }
                 }
                 if let Some(bone_component) = entity.get_component::<Bone>() {
                     new_bone_mat *= model_mat;
                     let bone_mat: Matrix4<f32> = new_bone_mat * bone_component.inverse_bind_pose;
-                    renderer.set_bone_transform(bone_component.bone_id, bone_mat);
+                    if let Some(armature_root_id) = bone_component.armature_root_runtime_id {
+                        renderer.set_bone_transform(
+                            entity_key(&scene, armature_root_id),
+                            bone_component.bone_id,
+                            bone_mat,
+                        );
+                    }
                 }
             }
 
             let children_ids = Scene::get_children_for_entity(scene.clone(), entity_id);
+            let armature = find_armature(&children_ids, scene.clone(), armature);
             for child_id in children_ids {
-                draw_entity_and_children(renderer, child_id, scene.clone(), mat, new_bone_mat);
+                draw_entity_and_children(
+                    renderer,
+                    child_id,
+                    scene.clone(),
+                    mat,
+                    new_bone_mat,
+                    armature,
+                );
             }
         }
     }
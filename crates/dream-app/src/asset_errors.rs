@@ -0,0 +1,21 @@
+use std::sync::Mutex;
+
+// set by the app when an asset fails to load, the editor shows them next to the asset
+static ASSET_LOAD_ERRORS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Remember why the asset with the given resource key failed to load
+pub fn report_asset_load_error(resource_key: &str, error: String) {
+    let mut asset_load_errors = ASSET_LOAD_ERRORS.lock().unwrap();
+    asset_load_errors.retain(|(key, _)| key != resource_key);
+    asset_load_errors.push((String::from(resource_key), error));
+}
+
+/// Why the asset with the given resource key failed to load, if it did
+pub fn get_asset_load_error(resource_key: &str) -> Option<String> {
+    ASSET_LOAD_ERRORS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(key, _)| key == resource_key)
+        .map(|(_, error)| error.clone())
+}
@@ -1,5 +1,9 @@
+mod animation_system;
 pub mod app;
+pub mod asset_errors;
 pub mod input;
+pub mod play_mode;
 mod python_script_component_system;
 mod scene_camera_component_system;
 mod system;
+mod transform_propagation_system;
@@ -0,0 +1,12 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set by the editor, the app starts or stops playing on its next update
+static PLAY_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_play_requested(play: bool) {
+    PLAY_REQUESTED.store(play, Ordering::Relaxed);
+}
+
+pub fn is_play_requested() -> bool {
+    PLAY_REQUESTED.load(Ordering::Relaxed)
+}
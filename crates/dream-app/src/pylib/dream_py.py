@@ -1,4 +1,5 @@
-from dream import dream_entity
+from dream import dream_entity, dream_find_by_name, dream_find_by_path, dream_find_all_by_path, dream_undo, \
+    dream_redo
 
 
 class Entity:
@@ -14,10 +15,89 @@ class Entity:
     def position(self, value):
         self.internal.set_position(value)
 
+    @property
+    def active(self):
+        return self.internal.is_active()
+
+    @active.setter
+    def active(self, value: bool):
+        self.internal.set_active(value)
+
+    def set_parent(self, parent, keep_world_transform: bool = True):
+        self.internal.set_parent(parent.handle, keep_world_transform)
+
+    def has_component(self, component: str):
+        return self.internal.has_component(component)
+
+    def add_component(self, component: str):
+        self.internal.add_component(component)
+
+    def remove_component(self, component: str):
+        self.internal.remove_component(component)
+
+    def get_field(self, component: str, field: str):
+        value = self.internal.get_field(component, field)
+        if hasattr(value, "x") and hasattr(value, "y") and hasattr(value, "z"):
+            return Vector3(obj=value)
+        return value
+
+    def set_field(self, component: str, field: str, value):
+        self.internal.set_field(component, field, value)
+
+    def play_animation(self, clip: str = None):
+        if clip is not None:
+            self.set_field("AnimationPlayer", "clip", clip)
+            self.set_field("AnimationPlayer", "time", 0.0)
+        self.set_field("AnimationPlayer", "playing", True)
+
+    def pause_animation(self):
+        self.set_field("AnimationPlayer", "playing", False)
+
+    def get_animation_parameter(self, name: str):
+        return self.internal.get_animation_parameter(name)
+
+    def set_animation_parameter(self, name: str, value):
+        # booleans are stored as 0 and 1
+        self.internal.set_animation_parameter(name, float(value))
+
+    def get_morph_weight(self, index: int):
+        return self.internal.get_morph_weight(index)
+
+    def set_morph_weight(self, index: int, value: float):
+        self.internal.set_morph_weight(index, float(value))
+
+    def on_enable(self):
+        pass
+
+    def on_disable(self):
+        pass
+
     def update(self, dt: float):
         pass
 
 
+def find_by_name(name: str):
+    handle = dream_find_by_name(name)
+    return Entity(handle) if handle is not None else None
+
+
+def find_by_path(path: str):
+    handle = dream_find_by_path(path)
+    return Entity(handle) if handle is not None else None
+
+
+def find_all(pattern: str):
+    return [Entity(handle) for handle in dream_find_all_by_path(pattern)]
+
+
+def undo():
+    return dream_undo()
+
+
+def redo():
+    return dream_redo()
+
+
 class Vector3:
     def __init__(self, x: float = 0.0, y: float = 0.0, z: float = 0.0, obj=None):
         self.x = x
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Mutex, Weak};
 
 use rustpython_vm::convert::ToPyResult;
@@ -7,17 +7,46 @@ use rustpython_vm::{
     PyResult, VirtualMachine,
 };
 
-use dream_ecs::component::PythonScript;
+use dream_ecs::animator::get_animation_state_machine;
+use dream_ecs::component::{Active, Animator, EntityRef, Guid, MorphWeights, PythonScript};
 use dream_ecs::entity::Entity;
+use dream_ecs::history::{ReparentEntity, SetComponent, SetComponentField, SetRegisteredComponent};
+use dream_ecs::reflect::{component_registry, FieldType, FieldValue};
 use dream_ecs::scene::Scene;
 
 use crate::system::System;
 
 static SCENE: Mutex<Option<Weak<Mutex<Scene>>>> = Mutex::new(None);
 
+/// Runtime ids are only unique within a scene, so scripts are keyed by scene and entity
+type ScriptKey = (usize, u64);
+
+fn script_key(scene: &Weak<Mutex<Scene>>, entity_id: u64) -> ScriptKey {
+    (scene.as_ptr() as usize, entity_id)
+}
+
+/// Call a method of a script instance if the script defines it, errors are logged
+fn call_script_method(
+    entity_script: &PyObjectRef,
+    method_name: &str,
+    args: Vec<PyObjectRef>,
+    vm: &VirtualMachine,
+) {
+    if let Ok(method) = entity_script.get_attr(method_name, vm) {
+        if let Err(e) = method.call(args, vm) {
+            let py_err = e.get_arg(0).unwrap();
+            log::error!("{}", py_err.str(vm).unwrap());
+            let line_number = e.traceback().unwrap().lineno;
+            log::error!("line {}", line_number);
+        }
+    }
+}
+
 pub struct PythonScriptComponentSystem {
     pub interpreter: Interpreter,
-    pub entity_script: HashMap<u64, Option<PyObjectRef>>,
+    pub entity_script: HashMap<ScriptKey, Option<PyObjectRef>>,
+    /// Scripts whose entity was active during the last update
+    pub enabled_scripts: HashSet<ScriptKey>,
     pub script_cache: HashMap<String, String>,
 }
 
@@ -30,6 +59,7 @@ impl Default for PythonScriptComponentSystem {
         Self {
             interpreter,
             entity_script: Default::default(),
+            enabled_scripts: Default::default(),
             script_cache: Default::default(),
         }
     }
@@ -37,16 +67,32 @@ impl Default for PythonScriptComponentSystem {
 
 impl System for PythonScriptComponentSystem {
     fn update(&mut self, dt: f32, scene: Weak<Mutex<Scene>>) {
-        if SCENE.lock().unwrap().is_none() {
-            *SCENE.lock().unwrap() = Some(scene.clone());
-        }
-        let python_entities = scene
-            .upgrade()
-            .expect("Unable to upgrade")
-            .lock()
-            .expect("Unable to lock")
-            .get_entities_with_component::<dream_ecs::component::PythonScript>();
-        for entity_id in python_entities {
+        // scripts talk to the scene that is being updated
+        *SCENE.lock().unwrap() = Some(scene.clone());
+        let python_entities: Vec<(u64, bool)> = {
+            let scene = scene.upgrade().expect("Unable to upgrade");
+            let scene = scene.lock().expect("Unable to lock");
+            scene
+                .get_entities_with_component::<dream_ecs::component::PythonScript>()
+                .into_iter()
+                .map(|entity_id| (entity_id, scene.is_active_in_hierarchy(entity_id)))
+                .collect()
+        };
+        for (entity_id, active) in python_entities {
+            if !active {
+                // scripts of inactive entities are not updated, they are only told when they
+                // get disabled
+                if self.enabled_scripts.remove(&script_key(&scene, entity_id)) {
+                    if let Some(Some(entity_script)) =
+                        self.entity_script.get(&script_key(&scene, entity_id))
+                    {
+                        self.interpreter.enter(|vm| {
+                            call_script_method(entity_script, "on_disable", vec![], vm);
+                        });
+                    }
+                }
+                continue;
+            }
             let mut script: String = include_str!("default-files/script.py").into();
             {
                 let entity = Entity::from_handle(entity_id, scene.clone());
@@ -57,7 +103,7 @@ impl System for PythonScriptComponentSystem {
                         .unwrap()
                         .upgrade();
                     let rh = resource_handle.unwrap();
-                    let script_path = &rh.path;
+                    let script_path = rh.get_path();
                     let script_key = &rh.key;
 
                     if self.script_cache.contains_key(script_key) {
@@ -128,28 +174,21 @@ impl System for PythonScriptComponentSystem {
                         .map(|entity_script| {
                             // cuz this run code object only returns the class definition which we extract the class name from
                             self.entity_script
-                                .entry(entity_id)
+                                .entry(script_key(&scene, entity_id))
                                 .or_insert(Some(entity_script));
                         })
                         .expect("Error running python code");
-                    if let Ok(update) = self
+                    let entity_script = self
                         .entity_script
-                        .get(&entity_id)
+                        .get(&script_key(&scene, entity_id))
                         .unwrap()
                         .as_ref()
-                        .unwrap()
-                        .get_attr("update", vm)
-                    {
-                        let args = vec![vm.ctx.new_float(dt as f64).into()];
-                        let res = update.call(args, vm);
-                        if let Err(..) = res {
-                            let e = res.unwrap_err();
-                            let py_err = e.get_arg(0).unwrap();
-                            log::error!("{}", py_err.str(vm).unwrap());
-                            let line_number = e.traceback().unwrap().lineno;
-                            log::error!("line {}", line_number);
-                        }
+                        .unwrap();
+                    if self.enabled_scripts.insert(script_key(&scene, entity_id)) {
+                        call_script_method(entity_script, "on_enable", vec![], vm);
                     }
+                    let args = vec![vm.ctx.new_float(dt as f64).into()];
+                    call_script_method(entity_script, "update", args, vm);
 
                     // TODO: allow other python scripts to get variables that are defined
                     // TODO: allow inspector to view attributes (have attributes map in script component)
@@ -175,6 +214,22 @@ impl System for PythonScriptComponentSystem {
             })
         }
     }
+
+    fn on_entity_destroyed(&mut self, scene: Weak<Mutex<Scene>>, entity_id: u64) {
+        // drop the script instance so it is not kept alive for an entity that no longer exists
+        self.entity_script.remove(&script_key(&scene, entity_id));
+        self.enabled_scripts.remove(&script_key(&scene, entity_id));
+    }
+
+    fn runs_in_edit_mode(&self) -> bool {
+        false
+    }
+
+    fn on_play_mode_changed(&mut self, _playing: bool) {
+        // every play session starts with fresh script instances
+        self.entity_script.clear();
+        self.enabled_scripts.clear();
+    }
 }
 
 #[pymodule]
@@ -194,6 +249,119 @@ pub(crate) mod dream_py {
         Ok(Vector3Internal { x, y, z })
     }
 
+    #[pyfunction]
+    fn dream_find_by_name(name: String, _vm: &VirtualMachine) -> PyResult<Option<u64>> {
+        let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+        let scene = scene.upgrade().expect("Unable to upgrade scene");
+        let entity_id = scene.lock().unwrap().find_by_name(name.as_str());
+        Ok(entity_id)
+    }
+
+    #[pyfunction]
+    fn dream_find_by_path(path: String, _vm: &VirtualMachine) -> PyResult<Option<u64>> {
+        let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+        let scene = scene.upgrade().expect("Unable to upgrade scene");
+        let entity_id = scene.lock().unwrap().find_by_path(path.as_str());
+        Ok(entity_id)
+    }
+
+    #[pyfunction]
+    fn dream_undo(vm: &VirtualMachine) -> PyResult<bool> {
+        let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+        Scene::undo(scene).map_err(|err| vm.new_runtime_error(err.to_string()))
+    }
+
+    #[pyfunction]
+    fn dream_redo(vm: &VirtualMachine) -> PyResult<bool> {
+        let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+        Scene::redo(scene).map_err(|err| vm.new_runtime_error(err.to_string()))
+    }
+
+    /// Guid of an entity of the current scene, commands refer to entities by guid
+    fn get_entity_guid(handle: u64, vm: &VirtualMachine) -> PyResult<Guid> {
+        let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+        let scene = scene.upgrade().expect("Unable to upgrade scene");
+        let guid = scene.lock().unwrap().get_entity_guid(handle);
+        guid.ok_or_else(|| vm.new_value_error(format!("Entity {handle} does not exist")))
+    }
+
+    fn field_value_to_py(
+        value: FieldValue,
+        field_type: FieldType,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyObjectRef> {
+        match value {
+            FieldValue::Bool(value) => value.to_pyresult(vm),
+            FieldValue::U32(value) => value.to_pyresult(vm),
+            FieldValue::F32(value) => (value as f64).to_pyresult(vm),
+            FieldValue::String(value) => value.to_pyresult(vm),
+            FieldValue::Vec3(value) => Vector3Internal::from(value).to_pyresult(vm),
+            FieldValue::Quat(value) => (
+                value.i as f64,
+                value.j as f64,
+                value.k as f64,
+                value.w as f64,
+            )
+                .to_pyresult(vm),
+            // enums are passed to scripts by variant name
+            FieldValue::Enum(idx) => match field_type {
+                FieldType::Enum(variants) => variants
+                    .get(idx)
+                    .map(|variant| String::from(*variant))
+                    .unwrap_or_default()
+                    .to_pyresult(vm),
+                _ => idx.to_pyresult(vm),
+            },
+            // entity references are passed to scripts by guid
+            FieldValue::Entity(entity_ref) => entity_ref.guid.map(|guid| guid.0).to_pyresult(vm),
+        }
+    }
+
+    fn py_to_field_value(
+        obj: PyObjectRef,
+        field_type: FieldType,
+        vm: &VirtualMachine,
+    ) -> PyResult<FieldValue> {
+        Ok(match field_type {
+            FieldType::Bool => FieldValue::Bool(obj.try_into_value(vm)?),
+            FieldType::U32 => FieldValue::U32(obj.try_into_value(vm)?),
+            FieldType::F32 => FieldValue::F32(obj.try_float(vm)?.to_f64() as f32),
+            FieldType::String => FieldValue::String(obj.try_into_value(vm)?),
+            FieldType::Vec3 => FieldValue::Vec3(dream_math::Vector3::from(
+                Vector3Internal::try_from_borrowed_object(vm, &obj)?,
+            )),
+            FieldType::Quat => {
+                let (i, j, k, w): (f64, f64, f64, f64) = obj.try_into_value(vm)?;
+                FieldValue::Quat(dream_math::UnitQuaternion::from_quaternion(
+                    dream_math::Quaternion::new(w as f32, i as f32, j as f32, k as f32),
+                ))
+            }
+            FieldType::Enum(variants) => {
+                let variant: String = obj.try_into_value(vm)?;
+                FieldValue::Enum(
+                    variants
+                        .iter()
+                        .position(|name| *name == variant)
+                        .ok_or_else(|| vm.new_value_error(format!("Unknown variant {variant}")))?,
+                )
+            }
+            FieldType::Entity => {
+                let guid: Option<String> = obj.try_into_value(vm)?;
+                FieldValue::Entity(EntityRef {
+                    guid: guid.map(Guid),
+                })
+            }
+        })
+    }
+
+    #[pyfunction]
+    fn dream_find_all_by_path(pattern: String, _vm: &VirtualMachine) -> PyResult<Vec<u64>> {
+        let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+        let scene = scene.upgrade().expect("Unable to upgrade scene");
+        let entity_ids = scene.lock().unwrap().find_all_by_path(pattern.as_str());
+        Ok(entity_ids)
+    }
+
     #[pyattr]
     #[pyclass(module = "dream", name = "EntityInternal")]
     #[derive(Debug, PyPayload)]
@@ -259,13 +427,195 @@ pub(crate) mod dream_py {
 
         #[inline]
         #[pymethod]
-        fn set_position(&self, position: Vector3Internal) {
+        fn set_position(&self, position: Vector3Internal, vm: &VirtualMachine) -> PyResult<()> {
             let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
-            let entity = dream_ecs::entity::Entity::from_handle(self.handle, scene);
+            let entity = dream_ecs::entity::Entity::from_handle(self.handle, scene.clone());
             let transform: Option<dream_ecs::component::Transform> = entity.get_component();
             let mut transform = transform.unwrap();
             transform.position = dream_math::Vector3::from(position);
-            entity.add_component(transform);
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(scene, SetComponent::new(guid, transform))
+                .map_err(|err| vm.new_runtime_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn is_active(&self) -> PyResult<bool> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let scene = scene.upgrade().expect("Unable to upgrade scene");
+            let active = scene.lock().unwrap().is_active_self(self.handle);
+            Ok(active)
+        }
+
+        #[pymethod]
+        fn set_active(&self, active: bool, vm: &VirtualMachine) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(scene, SetComponent::new(guid, Active::new(active)))
+                .map_err(|err| vm.new_runtime_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn has_component(&self, component: String) -> PyResult<bool> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let scene = scene.upgrade().expect("Unable to upgrade scene");
+            let scene = scene.lock().unwrap();
+            Ok(component_registry()
+                .get(component.as_str())
+                .map_or(false, |component| component.has(&scene, self.handle)))
+        }
+
+        #[pymethod]
+        fn add_component(&self, component: String, vm: &VirtualMachine) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(
+                scene,
+                SetRegisteredComponent::add_default(guid, component.as_str()),
+            )
+            .map_err(|err| vm.new_value_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn remove_component(&self, component: String, vm: &VirtualMachine) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(
+                scene,
+                SetRegisteredComponent::remove(guid, component.as_str()),
+            )
+            .map_err(|err| vm.new_value_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn get_field(
+            &self,
+            component: String,
+            field: String,
+            vm: &VirtualMachine,
+        ) -> PyResult<PyObjectRef> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let scene = scene.upgrade().expect("Unable to upgrade scene");
+            let (value, field_type) = {
+                let scene = scene.lock().unwrap();
+                let registry = component_registry();
+                let descriptor = registry.get(component.as_str()).ok_or_else(|| {
+                    vm.new_value_error(format!("No registered component {component}"))
+                })?;
+                let field_type = descriptor
+                    .get_field_descriptor(field.as_str())
+                    .ok_or_else(|| {
+                        vm.new_attribute_error(format!("{component} has no field {field}"))
+                    })?
+                    .field_type;
+                let value = descriptor
+                    .get_field(&scene, self.handle, field.as_str())
+                    .ok_or_else(|| {
+                        vm.new_value_error(format!("Entity has no {component} component"))
+                    })?;
+                (value, field_type)
+            };
+            field_value_to_py(value, field_type, vm)
+        }
+
+        #[pymethod]
+        fn set_field(
+            &self,
+            component: String,
+            field: String,
+            value: PyObjectRef,
+            vm: &VirtualMachine,
+        ) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let field_type = component_registry()
+                .get(component.as_str())
+                .and_then(|descriptor| descriptor.get_field_descriptor(field.as_str()))
+                .map(|field| field.field_type)
+                .ok_or_else(|| {
+                    vm.new_attribute_error(format!("{component} has no field {field}"))
+                })?;
+            let value = py_to_field_value(value, field_type, vm)?;
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(
+                scene,
+                SetComponentField::new(guid, component.as_str(), field.as_str(), value),
+            )
+            .map_err(|err| vm.new_value_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn get_animation_parameter(&self, name: String, vm: &VirtualMachine) -> PyResult<f64> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let entity = dream_ecs::entity::Entity::from_handle(self.handle, scene);
+            let animator: Animator = entity
+                .get_component()
+                .ok_or_else(|| vm.new_value_error("Entity has no Animator component".into()))?;
+            let value = animator
+                .resource_handle
+                .as_ref()
+                .and_then(|resource_handle| resource_handle.upgrade())
+                .and_then(|resource_handle| get_animation_state_machine(&resource_handle))
+                .map_or_else(
+                    || animator.parameters.get(&name).copied().unwrap_or_default(),
+                    |state_machine| state_machine.get_parameter(&animator, &name),
+                );
+            Ok(value as f64)
+        }
+
+        #[pymethod]
+        fn set_animation_parameter(
+            &self,
+            name: String,
+            value: f64,
+            vm: &VirtualMachine,
+        ) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let entity = dream_ecs::entity::Entity::from_handle(self.handle, scene.clone());
+            let mut animator: Animator = entity
+                .get_component()
+                .ok_or_else(|| vm.new_value_error("Entity has no Animator component".into()))?;
+            animator.set_parameter(name, value as f32);
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(scene, SetComponent::new(guid, animator))
+                .map_err(|err| vm.new_runtime_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn get_morph_weight(&self, idx: usize, vm: &VirtualMachine) -> PyResult<f64> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let entity = dream_ecs::entity::Entity::from_handle(self.handle, scene);
+            let morph_weights: MorphWeights = entity
+                .get_component()
+                .ok_or_else(|| vm.new_value_error("Entity has no MorphWeights component".into()))?;
+            Ok(morph_weights.get_weight(idx) as f64)
+        }
+
+        #[pymethod]
+        fn set_morph_weight(&self, idx: usize, value: f64, vm: &VirtualMachine) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let entity = dream_ecs::entity::Entity::from_handle(self.handle, scene.clone());
+            let mut morph_weights: MorphWeights = entity
+                .get_component()
+                .ok_or_else(|| vm.new_value_error("Entity has no MorphWeights component".into()))?;
+            morph_weights.set_weight(idx, value as f32);
+            let guid = get_entity_guid(self.handle, vm)?;
+            Scene::execute(scene, SetComponent::new(guid, morph_weights))
+                .map_err(|err| vm.new_runtime_error(err.to_string()))
+        }
+
+        #[pymethod]
+        fn set_parent(
+            &self,
+            parent_handle: u64,
+            keep_world_transform: bool,
+            vm: &VirtualMachine,
+        ) -> PyResult<()> {
+            let scene = SCENE.lock().unwrap().as_ref().unwrap().clone();
+            let command = ReparentEntity::new(
+                get_entity_guid(self.handle, vm)?,
+                get_entity_guid(parent_handle, vm)?,
+                keep_world_transform,
+            );
+            Scene::execute(scene, command).map_err(|err| vm.new_value_error(err.to_string()))
         }
     }
 
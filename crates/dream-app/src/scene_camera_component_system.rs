@@ -21,7 +21,7 @@ impl System for SceneCameraComponentSystem {
             .expect("Unable to upgrade")
             .lock()
             .expect("Unable to lock")
-            .get_entities_with_component::<dream_ecs::component::SceneCamera>();
+            .get_active_entities_with_component::<dream_ecs::component::SceneCamera>();
         if is_renderer_panel_active() {
             for entity_id in scene_camera_entities {
                 let entity = Entity::from_handle(entity_id, scene.clone());
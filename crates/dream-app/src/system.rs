@@ -22,4 +22,16 @@ use dream_ecs::scene::Scene;
 
 pub trait System {
     fn update(&mut self, dt: f32, scene: Weak<Mutex<Scene>>);
+
+    /// Called before update for every entity of the scene that was destroyed since the last
+    /// update, and for every entity of a scene that is unloaded
+    fn on_entity_destroyed(&mut self, _scene: Weak<Mutex<Scene>>, _entity_id: u64) {}
+
+    /// Whether update is called while the app is not playing, gameplay systems return false
+    fn runs_in_edit_mode(&self) -> bool {
+        true
+    }
+
+    /// Called after play mode started, or after the scenes were restored when it stopped
+    fn on_play_mode_changed(&mut self, _playing: bool) {}
 }
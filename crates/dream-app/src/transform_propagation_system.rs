@@ -0,0 +1,21 @@
+use std::sync::{Mutex, Weak};
+
+use dream_ecs::scene::Scene;
+
+use crate::system::System;
+
+/// Keeps the GlobalTransform components of the scene up to date, should run after any system
+/// that moves entities
+#[derive(Default)]
+pub struct TransformPropagationSystem {}
+
+impl System for TransformPropagationSystem {
+    fn update(&mut self, _dt: f32, scene: Weak<Mutex<Scene>>) {
+        scene
+            .upgrade()
+            .expect("Unable to upgrade")
+            .lock()
+            .expect("Unable to lock")
+            .propagate_transforms();
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use gltf::animation::util::ReadOutputs;
+use once_cell::sync::Lazy;
+use shipyard::EntityId;
+
+use dream_fs::fs::read_binary;
+use dream_math::{Quaternion, UnitQuaternion, Vector3};
+use dream_resource::resource_handle::ResourceHandle;
+
+use crate::component::{AnimationPlayer, GltfNode, Hierarchy, MorphWeights, Transform};
+use crate::scene::{read_gltf_buffers, Scene};
+
+/// How a channel goes from one keyframe to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    CubicSpline,
+}
+
+impl From<gltf::animation::Interpolation> for Interpolation {
+    fn from(interpolation: gltf::animation::Interpolation) -> Self {
+        match interpolation {
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        }
+    }
+}
+
+/// Component of the target entity that a channel writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationProperty {
+    Translation,
+    Rotation,
+    Scale,
+    /// weights of the MorphWeights component
+    Weights,
+}
+
+/// Keyframes of one property of one node
+#[derive(Debug, Clone)]
+pub struct AnimationChannel {
+    /// index of the glTF node the channel animates
+    pub target_node: usize,
+    pub property: AnimationProperty,
+    pub interpolation: Interpolation,
+    /// keyframe times in seconds, in increasing order
+    pub times: Vec<f32>,
+    /// keyframe values one after the other, cubic-spline keyframes are stored as in-tangent,
+    /// value and out-tangent
+    pub values: Vec<f32>,
+    /// number of floats in a value: 3 for translation and scale, 4 for rotation ([i, j, k, w])
+    /// and the number of morph targets for weights
+    pub stride: usize,
+}
+
+const IN_TANGENT: usize = 0;
+const VALUE: usize = 1;
+const OUT_TANGENT: usize = 2;
+
+impl AnimationChannel {
+    /// Value of the channel at a time, times outside of the keyframes are clamped
+    pub fn sample(&self, time: f32) -> Option<Vec<f32>> {
+        let last = self.times.len().checked_sub(1)?;
+        let next = self.times.partition_point(|key_time| *key_time <= time);
+        if next == 0 {
+            return self.keyframe(0, VALUE).map(|value| value.to_vec());
+        }
+        if next > last {
+            return self.keyframe(last, VALUE).map(|value| value.to_vec());
+        }
+        let prev = next - 1;
+        let delta = self.times[next] - self.times[prev];
+        let t = if delta > 0.0 {
+            (time - self.times[prev]) / delta
+        } else {
+            0.0
+        };
+        let p0 = self.keyframe(prev, VALUE)?;
+        let p1 = self.keyframe(next, VALUE)?;
+        let value = match self.interpolation {
+            Interpolation::Step => p0.to_vec(),
+            Interpolation::Linear => mix(self.property, p0, p1, t),
+            Interpolation::CubicSpline => {
+                // hermite spline, tangents are scaled by the keyframe interval
+                let m0 = self.keyframe(prev, OUT_TANGENT)?;
+                let m1 = self.keyframe(next, IN_TANGENT)?;
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+                let h10 = t3 - 2.0 * t2 + t;
+                let h01 = -2.0 * t3 + 3.0 * t2;
+                let h11 = t3 - t2;
+                (0..self.stride)
+                    .map(|i| h00 * p0[i] + h10 * delta * m0[i] + h01 * p1[i] + h11 * delta * m1[i])
+                    .collect()
+            }
+        };
+        Some(value)
+    }
+
+    fn keyframe(&self, keyframe: usize, part: usize) -> Option<&[f32]> {
+        let index = match self.interpolation {
+            Interpolation::CubicSpline => keyframe * 3 + part,
+            _ => keyframe,
+        };
+        self.values
+            .get(index * self.stride..(index + 1) * self.stride)
+    }
+}
+
+fn to_rotation(value: &[f32]) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_quaternion(Quaternion::new(value[3], value[0], value[1], value[2]))
+}
+
+fn from_rotation(rotation: UnitQuaternion<f32>) -> Vec<f32> {
+    vec![rotation.i, rotation.j, rotation.k, rotation.w]
+}
+
+/// Interpolate between two values of a property, rotations take the shortest path
+fn mix(property: AnimationProperty, a: &[f32], b: &[f32], weight: f32) -> Vec<f32> {
+    match property {
+        AnimationProperty::Rotation => {
+            let q1 = to_rotation(b);
+            // opposite rotations have no defined path, the second one is used
+            from_rotation(to_rotation(a).try_slerp(&q1, weight, 1.0e-6).unwrap_or(q1))
+        }
+        _ => a
+            .iter()
+            .zip(b)
+            .map(|(v0, v1)| v0 + (v1 - v0) * weight)
+            .collect(),
+    }
+}
+
+/// Apply the difference between value and reference to base, scaled by weight
+fn add_difference(
+    property: AnimationProperty,
+    base: &[f32],
+    value: &[f32],
+    reference: &[f32],
+    weight: f32,
+) -> Vec<f32> {
+    match property {
+        AnimationProperty::Rotation => {
+            let difference = to_rotation(reference).inverse() * to_rotation(value);
+            from_rotation(to_rotation(base) * difference.powf(weight))
+        }
+        // scales are multiplied, a zero reference has no meaningful ratio
+        AnimationProperty::Scale => base
+            .iter()
+            .zip(value)
+            .zip(reference)
+            .map(|((b, v), r)| {
+                if *r != 0.0 {
+                    b * (1.0 + (v / r - 1.0) * weight)
+                } else {
+                    *b
+                }
+            })
+            .collect(),
+        _ => base
+            .iter()
+            .zip(value)
+            .zip(reference)
+            .map(|((b, v), r)| b + (v - r) * weight)
+            .collect(),
+    }
+}
+
+/// Values of the channels of one or more clips, keyed by target node and property
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimationPose {
+    pub values: HashMap<(usize, AnimationProperty), Vec<f32>>,
+}
+
+impl AnimationPose {
+    /// Pose of a clip at a time
+    pub fn sample(clip: &AnimationClip, time: f32) -> Self {
+        Self {
+            values: clip
+                .channels
+                .iter()
+                .filter_map(|channel| {
+                    channel
+                        .sample(time)
+                        .map(|value| ((channel.target_node, channel.property), value))
+                })
+                .collect(),
+        }
+    }
+
+    /// Move towards another pose, weight 0 keeps this pose and 1 gives the other one. Values
+    /// this pose does not have are taken from the other pose.
+    pub fn blend(&mut self, other: &AnimationPose, weight: f32) {
+        for (key, value) in &other.values {
+            let blended = match self.values.get(key) {
+                Some(current) if current.len() == value.len() => mix(key.1, current, value, weight),
+                _ => value.clone(),
+            };
+            self.values.insert(*key, blended);
+        }
+    }
+
+    /// Add the difference between a pose and a reference pose (usually the first frame of an
+    /// additive clip), scaled by weight. Values this pose does not have start from the reference.
+    pub fn add(&mut self, pose: &AnimationPose, reference: &AnimationPose, weight: f32) {
+        for (key, value) in &pose.values {
+            let Some(reference_value) = reference.values.get(key) else {
+                continue;
+            };
+            let base = self.values.get(key).unwrap_or(reference_value);
+            if base.len() != value.len() || reference_value.len() != value.len() {
+                continue;
+            }
+            let added = add_difference(key.1, base, value, reference_value, weight);
+            self.values.insert(*key, added);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: String,
+    /// time of the last keyframe of all channels
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+/// Find a clip by name, an empty name is the first clip
+pub fn find_clip<'a>(clips: &'a [AnimationClip], name: &str) -> Option<&'a AnimationClip> {
+    if name.is_empty() {
+        clips.first()
+    } else {
+        clips.iter().find(|clip| clip.name == name)
+    }
+}
+
+/// Read the animation clips of a glTF document, unnamed clips are called "Animation <index>"
+pub fn read_gltf_animation_clips(gltf: &gltf::Gltf, buffer_data: &[Vec<u8>]) -> Vec<AnimationClip> {
+    gltf.animations()
+        .map(|animation| {
+            let mut channels = Vec::new();
+            for channel in animation.channels() {
+                let node = channel.target().node();
+                let reader = channel.reader(|buffer| Some(&buffer_data[buffer.index()]));
+                let (Some(inputs), Some(outputs)) = (reader.read_inputs(), reader.read_outputs())
+                else {
+                    continue;
+                };
+                let times: Vec<f32> = inputs.collect();
+                let interpolation = Interpolation::from(channel.sampler().interpolation());
+                let (property, values, stride) = match outputs {
+                    ReadOutputs::Translations(translations) => (
+                        AnimationProperty::Translation,
+                        translations.flatten().collect::<Vec<f32>>(),
+                        3,
+                    ),
+                    ReadOutputs::Rotations(rotations) => (
+                        AnimationProperty::Rotation,
+                        rotations.into_f32().flatten().collect(),
+                        4,
+                    ),
+                    ReadOutputs::Scales(scales) => {
+                        (AnimationProperty::Scale, scales.flatten().collect(), 3)
+                    }
+                    ReadOutputs::MorphTargetWeights(weights) => {
+                        // weights go to the mesh entity created below the node entity
+                        if node.mesh().is_none() {
+                            continue;
+                        }
+                        let values: Vec<f32> = weights.into_f32().collect();
+                        let values_per_keyframe = match interpolation {
+                            Interpolation::CubicSpline => 3,
+                            _ => 1,
+                        };
+                        let stride = values.len() / (times.len() * values_per_keyframe).max(1);
+                        (AnimationProperty::Weights, values, stride)
+                    }
+                };
+                channels.push(AnimationChannel {
+                    target_node: node.index(),
+                    property,
+                    interpolation,
+                    times,
+                    values,
+                    stride,
+                });
+            }
+            AnimationClip {
+                name: animation
+                    .name()
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("Animation {}", animation.index())),
+                duration: channels
+                    .iter()
+                    .filter_map(|channel| channel.times.last().copied())
+                    .fold(0.0, f32::max),
+                channels,
+            }
+        })
+        .collect()
+}
+
+/// Clips of the models used so far, keyed by resource guid
+static ANIMATION_CLIPS: Lazy<Mutex<HashMap<String, Arc<Vec<AnimationClip>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Animation clips of a glTF model, read from the file the first time they are needed. A model
+/// that cannot be read has no clips, it is read again the next time in case the file was fixed.
+pub fn get_animation_clips(resource_handle: &ResourceHandle) -> Arc<Vec<AnimationClip>> {
+    let mut animation_clips = ANIMATION_CLIPS
+        .lock()
+        .expect("Unable to acquire lock on animation clips");
+    if let Some(clips) = animation_clips.get(&resource_handle.key) {
+        return clips.clone();
+    }
+    match load_animation_clips(resource_handle) {
+        Ok(clips) => {
+            let clips = Arc::new(clips);
+            animation_clips.insert(resource_handle.key.clone(), clips.clone());
+            clips
+        }
+        Err(err) => {
+            log::error!(
+                "Unable to load animation clips of {}: {}",
+                resource_handle.get_path().display(),
+                err
+            );
+            Arc::new(Vec::new())
+        }
+    }
+}
+
+/// Forget the clips of a model, e.g. after its file changed, they are read again the next time
+/// they are played
+pub fn invalidate_animation_clips(guid: &str) {
+    ANIMATION_CLIPS
+        .lock()
+        .expect("Unable to acquire lock on animation clips")
+        .remove(guid);
+}
+
+/// Keep clips read while importing a model, so that they are not read again when played
+pub(crate) fn cache_animation_clips(guid: String, clips: Vec<AnimationClip>) {
+    ANIMATION_CLIPS
+        .lock()
+        .expect("Unable to acquire lock on animation clips")
+        .insert(guid, Arc::new(clips));
+}
+
+fn load_animation_clips(resource_handle: &ResourceHandle) -> Result<Vec<AnimationClip>> {
+    let path = resource_handle.get_path();
+    let gltf = gltf::Gltf::from_slice(&read_binary(path.clone(), true)?)
+        .map_err(|err| anyhow!("Unable to parse glTF: {}", err))?;
+    let base_dir = path.parent().unwrap_or(std::path::Path::new(""));
+    let buffer_data = read_gltf_buffers(&gltf, base_dir)?;
+    Ok(read_gltf_animation_clips(&gltf, &buffer_data))
+}
+
+/// Time of a looping clip
+fn loop_time(time: f32, duration: f32) -> f32 {
+    if duration > 0.0 {
+        time.rem_euclid(duration)
+    } else {
+        0.0
+    }
+}
+
+impl Scene {
+    /// Advance every active AnimationPlayer by dt seconds, after the animators chose their clips,
+    /// and write the blended pose into the entities it animates. A player animates the armatures
+    /// of the model it was added with, so each armature gets its own pose before it is drawn.
+    pub fn update_animation_players(&mut self, dt: f32) {
+        self.update_animators();
+        for entity_id in self.get_active_entities_with_component::<AnimationPlayer>() {
+            let Some(mut player) = self.get_component_by_id::<AnimationPlayer>(entity_id) else {
+                continue;
+            };
+            let Some(resource_handle) = player
+                .resource_handle
+                .as_ref()
+                .and_then(|resource_handle| resource_handle.upgrade())
+            else {
+                continue;
+            };
+            let clips = get_animation_clips(&resource_handle);
+            let Some(clip) = find_clip(&clips, &player.clip) else {
+                continue;
+            };
+            // paused players keep their pose, including fades and layers
+            let elapsed = if player.playing { dt } else { 0.0 };
+            player.advance(dt, clip.duration);
+            let mut pose = AnimationPose::sample(clip, player.time);
+
+            // the previous clip keeps playing until it is faded out
+            if let Some(mut fade) = player.fade.take() {
+                if let Some(fade_clip) = find_clip(&clips, &fade.clip) {
+                    fade.elapsed += elapsed;
+                    fade.time = loop_time(fade.time + elapsed * player.speed, fade_clip.duration);
+                    if fade.elapsed < fade.duration {
+                        let mut fade_pose = AnimationPose::sample(fade_clip, fade.time);
+                        fade_pose.blend(&pose, fade.elapsed / fade.duration);
+                        pose = fade_pose;
+                        player.fade = Some(fade);
+                    }
+                }
+            }
+
+            for layer in &mut player.layers {
+                let Some(layer_clip) = find_clip(&clips, &layer.clip) else {
+                    continue;
+                };
+                layer.time = loop_time(layer.time + elapsed * player.speed, layer_clip.duration);
+                let layer_pose = AnimationPose::sample(layer_clip, layer.time);
+                if layer.additive {
+                    let reference = AnimationPose::sample(layer_clip, 0.0);
+                    pose.add(&layer_pose, &reference, layer.weight);
+                } else {
+                    pose.blend(&layer_pose, layer.weight);
+                }
+            }
+
+            self.handle
+                .add_component(EntityId::from_inner(entity_id).unwrap(), player);
+            self.apply_animation_pose(entity_id, &pose);
+        }
+    }
+
+    /// Write the values of a clip at a time into the entities below entity_id
+    pub fn apply_animation_clip(&mut self, entity_id: u64, clip: &AnimationClip, time: f32) {
+        self.apply_animation_pose(entity_id, &AnimationPose::sample(clip, time));
+    }
+
+    /// Write a pose into the entities below entity_id, values whose target cannot be found are
+    /// skipped
+    pub fn apply_animation_pose(&mut self, entity_id: u64, pose: &AnimationPose) {
+        let targets = self.find_animation_targets(entity_id);
+        for ((target_node, property), value) in &pose.values {
+            let Some(&target_id) = targets.get(target_node) else {
+                continue;
+            };
+            let mut transform = self
+                .get_component_by_id::<Transform>(target_id)
+                .unwrap_or_default();
+            match property {
+                AnimationProperty::Translation => {
+                    transform.position = Vector3::new(value[0], value[1], value[2]);
+                }
+                AnimationProperty::Rotation => {
+                    transform.rotation = to_rotation(value);
+                    transform.euler_angles = transform.rotation.euler_angles();
+                }
+                AnimationProperty::Scale => {
+                    transform.scale = Vector3::new(value[0], value[1], value[2]);
+                }
+                AnimationProperty::Weights => {
+                    // weights go to the mesh entity created below the node entity
+                    if let Some(mesh_entity_id) = self.find_morph_weights_child(target_id) {
+                        self.handle.add_component(
+                            EntityId::from_inner(mesh_entity_id).unwrap(),
+                            MorphWeights::new(value.clone()),
+                        );
+                    }
+                    continue;
+                }
+            }
+            self.handle
+                .add_component(EntityId::from_inner(target_id).unwrap(), transform);
+            self.mark_transform_dirty(target_id);
+        }
+    }
+
+    /// Map of <glTF node index: entity created for the node> for the entities from entity_id
+    /// down. Models below that have an AnimationPlayer of their own are left to it.
+    fn find_animation_targets(&self, entity_id: u64) -> HashMap<usize, u64> {
+        let mut targets = HashMap::new();
+        let mut stack = vec![entity_id];
+        while let Some(cur_entity_id) = stack.pop() {
+            if cur_entity_id != entity_id
+                && self
+                    .get_component_by_id::<AnimationPlayer>(cur_entity_id)
+                    .is_some()
+            {
+                continue;
+            }
+            if let Some(gltf_node) = self.get_component_by_id::<GltfNode>(cur_entity_id) {
+                targets
+                    .entry(gltf_node.node_index as usize)
+                    .or_insert(cur_entity_id);
+            }
+            let mut children = self.get_children(cur_entity_id);
+            // children are visited in order, so the first entity of a node wins
+            children.reverse();
+            stack.extend(children);
+        }
+        targets
+    }
+
+    /// First child of an entity with MorphWeights, the mesh entity of a node entity
+    fn find_morph_weights_child(&self, entity_id: u64) -> Option<u64> {
+        self.get_children(entity_id)
+            .into_iter()
+            .find(|&child_id| self.get_component_by_id::<MorphWeights>(child_id).is_some())
+    }
+
+    fn get_children(&self, entity_id: u64) -> Vec<u64> {
+        let mut children = Vec::new();
+        let mut cur_child_id = self
+            .get_component_by_id::<Hierarchy>(entity_id)
+            .and_then(|hierarchy| hierarchy.first_child_runtime_id);
+        while let Some(child_id) = cur_child_id {
+            children.push(child_id);
+            cur_child_id = self
+                .get_component_by_id::<Hierarchy>(child_id)
+                .and_then(|hierarchy| hierarchy.next_sibling_runtime_id);
+        }
+        children
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use shipyard::EntityId;
+
+use dream_fs::fs::read_binary;
+use dream_resource::resource_handle::ResourceHandle;
+
+use crate::animation::{find_clip, get_animation_clips};
+use crate::component::{AnimationLayer, AnimationPlayer, Animator};
+use crate::scene::Scene;
+
+/// How a parameter is compared with the value of a condition
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOp {
+    Greater,
+    Less,
+    Equals,
+    NotEquals,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationCondition {
+    pub parameter: String,
+    pub op: ConditionOp,
+    /// booleans are written as 0 and 1
+    pub value: f32,
+}
+
+impl AnimationCondition {
+    pub fn is_met(&self, parameter_value: f32) -> bool {
+        match self.op {
+            ConditionOp::Greater => parameter_value > self.value,
+            ConditionOp::Less => parameter_value < self.value,
+            ConditionOp::Equals => parameter_value == self.value,
+            ConditionOp::NotEquals => parameter_value != self.value,
+        }
+    }
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationState {
+    pub name: String,
+    /// name of a clip of the model played by the AnimationPlayer
+    pub clip: String,
+    #[serde(default = "default_one")]
+    pub speed: f32,
+    #[serde(default = "default_true")]
+    pub looping: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationTransition {
+    /// state the transition leaves, None to leave any other state
+    #[serde(default)]
+    pub from: Option<String>,
+    pub to: String,
+    /// length of the cross-fade in seconds
+    #[serde(default)]
+    pub duration: f32,
+    /// fraction of the clip of the current state that has to be played before leaving it
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+    /// all conditions have to be met
+    #[serde(default)]
+    pub conditions: Vec<AnimationCondition>,
+}
+
+/// Clip played on top of every state
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationStateMachineLayer {
+    pub clip: String,
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    /// parameter the weight is read from, instead of the weight above
+    #[serde(default)]
+    pub weight_parameter: Option<String>,
+    #[serde(default)]
+    pub additive: bool,
+}
+
+/// Animation state machine asset, a YAML file (e.g. knight.animator) used by Animator components
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationStateMachine {
+    /// parameters and their default values
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f32>,
+    /// state entered first, the first state when None
+    #[serde(default)]
+    pub initial_state: Option<String>,
+    pub states: Vec<AnimationState>,
+    /// transitions are checked in order, the first one that can be taken is taken
+    #[serde(default)]
+    pub transitions: Vec<AnimationTransition>,
+    #[serde(default)]
+    pub layers: Vec<AnimationStateMachineLayer>,
+}
+
+impl AnimationStateMachine {
+    pub fn from_yaml(bytes: &[u8]) -> Result<Self> {
+        let state_machine: AnimationStateMachine = serde_yaml::from_slice(bytes)?;
+        if state_machine.states.is_empty() {
+            return Err(anyhow!("Animation state machine has no states"));
+        }
+        Ok(state_machine)
+    }
+
+    pub fn get_state(&self, name: &str) -> Option<&AnimationState> {
+        self.states.iter().find(|state| state.name == name)
+    }
+
+    pub fn get_initial_state(&self) -> Option<&AnimationState> {
+        match &self.initial_state {
+            Some(initial_state) => self.get_state(initial_state),
+            None => self.states.first(),
+        }
+    }
+
+    /// Value of a parameter for an animator, 0 for unknown parameters
+    pub fn get_parameter(&self, animator: &Animator, name: &str) -> f32 {
+        animator
+            .parameters
+            .get(name)
+            .or_else(|| self.parameters.get(name))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// First transition that can be taken from a state, normalized_time is the fraction of the
+    /// clip of the state that was played
+    pub fn find_transition(
+        &self,
+        animator: &Animator,
+        state: &str,
+        normalized_time: f32,
+    ) -> Option<&AnimationTransition> {
+        self.transitions.iter().find(|transition| {
+            let leaves_state = match &transition.from {
+                Some(from) => from == state,
+                // any state transitions do not restart the state they go to
+                None => transition.to != state,
+            };
+            leaves_state
+                && transition
+                    .exit_time
+                    .map_or(true, |exit_time| normalized_time >= exit_time)
+                && transition.conditions.iter().all(|condition| {
+                    condition.is_met(self.get_parameter(animator, &condition.parameter))
+                })
+        })
+    }
+}
+
+/// State machines used so far, keyed by resource guid, None for files that could not be read
+type StateMachineCache = HashMap<String, Option<Arc<AnimationStateMachine>>>;
+
+static ANIMATION_STATE_MACHINES: Lazy<Mutex<StateMachineCache>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Animation state machine asset, read from the file the first time it is needed. None when the
+/// file cannot be read, the error is only reported once.
+pub fn get_animation_state_machine(
+    resource_handle: &ResourceHandle,
+) -> Option<Arc<AnimationStateMachine>> {
+    let mut state_machines = ANIMATION_STATE_MACHINES
+        .lock()
+        .expect("Unable to acquire lock on animation state machines");
+    state_machines
+        .entry(resource_handle.key.clone())
+        .or_insert_with(|| {
+            read_binary(resource_handle.get_path(), true)
+                .and_then(|bytes| AnimationStateMachine::from_yaml(&bytes))
+                .map(Arc::new)
+                .map_err(|err| {
+                    log::error!(
+                        "Unable to load animation state machine {}: {}",
+                        resource_handle.get_path().display(),
+                        err
+                    );
+                })
+                .ok()
+        })
+        .clone()
+}
+
+impl Scene {
+    /// Take the transitions of every active Animator and update the clip and layers of the
+    /// AnimationPlayer of its entity
+    pub(crate) fn update_animators(&mut self) {
+        for entity_id in self.get_active_entities_with_component::<Animator>() {
+            let (Some(mut animator), Some(mut player)) = (
+                self.get_component_by_id::<Animator>(entity_id),
+                self.get_component_by_id::<AnimationPlayer>(entity_id),
+            ) else {
+                continue;
+            };
+            let Some(state_machine) = animator
+                .resource_handle
+                .as_ref()
+                .and_then(|resource_handle| resource_handle.upgrade())
+                .and_then(|resource_handle| get_animation_state_machine(&resource_handle))
+            else {
+                continue;
+            };
+            let clip_duration = player
+                .resource_handle
+                .as_ref()
+                .and_then(|resource_handle| resource_handle.upgrade())
+                .and_then(|resource_handle| {
+                    find_clip(&get_animation_clips(&resource_handle), &player.clip)
+                        .map(|clip| clip.duration)
+                })
+                .unwrap_or_default();
+            let normalized_time = if clip_duration > 0.0 {
+                player.time / clip_duration
+            } else {
+                1.0
+            };
+
+            let next_state = match state_machine.get_state(&animator.state) {
+                Some(_) => state_machine
+                    .find_transition(&animator, &animator.state, normalized_time)
+                    .and_then(|transition| {
+                        state_machine
+                            .get_state(&transition.to)
+                            .map(|state| (state, transition.duration))
+                    }),
+                // the initial state is entered without a fade
+                None => state_machine.get_initial_state().map(|state| (state, 0.0)),
+            };
+            if let Some((state, fade_duration)) = next_state {
+                animator.state = state.name.clone();
+                player.cross_fade(state.clip.clone(), fade_duration);
+                player.speed = state.speed;
+                player.looping = state.looping;
+                self.handle
+                    .add_component(EntityId::from_inner(entity_id).unwrap(), animator.clone());
+            }
+
+            // layers keep their time as long as their clip stays the same
+            player.layers = state_machine
+                .layers
+                .iter()
+                .enumerate()
+                .map(|(idx, layer)| AnimationLayer {
+                    clip: layer.clip.clone(),
+                    time: player
+                        .layers
+                        .get(idx)
+                        .filter(|player_layer| player_layer.clip == layer.clip)
+                        .map_or(0.0, |player_layer| player_layer.time),
+                    weight: match &layer.weight_parameter {
+                        Some(parameter) => state_machine.get_parameter(&animator, parameter),
+                        None => layer.weight,
+                    },
+                    additive: layer.additive,
+                })
+                .collect();
+            self.handle
+                .add_component(EntityId::from_inner(entity_id).unwrap(), player);
+        }
+    }
+}
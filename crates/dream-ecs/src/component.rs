@@ -1,7 +1,11 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::sync::{Mutex, Weak};
 
-use dream_math::{Matrix4, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use dream_math::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3};
 use dream_resource::resource_handle::ResourceHandle;
 use dream_resource::resource_manager::ResourceManager;
 
@@ -10,15 +14,59 @@ use crate::entity::Entity;
 use crate::scene::Scene;
 
 #[derive(shipyard::Component, Debug, Clone, PartialEq, Default)]
+#[track(All)]
 pub struct SceneCamera {}
 
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CameraProjection {
+    PERSPECTIVE = 0,
+    ORTHOGRAPHIC = 1,
+}
+
+impl Default for CameraProjection {
+    fn default() -> Self {
+        CameraProjection::PERSPECTIVE
+    }
+}
+
+/// Camera placed in a scene, e.g. one imported from a glTF file. It looks down its local -z axis.
+#[derive(shipyard::Component, Debug, Clone, PartialEq)]
+pub struct Camera {
+    pub projection: CameraProjection,
+    /// vertical field of view in radians of a perspective camera
+    pub fovy: f32,
+    /// width divided by height, 0 to use the aspect ratio of the viewport
+    pub aspect_ratio: f32,
+    /// half the width of the view of an orthographic camera
+    pub xmag: f32,
+    /// half the height of the view of an orthographic camera
+    pub ymag: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            projection: CameraProjection::PERSPECTIVE,
+            fovy: std::f32::consts::FRAC_PI_4,
+            aspect_ratio: 0.0,
+            xmag: 10.0,
+            ymag: 10.0,
+            znear: 0.1,
+            zfar: 4000.0,
+        }
+    }
+}
+
 #[derive(shipyard::Component, Debug, Clone, PartialEq)]
+#[track(All)]
 pub struct Transform {
     pub position: Vector3<f32>,
     pub rotation: UnitQuaternion<f32>,
     pub scale: Vector3<f32>,
     // dummy field for UI to keep track of euler angles
-    euler_angles: (f32, f32, f32),
+    pub(crate) euler_angles: (f32, f32, f32),
 }
 
 impl Default for Transform {
@@ -32,6 +80,26 @@ impl Default for Transform {
     }
 }
 
+/// World-space matrix of an entity, kept up to date by Scene::propagate_transforms
+#[derive(shipyard::Component, Debug, Clone, PartialEq)]
+pub struct GlobalTransform {
+    pub matrix: Matrix4<f32>,
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self {
+            matrix: Matrix4::identity(),
+        }
+    }
+}
+
+impl GlobalTransform {
+    pub fn get_position(&self) -> Vector3<f32> {
+        Vector3::new(self.matrix.m14, self.matrix.m24, self.matrix.m34)
+    }
+}
+
 impl Transform {
     pub fn new(position: Vector3<f32>, rotation: UnitQuaternion<f32>, scale: Vector3<f32>) -> Self {
         Self {
@@ -42,6 +110,38 @@ impl Transform {
         }
     }
 
+    /// Decompose an affine matrix into a transform, the matrix must not contain shear
+    pub fn from_matrix(mat: &Matrix4<f32>) -> Self {
+        let position = Vector3::new(mat.m14, mat.m24, mat.m34);
+        let mut x_axis: Vector3<f32> = mat.fixed_view::<3, 1>(0, 0).into_owned();
+        let mut y_axis: Vector3<f32> = mat.fixed_view::<3, 1>(0, 1).into_owned();
+        let mut z_axis: Vector3<f32> = mat.fixed_view::<3, 1>(0, 2).into_owned();
+        let mut scale = Vector3::new(x_axis.norm(), y_axis.norm(), z_axis.norm());
+        // a negative determinant means the matrix mirrors, so flip one of the axes
+        if x_axis.cross(&y_axis).dot(&z_axis) < 0.0 {
+            scale.x = -scale.x;
+        }
+        if scale.x != 0.0 {
+            x_axis /= scale.x;
+        }
+        if scale.y != 0.0 {
+            y_axis /= scale.y;
+        }
+        if scale.z != 0.0 {
+            z_axis /= scale.z;
+        }
+        let rotation_mat =
+            Rotation3::from_matrix(&Matrix3::from_columns(&[x_axis, y_axis, z_axis]));
+        let rotation = UnitQuaternion::from_rotation_matrix(&rotation_mat);
+        Transform::new(position, rotation, scale)
+    }
+
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.position)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+
     pub fn get_euler_angles(&self) -> (f32, f32, f32) {
         (
             self.euler_angles.0,
@@ -65,17 +165,21 @@ impl std::fmt::Display for Transform {
 }
 
 #[derive(shipyard::Component, Debug, Clone, PartialEq)]
+#[track(All)]
 pub struct Bone {
     pub is_root: bool,
     pub node_id: u32,
     pub bone_id: u32,
     pub inverse_bind_pose: Matrix4<f32>,
+    /// runtime id of the root bone entity of the armature this bone belongs to
+    pub armature_root_runtime_id: Option<u64>,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LightType {
     POINT = 0,
     DIRECTIONAL = 1,
+    SPOT = 2,
 }
 
 impl Default for LightType {
@@ -84,13 +188,18 @@ impl Default for LightType {
     }
 }
 
-#[derive(shipyard::Component, Default, Debug, Clone, PartialEq)]
+#[derive(shipyard::Component, Debug, Clone, PartialEq)]
+#[track(All)]
 pub struct Light {
     pub light_type: LightType,
     pub color: Vector3<f32>,
     pub radius: f32,
     pub direction: Vector3<f32>,
     pub cast_shadow: bool,
+    /// angle in radians from the direction at which the light of a spot light starts to fall off
+    pub inner_cone_angle: f32,
+    /// angle in radians from the direction at which the light of a spot light ends
+    pub outer_cone_angle: f32,
 }
 
 impl Light {
@@ -107,17 +216,81 @@ impl Light {
             radius,
             direction,
             cast_shadow,
+            inner_cone_angle: 0.0,
+            outer_cone_angle: std::f32::consts::FRAC_PI_4,
         }
     }
 }
 
+impl Default for Light {
+    fn default() -> Self {
+        Light::new(
+            LightType::default(),
+            Vector3::default(),
+            0.0,
+            Vector3::default(),
+            false,
+        )
+    }
+}
+
 impl std::fmt::Display for Light {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Light({})", self.color)
     }
 }
 
+/// Identifier of an entity that stays the same across runs, unlike its runtime id
+#[derive(shipyard::Component, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Guid(pub String);
+
+impl Guid {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+}
+
+impl std::fmt::Display for Guid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Guid({})", self.0)
+    }
+}
+
+/// Reference to an entity that can be stored in components and saved with the scene, it is
+/// resolved to a runtime id through the guid of the entity when it is used
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EntityRef {
+    pub guid: Option<Guid>,
+}
+
+impl EntityRef {
+    pub fn new(guid: Guid) -> Self {
+        Self { guid: Some(guid) }
+    }
+
+    /// Create a reference to an entity of a scene, empty if the entity has no guid
+    pub fn from_entity(scene: &Scene, entity_id: u64) -> Self {
+        Self {
+            guid: scene.get_entity_guid(entity_id),
+        }
+    }
+
+    /// Runtime id of the referenced entity, None if it is empty or the entity no longer exists
+    pub fn resolve(&self, scene: &Scene) -> Option<u64> {
+        self.guid
+            .as_ref()
+            .and_then(|guid| scene.get_entity_by_guid(guid))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guid.is_none()
+    }
+}
+
 #[derive(shipyard::Component, Default, Debug, Clone, PartialEq)]
+#[track(All)]
 pub struct Tag {
     pub name: String,
 }
@@ -134,8 +307,67 @@ impl std::fmt::Display for Tag {
     }
 }
 
-// TODO: when serializing this, we don't need to create a guid field cuz
-// when deserializing we can create a temporary map that maps <old runtime id: new runtime id>
+/// Whether an entity is drawn and updated by systems, entities without this component are active.
+/// Deactivating an entity also deactivates its descendants.
+#[derive(shipyard::Component, Debug, Clone, PartialEq)]
+#[track(All)]
+pub struct Active {
+    pub active: bool,
+}
+
+impl Active {
+    pub fn new(active: bool) -> Self {
+        Self { active }
+    }
+}
+
+impl Default for Active {
+    fn default() -> Self {
+        Self { active: true }
+    }
+}
+
+/// Bitmask of the render layers an entity is on. On a camera or light entity it is the mask of
+/// layers the camera sees or the light casts shadows from. Entities without this component are
+/// on the default layer, cameras and lights without it see every layer.
+#[derive(shipyard::Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[track(All)]
+pub struct RenderLayers {
+    pub mask: u32,
+}
+
+impl RenderLayers {
+    pub const DEFAULT: u32 = 1;
+    pub const ALL: u32 = u32::MAX;
+
+    pub fn new(mask: u32) -> Self {
+        Self { mask }
+    }
+
+    /// Mask with only one layer set, layers go from 0 to 31
+    pub fn layer(layer: u32) -> Self {
+        Self { mask: 1 << layer }
+    }
+
+    pub fn contains(&self, layer: u32) -> bool {
+        self.mask & (1 << layer) != 0
+    }
+
+    pub fn intersects(&self, other: &RenderLayers) -> bool {
+        self.mask & other.mask != 0
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self {
+            mask: Self::DEFAULT,
+        }
+    }
+}
+
+// when serializing this, ids are written as file-local ids and the loader remaps them through a
+// temporary map of <file id: new runtime id> (see serialization.rs)
 #[derive(shipyard::Component, Default, Debug, Clone, PartialEq)]
 pub struct Hierarchy {
     pub num_children: usize,
@@ -145,6 +377,7 @@ pub struct Hierarchy {
     pub next_sibling_runtime_id: Option<u64>,
 }
 #[derive(shipyard::Component, Debug, Clone, Default)]
+#[track(All)]
 pub struct MeshRenderer {
     pub resource_handle: Option<Weak<ResourceHandle>>,
     pub mesh_idx: Option<usize>,
@@ -188,12 +421,15 @@ impl MeshRenderer {
         Entity::from_handle(entity_handle, scene.clone())
             .add_component(MeshRenderer::new(Some(resource_handle), mesh_idx));
         if create_child_nodes {
-            Scene::add_gltf_scene(scene, entity_handle, resource_manager, guid);
+            if let Err(err) = Scene::add_gltf_scene(scene, entity_handle, resource_manager, guid) {
+                log::error!("Unable to add model to entity: {}", err);
+            }
         }
     }
 }
 
 #[derive(shipyard::Component, Debug, Clone, Default)]
+#[track(All)]
 pub struct PythonScript {
     pub resource_handle: Option<Weak<ResourceHandle>>,
 }
@@ -216,3 +452,256 @@ impl PythonScript {
             .add_component(PythonScript::new(Some(resource_handle)));
     }
 }
+
+/// Plays an animation clip of a glTF model. The clip animates the entities below this one that
+/// were created for the nodes of the model, they are found by their GltfNode (see animation.rs).
+#[derive(shipyard::Component, Debug, Clone)]
+#[track(All)]
+pub struct AnimationPlayer {
+    pub resource_handle: Option<Weak<ResourceHandle>>,
+    /// name of the clip, the first clip of the model is played when empty
+    pub clip: String,
+    /// playback position in seconds
+    pub time: f32,
+    pub speed: f32,
+    pub playing: bool,
+    pub looping: bool,
+    /// previous clip while it is faded out, see cross_fade
+    pub fade: Option<AnimationFade>,
+    /// clips played on top of the main clip, in order
+    pub layers: Vec<AnimationLayer>,
+}
+
+/// Clip that an AnimationPlayer keeps playing while it blends to its new clip
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationFade {
+    pub clip: String,
+    pub time: f32,
+    /// length of the fade in seconds
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+/// Clip blended over the main clip of an AnimationPlayer, layers loop and follow the speed of
+/// the player
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationLayer {
+    pub clip: String,
+    #[serde(default)]
+    pub time: f32,
+    pub weight: f32,
+    /// add the difference between the clip and its first frame instead of replacing the pose
+    #[serde(default)]
+    pub additive: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self {
+            resource_handle: None,
+            clip: String::new(),
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+            looping: true,
+            fade: None,
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl PartialEq for AnimationPlayer {
+    fn eq(&self, other: &Self) -> bool {
+        is_same_resource(&self.resource_handle, &other.resource_handle)
+            && self.clip == other.clip
+            && self.time == other.time
+            && self.speed == other.speed
+            && self.playing == other.playing
+            && self.looping == other.looping
+            && self.fade == other.fade
+            && self.layers == other.layers
+    }
+}
+
+fn is_same_resource(r1: &Option<Weak<ResourceHandle>>, r2: &Option<Weak<ResourceHandle>>) -> bool {
+    match (r1, r2) {
+        (Some(r1), Some(r2)) => r1.ptr_eq(r2),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new(resource_handle: Option<Weak<ResourceHandle>>, clip: String) -> Self {
+        Self {
+            resource_handle,
+            clip,
+            ..Default::default()
+        }
+    }
+
+    /// Play a clip from the beginning
+    pub fn play(&mut self, clip: String) {
+        self.clip = clip;
+        self.time = 0.0;
+        self.playing = true;
+    }
+
+    /// Blend from the current clip to another one over duration seconds, the new clip starts from
+    /// the beginning
+    pub fn cross_fade(&mut self, clip: String, duration: f32) {
+        self.fade = if duration > 0.0 {
+            Some(AnimationFade {
+                clip: std::mem::take(&mut self.clip),
+                time: self.time,
+                duration,
+                elapsed: 0.0,
+            })
+        } else {
+            None
+        };
+        self.play(clip);
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = true;
+    }
+
+    /// Move the playback position by dt scaled by the speed. Looping players wrap around,
+    /// the others stop at the end of the clip (or at its start when the speed is negative).
+    pub fn advance(&mut self, dt: f32, duration: f32) {
+        if !self.playing {
+            return;
+        }
+        if duration <= 0.0 {
+            self.time = 0.0;
+            return;
+        }
+        self.time += dt * self.speed;
+        if self.looping {
+            self.time = self.time.rem_euclid(duration);
+        } else if self.time < 0.0 || self.time > duration {
+            self.time = self.time.clamp(0.0, duration);
+            self.playing = false;
+        }
+    }
+}
+
+/// Drives the AnimationPlayer of the same entity with an animation state machine asset, see
+/// animator.rs
+#[derive(shipyard::Component, Debug, Clone, Default)]
+#[track(All)]
+pub struct Animator {
+    pub resource_handle: Option<Weak<ResourceHandle>>,
+    /// name of the current state, the state machine enters its initial state when empty
+    pub state: String,
+    /// parameters set on this animator, the others keep the default of the state machine
+    pub parameters: BTreeMap<String, f32>,
+}
+
+impl PartialEq for Animator {
+    fn eq(&self, other: &Self) -> bool {
+        is_same_resource(&self.resource_handle, &other.resource_handle)
+            && self.state == other.state
+            && self.parameters == other.parameters
+    }
+}
+
+impl Animator {
+    pub fn new(resource_handle: Option<Weak<ResourceHandle>>) -> Self {
+        Self {
+            resource_handle,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_parameter(&mut self, name: String, value: f32) {
+        self.parameters.insert(name, value);
+    }
+
+    pub fn add_to_entity(
+        scene: Weak<Mutex<Scene>>,
+        entity_handle: u64,
+        resource_manager: &ResourceManager,
+        guid: String,
+    ) {
+        let resource_handle = resource_manager
+            .get_resource(guid)
+            .expect("Resource handle cannot be found");
+        Entity::from_handle(entity_handle, scene)
+            .add_component(Animator::new(Some(resource_handle)));
+    }
+}
+
+/// Node of a glTF model that an imported entity was created for, animation clips find the
+/// entities they animate by it
+#[derive(shipyard::Component, Default, Debug, Clone, PartialEq)]
+pub struct GltfNode {
+    pub node_index: u32,
+}
+
+/// Weights of the morph targets of a mesh, written by the weights channels of animation clips
+/// and by scripts. Added with the default weights of the mesh when a model is imported.
+#[derive(shipyard::Component, Default, Debug, Clone, PartialEq)]
+pub struct MorphWeights {
+    pub weights: Vec<f32>,
+}
+
+impl MorphWeights {
+    pub fn new(weights: Vec<f32>) -> Self {
+        Self { weights }
+    }
+
+    /// Weight of a morph target, 0 for targets without a weight
+    pub fn get_weight(&self, idx: usize) -> f32 {
+        self.weights.get(idx).copied().unwrap_or_default()
+    }
+
+    /// Set the weight of a morph target, the weights grow to fit the target
+    pub fn set_weight(&mut self, idx: usize, weight: f32) {
+        if idx >= self.weights.len() {
+            self.weights.resize(idx + 1, 0.0);
+        }
+        self.weights[idx] = weight;
+    }
+}
+
+/// Override of a single property of an entity in a prefab instance
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PrefabOverride {
+    /// id of the overridden entity inside the prefab file
+    pub prefab_entity_id: u64,
+    /// name of the component as written in the prefab file (e.g. transform)
+    pub component: String,
+    /// name of the overridden property, None when the whole component is overridden
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    /// new value, null when the instance removed the component
+    pub value: serde_yaml::Value,
+}
+
+/// Marks the root entity of an instance of a prefab
+#[derive(shipyard::Component, Debug, Clone, Default)]
+pub struct PrefabInstance {
+    pub resource_handle: Option<Weak<ResourceHandle>>,
+    pub overrides: Vec<PrefabOverride>,
+}
+
+impl PrefabInstance {
+    pub fn new(resource_handle: Option<Weak<ResourceHandle>>) -> Self {
+        Self {
+            resource_handle,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+/// Links an entity of a prefab instance to the entity it was created from in the prefab file
+#[derive(shipyard::Component, Debug, Clone, PartialEq)]
+pub struct PrefabEntity {
+    pub prefab_entity_id: u64,
+}
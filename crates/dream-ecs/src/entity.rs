@@ -38,24 +38,24 @@ impl Entity {
         return self.handle;
     }
 
-    pub fn add_component<T: shipyard::TupleAddComponent>(&self, component: T) {
+    pub fn add_component<T: shipyard::TupleAddComponent + 'static>(&self, component: T) {
         let scene = self.scene.upgrade();
-        scene
-            .expect("Unable to upgrade scene smart pointer for getting component")
-            .lock()
-            .expect("Unable to get mutex lock")
+        let scene = scene.expect("Unable to upgrade scene smart pointer for getting component");
+        let mut scene_mutex_lock = scene.lock().expect("Unable to get mutex lock");
+        scene_mutex_lock
             .handle
             .add_component(EntityId::from_inner(self.handle).unwrap(), component);
+        scene_mutex_lock.on_component_written::<T>(self.handle);
     }
 
-    pub fn remove_component<T: shipyard::TupleRemove>(&self) {
+    pub fn remove_component<T: shipyard::TupleRemove + 'static>(&self) {
         let scene = self.scene.upgrade();
-        scene
-            .expect("Unable to upgrade scene smart pointer for removing component")
-            .lock()
-            .expect("Unable to get mutex lock")
+        let scene = scene.expect("Unable to upgrade scene smart pointer for removing component");
+        let mut scene_mutex_lock = scene.lock().expect("Unable to get mutex lock");
+        scene_mutex_lock
             .handle
             .remove::<T>(EntityId::from_inner(self.handle).unwrap());
+        scene_mutex_lock.on_component_written::<T>(self.handle);
     }
 
     pub fn get_component<T: shipyard::Component + Send + Sync + Clone>(&self) -> Option<T> {
@@ -0,0 +1,101 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use shipyard::{IntoIter, IntoWithId};
+
+use crate::reflect::component_registry;
+use crate::scene::Scene;
+
+/// Entities whose component of one type was added, changed or removed during a frame
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ComponentEvents {
+    pub added: Vec<u64>,
+    pub changed: Vec<u64>,
+    pub removed: Vec<u64>,
+}
+
+impl ComponentEvents {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Reads and resets the shipyard tracking data of one component type
+pub(crate) type EventCollector = fn(&shipyard::World) -> ComponentEvents;
+
+pub(crate) fn collect_tracked_events<T>(world: &shipyard::World) -> ComponentEvents
+where
+    T: shipyard::Component<Tracking = shipyard::track::All> + Send + Sync,
+{
+    world.run(|mut view: shipyard::ViewMut<T>| {
+        let mut events = ComponentEvents::default();
+        for (entity_id, _) in view.inserted().iter().with_id() {
+            events.added.push(entity_id.inner());
+        }
+        for (entity_id, _) in view.modified().iter().with_id() {
+            events.changed.push(entity_id.inner());
+        }
+        for entity_id in view.removed_or_deleted() {
+            events.removed.push(entity_id.inner());
+        }
+        view.clear_all_removed_and_deleted();
+        view.clear_all_inserted_and_modified();
+        events
+    })
+}
+
+impl Scene {
+    /// Record Added, Changed and Removed events for a component type, the component has to be
+    /// declared with #[track(All)]
+    pub fn track_component_events<T>(&mut self)
+    where
+        T: shipyard::Component<Tracking = shipyard::track::All> + Send + Sync,
+    {
+        self.event_collectors
+            .insert(TypeId::of::<T>(), collect_tracked_events::<T>);
+    }
+
+    /// Replace the events of the previous frame with the changes made since, called once per
+    /// frame before the systems are updated
+    pub fn collect_component_events(&mut self) {
+        let mut collectors: HashMap<TypeId, EventCollector> = component_registry()
+            .iter()
+            .filter_map(|component| Some((component.type_id, component.event_collector?)))
+            .collect();
+        collectors.extend(self.event_collectors.iter());
+        let mut component_events = HashMap::new();
+        for (type_id, collector) in &collectors {
+            let events = collector(&self.handle);
+            if !events.is_empty() {
+                component_events.insert(*type_id, events);
+            }
+        }
+        self.component_events = component_events;
+    }
+
+    /// Events of a component type recorded for the current frame
+    pub fn get_component_events<T: 'static>(&self) -> Option<&ComponentEvents> {
+        self.component_events.get(&TypeId::of::<T>())
+    }
+
+    /// Entities that got component T during the last frame
+    pub fn added<T: 'static>(&self) -> &[u64] {
+        self.get_component_events::<T>()
+            .map(|events| events.added.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Entities whose component T was replaced or mutably borrowed during the last frame
+    pub fn changed<T: 'static>(&self) -> &[u64] {
+        self.get_component_events::<T>()
+            .map(|events| events.changed.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Entities that lost component T, or were destroyed, during the last frame
+    pub fn removed<T: 'static>(&self) -> &[u64] {
+        self.get_component_events::<T>()
+            .map(|events| events.removed.as_slice())
+            .unwrap_or_default()
+    }
+}
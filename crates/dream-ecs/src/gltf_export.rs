@@ -0,0 +1,829 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use dream_math::{Matrix4, UnitQuaternion, Vector3};
+
+use crate::component::{
+    Bone, Camera, CameraProjection, Hierarchy, Light, LightType, MeshRenderer, MorphWeights, Tag,
+    Transform,
+};
+use crate::scene::{read_gltf_buffers, Scene};
+
+pub const GLB_FILE_EXTENSION: &str = "glb";
+
+/// Name of the .glb file an entity is exported to. Characters that could lead out of the folder
+/// or that file systems reject are replaced, so the file always ends up next to the others.
+pub fn glb_file_name(entity_name: &str) -> String {
+    let name: String = entity_name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        format!("Entity.{GLB_FILE_EXTENSION}")
+    } else {
+        format!("{name}.{GLB_FILE_EXTENSION}")
+    }
+}
+
+// accessor component types and buffer view targets of the glTF specification
+const FLOAT: u32 = 5126;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Model file a mesh renderer draws from, the meshes and materials are copied out of it
+struct SourceDocument {
+    gltf: gltf::Gltf,
+    buffer_data: Vec<Vec<u8>>,
+    base_dir: PathBuf,
+}
+
+impl SourceDocument {
+    fn read(path: &std::path::Path) -> Result<Self> {
+        let gltf = gltf::Gltf::from_slice(&dream_fs::fs::read_binary(path.to_path_buf(), true)?)
+            .map_err(|err| anyhow!("Unable to parse glTF {}: {}", path.display(), err))?;
+        let base_dir = path
+            .parent()
+            .unwrap_or(std::path::Path::new(""))
+            .to_path_buf();
+        let buffer_data = read_gltf_buffers(&gltf, &base_dir)?;
+        Ok(Self {
+            gltf,
+            buffer_data,
+            base_dir,
+        })
+    }
+}
+
+/// glTF document that is being written, all buffer views point into a single binary chunk
+#[derive(Default)]
+struct GltfWriter {
+    bin: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    nodes: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<Value>,
+    textures: Vec<Value>,
+    samplers: Vec<Value>,
+    images: Vec<Value>,
+    skins: Vec<Value>,
+    cameras: Vec<Value>,
+    lights: Vec<Value>,
+    /// node written for each entity
+    entity_nodes: HashMap<u64, usize>,
+    /// nodes drawing a skinned mesh, with the runtime id of the root bone of their armature
+    skinned_nodes: Vec<(usize, u64)>,
+    /// primitives written for a mesh of a source document, with or without skinning attributes
+    primitives: HashMap<(String, usize, bool), Vec<Value>>,
+    /// materials and images written for a source document
+    written_materials: HashMap<(String, usize), usize>,
+    written_images: HashMap<(String, usize), usize>,
+}
+
+impl GltfWriter {
+    fn push_buffer_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // every view starts at a multiple of 4 so accessors of any component type are aligned
+        self.bin.resize(align_to_4(self.bin.len()), 0);
+        let mut buffer_view = json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            buffer_view["target"] = json!(target);
+        }
+        self.bin.extend_from_slice(bytes);
+        self.buffer_views.push(buffer_view);
+        self.buffer_views.len() - 1
+    }
+
+    /// Write float data with `N` components per element, positions need their bounds
+    fn push_f32_accessor<const N: usize>(
+        &mut self,
+        values: &[[f32; N]],
+        accessor_type: &str,
+        target: Option<u32>,
+        with_bounds: bool,
+    ) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let buffer_view = self.push_buffer_view(&bytes, target);
+        let mut accessor = json!({
+            "bufferView": buffer_view,
+            "componentType": FLOAT,
+            "count": values.len(),
+            "type": accessor_type,
+        });
+        if with_bounds {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for value in values {
+                for i in 0..N {
+                    min[i] = min[i].min(value[i]);
+                    max[i] = max[i].max(value[i]);
+                }
+            }
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_joints_accessor(&mut self, joints: &[[u16; 4]]) -> usize {
+        let bytes: Vec<u8> = joints
+            .iter()
+            .flatten()
+            .flat_map(|joint| joint.to_le_bytes())
+            .collect();
+        let buffer_view = self.push_buffer_view(&bytes, Some(ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": buffer_view,
+            "componentType": UNSIGNED_SHORT,
+            "count": joints.len(),
+            "type": "VEC4",
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn push_indices_accessor(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|idx| idx.to_le_bytes()).collect();
+        let buffer_view = self.push_buffer_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": buffer_view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// Write a node for an entity and its descendants, returns the index of the node. The
+    /// armature is the root bone posing the meshes of the entity, found the same way as when
+    /// the scene is drawn.
+    fn write_entity(
+        &mut self,
+        scene: &Scene,
+        sources: &mut HashMap<String, SourceDocument>,
+        entity_id: u64,
+        armature: Option<u64>,
+    ) -> Result<usize> {
+        let name = scene
+            .get_component_by_id::<Tag>(entity_id)
+            .map(|tag| tag.name)
+            .unwrap_or_default();
+        let transform = scene
+            .get_component_by_id::<Transform>(entity_id)
+            .unwrap_or_default();
+        let rotation = transform.rotation.quaternion();
+        let mut node = json!({
+            "name": name,
+            "translation": [transform.position.x, transform.position.y, transform.position.z],
+            "rotation": [rotation.i, rotation.j, rotation.k, rotation.w],
+            "scale": [transform.scale.x, transform.scale.y, transform.scale.z],
+        });
+
+        // reserve the index of this node, the mesh and children are written first
+        let node_idx = self.nodes.len();
+        self.nodes.push(Value::Null);
+        self.entity_nodes.insert(entity_id, node_idx);
+
+        let children = get_child_ids(scene, entity_id);
+        let children_armature = children
+            .iter()
+            .copied()
+            .find(|child_id| {
+                scene
+                    .get_component_by_id::<Bone>(*child_id)
+                    .is_some_and(|bone| bone.is_root)
+            })
+            .or(armature);
+
+        // a node has a single mesh, an entity drawing its own mesh keeps it, otherwise the mesh
+        // of a child entity that only draws a mesh is merged into the node, which is how such
+        // entities are created when a model is imported
+        let (mesh_entity_id, mesh_armature) = if get_mesh(scene, entity_id).is_some() {
+            (Some(entity_id), armature)
+        } else {
+            let mesh_child = children
+                .iter()
+                .copied()
+                .find(|child_id| is_mesh_only_entity(scene, *child_id));
+            (mesh_child, children_armature)
+        };
+        if let Some(mesh_entity_id) = mesh_entity_id {
+            let (mesh, skinned) = self.write_mesh(scene, sources, mesh_entity_id, mesh_armature)?;
+            node["mesh"] = json!(mesh);
+            if let (true, Some(mesh_armature)) = (skinned, mesh_armature) {
+                self.skinned_nodes.push((node_idx, mesh_armature));
+            }
+        }
+
+        if let Some(camera) = scene.get_component_by_id::<Camera>(entity_id) {
+            node["camera"] = json!(self.cameras.len());
+            self.cameras.push(camera_to_json(&camera));
+        }
+        if let Some(light) = scene.get_component_by_id::<Light>(entity_id) {
+            node["extensions"] = json!({ "KHR_lights_punctual": { "light": self.lights.len() } });
+            self.lights.push(light_to_json(&light));
+        }
+
+        let mut child_nodes = Vec::new();
+        for child_id in children {
+            if Some(child_id) != mesh_entity_id {
+                child_nodes.push(self.write_entity(scene, sources, child_id, children_armature)?);
+            }
+        }
+        if !child_nodes.is_empty() {
+            node["children"] = json!(child_nodes);
+        }
+        self.nodes[node_idx] = node;
+        Ok(node_idx)
+    }
+
+    /// Write the mesh drawn by a mesh renderer, returns the index of the mesh and whether it is
+    /// skinned by the armature
+    fn write_mesh(
+        &mut self,
+        scene: &Scene,
+        sources: &mut HashMap<String, SourceDocument>,
+        entity_id: u64,
+        armature: Option<u64>,
+    ) -> Result<(usize, bool)> {
+        let (resource_key, resource_path, mesh_idx) =
+            get_mesh(scene, entity_id).ok_or_else(|| anyhow!("Entity has no mesh"))?;
+        if !sources.contains_key(&resource_key) {
+            sources.insert(resource_key.clone(), SourceDocument::read(&resource_path)?);
+        }
+        let source = &sources[&resource_key];
+        let mesh = source
+            .gltf
+            .meshes()
+            .nth(mesh_idx)
+            .ok_or_else(|| anyhow!("No mesh {} in {}", mesh_idx, resource_path.display()))?;
+        // skinning attributes are dropped when there is no armature to make a skin from
+        let skinned = armature.is_some()
+            && mesh
+                .primitives()
+                .any(|primitive| primitive.get(&gltf::Semantic::Joints(0)).is_some());
+
+        let primitives_key = (resource_key.clone(), mesh_idx, skinned);
+        if !self.primitives.contains_key(&primitives_key) {
+            let mut primitives = Vec::new();
+            for primitive in mesh.primitives() {
+                primitives.push(self.write_primitive(source, &resource_key, primitive, skinned)?);
+            }
+            self.primitives.insert(primitives_key.clone(), primitives);
+        }
+
+        let name = scene
+            .get_component_by_id::<Tag>(entity_id)
+            .map(|tag| tag.name)
+            .unwrap_or_default();
+        let mut mesh_json = json!({
+            "name": name,
+            "primitives": self.primitives[&primitives_key],
+        });
+        let num_morph_targets = mesh
+            .primitives()
+            .map(|primitive| primitive.morph_targets().len())
+            .max()
+            .unwrap_or_default();
+        if num_morph_targets > 0 {
+            let mut weights = scene
+                .get_component_by_id::<MorphWeights>(entity_id)
+                .map(|morph_weights| morph_weights.weights)
+                .or_else(|| mesh.weights().map(|weights| weights.to_vec()))
+                .unwrap_or_default();
+            weights.resize(num_morph_targets, 0.0);
+            mesh_json["weights"] = json!(weights);
+        }
+        self.meshes.push(mesh_json);
+        Ok((self.meshes.len() - 1, skinned))
+    }
+
+    fn write_primitive(
+        &mut self,
+        source: &SourceDocument,
+        resource_key: &str,
+        primitive: gltf::Primitive,
+        skinned: bool,
+    ) -> Result<Value> {
+        let reader =
+            primitive.reader(|buffer| source.buffer_data.get(buffer.index()).map(Vec::as_slice));
+        let positions: Vec<[f32; 3]> = reader
+            .read_positions()
+            .ok_or_else(|| anyhow!("Primitive {} has no positions", primitive.index()))?
+            .collect();
+        let mut attributes = json!({
+            "POSITION": self.push_f32_accessor(&positions, "VEC3", Some(ARRAY_BUFFER), true),
+        });
+        if let Some(normals) = reader.read_normals() {
+            let normals: Vec<[f32; 3]> = normals.collect();
+            attributes["NORMAL"] =
+                json!(self.push_f32_accessor(&normals, "VEC3", Some(ARRAY_BUFFER), false));
+        }
+        if let Some(tangents) = reader.read_tangents() {
+            let tangents: Vec<[f32; 4]> = tangents.collect();
+            attributes["TANGENT"] =
+                json!(self.push_f32_accessor(&tangents, "VEC4", Some(ARRAY_BUFFER), false));
+        }
+        if let Some(tex_coords) = reader.read_tex_coords(0) {
+            let tex_coords: Vec<[f32; 2]> = tex_coords.into_f32().collect();
+            attributes["TEXCOORD_0"] =
+                json!(self.push_f32_accessor(&tex_coords, "VEC2", Some(ARRAY_BUFFER), false));
+        }
+        if skinned {
+            if let (Some(joints), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
+                let joints: Vec<[u16; 4]> = joints.into_u16().collect();
+                // weights are normalized the same way as when the mesh is drawn
+                let weights: Vec<[f32; 4]> = weights
+                    .into_f32()
+                    .map(|weight| {
+                        let w_sum: f32 = weight.iter().sum();
+                        if w_sum > 0.0 {
+                            weight.map(|w| w / w_sum)
+                        } else {
+                            weight
+                        }
+                    })
+                    .collect();
+                attributes["JOINTS_0"] = json!(self.push_joints_accessor(&joints));
+                attributes["WEIGHTS_0"] =
+                    json!(self.push_f32_accessor(&weights, "VEC4", Some(ARRAY_BUFFER), false));
+            }
+        }
+
+        let mut primitive_json = json!({
+            "attributes": attributes,
+            "mode": primitive.mode().as_gl_enum(),
+        });
+        if let Some(indices) = reader.read_indices() {
+            let indices: Vec<u32> = indices.into_u32().collect();
+            primitive_json["indices"] = json!(self.push_indices_accessor(&indices));
+        }
+        let mut targets = Vec::new();
+        for (positions, normals, tangents) in reader.read_morph_targets() {
+            let mut target = json!({});
+            if let Some(positions) = positions {
+                let positions: Vec<[f32; 3]> = positions.collect();
+                target["POSITION"] =
+                    json!(self.push_f32_accessor(&positions, "VEC3", Some(ARRAY_BUFFER), true));
+            }
+            if let Some(normals) = normals {
+                let normals: Vec<[f32; 3]> = normals.collect();
+                target["NORMAL"] =
+                    json!(self.push_f32_accessor(&normals, "VEC3", Some(ARRAY_BUFFER), false));
+            }
+            if let Some(tangents) = tangents {
+                let tangents: Vec<[f32; 3]> = tangents.collect();
+                target["TANGENT"] =
+                    json!(self.push_f32_accessor(&tangents, "VEC3", Some(ARRAY_BUFFER), false));
+            }
+            targets.push(target);
+        }
+        if !targets.is_empty() {
+            primitive_json["targets"] = json!(targets);
+        }
+        if let Some(material_idx) = primitive.material().index() {
+            primitive_json["material"] =
+                json!(self.write_material(source, resource_key, material_idx)?);
+        }
+        Ok(primitive_json)
+    }
+
+    /// Write the factors and textures the renderer builds a material from
+    fn write_material(
+        &mut self,
+        source: &SourceDocument,
+        resource_key: &str,
+        material_idx: usize,
+    ) -> Result<usize> {
+        let material_key = (String::from(resource_key), material_idx);
+        if let Some(material) = self.written_materials.get(&material_key) {
+            return Ok(*material);
+        }
+        let material = source
+            .gltf
+            .materials()
+            .nth(material_idx)
+            .ok_or_else(|| anyhow!("No material {}", material_idx))?;
+        let pbr_properties = material.pbr_metallic_roughness();
+        let mut pbr_json = json!({
+            "baseColorFactor": pbr_properties.base_color_factor(),
+            "metallicFactor": pbr_properties.metallic_factor(),
+            "roughnessFactor": pbr_properties.roughness_factor(),
+        });
+        if let Some(texture_info) = pbr_properties.base_color_texture() {
+            let texture = self.write_texture(source, resource_key, texture_info.texture())?;
+            pbr_json["baseColorTexture"] = json!({ "index": texture });
+        }
+        if let Some(texture_info) = pbr_properties.metallic_roughness_texture() {
+            let texture = self.write_texture(source, resource_key, texture_info.texture())?;
+            pbr_json["metallicRoughnessTexture"] = json!({ "index": texture });
+        }
+        let alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => "OPAQUE",
+            gltf::material::AlphaMode::Mask => "MASK",
+            gltf::material::AlphaMode::Blend => "BLEND",
+        };
+        let mut material_json = json!({
+            "pbrMetallicRoughness": pbr_json,
+            "emissiveFactor": material.emissive_factor(),
+            "alphaMode": alpha_mode,
+            "doubleSided": material.double_sided(),
+        });
+        if let Some(name) = material.name() {
+            material_json["name"] = json!(name);
+        }
+        if let (gltf::material::AlphaMode::Mask, Some(alpha_cutoff)) =
+            (material.alpha_mode(), material.alpha_cutoff())
+        {
+            material_json["alphaCutoff"] = json!(alpha_cutoff);
+        }
+        if let Some(normal_texture) = material.normal_texture() {
+            let texture = self.write_texture(source, resource_key, normal_texture.texture())?;
+            material_json["normalTexture"] =
+                json!({ "index": texture, "scale": normal_texture.scale() });
+        }
+        if let Some(occlusion_texture) = material.occlusion_texture() {
+            let texture = self.write_texture(source, resource_key, occlusion_texture.texture())?;
+            material_json["occlusionTexture"] =
+                json!({ "index": texture, "strength": occlusion_texture.strength() });
+        }
+        if let Some(texture_info) = material.emissive_texture() {
+            let texture = self.write_texture(source, resource_key, texture_info.texture())?;
+            material_json["emissiveTexture"] = json!({ "index": texture });
+        }
+        self.materials.push(material_json);
+        self.written_materials
+            .insert(material_key, self.materials.len() - 1);
+        Ok(self.materials.len() - 1)
+    }
+
+    /// Write a texture with its own sampler, images are embedded in the binary chunk
+    fn write_texture(
+        &mut self,
+        source: &SourceDocument,
+        resource_key: &str,
+        texture: gltf::Texture,
+    ) -> Result<usize> {
+        let image = texture.source();
+        let image_key = (String::from(resource_key), image.index());
+        let image_idx = match self.written_images.get(&image_key) {
+            Some(image_idx) => *image_idx,
+            None => {
+                let (bytes, mime_type) = match image.source() {
+                    gltf::image::Source::View { view, mime_type } => {
+                        let begin = view.offset();
+                        let end = begin + view.length();
+                        let bytes = source
+                            .buffer_data
+                            .get(view.buffer().index())
+                            .and_then(|buffer| buffer.get(begin..end))
+                            .ok_or_else(|| {
+                                anyhow!("Image {} is outside of its buffer", image.index())
+                            })?;
+                        (bytes.to_vec(), Some(String::from(mime_type)))
+                    }
+                    gltf::image::Source::Uri { uri, mime_type } => {
+                        let (bytes, uri_mime_type) = dream_fs::fs::read_uri(uri, &source.base_dir)?;
+                        (bytes, uri_mime_type.or(mime_type.map(String::from)))
+                    }
+                };
+                // images in buffer views need a mime type, external images may not declare one
+                let mime_type = mime_type
+                    .or_else(|| guess_image_mime_type(&bytes).map(String::from))
+                    .ok_or_else(|| anyhow!("Unknown format of image {}", image.index()))?;
+                let buffer_view = self.push_buffer_view(&bytes, None);
+                let mut image_json = json!({ "bufferView": buffer_view, "mimeType": mime_type });
+                if let Some(name) = image.name() {
+                    image_json["name"] = json!(name);
+                }
+                self.images.push(image_json);
+                self.written_images.insert(image_key, self.images.len() - 1);
+                self.images.len() - 1
+            }
+        };
+
+        let sampler = texture.sampler();
+        let mut sampler_json = json!({
+            "wrapS": sampler.wrap_s().as_gl_enum(),
+            "wrapT": sampler.wrap_t().as_gl_enum(),
+        });
+        if let Some(mag_filter) = sampler.mag_filter() {
+            sampler_json["magFilter"] = json!(mag_filter.as_gl_enum());
+        }
+        if let Some(min_filter) = sampler.min_filter() {
+            sampler_json["minFilter"] = json!(min_filter.as_gl_enum());
+        }
+        self.samplers.push(sampler_json);
+        self.textures.push(json!({
+            "sampler": self.samplers.len() - 1,
+            "source": image_idx,
+        }));
+        Ok(self.textures.len() - 1)
+    }
+
+    /// Write a skin for every armature whose root bone was exported, joints are ordered by the
+    /// bone id the vertices of skinned meshes refer to
+    fn write_skins(&mut self, scene: &Scene) -> Result<()> {
+        let mut armature_skins = HashMap::new();
+        let mut exported_entities: Vec<u64> = self.entity_nodes.keys().copied().collect();
+        exported_entities.sort_by_key(|entity_id| self.entity_nodes[entity_id]);
+        for entity_id in &exported_entities {
+            let Some(bone) = scene.get_component_by_id::<Bone>(*entity_id) else {
+                continue;
+            };
+            if !bone.is_root {
+                continue;
+            }
+            let mut joints: Vec<(u64, Bone)> = exported_entities
+                .iter()
+                .filter_map(|joint_id| {
+                    scene
+                        .get_component_by_id::<Bone>(*joint_id)
+                        .filter(|joint| joint.armature_root_runtime_id == Some(*entity_id))
+                        .map(|joint| (*joint_id, joint))
+                })
+                .collect();
+            // bones that are not joints of the skin share an id with a joint, the joint is the
+            // one with an inverse bind pose
+            joints.sort_by_key(|(_, joint)| {
+                (
+                    joint.bone_id,
+                    joint.inverse_bind_pose == Matrix4::identity(),
+                )
+            });
+            joints.dedup_by_key(|(_, joint)| joint.bone_id);
+            if joints
+                .iter()
+                .enumerate()
+                .any(|(idx, (_, joint))| joint.bone_id as usize != idx)
+            {
+                log::warn!(
+                    "Bone ids of armature {} are not contiguous, skinned meshes may be posed wrong",
+                    entity_id
+                );
+            }
+            let inverse_bind_poses: Vec<[f32; 16]> = joints
+                .iter()
+                .map(|(_, joint)| {
+                    let mut inverse_bind_pose = [0.0; 16];
+                    inverse_bind_pose.copy_from_slice(joint.inverse_bind_pose.as_slice());
+                    inverse_bind_pose
+                })
+                .collect();
+            let inverse_bind_matrices =
+                self.push_f32_accessor(&inverse_bind_poses, "MAT4", None, false);
+            let joint_nodes: Vec<usize> = joints
+                .iter()
+                .map(|(joint_id, _)| self.entity_nodes[joint_id])
+                .collect();
+            self.skins.push(json!({
+                "inverseBindMatrices": inverse_bind_matrices,
+                "skeleton": self.entity_nodes[entity_id],
+                "joints": joint_nodes,
+            }));
+            armature_skins.insert(*entity_id, self.skins.len() - 1);
+        }
+        for (node_idx, armature) in &self.skinned_nodes {
+            let skin = armature_skins.get(armature).ok_or_else(|| {
+                anyhow!("Armature {} of a skinned mesh was not exported", armature)
+            })?;
+            self.nodes[*node_idx]["skin"] = json!(skin);
+        }
+        Ok(())
+    }
+
+    /// Assemble the .glb file, a JSON chunk followed by the binary chunk
+    fn into_glb(self, root_node: usize) -> Result<Vec<u8>> {
+        let mut root = json!({
+            "asset": { "version": "2.0", "generator": "Dream" },
+            "scene": 0,
+            "scenes": [{ "nodes": [root_node] }],
+        });
+        // empty arrays are not allowed by the specification
+        for (key, values) in [
+            ("nodes", self.nodes),
+            ("meshes", self.meshes),
+            ("materials", self.materials),
+            ("textures", self.textures),
+            ("samplers", self.samplers),
+            ("images", self.images),
+            ("skins", self.skins),
+            ("cameras", self.cameras),
+            ("accessors", self.accessors),
+            ("bufferViews", self.buffer_views),
+        ] {
+            if !values.is_empty() {
+                root[key] = Value::Array(values);
+            }
+        }
+        if !self.lights.is_empty() {
+            root["extensionsUsed"] = json!(["KHR_lights_punctual"]);
+            root["extensions"] = json!({ "KHR_lights_punctual": { "lights": self.lights } });
+        }
+        let mut bin = self.bin;
+        if !bin.is_empty() {
+            root["buffers"] = json!([{ "byteLength": bin.len() }]);
+        }
+
+        let mut json_chunk = serde_json::to_vec(&root)
+            .map_err(|err| anyhow!("Unable to serialize glTF: {}", err))?;
+        // chunks are padded to 4 bytes, with spaces for json and zeros for binary data
+        json_chunk.resize(align_to_4(json_chunk.len()), b' ');
+        bin.resize(align_to_4(bin.len()), 0);
+        let mut length = 12 + 8 + json_chunk.len();
+        if !bin.is_empty() {
+            length += 8 + bin.len();
+        }
+
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json_chunk);
+        if !bin.is_empty() {
+            glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+            glb.extend_from_slice(b"BIN\0");
+            glb.extend_from_slice(&bin);
+        }
+        Ok(glb)
+    }
+}
+
+fn align_to_4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn get_child_ids(scene: &Scene, entity_id: u64) -> Vec<u64> {
+    let mut children = Vec::new();
+    let mut cur_child = scene
+        .get_component_by_id::<Hierarchy>(entity_id)
+        .and_then(|hierarchy| hierarchy.first_child_runtime_id);
+    while let Some(child_id) = cur_child {
+        children.push(child_id);
+        cur_child = scene
+            .get_component_by_id::<Hierarchy>(child_id)
+            .and_then(|hierarchy| hierarchy.next_sibling_runtime_id);
+    }
+    children
+}
+
+/// Resource key, path and mesh index of the mesh drawn by an entity, entities a whole model
+/// was added to have a mesh renderer without a mesh
+fn get_mesh(scene: &Scene, entity_id: u64) -> Option<(String, PathBuf, usize)> {
+    let mesh_renderer = scene.get_component_by_id::<MeshRenderer>(entity_id)?;
+    let mesh_idx = mesh_renderer.mesh_idx?;
+    let resource_handle = mesh_renderer.resource_handle?.upgrade()?;
+    Some((
+        resource_handle.key.clone(),
+        resource_handle.get_path(),
+        mesh_idx,
+    ))
+}
+
+/// Whether an entity only draws a mesh, like the entities created for the meshes of a model
+fn is_mesh_only_entity(scene: &Scene, entity_id: u64) -> bool {
+    get_mesh(scene, entity_id).is_some()
+        && get_child_ids(scene, entity_id).is_empty()
+        && scene
+            .get_component_by_id::<Transform>(entity_id)
+            .map_or(true, |transform| {
+                transform.position == Vector3::zeros()
+                    && transform.rotation == UnitQuaternion::identity()
+                    && transform.scale == Vector3::new(1.0, 1.0, 1.0)
+            })
+        && scene.get_component_by_id::<Camera>(entity_id).is_none()
+        && scene.get_component_by_id::<Light>(entity_id).is_none()
+        && scene.get_component_by_id::<Bone>(entity_id).is_none()
+}
+
+fn camera_to_json(camera: &Camera) -> Value {
+    match camera.projection {
+        CameraProjection::PERSPECTIVE => {
+            let mut perspective = json!({
+                "yfov": camera.fovy,
+                "znear": camera.znear,
+                "zfar": camera.zfar,
+            });
+            // an aspect ratio of 0 follows the viewport, which is the default of glTF
+            if camera.aspect_ratio > 0.0 {
+                perspective["aspectRatio"] = json!(camera.aspect_ratio);
+            }
+            json!({ "type": "perspective", "perspective": perspective })
+        }
+        CameraProjection::ORTHOGRAPHIC => json!({
+            "type": "orthographic",
+            "orthographic": {
+                "xmag": camera.xmag,
+                "ymag": camera.ymag,
+                "znear": camera.znear,
+                "zfar": camera.zfar,
+            },
+        }),
+    }
+}
+
+/// KHR_lights_punctual light, the inverse of the conversion done when lights are imported. The
+/// light shines down the -z axis of its node.
+fn light_to_json(light: &Light) -> Value {
+    // glTF colors are in 0..1, the brightness of the light goes into the intensity
+    let brightness = light.color.max();
+    let color = if brightness > 0.0 {
+        light.color / brightness
+    } else {
+        light.color
+    };
+    let (light_type, intensity) = match light.light_type {
+        LightType::DIRECTIONAL => ("directional", brightness),
+        LightType::POINT => ("point", kilowatts_to_candela(brightness)),
+        LightType::SPOT => ("spot", kilowatts_to_candela(brightness)),
+    };
+    let mut light_json = json!({
+        "type": light_type,
+        "color": [color.x, color.y, color.z],
+        "intensity": intensity.max(0.0),
+    });
+    if light.light_type == LightType::SPOT {
+        let outer_cone_angle = light
+            .outer_cone_angle
+            .clamp(f32::EPSILON, std::f32::consts::FRAC_PI_2);
+        let inner_cone_angle = light
+            .inner_cone_angle
+            .clamp(0.0, outer_cone_angle - f32::EPSILON);
+        light_json["spot"] = json!({
+            "innerConeAngle": inner_cone_angle,
+            "outerConeAngle": outer_cone_angle,
+        });
+    }
+    light_json
+}
+
+fn kilowatts_to_candela(kilowatts: f32) -> f32 {
+    kilowatts * 683.0 / (4.0 * std::f32::consts::PI)
+}
+
+/// Mime type of a png or jpeg image from its signature
+fn guess_image_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+impl Scene {
+    /// Write an entity and its descendants to a binary glTF, with their transforms, meshes,
+    /// materials, armatures, cameras and lights. The entity becomes the root node, adding the
+    /// file to an entity recreates the descendants below it.
+    pub fn subtree_to_glb(&self, entity_id: u64) -> Result<Vec<u8>> {
+        if !self.is_alive(entity_id) {
+            return Err(anyhow!("Entity {} does not exist", entity_id));
+        }
+        let mut writer = GltfWriter::default();
+        let mut sources = HashMap::new();
+        // the armature of the entity is the root bone among its siblings
+        let armature = self
+            .get_component_by_id::<Hierarchy>(entity_id)
+            .and_then(|hierarchy| hierarchy.parent_runtime_id)
+            .and_then(|parent_id| {
+                get_child_ids(self, parent_id)
+                    .into_iter()
+                    .find(|sibling_id| {
+                        self.get_component_by_id::<Bone>(*sibling_id)
+                            .is_some_and(|bone| bone.is_root)
+                    })
+            });
+        let root_node = writer.write_entity(self, &mut sources, entity_id, armature)?;
+        writer.write_skins(self)?;
+        writer.into_glb(root_node)
+    }
+
+    /// Export an entity and its descendants as a .glb file, see subtree_to_glb
+    pub fn export_glb(&self, entity_id: u64, path: PathBuf) -> Result<()> {
+        let glb = self.subtree_to_glb(entity_id)?;
+        log::debug!("Exporting glTF to {}", path.to_str().unwrap_or("none"));
+        dream_fs::fs::write_binary(path, glb)
+    }
+}
@@ -0,0 +1,801 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, Weak};
+
+use anyhow::{anyhow, Result};
+use shipyard::EntityId;
+
+use crate::component::{
+    Bone, Guid, Hierarchy, MorphWeights, PrefabEntity, PrefabInstance, Transform,
+};
+use crate::entity::Entity;
+use crate::reflect::{component_registry, ComponentDescriptor, ComponentValue, FieldValue};
+use crate::scene::Scene;
+
+/// Number of commands that can be undone, older commands are dropped
+pub const MAX_UNDO_DEPTH: usize = 256;
+
+/// A reversible change to a scene. Entities are referred to by guid, since a destroyed entity
+/// that is brought back by undo gets a new runtime id.
+pub trait Command: Send {
+    /// Short description of the change (e.g. "Set Transform")
+    fn name(&self) -> String;
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()>;
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()>;
+}
+
+fn resolve_entity(scene: &Weak<Mutex<Scene>>, guid: &Guid) -> Result<u64> {
+    scene
+        .upgrade()
+        .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for command"))?
+        .lock()
+        .map_err(|_| anyhow!("Unable to acquire scene mutex for command"))?
+        .get_entity_by_guid(guid)
+        .ok_or_else(|| anyhow!("No entity with guid {}", guid))
+}
+
+fn short_type_name<T>() -> &'static str {
+    let type_name = std::any::type_name::<T>();
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+/// Commands that are applied and reverted as one, e.g. all changes of a drag gesture
+pub struct CommandGroup {
+    pub name: String,
+    pub commands: Vec<Box<dyn Command>>,
+}
+
+impl CommandGroup {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl Command for CommandGroup {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        for command in &mut self.commands {
+            command.apply(scene.clone())?;
+        }
+        Ok(())
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        for command in self.commands.iter_mut().rev() {
+            command.revert(scene.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// Add, replace or remove (when the component is None) a component of an entity
+pub struct SetComponent<T> {
+    pub entity: Guid,
+    pub component: Option<T>,
+    previous: Option<T>,
+}
+
+impl<T> SetComponent<T> {
+    pub fn new(entity: Guid, component: T) -> Self {
+        Self {
+            entity,
+            component: Some(component),
+            previous: None,
+        }
+    }
+
+    pub fn remove(entity: Guid) -> Self {
+        Self {
+            entity,
+            component: None,
+            previous: None,
+        }
+    }
+}
+
+fn write_component<T>(entity: &Entity, component: Option<T>)
+where
+    T: shipyard::Component
+        + shipyard::TupleAddComponent
+        + shipyard::TupleRemove
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    match component {
+        Some(component) => entity.add_component(component),
+        None => entity.remove_component::<T>(),
+    }
+}
+
+impl<T> Command for SetComponent<T>
+where
+    T: shipyard::Component
+        + shipyard::TupleAddComponent
+        + shipyard::TupleRemove
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    fn name(&self) -> String {
+        match self.component {
+            Some(_) => format!("Set {}", short_type_name::<T>()),
+            None => format!("Remove {}", short_type_name::<T>()),
+        }
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity = Entity::from_handle(resolve_entity(&scene, &self.entity)?, scene);
+        self.previous = entity.get_component::<T>();
+        write_component(&entity, self.component.clone());
+        Ok(())
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity = Entity::from_handle(resolve_entity(&scene, &self.entity)?, scene);
+        write_component(&entity, self.previous.clone());
+        Ok(())
+    }
+}
+
+/// Run a function on the descriptor of a registered component and the locked scene
+fn with_registered_component<R>(
+    scene: &Weak<Mutex<Scene>>,
+    component_name: &str,
+    f: impl FnOnce(&ComponentDescriptor, &mut Scene) -> Result<R>,
+) -> Result<R> {
+    let scene = scene
+        .upgrade()
+        .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for command"))?;
+    let mut scene_mutex_lock = scene
+        .lock()
+        .map_err(|_| anyhow!("Unable to acquire scene mutex for command"))?;
+    let registry = component_registry();
+    let component = registry
+        .get(component_name)
+        .ok_or_else(|| anyhow!("No registered component {}", component_name))?;
+    f(component, &mut scene_mutex_lock)
+}
+
+/// Set one field of a registered component, see reflect.rs
+pub struct SetComponentField {
+    pub entity: Guid,
+    pub component: String,
+    pub field: String,
+    pub value: FieldValue,
+    previous: Option<FieldValue>,
+}
+
+impl SetComponentField {
+    pub fn new(entity: Guid, component: &str, field: &str, value: FieldValue) -> Self {
+        Self {
+            entity,
+            component: String::from(component),
+            field: String::from(field),
+            value,
+            previous: None,
+        }
+    }
+}
+
+impl Command for SetComponentField {
+    fn name(&self) -> String {
+        format!("Set {}.{}", self.component, self.field)
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        self.previous = with_registered_component(&scene, &self.component, |component, scene| {
+            let previous = component
+                .get_field(scene, entity_id, &self.field)
+                .ok_or_else(|| anyhow!("Entity has no {}.{}", self.component, self.field))?;
+            component.set_field(scene, entity_id, &self.field, self.value.clone())?;
+            Ok(Some(previous))
+        })?;
+        Ok(())
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        let Some(previous) = self.previous.clone() else {
+            return Ok(());
+        };
+        with_registered_component(&scene, &self.component, |component, scene| {
+            component.set_field(scene, entity_id, &self.field, previous)
+        })
+    }
+}
+
+/// Add a registered component with its default value, or remove it, by component name
+pub struct SetRegisteredComponent {
+    pub entity: Guid,
+    pub component: String,
+    pub add: bool,
+    previous: Option<ComponentValue>,
+}
+
+impl SetRegisteredComponent {
+    pub fn add_default(entity: Guid, component: &str) -> Self {
+        Self {
+            entity,
+            component: String::from(component),
+            add: true,
+            previous: None,
+        }
+    }
+
+    pub fn remove(entity: Guid, component: &str) -> Self {
+        Self {
+            entity,
+            component: String::from(component),
+            add: false,
+            previous: None,
+        }
+    }
+}
+
+impl Command for SetRegisteredComponent {
+    fn name(&self) -> String {
+        if self.add {
+            format!("Add {}", self.component)
+        } else {
+            format!("Remove {}", self.component)
+        }
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        self.previous = with_registered_component(&scene, &self.component, |component, scene| {
+            if component.is_managed() {
+                return Err(anyhow!("{} is managed by the scene", self.component));
+            }
+            let previous = component.get(scene, entity_id);
+            if self.add {
+                component.add_default(scene, entity_id)?;
+            } else {
+                component.remove(scene, entity_id);
+            }
+            Ok(previous)
+        })?;
+        Ok(())
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        with_registered_component(&scene, &self.component, |component, scene| {
+            match &self.previous {
+                Some(previous) => {
+                    let previous = component.clone_value(&**previous)?;
+                    component.insert(scene, entity_id, previous)
+                }
+                None => {
+                    component.remove(scene, entity_id);
+                    Ok(())
+                }
+            }
+        })
+    }
+}
+
+/// Components of one entity, kept so that the entity can be brought back after it was destroyed
+struct EntitySnapshot {
+    runtime_id: u64,
+    guid: Option<Guid>,
+    hierarchy: Option<Hierarchy>,
+    bone: Option<Bone>,
+    morph_weights: Option<MorphWeights>,
+    prefab_instance: Option<PrefabInstance>,
+    prefab_entity: Option<PrefabEntity>,
+    /// Registered components, keyed by component name
+    reflected_components: Vec<(&'static str, ComponentValue)>,
+}
+
+fn add_optional_component<T>(scene: &mut Scene, entity_id: EntityId, component: &Option<T>)
+where
+    T: shipyard::Component + shipyard::TupleAddComponent + Send + Sync + Clone,
+{
+    if let Some(component) = component {
+        scene.handle.add_component(entity_id, component.clone());
+    }
+}
+
+/// An entity and its descendants, recreated with the same guids at the same place in the
+/// hierarchy
+struct SubtreeSnapshot {
+    parent: Option<Guid>,
+    child_index: Option<usize>,
+    entities: Vec<EntitySnapshot>,
+}
+
+impl SubtreeSnapshot {
+    fn capture(scene: &Weak<Mutex<Scene>>, entity_id: u64) -> Result<Self> {
+        let child_index = Scene::get_child_index(scene.clone(), entity_id);
+        let scene = scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for snapshot"))?;
+        let scene = scene
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for snapshot"))?;
+        if !scene.is_alive(entity_id) {
+            return Err(anyhow!("Entity {} does not exist", entity_id));
+        }
+        let parent = scene
+            .get_component_by_id::<Hierarchy>(entity_id)
+            .and_then(|hierarchy| hierarchy.parent_runtime_id)
+            .and_then(|parent_id| scene.get_entity_guid(parent_id));
+        let entities = scene
+            .collect_subtree(entity_id)
+            .into_iter()
+            .map(|runtime_id| EntitySnapshot {
+                runtime_id,
+                guid: scene.get_entity_guid(runtime_id),
+                hierarchy: scene.get_component_by_id(runtime_id),
+                bone: scene.get_component_by_id(runtime_id),
+                morph_weights: scene.get_component_by_id(runtime_id),
+                prefab_instance: scene.get_component_by_id(runtime_id),
+                prefab_entity: scene.get_component_by_id(runtime_id),
+                reflected_components: component_registry()
+                    .iter_unmanaged()
+                    .filter_map(|component| {
+                        component
+                            .get(&scene, runtime_id)
+                            .map(|value| (component.name, value))
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(Self {
+            parent,
+            child_index,
+            entities,
+        })
+    }
+
+    /// Recreate the entities, returns the new runtime id of the top entity
+    fn restore(&self, scene: Weak<Mutex<Scene>>) -> Result<u64> {
+        let scene_mutex = scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for snapshot"))?;
+        let mut scene_mutex_lock = scene_mutex
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for snapshot"))?;
+        let parent_id = match &self.parent {
+            Some(parent_guid) => scene_mutex_lock.get_entity_by_guid(parent_guid),
+            None => scene_mutex_lock.root_entity_runtime_id,
+        }
+        .ok_or_else(|| anyhow!("Parent of the restored entity no longer exists"))?;
+
+        let mut new_entity_ids: HashMap<u64, u64> = HashMap::new();
+        for entity in &self.entities {
+            let new_entity_id = scene_mutex_lock.handle.add_entity(()).inner();
+            new_entity_ids.insert(entity.runtime_id, new_entity_id);
+        }
+        // links to entities outside of the subtree are restored when it is added to its parent
+        let remap = |id: Option<u64>| id.and_then(|id| new_entity_ids.get(&id).copied());
+
+        for entity in &self.entities {
+            let new_entity_id = new_entity_ids[&entity.runtime_id];
+            let shipyard_entity_id = EntityId::from_inner(new_entity_id).unwrap();
+            if let Some(guid) = &entity.guid {
+                scene_mutex_lock.assign_guid(new_entity_id, guid.clone());
+            }
+            add_optional_component(
+                &mut scene_mutex_lock,
+                shipyard_entity_id,
+                &entity.morph_weights,
+            );
+            add_optional_component(
+                &mut scene_mutex_lock,
+                shipyard_entity_id,
+                &entity.prefab_instance,
+            );
+            add_optional_component(
+                &mut scene_mutex_lock,
+                shipyard_entity_id,
+                &entity.prefab_entity,
+            );
+            let registry = component_registry();
+            for (name, value) in &entity.reflected_components {
+                if let Some(component) = registry.get(name) {
+                    let value = component.clone_value(&**value)?;
+                    component.insert(&mut scene_mutex_lock, new_entity_id, value)?;
+                }
+            }
+            if let Some(hierarchy) = &entity.hierarchy {
+                scene_mutex_lock.handle.add_component(
+                    shipyard_entity_id,
+                    Hierarchy {
+                        num_children: hierarchy.num_children,
+                        parent_runtime_id: remap(hierarchy.parent_runtime_id),
+                        first_child_runtime_id: remap(hierarchy.first_child_runtime_id),
+                        prev_sibling_runtime_id: remap(hierarchy.prev_sibling_runtime_id),
+                        next_sibling_runtime_id: remap(hierarchy.next_sibling_runtime_id),
+                    },
+                );
+            }
+            if let Some(bone) = &entity.bone {
+                let mut bone = bone.clone();
+                bone.armature_root_runtime_id = bone
+                    .armature_root_runtime_id
+                    .map(|id| new_entity_ids.get(&id).copied().unwrap_or(id));
+                scene_mutex_lock
+                    .handle
+                    .add_component(shipyard_entity_id, bone);
+            }
+        }
+        let new_top_entity_id = new_entity_ids[&self.entities[0].runtime_id];
+
+        // drop mutex lock to allow the hierarchy to be modified
+        drop(scene_mutex_lock);
+        Scene::insert_child_at(
+            scene,
+            new_top_entity_id,
+            parent_id,
+            self.child_index.unwrap_or(usize::MAX),
+        )?;
+        Ok(new_top_entity_id)
+    }
+}
+
+/// Create an entity under a parent, or under the root entity when no parent is given
+pub struct CreateEntity {
+    /// Guid the new entity gets, use it to find the entity after the command was executed
+    pub guid: Guid,
+    pub name: Option<String>,
+    pub parent: Option<Guid>,
+    pub transform: Option<Transform>,
+    /// Entity and its descendants as they were when the creation was undone
+    snapshot: Option<SubtreeSnapshot>,
+}
+
+impl CreateEntity {
+    pub fn new(name: Option<String>, parent: Option<Guid>, transform: Option<Transform>) -> Self {
+        Self {
+            guid: Guid::generate(),
+            name,
+            parent,
+            transform,
+            snapshot: None,
+        }
+    }
+}
+
+impl Command for CreateEntity {
+    fn name(&self) -> String {
+        String::from("Create Entity")
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        // on redo the entity comes back with the children that were added to it before the undo
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.restore(scene)?;
+            return Ok(());
+        }
+        let parent_id = match &self.parent {
+            Some(parent_guid) => Some(resolve_entity(&scene, parent_guid)?),
+            None => None,
+        };
+        let entity_id = Scene::create_entity(
+            scene.clone(),
+            self.name.clone(),
+            parent_id,
+            self.transform.clone(),
+        )?;
+        scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for command"))?
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for command"))?
+            .assign_guid(entity_id, self.guid.clone());
+        Ok(())
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.guid)?;
+        self.snapshot = Some(SubtreeSnapshot::capture(&scene, entity_id)?);
+        Scene::destroy_entity(scene, entity_id)
+    }
+}
+
+/// Destroy an entity along with all of its descendants
+pub struct DestroyEntity {
+    pub entity: Guid,
+    snapshot: Option<SubtreeSnapshot>,
+}
+
+impl DestroyEntity {
+    pub fn new(entity: Guid) -> Self {
+        Self {
+            entity,
+            snapshot: None,
+        }
+    }
+}
+
+impl Command for DestroyEntity {
+    fn name(&self) -> String {
+        String::from("Destroy Entity")
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        self.snapshot = Some(SubtreeSnapshot::capture(&scene, entity_id)?);
+        Scene::destroy_entity(scene, entity_id)
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .ok_or_else(|| anyhow!("Destroy command was not applied"))?;
+        snapshot.restore(scene)?;
+        Ok(())
+    }
+}
+
+/// Move an entity and its children under a new parent, see Scene::move_entity
+pub struct ReparentEntity {
+    pub entity: Guid,
+    pub new_parent: Guid,
+    pub keep_world_transform: bool,
+    previous_parent: Option<Guid>,
+    previous_child_index: Option<usize>,
+    previous_transform: Option<Transform>,
+}
+
+impl ReparentEntity {
+    pub fn new(entity: Guid, new_parent: Guid, keep_world_transform: bool) -> Self {
+        Self {
+            entity,
+            new_parent,
+            keep_world_transform,
+            previous_parent: None,
+            previous_child_index: None,
+            previous_transform: None,
+        }
+    }
+}
+
+impl Command for ReparentEntity {
+    fn name(&self) -> String {
+        String::from("Reparent Entity")
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        let new_parent_id = resolve_entity(&scene, &self.new_parent)?;
+        let entity = Entity::from_handle(entity_id, scene.clone());
+        self.previous_parent = entity
+            .get_component::<Hierarchy>()
+            .and_then(|hierarchy| hierarchy.parent_runtime_id)
+            .and_then(|parent_id| {
+                Entity::from_handle(parent_id, scene.clone()).get_component::<Guid>()
+            });
+        self.previous_child_index = Scene::get_child_index(scene.clone(), entity_id);
+        self.previous_transform = entity.get_component::<Transform>();
+        Scene::move_entity(scene, entity_id, new_parent_id, self.keep_world_transform)
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        let previous_parent = self
+            .previous_parent
+            .as_ref()
+            .ok_or_else(|| anyhow!("Entity had no parent before it was moved"))?;
+        let previous_parent_id = resolve_entity(&scene, previous_parent)?;
+        Scene::move_entity(scene.clone(), entity_id, previous_parent_id, false)?;
+        if let Some(previous_child_index) = self.previous_child_index {
+            Scene::insert_child_at(
+                scene.clone(),
+                entity_id,
+                previous_parent_id,
+                previous_child_index,
+            )?;
+        }
+        if let Some(transform) = self.previous_transform.clone() {
+            Entity::from_handle(entity_id, scene).add_component(transform);
+        }
+        Ok(())
+    }
+}
+
+/// Move an entity to another position among its siblings
+pub struct SetChildIndex {
+    pub entity: Guid,
+    pub index: usize,
+    previous_index: Option<usize>,
+}
+
+impl SetChildIndex {
+    pub fn new(entity: Guid, index: usize) -> Self {
+        Self {
+            entity,
+            index,
+            previous_index: None,
+        }
+    }
+}
+
+fn get_parent_id(scene: &Weak<Mutex<Scene>>, entity_id: u64) -> Result<u64> {
+    Entity::from_handle(entity_id, scene.clone())
+        .get_component::<Hierarchy>()
+        .and_then(|hierarchy| hierarchy.parent_runtime_id)
+        .ok_or_else(|| anyhow!("Entity {} has no parent", entity_id))
+}
+
+impl Command for SetChildIndex {
+    fn name(&self) -> String {
+        String::from("Reorder Entity")
+    }
+
+    fn apply(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        let parent_id = get_parent_id(&scene, entity_id)?;
+        self.previous_index = Scene::get_child_index(scene.clone(), entity_id);
+        Scene::insert_child_at(scene, entity_id, parent_id, self.index)
+    }
+
+    fn revert(&mut self, scene: Weak<Mutex<Scene>>) -> Result<()> {
+        let entity_id = resolve_entity(&scene, &self.entity)?;
+        let parent_id = get_parent_id(&scene, entity_id)?;
+        if let Some(previous_index) = self.previous_index {
+            Scene::insert_child_at(scene, entity_id, parent_id, previous_index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Undo and redo stacks of a scene
+#[derive(Default)]
+pub struct CommandHistory {
+    undo_stack: VecDeque<Box<dyn Command>>,
+    redo_stack: Vec<Box<dyn Command>>,
+    /// Group that executed commands are added to, with the number of nested begin_group calls
+    open_group: Option<(CommandGroup, usize)>,
+}
+
+impl CommandHistory {
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.open_group.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Name of the command that undo would revert
+    pub fn get_undo_name(&self) -> Option<String> {
+        match &self.open_group {
+            Some((group, _)) => Some(group.name()),
+            None => self.undo_stack.back().map(|command| command.name()),
+        }
+    }
+
+    /// Name of the command that redo would apply
+    pub fn get_redo_name(&self) -> Option<String> {
+        self.redo_stack.last().map(|command| command.name())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.open_group = None;
+    }
+
+    /// Start collecting executed commands into one group, groups can be nested and the
+    /// outermost one gives the group its name
+    pub fn begin_group(&mut self, name: &str) {
+        match &mut self.open_group {
+            Some((_, depth)) => *depth += 1,
+            None => self.open_group = Some((CommandGroup::new(name), 1)),
+        }
+    }
+
+    /// Close the group opened by the matching begin_group
+    pub fn end_group(&mut self) {
+        if let Some((_, depth)) = &mut self.open_group {
+            *depth -= 1;
+            if *depth == 0 {
+                self.close_group();
+            }
+        }
+    }
+
+    fn close_group(&mut self) {
+        if let Some((group, _)) = self.open_group.take() {
+            // a gesture that did not change anything is not worth an undo step
+            if !group.commands.is_empty() {
+                self.push_undo(Box::new(group));
+            }
+        }
+    }
+
+    fn push_undo(&mut self, command: Box<dyn Command>) {
+        self.undo_stack.push_back(command);
+        if self.undo_stack.len() > MAX_UNDO_DEPTH {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    fn record(&mut self, command: Box<dyn Command>) {
+        self.redo_stack.clear();
+        match &mut self.open_group {
+            Some((group, _)) => group.commands.push(command),
+            None => self.push_undo(command),
+        }
+    }
+}
+
+impl Scene {
+    /// Apply a command and record it in the history of the scene so that it can be undone
+    pub fn execute(scene: Weak<Mutex<Scene>>, mut command: impl Command + 'static) -> Result<()> {
+        command.apply(scene.clone())?;
+        scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for command"))?
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for command"))?
+            .history
+            .record(Box::new(command));
+        Ok(())
+    }
+
+    /// Revert the last command, returns false when there is nothing to undo. A command that
+    /// fails to revert is dropped from the history.
+    pub fn undo(scene: Weak<Mutex<Scene>>) -> Result<bool> {
+        let scene_mutex = scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for undo"))?;
+        let command = {
+            let mut scene_mutex_lock = scene_mutex
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire scene mutex for undo"))?;
+            // undo ends a gesture that is still going on
+            scene_mutex_lock.history.close_group();
+            scene_mutex_lock.history.undo_stack.pop_back()
+        };
+        let Some(mut command) = command else {
+            return Ok(false);
+        };
+        command.revert(scene)?;
+        scene_mutex
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for undo"))?
+            .history
+            .redo_stack
+            .push(command);
+        Ok(true)
+    }
+
+    /// Apply the last undone command again, returns false when there is nothing to redo
+    pub fn redo(scene: Weak<Mutex<Scene>>) -> Result<bool> {
+        let scene_mutex = scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference for redo"))?;
+        let command = scene_mutex
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for redo"))?
+            .history
+            .redo_stack
+            .pop();
+        let Some(mut command) = command else {
+            return Ok(false);
+        };
+        command.apply(scene)?;
+        scene_mutex
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex for redo"))?
+            .history
+            .push_undo(command);
+        Ok(true)
+    }
+}
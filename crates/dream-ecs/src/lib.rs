@@ -1,3 +1,14 @@
+pub mod animation;
+pub mod animator;
 pub mod component;
 pub mod entity;
+pub mod events;
+pub mod gltf_export;
+pub mod history;
+pub mod name_index;
+pub mod prefab;
+pub mod query;
+pub mod reflect;
 pub mod scene;
+pub mod serialization;
+pub mod snapshot;
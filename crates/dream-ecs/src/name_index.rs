@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::component::{Hierarchy, Tag};
+use crate::scene::Scene;
+
+/// Separator between entity names in a hierarchy path (e.g. "Dark Knight/Armature/Hips")
+pub const PATH_SEPARATOR: char = '/';
+
+/// Map between entity names and runtime ids, kept in sync with the Tag components of a scene
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    ids_by_name: HashMap<String, HashSet<u64>>,
+    name_by_id: HashMap<u64, String>,
+}
+
+impl NameIndex {
+    pub fn insert(&mut self, entity_id: u64, name: String) {
+        self.remove(entity_id);
+        self.ids_by_name
+            .entry(name.clone())
+            .or_default()
+            .insert(entity_id);
+        self.name_by_id.insert(entity_id, name);
+    }
+
+    pub fn remove(&mut self, entity_id: u64) {
+        if let Some(name) = self.name_by_id.remove(&entity_id) {
+            if let Some(ids) = self.ids_by_name.get_mut(&name) {
+                ids.remove(&entity_id);
+                if ids.is_empty() {
+                    self.ids_by_name.remove(&name);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ids_by_name.clear();
+        self.name_by_id.clear();
+    }
+
+    /// Runtime ids of entities with exactly this name, sorted so results are deterministic
+    pub fn get(&self, name: &str) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .ids_by_name
+            .get(name)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Runtime ids of entities whose name matches a glob pattern
+    pub fn get_matching(&self, pattern: &str) -> Vec<u64> {
+        if !is_glob_pattern(pattern) {
+            return self.get(pattern);
+        }
+        let mut ids: Vec<u64> = self
+            .ids_by_name
+            .iter()
+            .filter(|(name, _)| glob_match(pattern, name))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.contains('*') || pattern.contains('?')
+}
+
+/// Match a name against a pattern where * matches any number of characters and ? matches one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut pattern_idx, mut text_idx) = (0, 0);
+    // position of the last * and the text position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while text_idx < text.len() {
+        match pattern.get(pattern_idx) {
+            Some('*') => {
+                backtrack = Some((pattern_idx, text_idx));
+                pattern_idx += 1;
+            }
+            Some(c) if *c == '?' || *c == text[text_idx] => {
+                pattern_idx += 1;
+                text_idx += 1;
+            }
+            _ => match backtrack {
+                // let the last * swallow one more character
+                Some((star_idx, star_text_idx)) => {
+                    pattern_idx = star_idx + 1;
+                    text_idx = star_text_idx + 1;
+                    backtrack = Some((star_idx, star_text_idx + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[pattern_idx..].iter().all(|c| *c == '*')
+}
+
+/// Match path segments against pattern segments where ** matches any number of segments
+fn glob_match_path(pattern: &[&str], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| glob_match_path(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                glob_match(segment, name) && glob_match_path(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+impl Scene {
+    /// Keep the name index up to date after the Tag of an entity was added or changed
+    pub(crate) fn index_entity_name(&mut self, entity_id: u64) {
+        match self.get_component_by_id::<Tag>(entity_id) {
+            Some(tag) => self.name_index.insert(entity_id, tag.name),
+            None => self.name_index.remove(entity_id),
+        }
+    }
+
+    /// Find an entity by its name, the entity with the lowest runtime id wins when several have
+    /// the same name
+    pub fn find_by_name(&self, name: &str) -> Option<u64> {
+        self.find_all_by_name(name).into_iter().next()
+    }
+
+    /// Find all entities whose name matches a name or a glob pattern (e.g. "Cube*")
+    pub fn find_all_by_name(&self, pattern: &str) -> Vec<u64> {
+        self.name_index
+            .get_matching(pattern)
+            .into_iter()
+            .filter(|entity_id| self.is_alive(*entity_id))
+            .collect()
+    }
+
+    /// Find an entity by the names of its ancestors below the root entity and its own name,
+    /// e.g. "Dark Knight/Armature/Hips"
+    pub fn find_by_path(&self, path: &str) -> Option<u64> {
+        self.find_all_by_path(path).into_iter().next()
+    }
+
+    /// Find all entities matching a path, segments of the path can be glob patterns and ** matches
+    /// any number of segments (e.g. "Dark Knight/**/Hand*")
+    pub fn find_all_by_path(&self, path: &str) -> Vec<u64> {
+        let segments: Vec<&str> = path
+            .split(PATH_SEPARATOR)
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let Some(last_segment) = segments.last() else {
+            return Vec::new();
+        };
+        if segments.iter().any(|segment| is_glob_pattern(segment)) {
+            return self.find_all_by_path_pattern(&segments);
+        }
+        // start from the entities named like the last segment and walk up to the root
+        self.find_all_by_name(last_segment)
+            .into_iter()
+            .filter(|entity_id| self.get_entity_path_names(*entity_id) == segments)
+            .collect()
+    }
+
+    /// Path of an entity below the root entity, see find_by_path
+    pub fn get_entity_path(&self, entity_id: u64) -> String {
+        self.get_entity_path_names(entity_id)
+            .join(PATH_SEPARATOR.to_string().as_str())
+    }
+
+    fn get_entity_path_names(&self, entity_id: u64) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut cur_entity_id = Some(entity_id);
+        while let Some(cur_entity_id_unwrapped) = cur_entity_id {
+            if self.root_entity_runtime_id == Some(cur_entity_id_unwrapped) {
+                break;
+            }
+            names.push(
+                self.get_component_by_id::<Tag>(cur_entity_id_unwrapped)
+                    .map(|tag| tag.name)
+                    .unwrap_or_default(),
+            );
+            cur_entity_id = self
+                .get_component_by_id::<Hierarchy>(cur_entity_id_unwrapped)
+                .and_then(|hierarchy| hierarchy.parent_runtime_id);
+        }
+        names.reverse();
+        names
+    }
+
+    fn find_all_by_path_pattern(&self, pattern: &[&str]) -> Vec<u64> {
+        let Some(root_entity_id) = self.root_entity_runtime_id else {
+            return Vec::new();
+        };
+        // walk the whole hierarchy, keeping track of the path of the current entity
+        let mut result = Vec::new();
+        let mut stack: Vec<(u64, Vec<String>)> = vec![(root_entity_id, Vec::new())];
+        while let Some((entity_id, path)) = stack.pop() {
+            if !path.is_empty() && glob_match_path(pattern, &path) {
+                result.push(entity_id);
+            }
+            let mut cur_child_id = self
+                .get_component_by_id::<Hierarchy>(entity_id)
+                .and_then(|hierarchy| hierarchy.first_child_runtime_id);
+            while let Some(child_id) = cur_child_id {
+                let mut child_path = path.clone();
+                child_path.push(
+                    self.get_component_by_id::<Tag>(child_id)
+                        .map(|tag| tag.name)
+                        .unwrap_or_default(),
+                );
+                stack.push((child_id, child_path));
+                cur_child_id = self
+                    .get_component_by_id::<Hierarchy>(child_id)
+                    .and_then(|hierarchy| hierarchy.next_sibling_runtime_id);
+            }
+        }
+        result.sort_unstable();
+        result
+    }
+}
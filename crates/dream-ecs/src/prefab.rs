@@ -0,0 +1,585 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, Weak};
+
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use shipyard::{EntityId, IntoIter, IntoWithId};
+
+use dream_resource::resource_handle::ResourceHandle;
+use dream_resource::resource_manager::ResourceManager;
+
+use crate::component::{
+    Bone, Guid, Hierarchy, MorphWeights, PrefabEntity, PrefabInstance, PrefabOverride,
+};
+use crate::reflect::component_registry;
+use crate::scene::Scene;
+use crate::serialization::{remap_file_id, upgrade_scene_data, EntityData, SceneData};
+
+pub const PREFAB_FILE_EXTENSION: &str = "prefab";
+
+/// Keys of entity data that are rebuilt from the prefab on every sync and are never overridden
+const NON_OVERRIDABLE_KEYS: [&str; 5] = [
+    "id",
+    "guid",
+    "hierarchy",
+    "prefab_instance",
+    "prefab_entity",
+];
+
+fn entity_data_to_mapping(entity_data: &EntityData) -> Result<Mapping> {
+    match serde_yaml::to_value(entity_data) {
+        Ok(Value::Mapping(mapping)) => Ok(mapping),
+        Ok(_) => Err(anyhow!("Entity data is not a mapping")),
+        Err(err) => Err(anyhow!("Unable to serialize entity data: {}", err)),
+    }
+}
+
+/// Compute the overrides needed to turn the prefab version of an entity into its current version
+fn diff_entity_data(base: &EntityData, current: &EntityData) -> Result<Vec<PrefabOverride>> {
+    let base_mapping = entity_data_to_mapping(base)?;
+    let current_mapping = entity_data_to_mapping(current)?;
+    let components: BTreeSet<&str> = base_mapping
+        .keys()
+        .chain(current_mapping.keys())
+        .filter_map(|key| key.as_str())
+        .filter(|key| !NON_OVERRIDABLE_KEYS.contains(key))
+        .collect();
+
+    let mut overrides = Vec::new();
+    for component in components {
+        let base_value = base_mapping.get(component).unwrap_or(&Value::Null);
+        let current_value = current_mapping.get(component).unwrap_or(&Value::Null);
+        if base_value == current_value {
+            continue;
+        }
+        match (base_value, current_value) {
+            // only store the properties that changed, so later changes to the other properties
+            // in the prefab still reach this instance
+            (Value::Mapping(base_fields), Value::Mapping(current_fields)) => {
+                let properties: BTreeSet<&str> = base_fields
+                    .keys()
+                    .chain(current_fields.keys())
+                    .filter_map(|key| key.as_str())
+                    .collect();
+                for property in properties {
+                    let base_field = base_fields.get(property).unwrap_or(&Value::Null);
+                    let current_field = current_fields.get(property).unwrap_or(&Value::Null);
+                    if base_field != current_field {
+                        overrides.push(PrefabOverride {
+                            prefab_entity_id: base.id,
+                            component: String::from(component),
+                            property: Some(String::from(property)),
+                            value: current_field.clone(),
+                        });
+                    }
+                }
+            }
+            _ => overrides.push(PrefabOverride {
+                prefab_entity_id: base.id,
+                component: String::from(component),
+                property: None,
+                value: current_value.clone(),
+            }),
+        }
+    }
+    Ok(overrides)
+}
+
+/// Apply the overrides of an instance to the prefab version of an entity
+fn apply_overrides(entity_data: &EntityData, overrides: &[PrefabOverride]) -> Result<EntityData> {
+    if overrides.is_empty() {
+        return Ok(entity_data.clone());
+    }
+    let mut mapping = entity_data_to_mapping(entity_data)?;
+    for prefab_override in overrides {
+        let component = prefab_override.component.as_str();
+        match &prefab_override.property {
+            None if prefab_override.value.is_null() => {
+                mapping.remove(component);
+            }
+            None => {
+                mapping.insert(
+                    Value::String(String::from(component)),
+                    prefab_override.value.clone(),
+                );
+            }
+            Some(property) => match mapping.get_mut(component) {
+                Some(Value::Mapping(fields)) => {
+                    fields.insert(
+                        Value::String(property.clone()),
+                        prefab_override.value.clone(),
+                    );
+                }
+                _ => log::warn!(
+                    "Skipping override of {}.{} since the prefab no longer has this component",
+                    component,
+                    property
+                ),
+            },
+        }
+    }
+    serde_yaml::from_value(Value::Mapping(mapping))
+        .map_err(|err| anyhow!("Unable to apply prefab overrides: {}", err))
+}
+
+/// Remove instance specific data so entity data can be written to a prefab file
+fn strip_prefab_data(mut scene_data: SceneData) -> SceneData {
+    for entity_data in &mut scene_data.entities {
+        // every instance gives its entities their own guids
+        entity_data.guid = None;
+        entity_data.prefab_instance = None;
+        entity_data.prefab_entity = None;
+    }
+    scene_data
+}
+
+fn read_prefab_data(
+    resource_manager: &ResourceManager,
+    guid: &str,
+) -> Result<(SceneData, Weak<ResourceHandle>)> {
+    let resource_handle = resource_manager
+        .get_resource(String::from(guid))
+        .ok_or_else(|| anyhow!("No prefab with guid {}", guid))?;
+    let path = resource_handle
+        .upgrade()
+        .ok_or_else(|| anyhow!("Resource handle for prefab {} was dropped", guid))?
+        .get_path();
+    let bytes = dream_fs::fs::read_binary(path.clone(), true).map_err(|err| {
+        anyhow!(
+            "Unable to read prefab file {}: {}",
+            path.to_str().unwrap_or("none"),
+            err
+        )
+    })?;
+    let scene_data: SceneData = serde_yaml::from_slice(bytes.as_slice())
+        .map_err(|err| anyhow!("Unable to deserialize prefab: {}", err))?;
+    let scene_data = upgrade_scene_data(&scene_data)?.into_owned();
+    Ok((scene_data, resource_handle))
+}
+
+fn write_prefab_data(path: PathBuf, prefab_data: &SceneData) -> Result<()> {
+    let yaml = serde_yaml::to_string(prefab_data)
+        .map_err(|err| anyhow!("Unable to serialize prefab: {}", err))?;
+    log::debug!("Saving prefab to {}", path.to_str().unwrap_or("none"));
+    dream_fs::fs::write_binary(path, yaml.into_bytes())
+}
+
+impl Scene {
+    /// Save an entity and its descendants as a .prefab file and turn the entity into an instance
+    /// of it, returns the guid of the new prefab
+    pub fn create_prefab(
+        &mut self,
+        entity_id: u64,
+        path: PathBuf,
+        resource_manager: &mut ResourceManager,
+    ) -> Result<String> {
+        if self.root_entity_runtime_id == Some(entity_id) {
+            return Err(anyhow!("Cannot create a prefab from the root entity"));
+        }
+        if !self.is_alive(entity_id) {
+            return Err(anyhow!("Entity {} does not exist", entity_id));
+        }
+        let prefab_data = strip_prefab_data(self.subtree_to_scene_data(entity_id));
+        write_prefab_data(path.clone(), &prefab_data)?;
+        let guid = resource_manager.register_resource(path);
+
+        // the subtree is numbered the same way as in subtree_to_scene_data
+        for (prefab_entity_id, runtime_id) in
+            self.collect_subtree(entity_id).into_iter().enumerate()
+        {
+            self.handle.add_component(
+                EntityId::from_inner(runtime_id).unwrap(),
+                PrefabEntity {
+                    prefab_entity_id: prefab_entity_id as u64,
+                },
+            );
+        }
+        self.handle.add_component(
+            EntityId::from_inner(entity_id).unwrap(),
+            PrefabInstance::new(resource_manager.get_resource(guid.clone())),
+        );
+        self.prefabs.insert(guid.clone(), prefab_data);
+        Ok(guid)
+    }
+
+    /// Create a new instance of a prefab as a child of the given parent (root entity if None),
+    /// returns the runtime id of the instance root
+    pub fn instantiate_prefab(
+        scene: Weak<Mutex<Scene>>,
+        guid: String,
+        parent_id: Option<u64>,
+        resource_manager: &ResourceManager,
+    ) -> Result<u64> {
+        let (prefab_data, resource_handle) = read_prefab_data(resource_manager, guid.as_str())?;
+        let scene_arc = scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene"))?;
+        let instance_root_id = {
+            let mut scene_mutex_lock = scene_arc
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire scene mutex"))?;
+            let instance_root_id = scene_mutex_lock
+                .handle
+                .add_entity(Hierarchy::default())
+                .inner();
+            scene_mutex_lock.sync_prefab_instance(
+                instance_root_id,
+                &prefab_data,
+                Some(resource_handle),
+                &[],
+                resource_manager,
+            )?;
+            scene_mutex_lock.prefabs.insert(guid, prefab_data);
+            instance_root_id
+        };
+        let parent_id = match parent_id {
+            Some(parent_id) => parent_id,
+            None => scene_arc
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire scene mutex"))?
+                .root_entity_runtime_id
+                .ok_or_else(|| anyhow!("Scene has no root entity"))?,
+        };
+        Scene::add_child_to_entity(scene, instance_root_id, parent_id)?;
+        Ok(instance_root_id)
+    }
+
+    /// Runtime ids of the roots of all instances of a prefab
+    pub fn get_prefab_instances(&self, guid: &str) -> Vec<u64> {
+        let mut instances = Vec::new();
+        self.handle
+            .run(|prefab_instances: shipyard::View<PrefabInstance>| {
+                for (id, prefab_instance) in prefab_instances.iter().with_id() {
+                    if prefab_instance
+                        .resource_handle
+                        .as_ref()
+                        .and_then(|resource_handle| resource_handle.upgrade())
+                        .map_or(false, |resource_handle| resource_handle.key == guid)
+                    {
+                        instances.push(id.inner());
+                    }
+                }
+            });
+        instances
+    }
+
+    /// Store the differences between every prefab instance and its prefab as overrides
+    pub fn record_prefab_overrides(&mut self) {
+        let mut instances: Vec<(u64, String)> = Vec::new();
+        self.handle
+            .run(|prefab_instances: shipyard::View<PrefabInstance>| {
+                for (id, prefab_instance) in prefab_instances.iter().with_id() {
+                    if let Some(resource_handle) = prefab_instance
+                        .resource_handle
+                        .as_ref()
+                        .and_then(|resource_handle| resource_handle.upgrade())
+                    {
+                        instances.push((id.inner(), resource_handle.key.clone()));
+                    }
+                }
+            });
+        for (instance_root_id, guid) in instances {
+            let Some(prefab_data) = self.prefabs.get(&guid) else {
+                continue;
+            };
+            match self.diff_prefab_instance(instance_root_id, prefab_data) {
+                Ok(overrides) => {
+                    if let Some(mut prefab_instance) =
+                        self.get_component_by_id::<PrefabInstance>(instance_root_id)
+                    {
+                        prefab_instance.overrides = overrides;
+                        self.handle.add_component(
+                            EntityId::from_inner(instance_root_id).unwrap(),
+                            prefab_instance,
+                        );
+                    }
+                }
+                Err(err) => log::warn!(
+                    "Unable to record overrides of prefab instance {}: {}",
+                    instance_root_id,
+                    err
+                ),
+            }
+        }
+    }
+
+    /// Reload a prefab from disk and rebuild all of its instances, keeping their overrides
+    pub fn update_prefab_instances(
+        &mut self,
+        guid: &str,
+        resource_manager: &ResourceManager,
+    ) -> Result<()> {
+        let (prefab_data, resource_handle) = read_prefab_data(resource_manager, guid)?;
+        self.record_prefab_overrides();
+        for instance_root_id in self.get_prefab_instances(guid) {
+            let overrides = self
+                .get_component_by_id::<PrefabInstance>(instance_root_id)
+                .map(|prefab_instance| prefab_instance.overrides)
+                .unwrap_or_default();
+            self.sync_prefab_instance(
+                instance_root_id,
+                &prefab_data,
+                Some(resource_handle.clone()),
+                &overrides,
+                resource_manager,
+            )?;
+        }
+        self.prefabs.insert(String::from(guid), prefab_data);
+        Ok(())
+    }
+
+    /// Write the current state of a prefab instance to its prefab and update all other instances
+    pub fn apply_prefab_instance(
+        &mut self,
+        instance_root_id: u64,
+        resource_manager: &ResourceManager,
+    ) -> Result<()> {
+        let resource_handle = self
+            .get_component_by_id::<PrefabInstance>(instance_root_id)
+            .and_then(|prefab_instance| prefab_instance.resource_handle)
+            .and_then(|resource_handle| resource_handle.upgrade())
+            .ok_or_else(|| anyhow!("Entity {} is not a prefab instance", instance_root_id))?;
+
+        // keep the ids of entities that came from the prefab, so overrides of other instances
+        // still point at the same entities
+        let subtree = self.collect_subtree(instance_root_id);
+        let mut file_ids: HashMap<u64, u64> = HashMap::new();
+        for runtime_id in &subtree {
+            if let Some(prefab_entity) = self.get_component_by_id::<PrefabEntity>(*runtime_id) {
+                file_ids.insert(*runtime_id, prefab_entity.prefab_entity_id);
+            }
+        }
+        let mut next_file_id = file_ids.values().max().map_or(0, |max_id| max_id + 1);
+        for runtime_id in &subtree {
+            if !file_ids.contains_key(runtime_id) {
+                file_ids.insert(*runtime_id, next_file_id);
+                self.handle.add_component(
+                    EntityId::from_inner(*runtime_id).unwrap(),
+                    PrefabEntity {
+                        prefab_entity_id: next_file_id,
+                    },
+                );
+                next_file_id += 1;
+            }
+        }
+        let prefab_data =
+            strip_prefab_data(self.subtree_to_scene_data_with_ids(instance_root_id, &file_ids));
+        write_prefab_data(resource_handle.get_path(), &prefab_data)?;
+
+        // this instance now matches the prefab exactly
+        if let Some(mut prefab_instance) =
+            self.get_component_by_id::<PrefabInstance>(instance_root_id)
+        {
+            prefab_instance.overrides.clear();
+            self.handle.add_component(
+                EntityId::from_inner(instance_root_id).unwrap(),
+                prefab_instance,
+            );
+        }
+        self.record_prefab_overrides();
+        for other_root_id in self.get_prefab_instances(resource_handle.key.as_str()) {
+            if other_root_id == instance_root_id {
+                continue;
+            }
+            let overrides = self
+                .get_component_by_id::<PrefabInstance>(other_root_id)
+                .map(|prefab_instance| prefab_instance.overrides)
+                .unwrap_or_default();
+            self.sync_prefab_instance(
+                other_root_id,
+                &prefab_data,
+                resource_manager.get_resource(resource_handle.key.clone()),
+                &overrides,
+                resource_manager,
+            )?;
+        }
+        self.prefabs
+            .insert(resource_handle.key.clone(), prefab_data);
+        Ok(())
+    }
+
+    /// Rebuild every prefab instance from its prefab file using the overrides stored in the scene
+    pub(crate) fn sync_prefab_instances_after_load(&mut self, resource_manager: &ResourceManager) {
+        let mut guids: BTreeSet<String> = BTreeSet::new();
+        self.handle
+            .run(|prefab_instances: shipyard::View<PrefabInstance>| {
+                for prefab_instance in prefab_instances.iter() {
+                    if let Some(resource_handle) = prefab_instance
+                        .resource_handle
+                        .as_ref()
+                        .and_then(|resource_handle| resource_handle.upgrade())
+                    {
+                        guids.insert(resource_handle.key.clone());
+                    }
+                }
+            });
+        for guid in guids {
+            let (prefab_data, resource_handle) =
+                match read_prefab_data(resource_manager, guid.as_str()) {
+                    Ok(prefab) => prefab,
+                    Err(err) => {
+                        log::warn!("Unable to load prefab {}: {}", guid, err);
+                        continue;
+                    }
+                };
+            for instance_root_id in self.get_prefab_instances(guid.as_str()) {
+                let overrides = self
+                    .get_component_by_id::<PrefabInstance>(instance_root_id)
+                    .map(|prefab_instance| prefab_instance.overrides)
+                    .unwrap_or_default();
+                if let Err(err) = self.sync_prefab_instance(
+                    instance_root_id,
+                    &prefab_data,
+                    Some(resource_handle.clone()),
+                    &overrides,
+                    resource_manager,
+                ) {
+                    log::warn!(
+                        "Unable to update prefab instance {}: {}",
+                        instance_root_id,
+                        err
+                    );
+                }
+            }
+            self.prefabs.insert(guid, prefab_data);
+        }
+    }
+
+    fn diff_prefab_instance(
+        &self,
+        instance_root_id: u64,
+        prefab_data: &SceneData,
+    ) -> Result<Vec<PrefabOverride>> {
+        let mut file_ids: HashMap<u64, u64> = HashMap::new();
+        for runtime_id in self.collect_subtree(instance_root_id) {
+            if let Some(prefab_entity) = self.get_component_by_id::<PrefabEntity>(runtime_id) {
+                file_ids.insert(runtime_id, prefab_entity.prefab_entity_id);
+            }
+        }
+        let remap = |runtime_id: Option<u64>| runtime_id.and_then(|id| file_ids.get(&id).copied());
+        let mut overrides = Vec::new();
+        for (runtime_id, prefab_entity_id) in &file_ids {
+            let Some(base) = prefab_data
+                .entities
+                .iter()
+                .find(|entity_data| entity_data.id == *prefab_entity_id)
+            else {
+                continue;
+            };
+            let current = self.entity_to_data(*runtime_id, *prefab_entity_id, &remap);
+            overrides.extend(diff_entity_data(base, &current)?);
+        }
+        // keep the order stable so saved scenes do not change needlessly
+        overrides.sort_by(|a, b| {
+            (a.prefab_entity_id, &a.component, &a.property).cmp(&(
+                b.prefab_entity_id,
+                &b.component,
+                &b.property,
+            ))
+        });
+        Ok(overrides)
+    }
+
+    /// Replace the contents of an instance with the prefab with overrides applied, the instance
+    /// root keeps its runtime id and its place in the scene
+    fn sync_prefab_instance(
+        &mut self,
+        instance_root_id: u64,
+        prefab_data: &SceneData,
+        resource_handle: Option<Weak<ResourceHandle>>,
+        overrides: &[PrefabOverride],
+        resource_manager: &ResourceManager,
+    ) -> Result<()> {
+        let prefab_root_id = prefab_data
+            .root_entity_id
+            .ok_or_else(|| anyhow!("Prefab has no root entity"))?;
+        let instance_root_entity_id = EntityId::from_inner(instance_root_id)
+            .ok_or_else(|| anyhow!("Invalid runtime id {}", instance_root_id))?;
+        let old_hierarchy = self
+            .get_component_by_id::<Hierarchy>(instance_root_id)
+            .unwrap_or_default();
+
+        // keep the guids of entities that are recreated, so references to them stay valid
+        let mut old_guids: HashMap<u64, Guid> = HashMap::new();
+        for runtime_id in self.collect_subtree(instance_root_id) {
+            if let (Some(prefab_entity), Some(guid)) = (
+                self.get_component_by_id::<PrefabEntity>(runtime_id),
+                self.get_entity_guid(runtime_id),
+            ) {
+                old_guids.insert(prefab_entity.prefab_entity_id, guid);
+            }
+        }
+
+        // descendants are recreated from the prefab
+        let old_descendants: Vec<u64> = self
+            .collect_subtree(instance_root_id)
+            .into_iter()
+            .skip(1)
+            .collect();
+        for runtime_id in &old_descendants {
+            self.forget_entity(*runtime_id);
+            self.handle
+                .delete_entity(EntityId::from_inner(*runtime_id).unwrap());
+        }
+        self.destroyed_entity_runtime_ids.extend(old_descendants);
+        self.handle
+            .delete_component::<(Bone, MorphWeights, PrefabEntity)>(instance_root_entity_id);
+        for component in component_registry().iter_unmanaged() {
+            component.remove(self, instance_root_id);
+        }
+        self.name_index.remove(instance_root_id);
+
+        let mut runtime_ids: HashMap<u64, u64> = HashMap::new();
+        for entity_data in &prefab_data.entities {
+            let runtime_id = if entity_data.id == prefab_root_id {
+                instance_root_id
+            } else {
+                self.handle.add_entity(()).inner()
+            };
+            runtime_ids.insert(entity_data.id, runtime_id);
+        }
+        let remap = |file_id: Option<u64>| remap_file_id(&runtime_ids, file_id);
+        for entity_data in &prefab_data.entities {
+            let entity_overrides: Vec<PrefabOverride> = overrides
+                .iter()
+                .filter(|prefab_override| prefab_override.prefab_entity_id == entity_data.id)
+                .cloned()
+                .collect();
+            let mut entity_data = apply_overrides(entity_data, &entity_overrides)?;
+            entity_data.guid = old_guids.get(&entity_data.id).map(|guid| guid.0.clone());
+            let runtime_id = runtime_ids[&entity_data.id];
+            self.add_entity_data_components(runtime_id, &entity_data, &remap, resource_manager)?;
+            self.handle.add_component(
+                EntityId::from_inner(runtime_id).unwrap(),
+                PrefabEntity {
+                    prefab_entity_id: entity_data.id,
+                },
+            );
+        }
+
+        // the prefab only knows about the instance's children, keep its links to the scene
+        let new_hierarchy = self
+            .get_component_by_id::<Hierarchy>(instance_root_id)
+            .unwrap_or_default();
+        self.handle.add_component(
+            instance_root_entity_id,
+            Hierarchy {
+                num_children: new_hierarchy.num_children,
+                parent_runtime_id: old_hierarchy.parent_runtime_id,
+                first_child_runtime_id: new_hierarchy.first_child_runtime_id,
+                prev_sibling_runtime_id: old_hierarchy.prev_sibling_runtime_id,
+                next_sibling_runtime_id: old_hierarchy.next_sibling_runtime_id,
+            },
+        );
+        self.handle.add_component(
+            instance_root_entity_id,
+            PrefabInstance {
+                resource_handle,
+                overrides: overrides.to_vec(),
+            },
+        );
+        self.mark_transform_dirty(instance_root_id);
+        Ok(())
+    }
+}
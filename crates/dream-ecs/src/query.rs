@@ -0,0 +1,303 @@
+use std::marker::PhantomData;
+
+use shipyard::{EntityId, IntoIter, IntoWithId};
+
+use crate::scene::Scene;
+
+/// Part of a query, implemented for &T (read access), &mut T (write access), the With<T> and
+/// Without<T> filters and tuples of these
+pub trait QueryTerm {
+    /// Borrowed shipyard storages needed by the term
+    type View<'v>;
+    /// What the term yields for each matched entity
+    type Item<'a>;
+
+    fn borrow(world: &shipyard::World) -> Self::View<'_>;
+
+    /// Number of entities that can match, None when the term cannot drive the iteration
+    fn len(view: &Self::View<'_>) -> Option<usize>;
+
+    /// Entities that can match, only called when len returned Some
+    fn ids(view: &Self::View<'_>) -> Vec<EntityId>;
+
+    fn contains(view: &Self::View<'_>, entity_id: EntityId) -> bool;
+
+    /// Get the item of an entity, the entity must be contained in the view
+    fn fetch<'a, 'v: 'a>(view: &'a mut Self::View<'v>, entity_id: EntityId) -> Self::Item<'a>;
+}
+
+/// Only match entities that have component T, without borrowing it
+pub struct With<T>(PhantomData<T>);
+
+/// Only match entities that do not have component T
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: shipyard::Component + Send + Sync> QueryTerm for &T {
+    type View<'v> = shipyard::View<'v, T>;
+    type Item<'a> = &'a T;
+
+    fn borrow(world: &shipyard::World) -> Self::View<'_> {
+        world
+            .borrow::<shipyard::View<T>>()
+            .expect("Unable to borrow view for query")
+    }
+
+    fn len(view: &Self::View<'_>) -> Option<usize> {
+        Some(view.len())
+    }
+
+    fn ids(view: &Self::View<'_>) -> Vec<EntityId> {
+        view.iter()
+            .with_id()
+            .map(|(entity_id, _)| entity_id)
+            .collect()
+    }
+
+    fn contains(view: &Self::View<'_>, entity_id: EntityId) -> bool {
+        view.contains(entity_id)
+    }
+
+    fn fetch<'a, 'v: 'a>(view: &'a mut Self::View<'v>, entity_id: EntityId) -> Self::Item<'a> {
+        &view[entity_id]
+    }
+}
+
+/// Writes go through shipyard::Mut so they are reported as changed components
+impl<T: shipyard::Component<Tracking = shipyard::track::All> + Send + Sync> QueryTerm for &mut T {
+    type View<'v> = shipyard::ViewMut<'v, T>;
+    type Item<'a> = shipyard::Mut<'a, T>;
+
+    fn borrow(world: &shipyard::World) -> Self::View<'_> {
+        world
+            .borrow::<shipyard::ViewMut<T>>()
+            .expect("Unable to borrow mutable view for query")
+    }
+
+    fn len(view: &Self::View<'_>) -> Option<usize> {
+        Some(view.len())
+    }
+
+    fn ids(view: &Self::View<'_>) -> Vec<EntityId> {
+        view.iter()
+            .with_id()
+            .map(|(entity_id, _)| entity_id)
+            .collect()
+    }
+
+    fn contains(view: &Self::View<'_>, entity_id: EntityId) -> bool {
+        view.contains(entity_id)
+    }
+
+    fn fetch<'a, 'v: 'a>(view: &'a mut Self::View<'v>, entity_id: EntityId) -> Self::Item<'a> {
+        shipyard::Get::get(view, entity_id).expect("Query matched an entity without the component")
+    }
+}
+
+impl<T: shipyard::Component + Send + Sync> QueryTerm for With<T> {
+    type View<'v> = shipyard::View<'v, T>;
+    type Item<'a> = ();
+
+    fn borrow(world: &shipyard::World) -> Self::View<'_> {
+        world
+            .borrow::<shipyard::View<T>>()
+            .expect("Unable to borrow view for query")
+    }
+
+    fn len(view: &Self::View<'_>) -> Option<usize> {
+        Some(view.len())
+    }
+
+    fn ids(view: &Self::View<'_>) -> Vec<EntityId> {
+        view.iter()
+            .with_id()
+            .map(|(entity_id, _)| entity_id)
+            .collect()
+    }
+
+    fn contains(view: &Self::View<'_>, entity_id: EntityId) -> bool {
+        view.contains(entity_id)
+    }
+
+    fn fetch<'a, 'v: 'a>(_view: &'a mut Self::View<'v>, _entity_id: EntityId) -> Self::Item<'a> {}
+}
+
+impl<T: shipyard::Component + Send + Sync> QueryTerm for Without<T> {
+    type View<'v> = shipyard::View<'v, T>;
+    type Item<'a> = ();
+
+    fn borrow(world: &shipyard::World) -> Self::View<'_> {
+        world
+            .borrow::<shipyard::View<T>>()
+            .expect("Unable to borrow view for query")
+    }
+
+    fn len(_view: &Self::View<'_>) -> Option<usize> {
+        None
+    }
+
+    fn ids(_view: &Self::View<'_>) -> Vec<EntityId> {
+        Vec::new()
+    }
+
+    fn contains(view: &Self::View<'_>, entity_id: EntityId) -> bool {
+        !view.contains(entity_id)
+    }
+
+    fn fetch<'a, 'v: 'a>(_view: &'a mut Self::View<'v>, _entity_id: EntityId) -> Self::Item<'a> {}
+}
+
+/// Empty filter
+impl QueryTerm for () {
+    type View<'v> = ();
+    type Item<'a> = ();
+
+    fn borrow(_world: &shipyard::World) -> Self::View<'_> {}
+
+    fn len(_view: &Self::View<'_>) -> Option<usize> {
+        None
+    }
+
+    fn ids(_view: &Self::View<'_>) -> Vec<EntityId> {
+        Vec::new()
+    }
+
+    fn contains(_view: &Self::View<'_>, _entity_id: EntityId) -> bool {
+        true
+    }
+
+    fn fetch<'a, 'v: 'a>(_view: &'a mut Self::View<'v>, _entity_id: EntityId) -> Self::Item<'a> {}
+}
+
+macro_rules! impl_query_term_for_tuple {
+    ($(($term:ident, $idx:tt)),+) => {
+        impl<$($term: QueryTerm),+> QueryTerm for ($($term,)+) {
+            type View<'v> = ($($term::View<'v>,)+);
+            type Item<'a> = ($($term::Item<'a>,)+);
+
+            fn borrow(world: &shipyard::World) -> Self::View<'_> {
+                ($($term::borrow(world),)+)
+            }
+
+            fn len(view: &Self::View<'_>) -> Option<usize> {
+                [$($term::len(&view.$idx)),+].into_iter().flatten().min()
+            }
+
+            fn ids(view: &Self::View<'_>) -> Vec<EntityId> {
+                // drive the iteration with the smallest storage
+                let min_len = Self::len(view);
+                $(
+                    if min_len.is_some() && $term::len(&view.$idx) == min_len {
+                        return $term::ids(&view.$idx);
+                    }
+                )+
+                Vec::new()
+            }
+
+            fn contains(view: &Self::View<'_>, entity_id: EntityId) -> bool {
+                $($term::contains(&view.$idx, entity_id))&&+
+            }
+
+            fn fetch<'a, 'v: 'a>(
+                view: &'a mut Self::View<'v>,
+                entity_id: EntityId,
+            ) -> Self::Item<'a> {
+                ($($term::fetch(&mut view.$idx, entity_id),)+)
+            }
+        }
+    };
+}
+
+impl_query_term_for_tuple!((A, 0));
+impl_query_term_for_tuple!((A, 0), (B, 1));
+impl_query_term_for_tuple!((A, 0), (B, 1), (C, 2));
+impl_query_term_for_tuple!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_query_term_for_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_query_term_for_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+impl_query_term_for_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6));
+impl_query_term_for_tuple!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7)
+);
+
+/// Storages borrowed for a query, items are handed out without cloning while the scene is locked
+pub struct Query<'s, Q: QueryTerm, F: QueryTerm = ()> {
+    data: Q::View<'s>,
+    filter: F::View<'s>,
+}
+
+impl<'s, Q: QueryTerm, F: QueryTerm> Query<'s, Q, F> {
+    fn new(world: &'s shipyard::World) -> Self {
+        Self {
+            data: Q::borrow(world),
+            filter: F::borrow(world),
+        }
+    }
+
+    fn matching_ids(&self) -> Vec<EntityId> {
+        let candidate_ids = match (Q::len(&self.data), F::len(&self.filter)) {
+            (Some(data_len), Some(filter_len)) if filter_len < data_len => F::ids(&self.filter),
+            (Some(_), _) => Q::ids(&self.data),
+            (None, Some(_)) => F::ids(&self.filter),
+            (None, None) => {
+                log::warn!("Query needs at least one component or With<T> term");
+                Vec::new()
+            }
+        };
+        candidate_ids
+            .into_iter()
+            .filter(|entity_id| {
+                Q::contains(&self.data, *entity_id) && F::contains(&self.filter, *entity_id)
+            })
+            .collect()
+    }
+
+    /// Runtime ids of all matched entities
+    pub fn ids(&self) -> Vec<u64> {
+        self.matching_ids()
+            .into_iter()
+            .map(|entity_id| entity_id.inner())
+            .collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.matching_ids().len()
+    }
+
+    /// Get the item of a single entity if it is matched by the query
+    pub fn get(&mut self, entity_id: u64) -> Option<Q::Item<'_>> {
+        let entity_id = EntityId::from_inner(entity_id)?;
+        if Q::contains(&self.data, entity_id) && F::contains(&self.filter, entity_id) {
+            Some(Q::fetch(&mut self.data, entity_id))
+        } else {
+            None
+        }
+    }
+
+    /// Call f with the runtime id and item of every matched entity
+    pub fn for_each(&mut self, mut f: impl FnMut(u64, Q::Item<'_>)) {
+        for entity_id in self.matching_ids() {
+            f(entity_id.inner(), Q::fetch(&mut self.data, entity_id));
+        }
+    }
+}
+
+impl Scene {
+    /// Query components of all entities under a single lock of the scene, e.g.
+    /// scene.query::<(&Transform, &mut Light)>().for_each(|entity_id, (transform, light)| ...)
+    /// Writes through &mut T bypass the scene, call on_component_written::<T> for the written
+    /// entities once the query is dropped so the GlobalTransform and the name index follow them.
+    pub fn query<Q: QueryTerm>(&self) -> Query<'_, Q> {
+        Query::new(&self.handle)
+    }
+
+    /// Same as query, but only entities matched by the filter (e.g. Without<Bone>) are visited
+    pub fn query_filtered<Q: QueryTerm, F: QueryTerm>(&self) -> Query<'_, Q, F> {
+        Query::new(&self.handle)
+    }
+}
@@ -0,0 +1,913 @@
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+use std::sync::{RwLock, RwLockReadGuard, Weak};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde_yaml::{Mapping, Value};
+use shipyard::EntityId;
+
+use dream_math::{Quaternion, UnitQuaternion, Vector3};
+use dream_resource::resource_handle::ResourceHandle;
+
+use crate::component::{
+    Active, AnimationPlayer, Animator, Bone, Camera, CameraProjection, EntityRef, GltfNode, Guid,
+    Hierarchy, Light, LightType, MeshRenderer, MorphWeights, PrefabEntity, PrefabInstance,
+    PythonScript, RenderLayers, SceneCamera, Tag, Transform,
+};
+use crate::events::{collect_tracked_events, EventCollector};
+use crate::scene::Scene;
+
+/// Type of a reflected field, decides which widget the inspector shows for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Bool,
+    U32,
+    F32,
+    String,
+    Vec3,
+    Quat,
+    /// one of a fixed list of variant names
+    Enum(&'static [&'static str]),
+    Entity,
+}
+
+/// Value of a reflected field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Bool(bool),
+    U32(u32),
+    F32(f32),
+    String(String),
+    Vec3(Vector3<f32>),
+    Quat(UnitQuaternion<f32>),
+    /// index into the variant names of the field type
+    Enum(usize),
+    Entity(EntityRef),
+}
+
+impl FieldValue {
+    pub fn get_type_name(&self) -> &'static str {
+        match self {
+            FieldValue::Bool(_) => "bool",
+            FieldValue::U32(_) => "u32",
+            FieldValue::F32(_) => "f32",
+            FieldValue::String(_) => "string",
+            FieldValue::Vec3(_) => "vec3",
+            FieldValue::Quat(_) => "quat",
+            FieldValue::Enum(_) => "enum",
+            FieldValue::Entity(_) => "entity",
+        }
+    }
+
+    /// YAML representation, used by .scene files for components without dedicated data
+    pub fn to_yaml(&self, field_type: FieldType) -> Value {
+        match (self, field_type) {
+            (FieldValue::Bool(value), _) => Value::from(*value),
+            (FieldValue::U32(value), _) => Value::from(*value),
+            (FieldValue::F32(value), _) => Value::from(*value),
+            (FieldValue::String(value), _) => Value::from(value.clone()),
+            (FieldValue::Vec3(value), _) => Value::from(vec![value.x, value.y, value.z]),
+            // stored as [i, j, k, w], like TransformData
+            (FieldValue::Quat(value), _) => Value::from(vec![value.i, value.j, value.k, value.w]),
+            (FieldValue::Enum(idx), FieldType::Enum(variants)) => match variants.get(*idx) {
+                Some(variant) => Value::from(*variant),
+                None => Value::Null,
+            },
+            (FieldValue::Enum(idx), _) => Value::from(*idx),
+            // stored by guid, so the reference still resolves after loading
+            (FieldValue::Entity(entity_ref), _) => match &entity_ref.guid {
+                Some(guid) => Value::from(guid.0.clone()),
+                None => Value::Null,
+            },
+        }
+    }
+
+    pub fn from_yaml(field_type: FieldType, value: &Value) -> Result<Self> {
+        let parse_err = |err: serde_yaml::Error| anyhow!("Unable to parse field value: {}", err);
+        Ok(match field_type {
+            FieldType::Bool => {
+                FieldValue::Bool(serde_yaml::from_value(value.clone()).map_err(parse_err)?)
+            }
+            FieldType::U32 => {
+                FieldValue::U32(serde_yaml::from_value(value.clone()).map_err(parse_err)?)
+            }
+            FieldType::F32 => {
+                FieldValue::F32(serde_yaml::from_value(value.clone()).map_err(parse_err)?)
+            }
+            FieldType::String => {
+                FieldValue::String(serde_yaml::from_value(value.clone()).map_err(parse_err)?)
+            }
+            FieldType::Vec3 => {
+                let [x, y, z]: [f32; 3] =
+                    serde_yaml::from_value(value.clone()).map_err(parse_err)?;
+                FieldValue::Vec3(Vector3::new(x, y, z))
+            }
+            FieldType::Quat => {
+                let [i, j, k, w]: [f32; 4] =
+                    serde_yaml::from_value(value.clone()).map_err(parse_err)?;
+                FieldValue::Quat(UnitQuaternion::from_quaternion(Quaternion::new(w, i, j, k)))
+            }
+            FieldType::Enum(variants) => {
+                let variant: String = serde_yaml::from_value(value.clone()).map_err(parse_err)?;
+                FieldValue::Enum(
+                    variants
+                        .iter()
+                        .position(|name| *name == variant)
+                        .ok_or_else(|| anyhow!("Unknown variant {}", variant))?,
+                )
+            }
+            FieldType::Entity => {
+                let guid: Option<String> =
+                    serde_yaml::from_value(value.clone()).map_err(parse_err)?;
+                FieldValue::Entity(EntityRef {
+                    guid: guid.map(Guid),
+                })
+            }
+        })
+    }
+}
+
+/// Types that can be the type of a reflected field
+pub trait Reflect: Sized {
+    fn field_type() -> FieldType;
+    fn to_field_value(&self) -> FieldValue;
+    fn from_field_value(value: FieldValue) -> Result<Self>;
+}
+
+fn wrong_field_type(value: &FieldValue, expected: FieldType) -> anyhow::Error {
+    anyhow!(
+        "Expected a value of type {:?} but got a {}",
+        expected,
+        value.get_type_name()
+    )
+}
+
+macro_rules! impl_reflect {
+    ($type:ty, $variant:ident) => {
+        impl Reflect for $type {
+            fn field_type() -> FieldType {
+                FieldType::$variant
+            }
+
+            fn to_field_value(&self) -> FieldValue {
+                FieldValue::$variant(Clone::clone(self))
+            }
+
+            fn from_field_value(value: FieldValue) -> Result<Self> {
+                match value {
+                    FieldValue::$variant(value) => Ok(value),
+                    value => Err(wrong_field_type(&value, FieldType::$variant)),
+                }
+            }
+        }
+    };
+}
+
+impl_reflect!(bool, Bool);
+impl_reflect!(u32, U32);
+impl_reflect!(f32, F32);
+impl_reflect!(String, String);
+impl_reflect!(Vector3<f32>, Vec3);
+impl_reflect!(UnitQuaternion<f32>, Quat);
+impl_reflect!(EntityRef, Entity);
+
+const LIGHT_TYPE_VARIANTS: &[&str] = &["POINT", "DIRECTIONAL", "SPOT"];
+
+impl Reflect for LightType {
+    fn field_type() -> FieldType {
+        FieldType::Enum(LIGHT_TYPE_VARIANTS)
+    }
+
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Enum(*self as usize)
+    }
+
+    fn from_field_value(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Enum(0) => Ok(LightType::POINT),
+            FieldValue::Enum(1) => Ok(LightType::DIRECTIONAL),
+            FieldValue::Enum(2) => Ok(LightType::SPOT),
+            value => Err(wrong_field_type(&value, Self::field_type())),
+        }
+    }
+}
+
+const CAMERA_PROJECTION_VARIANTS: &[&str] = &["PERSPECTIVE", "ORTHOGRAPHIC"];
+
+impl Reflect for CameraProjection {
+    fn field_type() -> FieldType {
+        FieldType::Enum(CAMERA_PROJECTION_VARIANTS)
+    }
+
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Enum(*self as usize)
+    }
+
+    fn from_field_value(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Enum(0) => Ok(CameraProjection::PERSPECTIVE),
+            FieldValue::Enum(1) => Ok(CameraProjection::ORTHOGRAPHIC),
+            value => Err(wrong_field_type(&value, Self::field_type())),
+        }
+    }
+}
+
+type FieldGetter = Box<dyn Fn(&dyn Any) -> Option<FieldValue> + Send + Sync>;
+type FieldSetter = Box<dyn Fn(&mut dyn Any, FieldValue) -> Result<()> + Send + Sync>;
+
+/// Name, type and accessors of one field of a reflected component
+pub struct FieldDescriptor {
+    pub name: &'static str,
+    pub field_type: FieldType,
+    getter: FieldGetter,
+    setter: Option<FieldSetter>,
+}
+
+impl FieldDescriptor {
+    /// Read the field of a component value returned by ComponentDescriptor::get
+    pub fn get(&self, component: &dyn Any) -> Option<FieldValue> {
+        (self.getter)(component)
+    }
+
+    pub fn set(&self, component: &mut dyn Any, value: FieldValue) -> Result<()> {
+        match &self.setter {
+            Some(setter) => setter(component, value),
+            None => Err(anyhow!("Field {} is read-only", self.name)),
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.setter.is_none()
+    }
+}
+
+/// A component value of any registered type
+pub type ComponentValue = Box<dyn Any + Send + Sync>;
+
+/// Everything needed to work with a component type without knowing it at compile time
+pub struct ComponentDescriptor {
+    pub name: &'static str,
+    pub type_id: TypeId,
+    pub fields: Vec<FieldDescriptor>,
+    /// EntityData has its own entry for this component, so it is not written as reflected fields.
+    /// Only saving looks at this, copies, undo and prefabs handle every unmanaged component alike.
+    pub(crate) has_dedicated_data: bool,
+    /// Kept up to date by the scene itself, like hierarchy links and guids. It is shown but never
+    /// added, removed or copied through the registry.
+    managed: bool,
+    /// Set for components declared with #[track(All)], whose events every scene records
+    pub(crate) event_collector: Option<EventCollector>,
+    has_fn: fn(&Scene, u64) -> bool,
+    get_fn: fn(&Scene, u64) -> Option<ComponentValue>,
+    insert_fn: fn(&mut Scene, u64, ComponentValue) -> Result<()>,
+    remove_fn: fn(&mut Scene, u64),
+    default_fn: Option<fn() -> ComponentValue>,
+    clone_fn: fn(&dyn Any) -> Option<ComponentValue>,
+}
+
+impl ComponentDescriptor {
+    pub fn is_managed(&self) -> bool {
+        self.managed
+    }
+
+    pub fn has(&self, scene: &Scene, entity_id: u64) -> bool {
+        (self.has_fn)(scene, entity_id)
+    }
+
+    /// Copy of the component of an entity
+    pub fn get(&self, scene: &Scene, entity_id: u64) -> Option<ComponentValue> {
+        (self.get_fn)(scene, entity_id)
+    }
+
+    /// Add a component to an entity, replacing the one it has
+    pub fn insert(
+        &self,
+        scene: &mut Scene,
+        entity_id: u64,
+        component: ComponentValue,
+    ) -> Result<()> {
+        (self.insert_fn)(scene, entity_id, component)
+    }
+
+    pub fn remove(&self, scene: &mut Scene, entity_id: u64) {
+        (self.remove_fn)(scene, entity_id)
+    }
+
+    /// Copy of a component value of this type
+    pub fn clone_value(&self, component: &dyn Any) -> Result<ComponentValue> {
+        (self.clone_fn)(component).ok_or_else(|| anyhow!("Component value is not a {}", self.name))
+    }
+
+    pub fn create_default(&self) -> Result<ComponentValue> {
+        let default_fn = self
+            .default_fn
+            .ok_or_else(|| anyhow!("Component {} is managed by the scene", self.name))?;
+        Ok(default_fn())
+    }
+
+    pub fn add_default(&self, scene: &mut Scene, entity_id: u64) -> Result<()> {
+        self.insert(scene, entity_id, self.create_default()?)
+    }
+
+    /// Copy the component from one entity to another, returns false if the source entity does
+    /// not have it
+    pub fn clone_component(
+        &self,
+        from_scene: &Scene,
+        from_entity_id: u64,
+        to_scene: &mut Scene,
+        to_entity_id: u64,
+    ) -> Result<bool> {
+        match self.get(from_scene, from_entity_id) {
+            Some(component) => {
+                self.insert(to_scene, to_entity_id, component)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn get_field_descriptor(&self, field_name: &str) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|field| field.name == field_name)
+    }
+
+    pub fn get_field(&self, scene: &Scene, entity_id: u64, field_name: &str) -> Option<FieldValue> {
+        let field = self.get_field_descriptor(field_name)?;
+        field.get(&*self.get(scene, entity_id)?)
+    }
+
+    pub fn set_field(
+        &self,
+        scene: &mut Scene,
+        entity_id: u64,
+        field_name: &str,
+        value: FieldValue,
+    ) -> Result<()> {
+        let field = self
+            .get_field_descriptor(field_name)
+            .ok_or_else(|| anyhow!("Component {} has no field {}", self.name, field_name))?;
+        let mut component = self
+            .get(scene, entity_id)
+            .ok_or_else(|| anyhow!("Entity {} has no {} component", entity_id, self.name))?;
+        field.set(&mut *component, value)?;
+        self.insert(scene, entity_id, component)
+    }
+
+    /// Point the entity reference fields of a component value to other entities, references
+    /// that map to None are kept
+    pub(crate) fn remap_entity_refs(
+        &self,
+        component: &mut dyn Any,
+        remap: impl Fn(&Guid) -> Option<Guid>,
+    ) -> Result<()> {
+        for field in self.fields.iter().filter(|field| !field.is_read_only()) {
+            if let Some(FieldValue::Entity(EntityRef { guid: Some(guid) })) = field.get(component) {
+                if let Some(new_guid) = remap(&guid) {
+                    field.set(component, FieldValue::Entity(EntityRef::new(new_guid)))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Mapping of <field name: value> of the component of an entity
+    pub(crate) fn to_yaml(&self, scene: &Scene, entity_id: u64) -> Option<Value> {
+        let component = self.get(scene, entity_id)?;
+        let mut mapping = Mapping::new();
+        for field in &self.fields {
+            if let Some(value) = field.get(&*component) {
+                mapping.insert(Value::from(field.name), value.to_yaml(field.field_type));
+            }
+        }
+        Some(Value::Mapping(mapping))
+    }
+
+    /// Component built from a mapping written by to_yaml, missing fields keep their defaults
+    pub(crate) fn parse_yaml(&self, value: &Value) -> Result<ComponentValue> {
+        let mut component = self.create_default()?;
+        if let Value::Mapping(mapping) = value {
+            for field in self.fields.iter().filter(|field| !field.is_read_only()) {
+                if let Some(field_value) = mapping.get(field.name) {
+                    let field_value = FieldValue::from_yaml(field.field_type, field_value)
+                        .map_err(|err| anyhow!("{}.{}: {}", self.name, field.name, err))?;
+                    field.set(&mut *component, field_value)?;
+                }
+            }
+        }
+        Ok(component)
+    }
+}
+
+/// Bounds of the components that can be registered
+pub trait ReflectComponent:
+    shipyard::Component
+    + shipyard::TupleAddComponent
+    + shipyard::TupleRemove
+    + Default
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> ReflectComponent for T where
+    T: shipyard::Component
+        + shipyard::TupleAddComponent
+        + shipyard::TupleRemove
+        + Default
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+/// Bounds of the components the scene keeps up to date itself, see ComponentRegistration::managed
+pub trait ManagedComponent: shipyard::Component + Clone + Send + Sync + 'static {}
+
+impl<T> ManagedComponent for T where T: shipyard::Component + Clone + Send + Sync + 'static {}
+
+fn has_component<T: ManagedComponent>(scene: &Scene, entity_id: u64) -> bool {
+    scene.get_component_by_id::<T>(entity_id).is_some()
+}
+
+fn get_component<T: ManagedComponent>(scene: &Scene, entity_id: u64) -> Option<ComponentValue> {
+    scene
+        .get_component_by_id::<T>(entity_id)
+        .map(|component| Box::new(component) as ComponentValue)
+}
+
+fn insert_component<T: ReflectComponent>(
+    scene: &mut Scene,
+    entity_id: u64,
+    component: ComponentValue,
+) -> Result<()> {
+    let component = component
+        .downcast::<T>()
+        .map_err(|_| anyhow!("Component value is not a {}", std::any::type_name::<T>()))?;
+    let shipyard_entity_id =
+        EntityId::from_inner(entity_id).ok_or_else(|| anyhow!("Invalid entity {}", entity_id))?;
+    scene.handle.add_component(shipyard_entity_id, *component);
+    scene.on_component_written::<T>(entity_id);
+    Ok(())
+}
+
+fn remove_component<T: ReflectComponent>(scene: &mut Scene, entity_id: u64) {
+    if let Some(shipyard_entity_id) = EntityId::from_inner(entity_id) {
+        scene.handle.remove::<T>(shipyard_entity_id);
+        scene.on_component_written::<T>(entity_id);
+    }
+}
+
+fn insert_managed_component<T: ManagedComponent>(
+    _scene: &mut Scene,
+    _entity_id: u64,
+    _component: ComponentValue,
+) -> Result<()> {
+    Err(anyhow!(
+        "{} is managed by the scene",
+        std::any::type_name::<T>()
+    ))
+}
+
+fn remove_managed_component(_scene: &mut Scene, _entity_id: u64) {}
+
+fn default_component<T: ReflectComponent>() -> ComponentValue {
+    Box::<T>::default()
+}
+
+fn clone_component_value<T: ManagedComponent>(component: &dyn Any) -> Option<ComponentValue> {
+    component
+        .downcast_ref::<T>()
+        .map(|component| Box::new(component.clone()) as ComponentValue)
+}
+
+/// Builder for the descriptor of a component type
+pub struct ComponentRegistration<T> {
+    descriptor: ComponentDescriptor,
+    _component: PhantomData<T>,
+}
+
+impl<T: ReflectComponent> ComponentRegistration<T> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            descriptor: ComponentDescriptor {
+                name,
+                type_id: TypeId::of::<T>(),
+                fields: Vec::new(),
+                has_dedicated_data: false,
+                managed: false,
+                event_collector: None,
+                has_fn: has_component::<T>,
+                get_fn: get_component::<T>,
+                insert_fn: insert_component::<T>,
+                remove_fn: remove_component::<T>,
+                default_fn: Some(default_component::<T>),
+                clone_fn: clone_component_value::<T>,
+            },
+            _component: PhantomData,
+        }
+    }
+}
+
+impl<T: ManagedComponent> ComponentRegistration<T> {
+    /// A component the scene keeps up to date itself, it can be inspected and its fields are
+    /// read-only. Such components are saved as dedicated entity data.
+    pub(crate) fn managed(name: &'static str) -> Self {
+        Self {
+            descriptor: ComponentDescriptor {
+                name,
+                type_id: TypeId::of::<T>(),
+                fields: Vec::new(),
+                has_dedicated_data: true,
+                managed: true,
+                event_collector: None,
+                has_fn: has_component::<T>,
+                get_fn: get_component::<T>,
+                insert_fn: insert_managed_component::<T>,
+                remove_fn: remove_managed_component,
+                default_fn: None,
+                clone_fn: clone_component_value::<T>,
+            },
+            _component: PhantomData,
+        }
+    }
+
+    pub fn field<V: Reflect + 'static>(
+        mut self,
+        name: &'static str,
+        get: fn(&T) -> V,
+        set: fn(&mut T, V),
+    ) -> Self {
+        self.descriptor.fields.push(FieldDescriptor {
+            name,
+            field_type: V::field_type(),
+            getter: Self::getter(get),
+            setter: Some(Box::new(move |component: &mut dyn Any, value| {
+                let component = component
+                    .downcast_mut::<T>()
+                    .ok_or_else(|| anyhow!("Component value is not a {}", name))?;
+                set(component, V::from_field_value(value)?);
+                Ok(())
+            })),
+        });
+        self
+    }
+
+    /// A field that is shown but can not be changed through reflection
+    pub fn read_only_field<V: Reflect + 'static>(
+        mut self,
+        name: &'static str,
+        get: fn(&T) -> V,
+    ) -> Self {
+        self.descriptor.fields.push(FieldDescriptor {
+            name,
+            field_type: V::field_type(),
+            getter: Self::getter(get),
+            setter: None,
+        });
+        self
+    }
+
+    fn getter<V: Reflect + 'static>(get: fn(&T) -> V) -> FieldGetter {
+        Box::new(move |component: &dyn Any| {
+            component
+                .downcast_ref::<T>()
+                .map(|component| get(component).to_field_value())
+        })
+    }
+
+    fn with_dedicated_data(mut self) -> Self {
+        self.descriptor.has_dedicated_data = true;
+        self
+    }
+}
+
+impl<T> ComponentRegistration<T>
+where
+    T: ManagedComponent + shipyard::Component<Tracking = shipyard::track::All>,
+{
+    /// Record Added, Changed and Removed events of the component in every scene
+    pub fn tracked(mut self) -> Self {
+        self.descriptor.event_collector = Some(collect_tracked_events::<T>);
+        self
+    }
+}
+
+/// Component types that can be inspected, saved, undone and scripted without knowing them at
+/// compile time, in registration order
+#[derive(Default)]
+pub struct ComponentRegistry {
+    components: Vec<ComponentDescriptor>,
+}
+
+impl ComponentRegistry {
+    /// Register a component type, replacing an earlier registration with the same name
+    pub fn register<T: ManagedComponent>(&mut self, registration: ComponentRegistration<T>) {
+        let descriptor = registration.descriptor;
+        self.components.retain(|component| {
+            component.name != descriptor.name && component.type_id != descriptor.type_id
+        });
+        self.components.push(descriptor);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ComponentDescriptor> {
+        self.components
+            .iter()
+            .find(|component| component.name == name)
+    }
+
+    pub fn get_by_type<T: 'static>(&self) -> Option<&ComponentDescriptor> {
+        self.components
+            .iter()
+            .find(|component| component.type_id == TypeId::of::<T>())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentDescriptor> {
+        self.components.iter()
+    }
+
+    /// Components that can be added, removed and copied through the registry
+    pub fn iter_unmanaged(&self) -> impl Iterator<Item = &ComponentDescriptor> {
+        self.components
+            .iter()
+            .filter(|component| !component.managed)
+    }
+
+    /// Components that are saved as reflected fields instead of dedicated entity data
+    pub(crate) fn iter_without_dedicated_data(&self) -> impl Iterator<Item = &ComponentDescriptor> {
+        self.components
+            .iter()
+            .filter(|component| !component.has_dedicated_data)
+    }
+
+    fn with_builtin_components() -> Self {
+        let mut registry = Self::default();
+        registry.register(
+            ComponentRegistration::<Tag>::new("Tag")
+                .field("name", |tag| tag.name.clone(), |tag, name| tag.name = name)
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<Active>::new("Active")
+                .field(
+                    "active",
+                    |active| active.active,
+                    |active, value| active.active = value,
+                )
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<RenderLayers>::new("RenderLayers")
+                .field(
+                    "mask",
+                    |render_layers| render_layers.mask,
+                    |render_layers, mask| render_layers.mask = mask,
+                )
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<Transform>::new("Transform")
+                .field(
+                    "position",
+                    |transform| transform.position,
+                    |transform, position| transform.position = position,
+                )
+                .field(
+                    "rotation",
+                    |transform| transform.rotation,
+                    |transform, rotation| {
+                        transform.rotation = rotation;
+                        transform.euler_angles = rotation.euler_angles();
+                    },
+                )
+                .field(
+                    "scale",
+                    |transform| transform.scale,
+                    |transform, scale| transform.scale = scale,
+                )
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<Light>::new("Light")
+                .field(
+                    "light_type",
+                    |light| light.light_type,
+                    |light, light_type| light.light_type = light_type,
+                )
+                .field(
+                    "color",
+                    |light| light.color,
+                    |light, color| light.color = color,
+                )
+                .field(
+                    "radius",
+                    |light| light.radius,
+                    |light, radius| light.radius = radius,
+                )
+                .field(
+                    "direction",
+                    |light| light.direction,
+                    |light, direction| light.direction = direction,
+                )
+                .field(
+                    "cast_shadow",
+                    |light| light.cast_shadow,
+                    |light, cast_shadow| light.cast_shadow = cast_shadow,
+                )
+                .field(
+                    "inner_cone_angle",
+                    |light| light.inner_cone_angle,
+                    |light, inner_cone_angle| light.inner_cone_angle = inner_cone_angle,
+                )
+                .field(
+                    "outer_cone_angle",
+                    |light| light.outer_cone_angle,
+                    |light, outer_cone_angle| light.outer_cone_angle = outer_cone_angle,
+                )
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<SceneCamera>::new("SceneCamera")
+                .tracked()
+                .with_dedicated_data(),
+        );
+        // saved, copied and undone through its fields, it has no dedicated data
+        registry.register(
+            ComponentRegistration::<Camera>::new("Camera")
+                .field(
+                    "projection",
+                    |camera| camera.projection,
+                    |camera, projection| camera.projection = projection,
+                )
+                .field(
+                    "fovy",
+                    |camera| camera.fovy,
+                    |camera, fovy| camera.fovy = fovy,
+                )
+                .field(
+                    "aspect_ratio",
+                    |camera| camera.aspect_ratio,
+                    |camera, aspect_ratio| camera.aspect_ratio = aspect_ratio,
+                )
+                .field(
+                    "xmag",
+                    |camera| camera.xmag,
+                    |camera, xmag| camera.xmag = xmag,
+                )
+                .field(
+                    "ymag",
+                    |camera| camera.ymag,
+                    |camera, ymag| camera.ymag = ymag,
+                )
+                .field(
+                    "znear",
+                    |camera| camera.znear,
+                    |camera, znear| camera.znear = znear,
+                )
+                .field(
+                    "zfar",
+                    |camera| camera.zfar,
+                    |camera, zfar| camera.zfar = zfar,
+                ),
+        );
+        registry.register(
+            ComponentRegistration::<MeshRenderer>::new("MeshRenderer")
+                .read_only_field("model", |mesh_renderer| {
+                    resource_key(&mesh_renderer.resource_handle)
+                })
+                .read_only_field("mesh_idx", |mesh_renderer| {
+                    mesh_renderer.mesh_idx.unwrap_or_default() as u32
+                })
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<PythonScript>::new("PythonScript")
+                .read_only_field("script", |python_script| {
+                    resource_key(&python_script.resource_handle)
+                })
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(
+            ComponentRegistration::<AnimationPlayer>::new("AnimationPlayer")
+                .read_only_field("model", |animation_player| {
+                    resource_key(&animation_player.resource_handle)
+                })
+                .field(
+                    "clip",
+                    |animation_player| animation_player.clip.clone(),
+                    |animation_player, clip| animation_player.clip = clip,
+                )
+                .field(
+                    "time",
+                    |animation_player| animation_player.time,
+                    |animation_player, time| animation_player.time = time,
+                )
+                .field(
+                    "speed",
+                    |animation_player| animation_player.speed,
+                    |animation_player, speed| animation_player.speed = speed,
+                )
+                .field(
+                    "playing",
+                    |animation_player| animation_player.playing,
+                    |animation_player, playing| animation_player.playing = playing,
+                )
+                .field(
+                    "looping",
+                    |animation_player| animation_player.looping,
+                    |animation_player, looping| animation_player.looping = looping,
+                )
+                .tracked()
+                .with_dedicated_data(),
+        );
+        // parameters are set through Animator::set_parameter, they are not fixed fields
+        registry.register(
+            ComponentRegistration::<Animator>::new("Animator")
+                .read_only_field("state_machine", |animator| {
+                    resource_key(&animator.resource_handle)
+                })
+                .read_only_field("state", |animator| animator.state.clone())
+                .tracked()
+                .with_dedicated_data(),
+        );
+        registry.register(ComponentRegistration::<GltfNode>::new("GltfNode").field(
+            "node_index",
+            |gltf_node| gltf_node.node_index,
+            |gltf_node, node_index| gltf_node.node_index = node_index,
+        ));
+        registry.register(
+            ComponentRegistration::<Guid>::managed("Guid")
+                .read_only_field("guid", |guid| guid.0.clone()),
+        );
+        registry.register(
+            ComponentRegistration::<Hierarchy>::managed("Hierarchy")
+                .read_only_field("num_children", |hierarchy| hierarchy.num_children as u32),
+        );
+        registry.register(
+            ComponentRegistration::<Bone>::managed("Bone")
+                .read_only_field("is_root", |bone| bone.is_root)
+                .read_only_field("node_id", |bone| bone.node_id)
+                .read_only_field("bone_id", |bone| bone.bone_id)
+                .tracked(),
+        );
+        // weights are written by animation clips and scripts, see MorphWeights
+        registry.register(
+            ComponentRegistration::<MorphWeights>::managed("MorphWeights")
+                .read_only_field("weights", |morph_weights| {
+                    format!("{:?}", morph_weights.weights)
+                }),
+        );
+        registry.register(
+            ComponentRegistration::<PrefabInstance>::managed("PrefabInstance")
+                .read_only_field("prefab", |prefab_instance| {
+                    resource_key(&prefab_instance.resource_handle)
+                })
+                .read_only_field("overrides", |prefab_instance| {
+                    prefab_instance.overrides.len() as u32
+                }),
+        );
+        registry.register(
+            ComponentRegistration::<PrefabEntity>::managed("PrefabEntity")
+                .read_only_field("prefab_entity_id", |prefab_entity| {
+                    prefab_entity.prefab_entity_id as u32
+                }),
+        );
+        registry
+    }
+}
+
+fn resource_key(resource_handle: &Option<Weak<ResourceHandle>>) -> String {
+    resource_handle
+        .as_ref()
+        .and_then(|resource_handle| resource_handle.upgrade())
+        .map(|resource_handle| resource_handle.key.clone())
+        .unwrap_or_default()
+}
+
+static COMPONENT_REGISTRY: Lazy<RwLock<ComponentRegistry>> =
+    Lazy::new(|| RwLock::new(ComponentRegistry::with_builtin_components()));
+
+/// Register a component type with the global registry, e.g. a component defined in a user crate
+pub fn register_component<T: ReflectComponent>(registration: ComponentRegistration<T>) {
+    COMPONENT_REGISTRY
+        .write()
+        .expect("Unable to acquire lock on component registry")
+        .register(registration);
+}
+
+/// The global registry, which holds the built-in components and the registered ones
+pub fn component_registry() -> RwLockReadGuard<'static, ComponentRegistry> {
+    COMPONENT_REGISTRY
+        .read()
+        .expect("Unable to acquire lock on component registry")
+}
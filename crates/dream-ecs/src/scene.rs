@@ -16,54 +16,151 @@
  *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
  **********************************************************************************/
 
+use std::any::TypeId;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex, Weak};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use gltf::buffer::Source;
-use shipyard::{IntoIter, IntoWithId};
+use shipyard::{EntityId, Get};
 
-use dream_fs::fs::read_binary;
-use dream_math::Matrix4;
+use dream_fs::fs::{read_binary, read_uri};
+use dream_math::{Matrix4, UnitQuaternion, Vector3};
 use dream_resource::resource_manager::ResourceManager;
 
-use crate::component::{Bone, Hierarchy, MeshRenderer, Tag, Transform};
+use crate::animation::{cache_animation_clips, read_gltf_animation_clips};
+use crate::component::{
+    Active, AnimationPlayer, Bone, Camera, CameraProjection, GlobalTransform, GltfNode, Guid,
+    Hierarchy, Light, LightType, MeshRenderer, MorphWeights, PrefabEntity, PrefabInstance, Tag,
+    Transform,
+};
 use crate::entity::Entity;
+use crate::events::{ComponentEvents, EventCollector};
+use crate::history::CommandHistory;
+use crate::name_index::NameIndex;
+use crate::query::With;
+use crate::reflect::component_registry;
+use crate::serialization::SceneData;
 
 // pub(crate) static SCENE: Lazy<Mutex<Scene>> = Lazy::new(|| Mutex::new(Scene::default()));
 
 pub struct Scene {
-    pub name: &'static str,
+    pub name: String,
+    /// Resource guid of the .scene file the scene was loaded from
+    pub guid: Option<String>,
     pub root_entity_runtime_id: Option<u64>,
     pub handle: shipyard::World,
+    /// Entities destroyed since the systems were last notified
+    pub destroyed_entity_runtime_ids: Vec<u64>,
+    /// Prefab data that the instances in this scene were last synced to, keyed by prefab guid
+    pub prefabs: HashMap<String, SceneData>,
+    /// Entities whose Transform or parent changed since the last transform propagation
+    pub dirty_transform_runtime_ids: HashSet<u64>,
+    /// Lookup of entities by their Tag, used by find_by_name and find_by_path
+    pub(crate) name_index: NameIndex,
+    /// Map of <entity guid: runtime id>
+    pub(crate) guid_to_runtime_id: HashMap<Guid, u64>,
+    pub(crate) event_collectors: HashMap<TypeId, EventCollector>,
+    /// Added, changed and removed components of the last frame, keyed by component type
+    pub(crate) component_events: HashMap<TypeId, ComponentEvents>,
+    /// Commands that can be undone and redone, see Scene::execute
+    pub history: CommandHistory,
 }
 
 impl Scene {
     pub fn create() -> Arc<Mutex<Scene>> {
-        Arc::new(Mutex::new(Self {
-            name: "scene",
+        Arc::new(Mutex::new(Self::empty()))
+    }
+
+    /// Scene without entities that is not shared yet
+    pub(crate) fn empty() -> Self {
+        Self {
+            name: String::from("scene"),
+            guid: None,
             handle: shipyard::World::new(),
             root_entity_runtime_id: None,
-        }))
+            destroyed_entity_runtime_ids: Vec::new(),
+            prefabs: HashMap::new(),
+            dirty_transform_runtime_ids: HashSet::new(),
+            name_index: NameIndex::default(),
+            guid_to_runtime_id: HashMap::new(),
+            event_collectors: HashMap::new(),
+            component_events: HashMap::new(),
+            history: CommandHistory::default(),
+        }
     }
 
     pub fn get_entities_with_component<T: shipyard::Component + Send + Sync + Clone>(
         &self,
     ) -> Vec<u64> {
-        let mut entity_id_vec = Vec::new();
-        self.handle.run(|vm_view: shipyard::ViewMut<T>| {
-            for t in vm_view.iter().with_id() {
-                let entity_id = t.0;
-                entity_id_vec.push(entity_id);
+        self.query::<With<T>>().ids()
+    }
+
+    /// Entities with component T that are active, see is_active_in_hierarchy
+    pub fn get_active_entities_with_component<T: shipyard::Component + Send + Sync + Clone>(
+        &self,
+    ) -> Vec<u64> {
+        self.get_entities_with_component::<T>()
+            .into_iter()
+            .filter(|entity_id| self.is_active_in_hierarchy(*entity_id))
+            .collect()
+    }
+
+    /// Whether the Active component of the entity itself is set, ignoring its ancestors
+    pub fn is_active_self(&self, entity_id: u64) -> bool {
+        self.get_component_by_id::<Active>(entity_id)
+            .map_or(true, |active| active.active)
+    }
+
+    /// Whether the entity and all of its ancestors are active
+    pub fn is_active_in_hierarchy(&self, entity_id: u64) -> bool {
+        let mut cur_entity_id = Some(entity_id);
+        while let Some(cur_entity_id_unwrapped) = cur_entity_id {
+            if !self.is_active_self(cur_entity_id_unwrapped) {
+                return false;
             }
-        });
-        let mut entity_vec = Vec::new();
-        for entity_id in &entity_id_vec {
-            entity_vec.push(entity_id.inner());
+            cur_entity_id = self
+                .get_component_by_id::<Hierarchy>(cur_entity_id_unwrapped)
+                .and_then(|hierarchy| hierarchy.parent_runtime_id);
         }
-        entity_vec
+        true
+    }
+
+    pub(crate) fn get_component_by_id<T: shipyard::Component + Send + Sync + Clone>(
+        &self,
+        entity_id: u64,
+    ) -> Option<T> {
+        self.handle.run(|view: shipyard::View<T>| {
+            view.get(EntityId::from_inner(entity_id).unwrap())
+                .ok()
+                .cloned()
+        })
     }
 
+    /// Collect the entity and its descendants in depth-first order, following the child order
+    /// of the hierarchy
+    pub fn collect_subtree(&self, entity_id: u64) -> Vec<u64> {
+        let mut result = Vec::new();
+        let mut stack = vec![entity_id];
+        while let Some(cur_entity_id) = stack.pop() {
+            result.push(cur_entity_id);
+            let mut children = Vec::new();
+            if let Some(hierarchy) = self.get_component_by_id::<Hierarchy>(cur_entity_id) {
+                let mut cur_child = hierarchy.first_child_runtime_id;
+                while let Some(child_id) = cur_child {
+                    children.push(child_id);
+                    cur_child = self
+                        .get_component_by_id::<Hierarchy>(child_id)
+                        .and_then(|h| h.next_sibling_runtime_id);
+                }
+            }
+            // push in reverse so the first child is visited first
+            stack.extend(children.into_iter().rev());
+        }
+        result
+    }
+
+    /// Children of an entity in hierarchy order, the order is kept when the scene is saved
     pub fn get_children_for_entity(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Vec<u64> {
         let entity = Entity::from_handle(entity_id, scene.clone());
         let hierarchy_component: Option<Hierarchy> = entity.get_component();
@@ -84,53 +181,260 @@ impl Scene {
         result
     }
 
+    /// Add an entity as the last child of a parent, see insert_child_at
     pub fn add_child_to_entity(
         scene: Weak<Mutex<Scene>>,
         child_entity_id: u64,
         parent_entity_id: u64,
-    ) {
+    ) -> Result<()> {
+        Scene::insert_child_at(scene, child_entity_id, parent_entity_id, usize::MAX)
+    }
+
+    /// Insert an entity into the children list of a parent at index, an index past the end
+    /// appends it. An entity that already has a parent is unlinked from it first, its children
+    /// move with it. Inserting an entity under itself or its own descendant is an error.
+    pub fn insert_child_at(
+        scene: Weak<Mutex<Scene>>,
+        child_entity_id: u64,
+        parent_entity_id: u64,
+        index: usize,
+    ) -> Result<()> {
+        if child_entity_id == parent_entity_id
+            || Scene::is_ancestor(scene.clone(), child_entity_id, parent_entity_id)
+        {
+            return Err(anyhow!(
+                "Unable to insert entity {} under its own descendant {}",
+                child_entity_id,
+                parent_entity_id
+            ));
+        }
         let child_entity = Entity::from_handle(child_entity_id, scene.clone());
         let parent_entity = Entity::from_handle(parent_entity_id, scene.clone());
 
         if child_entity.has_component::<Hierarchy>() && parent_entity.has_component::<Hierarchy>() {
-            let mut parent_hierarchy_component: Hierarchy = parent_entity.get_component().unwrap();
-            let mut child_hierarchy_component: Hierarchy = child_entity.get_component().unwrap();
+            Scene::remove_from_parent(scene.clone(), child_entity_id);
+
+            // find the siblings the child ends up between
+            let siblings = Scene::get_children_for_entity(scene.clone(), parent_entity_id);
+            let index = index.min(siblings.len());
+            let prev_sibling_id = index.checked_sub(1).map(|prev_idx| siblings[prev_idx]);
+            let next_sibling_id = siblings.get(index).copied();
 
-            if child_hierarchy_component.parent_runtime_id.is_some() {
-                // TODO: if child already has parent, remove it from that parent (not a full remove cuz children need to move with it)
-                // ^ might be best to create a general 'move' method where you move a child from one parent to a different parent
-                todo!();
+            if let Some(prev_sibling_id) = prev_sibling_id {
+                let prev_sibling_entity = Entity::from_handle(prev_sibling_id, scene.clone());
+                if let Some(mut prev_sibling_hierarchy) =
+                    prev_sibling_entity.get_component::<Hierarchy>()
+                {
+                    prev_sibling_hierarchy.next_sibling_runtime_id = Some(child_entity_id);
+                    prev_sibling_entity.add_component(prev_sibling_hierarchy);
+                }
+            }
+            if let Some(next_sibling_id) = next_sibling_id {
+                let next_sibling_entity = Entity::from_handle(next_sibling_id, scene.clone());
+                if let Some(mut next_sibling_hierarchy) =
+                    next_sibling_entity.get_component::<Hierarchy>()
+                {
+                    next_sibling_hierarchy.prev_sibling_runtime_id = Some(child_entity_id);
+                    next_sibling_entity.add_component(next_sibling_hierarchy);
+                }
             }
 
+            let mut parent_hierarchy_component: Hierarchy = parent_entity.get_component().unwrap();
+            let mut child_hierarchy_component: Hierarchy = child_entity.get_component().unwrap();
             parent_hierarchy_component.num_children += 1;
-            if parent_hierarchy_component.first_child_runtime_id.is_none() {
-                // set child as first child of parent
+            if index == 0 {
                 parent_hierarchy_component.first_child_runtime_id = Some(child_entity_id);
-            } else {
-                // insert entity to front of children list
-                // set first child of parent to new child
-                let former_first_child = parent_hierarchy_component.first_child_runtime_id;
-                parent_hierarchy_component.first_child_runtime_id = Some(child_entity_id);
-                // set child hierarchy component next to former first child
-                child_hierarchy_component.next_sibling_runtime_id = former_first_child;
-                // set former first child's previous to this child
-                if let Some(former_first_child_entity_id) = former_first_child {
-                    let former_first_child_entity =
-                        Entity::from_handle(former_first_child_entity_id, scene);
-                    if former_first_child_entity.has_component::<Hierarchy>() {
-                        let mut former_first_child_hierarchy_component: Hierarchy =
-                            former_first_child_entity.get_component().unwrap();
-                        former_first_child_hierarchy_component.prev_sibling_runtime_id =
-                            Some(child_entity_id);
-                        former_first_child_entity
-                            .add_component(former_first_child_hierarchy_component);
-                    }
-                }
             }
             child_hierarchy_component.parent_runtime_id = Some(parent_entity_id);
+            child_hierarchy_component.prev_sibling_runtime_id = prev_sibling_id;
+            child_hierarchy_component.next_sibling_runtime_id = next_sibling_id;
             parent_entity.add_component(parent_hierarchy_component);
             child_entity.add_component(child_hierarchy_component);
+            // world transforms of the moved subtree depend on the new parent
+            if let Some(scene) = scene.upgrade() {
+                scene
+                    .lock()
+                    .expect("Unable to acquire scene mutex when adding child")
+                    .mark_transform_dirty(child_entity_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Position of an entity in the children list of its parent
+    pub fn get_child_index(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Option<usize> {
+        let parent_id = Entity::from_handle(entity_id, scene.clone())
+            .get_component::<Hierarchy>()
+            .and_then(|hierarchy| hierarchy.parent_runtime_id)?;
+        Scene::get_children_for_entity(scene, parent_id)
+            .iter()
+            .position(|child_id| *child_id == entity_id)
+    }
+
+    /// Swap an entity with its previous sibling, returns false when it is already the first child
+    pub fn move_sibling_up(scene: Weak<Mutex<Scene>>, entity_id: u64) -> bool {
+        let parent_id = Entity::from_handle(entity_id, scene.clone())
+            .get_component::<Hierarchy>()
+            .and_then(|hierarchy| hierarchy.parent_runtime_id);
+        match (parent_id, Scene::get_child_index(scene.clone(), entity_id)) {
+            (Some(parent_id), Some(index)) if index > 0 => {
+                Scene::insert_child_at(scene, entity_id, parent_id, index - 1).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Swap an entity with its next sibling, returns false when it is already the last child
+    pub fn move_sibling_down(scene: Weak<Mutex<Scene>>, entity_id: u64) -> bool {
+        let parent_id = Entity::from_handle(entity_id, scene.clone())
+            .get_component::<Hierarchy>()
+            .and_then(|hierarchy| hierarchy.parent_runtime_id);
+        let Some(parent_id) = parent_id else {
+            return false;
+        };
+        let num_children = Scene::get_children_for_entity(scene.clone(), parent_id).len();
+        match Scene::get_child_index(scene.clone(), entity_id) {
+            Some(index) if index + 1 < num_children => {
+                // once unlinked, the entity goes after its former next sibling
+                Scene::insert_child_at(scene, entity_id, parent_id, index + 1).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Unlink an entity from the children list of its parent, the children of the entity are kept
+    pub(crate) fn remove_from_parent(scene: Weak<Mutex<Scene>>, entity_id: u64) {
+        let entity = Entity::from_handle(entity_id, scene.clone());
+        let mut hierarchy_component: Hierarchy = match entity.get_component() {
+            Some(hierarchy_component) => hierarchy_component,
+            None => return,
+        };
+        let prev_sibling_id = hierarchy_component.prev_sibling_runtime_id;
+        let next_sibling_id = hierarchy_component.next_sibling_runtime_id;
+
+        // link previous and next siblings to each other
+        if let Some(prev_sibling_id) = prev_sibling_id {
+            let prev_sibling_entity = Entity::from_handle(prev_sibling_id, scene.clone());
+            if let Some(mut prev_sibling_hierarchy) =
+                prev_sibling_entity.get_component::<Hierarchy>()
+            {
+                prev_sibling_hierarchy.next_sibling_runtime_id = next_sibling_id;
+                prev_sibling_entity.add_component(prev_sibling_hierarchy);
+            }
+        }
+        if let Some(next_sibling_id) = next_sibling_id {
+            let next_sibling_entity = Entity::from_handle(next_sibling_id, scene.clone());
+            if let Some(mut next_sibling_hierarchy) =
+                next_sibling_entity.get_component::<Hierarchy>()
+            {
+                next_sibling_hierarchy.prev_sibling_runtime_id = prev_sibling_id;
+                next_sibling_entity.add_component(next_sibling_hierarchy);
+            }
+        }
+
+        // update children list of parent
+        if let Some(parent_id) = hierarchy_component.parent_runtime_id {
+            let parent_entity = Entity::from_handle(parent_id, scene);
+            if let Some(mut parent_hierarchy) = parent_entity.get_component::<Hierarchy>() {
+                if parent_hierarchy.first_child_runtime_id == Some(entity_id) {
+                    parent_hierarchy.first_child_runtime_id = next_sibling_id;
+                }
+                parent_hierarchy.num_children = parent_hierarchy.num_children.saturating_sub(1);
+                parent_entity.add_component(parent_hierarchy);
+            }
         }
+
+        hierarchy_component.parent_runtime_id = None;
+        hierarchy_component.prev_sibling_runtime_id = None;
+        hierarchy_component.next_sibling_runtime_id = None;
+        entity.add_component(hierarchy_component);
+    }
+
+    /// Move an entity and its children under a new parent. When keep_world_transform is set, the
+    /// local transform of the entity is changed so that it stays at the same place in the world.
+    pub fn move_entity(
+        scene: Weak<Mutex<Scene>>,
+        entity_id: u64,
+        new_parent_id: u64,
+        keep_world_transform: bool,
+    ) -> Result<()> {
+        let root_entity_id = scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade scene weak reference when moving entity"))?
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex when moving entity"))?
+            .root_entity_runtime_id;
+        if root_entity_id == Some(entity_id) {
+            return Err(anyhow!("Unable to move the root entity"));
+        }
+        if entity_id == new_parent_id || Scene::is_ancestor(scene.clone(), entity_id, new_parent_id)
+        {
+            return Err(anyhow!(
+                "Unable to move entity {} under its own descendant {}",
+                entity_id,
+                new_parent_id
+            ));
+        }
+
+        let entity = Entity::from_handle(entity_id, scene.clone());
+        let new_parent_entity = Entity::from_handle(new_parent_id, scene.clone());
+        if !entity.has_component::<Hierarchy>() || !new_parent_entity.has_component::<Hierarchy>() {
+            return Err(anyhow!(
+                "Unable to move entity {} to {} since one of them has no hierarchy component",
+                entity_id,
+                new_parent_id
+            ));
+        }
+
+        let world_mat = if keep_world_transform {
+            Some(Scene::get_world_matrix(scene.clone(), entity_id))
+        } else {
+            None
+        };
+
+        Scene::add_child_to_entity(scene.clone(), entity_id, new_parent_id)?;
+
+        if let Some(world_mat) = world_mat {
+            let parent_world_mat = Scene::get_world_matrix(scene, new_parent_id);
+            let local_mat = parent_world_mat
+                .try_inverse()
+                .unwrap_or_else(Matrix4::identity)
+                * world_mat;
+            entity.add_component(Transform::from_matrix(&local_mat));
+        }
+        Ok(())
+    }
+
+    /// Check whether ancestor_id is found when walking up the hierarchy from entity_id
+    pub fn is_ancestor(scene: Weak<Mutex<Scene>>, ancestor_id: u64, entity_id: u64) -> bool {
+        let mut cur_entity_id = Entity::from_handle(entity_id, scene.clone())
+            .get_component::<Hierarchy>()
+            .and_then(|hierarchy| hierarchy.parent_runtime_id);
+        while let Some(cur_entity_id_unwrapped) = cur_entity_id {
+            if cur_entity_id_unwrapped == ancestor_id {
+                return true;
+            }
+            cur_entity_id = Entity::from_handle(cur_entity_id_unwrapped, scene.clone())
+                .get_component::<Hierarchy>()
+                .and_then(|hierarchy| hierarchy.parent_runtime_id);
+        }
+        false
+    }
+
+    /// Compute the world matrix of an entity by combining the transforms of it and its ancestors
+    pub fn get_world_matrix(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Matrix4<f32> {
+        let mut mat = Matrix4::identity();
+        let mut cur_entity_id = Some(entity_id);
+        while let Some(cur_entity_id_unwrapped) = cur_entity_id {
+            let entity = Entity::from_handle(cur_entity_id_unwrapped, scene.clone());
+            if let Some(transform) = entity.get_component::<Transform>() {
+                mat = transform.to_matrix() * mat;
+            }
+            cur_entity_id = entity
+                .get_component::<Hierarchy>()
+                .and_then(|hierarchy| hierarchy.parent_runtime_id);
+        }
+        mat
     }
 
     pub fn create_entity(
@@ -156,7 +460,10 @@ impl Scene {
                     Tag::new("Root".into()),
                 ))
                 .inner();
+            scene_mutex_lock.assign_guid(new_root_entity, Guid::generate());
             scene_mutex_lock.root_entity_runtime_id = Some(new_root_entity);
+            scene_mutex_lock.mark_transform_dirty(new_root_entity);
+            scene_mutex_lock.index_entity_name(new_root_entity);
         }
         // create new entity and make it child of the root
         let new_entity_id = scene_mutex_lock
@@ -167,59 +474,339 @@ impl Scene {
                 Tag::new(name.unwrap_or(String::from("Entity"))),
             ))
             .inner();
+        scene_mutex_lock.index_entity_name(new_entity_id);
+        scene_mutex_lock.assign_guid(new_entity_id, Guid::generate());
         let root_id = scene_mutex_lock.root_entity_runtime_id.unwrap();
         // drop mutex lock to allow other threads to modify scene
         drop(scene_mutex_lock);
-        Scene::add_child_to_entity(scene, new_entity_id, parent_id.unwrap_or(root_id));
+        Scene::add_child_to_entity(scene, new_entity_id, parent_id.unwrap_or(root_id))?;
         Ok(new_entity_id)
     }
 
+    /// Destroy an entity along with all of its descendants
+    pub fn destroy_entity(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Result<()> {
+        let scene_mutex = scene.upgrade().ok_or_else(|| {
+            anyhow!("Unable to upgrade scene weak reference when destroying entity")
+        })?;
+        {
+            let scene_mutex_lock = scene_mutex
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire scene mutex when destroying entity"))?;
+            if scene_mutex_lock.root_entity_runtime_id == Some(entity_id) {
+                return Err(anyhow!("Unable to destroy the root entity"));
+            }
+            if !scene_mutex_lock.is_alive(entity_id) {
+                return Err(anyhow!(
+                    "Unable to destroy entity {} since it does not exist",
+                    entity_id
+                ));
+            }
+        }
+        // unlink the entity from its parent and siblings, the subtree below it is deleted as a whole
+        Scene::remove_from_parent(scene, entity_id);
+        let mut scene_mutex_lock = scene_mutex
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex when destroying entity"))?;
+        let entity_ids = scene_mutex_lock.collect_subtree(entity_id);
+        scene_mutex_lock.delete_entities(&entity_ids);
+        scene_mutex_lock
+            .destroyed_entity_runtime_ids
+            .extend(entity_ids);
+        Ok(())
+    }
+
+    /// Delete entities that are already unlinked from their parents, without telling systems
+    /// about them
+    pub(crate) fn delete_entities(&mut self, entity_ids: &[u64]) {
+        for entity_id in entity_ids {
+            self.forget_entity(*entity_id);
+            self.dirty_transform_runtime_ids.remove(entity_id);
+            if let Some(entity_id) = EntityId::from_inner(*entity_id) {
+                self.handle.delete_entity(entity_id);
+            }
+        }
+    }
+
+    /// Deep copy an entity and its descendants with all of their components, the copy is added
+    /// under new_parent_id or under the parent of the original entity when none is given
+    pub fn duplicate_entity(
+        scene: Weak<Mutex<Scene>>,
+        entity_id: u64,
+        new_parent_id: Option<u64>,
+    ) -> Result<u64> {
+        let scene_mutex = scene.upgrade().ok_or_else(|| {
+            anyhow!("Unable to upgrade scene weak reference when duplicating entity")
+        })?;
+        let mut scene_mutex_lock = scene_mutex
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex when duplicating entity"))?;
+        if scene_mutex_lock.root_entity_runtime_id == Some(entity_id) {
+            return Err(anyhow!("Unable to duplicate the root entity"));
+        }
+        if !scene_mutex_lock.is_alive(entity_id) {
+            return Err(anyhow!(
+                "Unable to duplicate entity {} since it does not exist",
+                entity_id
+            ));
+        }
+        let parent_id = new_parent_id
+            .or_else(|| {
+                scene_mutex_lock
+                    .get_component_by_id::<Hierarchy>(entity_id)
+                    .and_then(|hierarchy| hierarchy.parent_runtime_id)
+            })
+            .or(scene_mutex_lock.root_entity_runtime_id)
+            .ok_or_else(|| anyhow!("No parent found for duplicate of entity {}", entity_id))?;
+        if !scene_mutex_lock.is_alive(parent_id) {
+            return Err(anyhow!(
+                "Unable to duplicate entity {} under {} since it does not exist",
+                entity_id,
+                parent_id
+            ));
+        }
+
+        // create the new entities first, so references between them can be remapped
+        let entity_ids = scene_mutex_lock.collect_subtree(entity_id);
+        let mut new_entity_ids: HashMap<u64, u64> = HashMap::new();
+        for old_entity_id in &entity_ids {
+            let new_entity_id = scene_mutex_lock.handle.add_entity(()).inner();
+            new_entity_ids.insert(*old_entity_id, new_entity_id);
+        }
+        // references to entities outside of the copied subtree are dropped
+        let remap = |id: Option<u64>| id.and_then(|id| new_entity_ids.get(&id).copied());
+        // guids have to stay unique, so the copy gets new ones
+        let new_guids: HashMap<Guid, Guid> = entity_ids
+            .iter()
+            .filter_map(|id| scene_mutex_lock.get_entity_guid(*id))
+            .map(|guid| (guid, Guid::generate()))
+            .collect();
+
+        let mut copy_components = || -> Result<()> {
+            for old_entity_id in &entity_ids {
+                let old_entity_id = *old_entity_id;
+                let new_entity_id = new_entity_ids[&old_entity_id];
+                let new_guid = scene_mutex_lock
+                    .get_entity_guid(old_entity_id)
+                    .and_then(|guid| new_guids.get(&guid).cloned())
+                    .unwrap_or_else(Guid::generate);
+                scene_mutex_lock.assign_guid(new_entity_id, new_guid);
+                scene_mutex_lock.copy_component::<MorphWeights>(old_entity_id, new_entity_id);
+                scene_mutex_lock.copy_component::<PrefabInstance>(old_entity_id, new_entity_id);
+                scene_mutex_lock.copy_component::<PrefabEntity>(old_entity_id, new_entity_id);
+                for component in component_registry().iter_unmanaged() {
+                    if let Some(mut value) = component.get(&scene_mutex_lock, old_entity_id) {
+                        // references within the copied subtree point to the copies, like bones
+                        component
+                            .remap_entity_refs(&mut *value, |guid| new_guids.get(guid).cloned())?;
+                        component.insert(&mut scene_mutex_lock, new_entity_id, value)?;
+                    }
+                }
+                // the copy gets its own links, the top entity of the copy is linked to its parent below
+                if let Some(hierarchy) =
+                    scene_mutex_lock.get_component_by_id::<Hierarchy>(old_entity_id)
+                {
+                    scene_mutex_lock.handle.add_component(
+                        EntityId::from_inner(new_entity_id).unwrap(),
+                        Hierarchy {
+                            num_children: hierarchy.num_children,
+                            parent_runtime_id: remap(hierarchy.parent_runtime_id),
+                            first_child_runtime_id: remap(hierarchy.first_child_runtime_id),
+                            prev_sibling_runtime_id: remap(hierarchy.prev_sibling_runtime_id),
+                            next_sibling_runtime_id: remap(hierarchy.next_sibling_runtime_id),
+                        },
+                    );
+                }
+                // point bones to the copied armature so that the copy is posed independently
+                if let Some(mut bone) = scene_mutex_lock.get_component_by_id::<Bone>(old_entity_id)
+                {
+                    bone.armature_root_runtime_id = bone
+                        .armature_root_runtime_id
+                        .map(|id| new_entity_ids.get(&id).copied().unwrap_or(id));
+                    scene_mutex_lock
+                        .handle
+                        .add_component(EntityId::from_inner(new_entity_id).unwrap(), bone);
+                }
+            }
+            Ok(())
+        };
+        if let Err(err) = copy_components() {
+            // none of the copies are linked to the existing entities yet
+            scene_mutex_lock.delete_entities(&new_entity_ids.values().copied().collect::<Vec<_>>());
+            return Err(err);
+        }
+        let new_root_entity_id = new_entity_ids[&entity_id];
+
+        // drop mutex lock to allow other threads to modify scene
+        drop(scene_mutex_lock);
+        Scene::add_child_to_entity(scene, new_root_entity_id, parent_id)?;
+        Ok(new_root_entity_id)
+    }
+
+    /// Copy a component from one entity to another if the source entity has it
+    fn copy_component<T>(&mut self, from_entity_id: u64, to_entity_id: u64)
+    where
+        T: shipyard::Component + Send + Sync + Clone + shipyard::TupleAddComponent,
+    {
+        if let Some(component) = self.get_component_by_id::<T>(from_entity_id) {
+            self.handle
+                .add_component(EntityId::from_inner(to_entity_id).unwrap(), component);
+        }
+    }
+
+    /// Keep derived data up to date after a component of type T was added, replaced or removed
+    pub fn on_component_written<T: 'static>(&mut self, entity_id: u64) {
+        if TypeId::of::<T>() == TypeId::of::<Transform>() {
+            self.mark_transform_dirty(entity_id);
+        } else if TypeId::of::<T>() == TypeId::of::<Tag>() {
+            self.index_entity_name(entity_id);
+        }
+    }
+
+    pub fn is_alive(&self, entity_id: u64) -> bool {
+        match EntityId::from_inner(entity_id) {
+            Some(entity_id) => self
+                .handle
+                .run(|entities: shipyard::EntitiesView| entities.is_alive(entity_id)),
+            None => false,
+        }
+    }
+
+    /// Take the list of entities destroyed since the last call, used to notify systems
+    pub fn take_destroyed_entities(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.destroyed_entity_runtime_ids)
+    }
+
+    /// Give an entity a guid, replacing its previous one
+    pub(crate) fn assign_guid(&mut self, entity_id: u64, guid: Guid) {
+        if let Some(old_guid) = self.get_entity_guid(entity_id) {
+            self.guid_to_runtime_id.remove(&old_guid);
+        }
+        self.handle
+            .add_component(EntityId::from_inner(entity_id).unwrap(), guid.clone());
+        self.guid_to_runtime_id.insert(guid, entity_id);
+    }
+
+    /// Remove an entity that is about to be deleted from the name and guid lookups
+    pub(crate) fn forget_entity(&mut self, entity_id: u64) {
+        self.name_index.remove(entity_id);
+        if let Some(guid) = self.get_entity_guid(entity_id) {
+            self.guid_to_runtime_id.remove(&guid);
+        }
+    }
+
+    pub fn get_entity_guid(&self, entity_id: u64) -> Option<Guid> {
+        self.get_component_by_id::<Guid>(entity_id)
+    }
+
+    /// Runtime id of the entity with the given guid in this run
+    pub fn get_entity_by_guid(&self, guid: &Guid) -> Option<u64> {
+        self.guid_to_runtime_id
+            .get(guid)
+            .copied()
+            .filter(|entity_id| self.is_alive(*entity_id))
+    }
+
+    /// Schedule the GlobalTransform of an entity and its descendants to be recomputed
+    pub fn mark_transform_dirty(&mut self, entity_id: u64) {
+        self.dirty_transform_runtime_ids.insert(entity_id);
+    }
+
+    /// Recompute the GlobalTransform of every dirty entity and its descendants, subtrees whose
+    /// transforms did not change keep their cached matrices
+    pub fn propagate_transforms(&mut self) {
+        if self.dirty_transform_runtime_ids.is_empty() {
+            return;
+        }
+        let dirty_entity_ids = std::mem::take(&mut self.dirty_transform_runtime_ids);
+        for entity_id in &dirty_entity_ids {
+            if !self.is_alive(*entity_id) {
+                continue;
+            }
+            // a dirty ancestor recomputes this subtree as well
+            let parent_id = self
+                .get_component_by_id::<Hierarchy>(*entity_id)
+                .and_then(|hierarchy| hierarchy.parent_runtime_id);
+            let mut cur_entity_id = parent_id;
+            let mut has_dirty_ancestor = false;
+            while let Some(cur_entity_id_unwrapped) = cur_entity_id {
+                if dirty_entity_ids.contains(&cur_entity_id_unwrapped) {
+                    has_dirty_ancestor = true;
+                    break;
+                }
+                cur_entity_id = self
+                    .get_component_by_id::<Hierarchy>(cur_entity_id_unwrapped)
+                    .and_then(|hierarchy| hierarchy.parent_runtime_id);
+            }
+            if has_dirty_ancestor {
+                continue;
+            }
+            let parent_mat = match parent_id {
+                Some(parent_id) => self
+                    .get_component_by_id::<GlobalTransform>(parent_id)
+                    .map(|global_transform| global_transform.matrix)
+                    .unwrap_or_else(Matrix4::identity),
+                None => Matrix4::identity(),
+            };
+            self.propagate_transform_subtree(*entity_id, parent_mat);
+        }
+    }
+
+    fn propagate_transform_subtree(&mut self, entity_id: u64, parent_mat: Matrix4<f32>) {
+        let mut stack = vec![(entity_id, parent_mat)];
+        while let Some((cur_entity_id, cur_parent_mat)) = stack.pop() {
+            let mat = match self.get_component_by_id::<Transform>(cur_entity_id) {
+                Some(transform) => cur_parent_mat * transform.to_matrix(),
+                None => cur_parent_mat,
+            };
+            self.handle.add_component(
+                EntityId::from_inner(cur_entity_id).unwrap(),
+                GlobalTransform { matrix: mat },
+            );
+            let mut cur_child_id = self
+                .get_component_by_id::<Hierarchy>(cur_entity_id)
+                .and_then(|hierarchy| hierarchy.first_child_runtime_id);
+            while let Some(child_id) = cur_child_id {
+                stack.push((child_id, mat));
+                cur_child_id = self
+                    .get_component_by_id::<Hierarchy>(child_id)
+                    .and_then(|hierarchy| hierarchy.next_sibling_runtime_id);
+            }
+        }
+    }
+
+    /// Create entities below entity_id for the nodes of a glTF model. The file is read before
+    /// any entity is created, so a model that cannot be read leaves the scene untouched.
     pub fn add_gltf_scene(
         scene: Weak<Mutex<Scene>>,
         entity_id: u64,
         resource_manager: &ResourceManager,
         guid: String,
-    ) {
+    ) -> Result<()> {
         let resource_handle = resource_manager
             .get_resource(guid.clone())
-            .expect("Resource handle cannot be found");
+            .ok_or_else(|| anyhow!("No resource with guid {}", guid))?;
         let upgraded_resource_handle = resource_handle
             .upgrade()
-            .expect("Unable to upgrade resource handle");
-        let path = &upgraded_resource_handle
-            .path
-            .to_str()
-            .expect("Unable to get resource path");
+            .ok_or_else(|| anyhow!("Resource {} was dropped", guid))?;
+        let resource_path = upgraded_resource_handle.get_path();
         let gltf = gltf::Gltf::from_slice(
-            &read_binary(std::path::PathBuf::from(path), true)
-                .unwrap_or_else(|_| panic!("Error loading binary for glb {}", path)),
+            &read_binary(resource_path.clone(), true)
+                .with_context(|| format!("Unable to read {}", resource_path.display()))?,
         )
-        .expect("Error loading from slice for glb");
-
-        let mut buffer_data = Vec::new();
-        for buffer in gltf.buffers() {
-            match buffer.source() {
-                Source::Bin => {
-                    if let Some(blob) = gltf.blob.as_deref() {
-                        buffer_data.push(Vec::from(blob));
-                    };
-                }
-                Source::Uri(uri) => {
-                    let bin = read_binary(std::path::PathBuf::from(uri), false)
-                        .unwrap_or_else(|_| panic!("unable to load binary at uri {}", uri));
-                    buffer_data.push(bin);
-                }
-            }
-        }
+        .with_context(|| format!("Unable to parse glTF {}", resource_path.display()))?;
 
-        // TODO: apply transformations of gltf_scene to this current entity (with id entity_id)
-        for gltf_scene in gltf.scenes() {
-            // println!("Scene name: {}", gltf_scene.clone().name().unwrap());
-            for node in gltf_scene.nodes() {
+        let base_dir = resource_path.parent().unwrap_or(std::path::Path::new(""));
+        let buffer_data = read_gltf_buffers(&gltf, base_dir)?;
+
+        // the skins are read before any entity is created, so that a model with broken skins
+        // leaves the scene untouched
+        let scene_skins = gltf
+            .scenes()
+            .map(|gltf_scene| -> Result<_> {
                 let mut skin_root_nodes = HashSet::new();
                 let mut inverse_bind_poses = HashMap::new();
                 let mut joint_node_id_to_joint_id = HashMap::new();
-                gltf.skins().for_each(|gltf_skin| {
+                for gltf_skin in gltf.skins() {
                     match gltf_skin.skeleton() {
                         Some(skeleton) => {
                             skin_root_nodes.insert(skeleton.index() as u32);
@@ -235,12 +822,14 @@ impl Scene {
                             }
                         }
                     }
-                    let reader = gltf_skin.reader(|buffer| Some(&buffer_data[buffer.index()]));
-                    let inverse_bindposes: Vec<dream_math::Matrix4<f32>> = reader
-                        .read_inverse_bind_matrices()
-                        .unwrap()
-                        .map(|mat| mat.into())
-                        .collect();
+                    let reader = gltf_skin
+                        .reader(|buffer| buffer_data.get(buffer.index()).map(Vec::as_slice));
+                    // a skin without inverse bind matrices binds every joint with the identity
+                    let inverse_bindposes: Vec<dream_math::Matrix4<f32>> =
+                        match reader.read_inverse_bind_matrices() {
+                            Some(matrices) => matrices.map(|mat| mat.into()).collect(),
+                            None => vec![Matrix4::identity(); gltf_skin.joints().len()],
+                        };
 
                     // how to map inverse bind matrices to joints: https://stackoverflow.com/questions/64904889/what-is-the-correct-mapping-of-inverse-bind-matrices
                     log::debug!("Number of inverse bind poses {:?}", inverse_bindposes.len());
@@ -250,26 +839,45 @@ impl Scene {
                     );
 
                     // TODO: associate the index of the joint node and its index in the joints array - this is what we should use
-                    let mut idx = 0;
-                    gltf_skin.joints().for_each(|joint| {
-                        // log::debug!(
-                        //     "Inverse bind pose for idx {:?} joint {:?} is {:?}",
-                        //     idx,
-                        //     joint.index(),
-                        //     inverse_bindposes[idx]
-                        // );
+                    for (idx, joint) in gltf_skin.joints().enumerate() {
+                        let inverse_bindpose = inverse_bindposes.get(idx).ok_or_else(|| {
+                            anyhow!(
+                                "Skin {} of {} has no inverse bind matrix for joint {}",
+                                gltf_skin.index(),
+                                resource_path.display(),
+                                idx
+                            )
+                        })?;
                         joint_node_id_to_joint_id.insert(joint.index() as u32, idx as u32);
-                        inverse_bind_poses.insert(joint.index() as u32, inverse_bindposes[idx]);
-                        idx += 1;
-                    });
-                });
+                        inverse_bind_poses.insert(joint.index() as u32, *inverse_bindpose);
+                    }
+                }
+                Ok((
+                    skin_root_nodes,
+                    inverse_bind_poses,
+                    joint_node_id_to_joint_id,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
+        // TODO: apply transformations of gltf_scene to this current entity (with id entity_id)
+        for (gltf_scene, (skin_root_nodes, inverse_bind_poses, joint_node_id_to_joint_id)) in
+            gltf.scenes().zip(scene_skins)
+        {
+            // println!("Scene name: {}", gltf_scene.clone().name().unwrap());
+            for node in gltf_scene.nodes() {
                 // set transform of root node of GLTF scene to this entity we are adding scene to
-                {
+                let root_rotation = {
                     let transform = get_gltf_transform(&node);
+                    let rotation = transform.rotation;
                     let entity = Entity::from_handle(entity_id, scene.clone());
                     entity.add_component(transform);
-                }
+                    entity.add_component(GltfNode {
+                        node_index: node.index() as u32,
+                    });
+                    rotation
+                };
+                add_gltf_camera_and_light(&node, root_rotation, entity_id, scene.clone());
                 let node_idx = &(node.index() as u32);
                 process_gltf_child_node(
                     node,
@@ -281,10 +889,23 @@ impl Scene {
                     guid.clone(),
                     entity_id,
                     skin_root_nodes.contains(node_idx),
-                );
+                    None,
+                    root_rotation,
+                )?;
             }
         }
 
+        // clips are played by an AnimationPlayer on the entity the model is added to, they are
+        // cached even when there are none so that clips of an older version of the file are gone
+        let animation_clips = read_gltf_animation_clips(&gltf, &buffer_data);
+        if let Some(first_clip) = animation_clips.first() {
+            Entity::from_handle(entity_id, scene).add_component(AnimationPlayer::new(
+                Some(resource_handle),
+                first_clip.name.clone(),
+            ));
+        }
+        cache_animation_clips(guid, animation_clips);
+
         fn count_number_of_gltf_node_descendents<'a>(child_node: &'a gltf::Node) -> i32 {
             let mut count = 1;
             for child in child_node.children() {
@@ -310,62 +931,167 @@ impl Scene {
             guid: String,
             entity_id: u64,
             is_bone: bool,
-        ) {
-            match child_node.mesh() {
-                None => {
-                    for child in child_node.children() {
-                        let new_entity_id = Scene::create_entity(
-                            scene.clone(),
-                            Some(child.name().unwrap_or("Node").into()),
-                            Some(entity_id),
-                            Some(get_gltf_transform(&child)),
-                        )
-                        .expect("Unable to create entity while traversing GLTF nodes");
-                        let is_skin_root = skin_root_nodes.contains(&(child.index() as u32));
-                        let is_bone = is_bone || is_skin_root;
-                        if is_bone {
-                            let entity = Entity::from_handle(new_entity_id, scene.clone());
-                            entity.add_component(Bone {
-                                is_root: is_skin_root,
-                                node_id: child.index() as u32,
-                                bone_id: *joint_node_id_to_joint_id
-                                    .get(&(child.index() as u32))
-                                    .unwrap_or(&1),
-                                inverse_bind_pose: *inverse_bind_poses
-                                    .get(&(child.index() as u32))
-                                    .unwrap_or(&Matrix4::<f32>::identity()),
-                            });
-                        }
-                        process_gltf_child_node(
-                            child,
-                            skin_root_nodes,
-                            inverse_bind_poses,
-                            joint_node_id_to_joint_id,
-                            scene.clone(),
-                            resource_manager,
-                            guid.clone(),
-                            new_entity_id,
-                            is_bone,
-                        );
-                    }
+            armature_root_id: Option<u64>,
+            rotation: UnitQuaternion<f32>,
+        ) -> Result<()> {
+            // the mesh of a node is drawn by a child entity that comes before the entities of
+            // the child nodes
+            if let Some(mesh) = child_node.mesh() {
+                let new_entity_id = Scene::create_entity(
+                    scene.clone(),
+                    Some(mesh.name().unwrap_or("Mesh").into()),
+                    Some(entity_id),
+                    None,
+                )
+                .context("Unable to create entity while traversing GLTF mesh nodes")?;
+                // meshes with morph targets start with the default weights of the mesh
+                let num_morph_targets = mesh
+                    .primitives()
+                    .map(|primitive| primitive.morph_targets().len())
+                    .max()
+                    .unwrap_or_default();
+                if num_morph_targets > 0 {
+                    let weights = mesh
+                        .weights()
+                        .map(|weights| weights.to_vec())
+                        .unwrap_or_else(|| vec![0.0; num_morph_targets]);
+                    Entity::from_handle(new_entity_id, scene.clone())
+                        .add_component(MorphWeights::new(weights));
                 }
-                Some(mesh) => {
-                    let new_entity_id = Scene::create_entity(
-                        scene.clone(),
-                        Some(mesh.name().unwrap_or("Mesh").into()),
-                        Some(entity_id),
-                        None,
-                    )
-                    .expect("Unable to create entity while traversing GLTF mesh nodes");
-                    MeshRenderer::add_to_entity(
-                        scene,
-                        new_entity_id,
-                        resource_manager,
-                        guid,
-                        false,
-                        Some(mesh.index()),
-                    );
+                MeshRenderer::add_to_entity(
+                    scene.clone(),
+                    new_entity_id,
+                    resource_manager,
+                    guid.clone(),
+                    false,
+                    Some(mesh.index()),
+                );
+            }
+            for child in child_node.children() {
+                let transform = get_gltf_transform(&child);
+                let child_rotation = rotation * transform.rotation;
+                let new_entity_id = Scene::create_entity(
+                    scene.clone(),
+                    Some(child.name().unwrap_or("Node").into()),
+                    Some(entity_id),
+                    Some(transform),
+                )
+                .context("Unable to create entity while traversing GLTF nodes")?;
+                Entity::from_handle(new_entity_id, scene.clone()).add_component(GltfNode {
+                    node_index: child.index() as u32,
+                });
+                add_gltf_camera_and_light(&child, child_rotation, new_entity_id, scene.clone());
+                let is_skin_root = skin_root_nodes.contains(&(child.index() as u32));
+                let is_bone = is_bone || is_skin_root;
+                let armature_root_id = if is_skin_root {
+                    Some(new_entity_id)
+                } else {
+                    armature_root_id
+                };
+                if is_bone {
+                    let entity = Entity::from_handle(new_entity_id, scene.clone());
+                    entity.add_component(Bone {
+                        is_root: is_skin_root,
+                        node_id: child.index() as u32,
+                        bone_id: *joint_node_id_to_joint_id
+                            .get(&(child.index() as u32))
+                            .unwrap_or(&1),
+                        inverse_bind_pose: *inverse_bind_poses
+                            .get(&(child.index() as u32))
+                            .unwrap_or(&Matrix4::<f32>::identity()),
+                        armature_root_runtime_id: armature_root_id,
+                    });
                 }
+                process_gltf_child_node(
+                    child,
+                    skin_root_nodes,
+                    inverse_bind_poses,
+                    joint_node_id_to_joint_id,
+                    scene.clone(),
+                    resource_manager,
+                    guid.clone(),
+                    new_entity_id,
+                    is_bone,
+                    armature_root_id,
+                    child_rotation,
+                )?;
+            }
+            Ok(())
+        }
+
+        // camera and KHR_lights_punctual light of a node, rotation is the orientation of the node
+        // in the model
+        fn add_gltf_camera_and_light(
+            node: &gltf::Node,
+            rotation: UnitQuaternion<f32>,
+            entity_id: u64,
+            scene: Weak<Mutex<Scene>>,
+        ) {
+            let entity = Entity::from_handle(entity_id, scene);
+            if let Some(gltf_camera) = node.camera() {
+                let camera = match gltf_camera.projection() {
+                    gltf::camera::Projection::Perspective(perspective) => Camera {
+                        projection: CameraProjection::PERSPECTIVE,
+                        fovy: perspective.yfov(),
+                        aspect_ratio: perspective.aspect_ratio().unwrap_or_default(),
+                        znear: perspective.znear(),
+                        // infinite projections are not supported, the default far plane is used
+                        zfar: perspective.zfar().unwrap_or(Camera::default().zfar),
+                        ..Default::default()
+                    },
+                    gltf::camera::Projection::Orthographic(orthographic) => Camera {
+                        projection: CameraProjection::ORTHOGRAPHIC,
+                        xmag: orthographic.xmag(),
+                        ymag: orthographic.ymag(),
+                        znear: orthographic.znear(),
+                        zfar: orthographic.zfar(),
+                        ..Default::default()
+                    },
+                };
+                entity.add_component(camera);
+            }
+            if let Some(gltf_light) = node.light() {
+                // lights shine down the local -z axis of their node
+                let direction = rotation.transform_vector(&Vector3::new(0.0, 0.0, -1.0));
+                // glTF intensities are in lux for directional lights and in candela for point
+                // and spot lights, which are converted back to the kilowatts they were exported
+                // from, so that a 1000 W Blender lamp is as bright as a sun of strength 1
+                let color = Vector3::from(gltf_light.color());
+                let light = match gltf_light.kind() {
+                    gltf::khr_lights_punctual::Kind::Directional => Light::new(
+                        LightType::DIRECTIONAL,
+                        color * gltf_light.intensity(),
+                        1.0,
+                        direction,
+                        true,
+                    ),
+                    gltf::khr_lights_punctual::Kind::Point => Light::new(
+                        LightType::POINT,
+                        color * candela_to_kilowatts(gltf_light.intensity()),
+                        1.0,
+                        direction,
+                        false,
+                    ),
+                    gltf::khr_lights_punctual::Kind::Spot {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                    } => Light {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                        ..Light::new(
+                            LightType::SPOT,
+                            color * candela_to_kilowatts(gltf_light.intensity()),
+                            1.0,
+                            direction,
+                            false,
+                        )
+                    },
+                };
+                entity.add_component(light);
+            }
+
+            fn candela_to_kilowatts(candela: f32) -> f32 {
+                candela * 4.0 * std::f32::consts::PI / 683.0
             }
         }
 
@@ -388,6 +1114,8 @@ impl Scene {
             let scale = dream_math::Vector3::new(gltf_scale[0], gltf_scale[1], gltf_scale[2]);
             Transform::new(position, rotation, scale)
         }
+
+        Ok(())
     }
 }
 
@@ -400,3 +1128,26 @@ impl ToEntity for u64 {
         Entity::from_handle(*self, scene)
     }
 }
+
+/// Contents of the buffers of a glTF document, in the order of its buffers.
+/// External buffers are resolved relative to `base_dir`, the folder of the document
+pub(crate) fn read_gltf_buffers(
+    gltf: &gltf::Gltf,
+    base_dir: &std::path::Path,
+) -> Result<Vec<Vec<u8>>> {
+    let mut buffer_data = Vec::new();
+    for buffer in gltf.buffers() {
+        match buffer.source() {
+            Source::Bin => {
+                if let Some(blob) = gltf.blob.as_deref() {
+                    buffer_data.push(Vec::from(blob));
+                };
+            }
+            Source::Uri(uri) => {
+                let (bin, _) = read_uri(uri, base_dir)?;
+                buffer_data.push(bin);
+            }
+        }
+    }
+    Ok(buffer_data)
+}
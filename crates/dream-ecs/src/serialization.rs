@@ -0,0 +1,863 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use shipyard::EntityId;
+
+use dream_math::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+use dream_resource::resource_handle::ResourceHandle;
+use dream_resource::resource_manager::ResourceManager;
+
+use crate::component::{
+    Active, AnimationFade, AnimationLayer, AnimationPlayer, Animator, Bone, Guid, Hierarchy, Light,
+    LightType, MeshRenderer, MorphWeights, PrefabEntity, PrefabInstance, PrefabOverride,
+    PythonScript, RenderLayers, SceneCamera, Tag, Transform,
+};
+use crate::reflect::component_registry;
+use crate::scene::Scene;
+
+/// Version written to every scene file, bump this whenever the layout of [SceneData] changes and
+/// upgrade files of the previous version in [upgrade_scene_data]
+/// 1: tags, transforms, hierarchy, lights, cameras, bones, meshes and scripts
+/// 2: guids, active flags, render layers, prefabs, animation, morph weights, registered
+///    components and the armature root of bones
+pub const SCENE_FILE_VERSION: u32 = 2;
+
+/// Extension used for scene files in the project folder
+pub const SCENE_FILE_EXTENSION: &str = "scene";
+
+/// On-disk representation of a scene
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SceneData {
+    pub version: u32,
+    pub name: String,
+    pub root_entity_id: Option<u64>,
+    pub entities: Vec<EntityData>,
+}
+
+impl SceneData {
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        serde_yaml::from_str(yaml).map_err(|err| anyhow!("Unable to deserialize scene: {}", err))
+    }
+}
+
+/// On-disk representation of an entity, ids in here are local to the file and are remapped to
+/// new runtime ids when the file is loaded
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct EntityData {
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_layers: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hierarchy: Option<HierarchyData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<LightData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene_camera: Option<SceneCameraData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bone: Option<BoneData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh_renderer: Option<MeshRendererData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_script: Option<PythonScriptData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_player: Option<AnimationPlayerData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animator: Option<AnimatorData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub morph_weights: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefab_instance: Option<PrefabInstanceData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefab_entity: Option<u64>,
+    /// Registered components without an entry above, keyed by component name (see reflect.rs)
+    #[serde(flatten)]
+    pub components: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransformData {
+    pub position: [f32; 3],
+    /// quaternion stored as [i, j, k, w]
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    pub euler_angles: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct HierarchyData {
+    pub num_children: usize,
+    pub parent: Option<u64>,
+    pub first_child: Option<u64>,
+    pub prev_sibling: Option<u64>,
+    pub next_sibling: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LightData {
+    pub light_type: LightType,
+    pub color: [f32; 3],
+    pub radius: f32,
+    pub direction: [f32; 3],
+    pub cast_shadow: bool,
+    #[serde(default)]
+    pub inner_cone_angle: f32,
+    #[serde(default = "default_outer_cone_angle")]
+    pub outer_cone_angle: f32,
+}
+
+fn default_outer_cone_angle() -> f32 {
+    std::f32::consts::FRAC_PI_4
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SceneCameraData {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoneData {
+    pub is_root: bool,
+    pub node_id: u32,
+    pub bone_id: u32,
+    /// column-major 4x4 matrix
+    pub inverse_bind_pose: [[f32; 4]; 4],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub armature_root: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct MeshRendererData {
+    pub guid: Option<String>,
+    pub mesh_idx: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PythonScriptData {
+    pub guid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationPlayerData {
+    pub guid: Option<String>,
+    #[serde(default)]
+    pub clip: String,
+    #[serde(default)]
+    pub time: f32,
+    pub speed: f32,
+    pub playing: bool,
+    pub looping: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade: Option<AnimationFade>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<AnimationLayer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AnimatorData {
+    pub guid: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub state: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PrefabInstanceData {
+    pub guid: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<PrefabOverride>,
+}
+
+impl From<&Transform> for TransformData {
+    fn from(transform: &Transform) -> Self {
+        let euler_angles = transform.get_euler_angles();
+        let rotation = transform.rotation.quaternion().coords;
+        Self {
+            position: transform.position.into(),
+            rotation: [rotation.x, rotation.y, rotation.z, rotation.w],
+            scale: transform.scale.into(),
+            euler_angles: [euler_angles.0, euler_angles.1, euler_angles.2],
+        }
+    }
+}
+
+impl From<&TransformData> for Transform {
+    fn from(data: &TransformData) -> Self {
+        Transform {
+            position: Vector3::from(data.position),
+            // the quaternion was already normalized when it was saved, so avoid renormalizing to
+            // keep the loaded value bit-for-bit identical
+            rotation: UnitQuaternion::new_unchecked(Quaternion::new(
+                data.rotation[3],
+                data.rotation[0],
+                data.rotation[1],
+                data.rotation[2],
+            )),
+            scale: Vector3::from(data.scale),
+            euler_angles: (
+                data.euler_angles[0],
+                data.euler_angles[1],
+                data.euler_angles[2],
+            ),
+        }
+    }
+}
+
+impl From<&Light> for LightData {
+    fn from(light: &Light) -> Self {
+        Self {
+            light_type: light.light_type,
+            color: light.color.into(),
+            radius: light.radius,
+            direction: light.direction.into(),
+            cast_shadow: light.cast_shadow,
+            inner_cone_angle: light.inner_cone_angle,
+            outer_cone_angle: light.outer_cone_angle,
+        }
+    }
+}
+
+impl From<&LightData> for Light {
+    fn from(data: &LightData) -> Self {
+        Light {
+            inner_cone_angle: data.inner_cone_angle,
+            outer_cone_angle: data.outer_cone_angle,
+            ..Light::new(
+                data.light_type,
+                Vector3::from(data.color),
+                data.radius,
+                Vector3::from(data.direction),
+                data.cast_shadow,
+            )
+        }
+    }
+}
+
+impl From<&MeshRenderer> for MeshRendererData {
+    fn from(mesh_renderer: &MeshRenderer) -> Self {
+        Self {
+            guid: resource_guid(&mesh_renderer.resource_handle),
+            mesh_idx: mesh_renderer.mesh_idx,
+        }
+    }
+}
+
+impl From<&AnimationPlayer> for AnimationPlayerData {
+    fn from(animation_player: &AnimationPlayer) -> Self {
+        Self {
+            guid: resource_guid(&animation_player.resource_handle),
+            clip: animation_player.clip.clone(),
+            time: animation_player.time,
+            speed: animation_player.speed,
+            playing: animation_player.playing,
+            looping: animation_player.looping,
+            fade: animation_player.fade.clone(),
+            layers: animation_player.layers.clone(),
+        }
+    }
+}
+
+impl From<&Animator> for AnimatorData {
+    fn from(animator: &Animator) -> Self {
+        Self {
+            guid: resource_guid(&animator.resource_handle),
+            state: animator.state.clone(),
+            parameters: animator.parameters.clone(),
+        }
+    }
+}
+
+impl From<&PrefabInstance> for PrefabInstanceData {
+    fn from(prefab_instance: &PrefabInstance) -> Self {
+        Self {
+            guid: resource_guid(&prefab_instance.resource_handle),
+            overrides: prefab_instance.overrides.clone(),
+        }
+    }
+}
+
+impl From<&PythonScript> for PythonScriptData {
+    fn from(python_script: &PythonScript) -> Self {
+        Self {
+            guid: resource_guid(&python_script.resource_handle),
+        }
+    }
+}
+
+fn resource_guid(resource_handle: &Option<Weak<ResourceHandle>>) -> Option<String> {
+    resource_handle
+        .as_ref()
+        .and_then(|resource_handle| resource_handle.upgrade())
+        .map(|resource_handle| resource_handle.key.clone())
+}
+
+/// Bring scene data written by an older version up to [SCENE_FILE_VERSION]
+pub(crate) fn upgrade_scene_data(scene_data: &SceneData) -> Result<Cow<SceneData>> {
+    match scene_data.version {
+        SCENE_FILE_VERSION => Ok(Cow::Borrowed(scene_data)),
+        1 => {
+            let mut upgraded = scene_data.clone();
+            set_armature_roots_of_bones(&mut upgraded);
+            upgraded.version = SCENE_FILE_VERSION;
+            Ok(Cow::Owned(upgraded))
+        }
+        version if version > SCENE_FILE_VERSION => Err(anyhow!(
+            "Scene file version {} is newer than supported version {}",
+            version,
+            SCENE_FILE_VERSION
+        )),
+        version => Err(anyhow!("Unknown scene file version {}", version)),
+    }
+}
+
+/// Version 1 did not store the armature of a bone, which is the closest root bone among the bone
+/// and its ancestors
+fn set_armature_roots_of_bones(scene_data: &mut SceneData) {
+    let entities: HashMap<u64, &EntityData> = scene_data
+        .entities
+        .iter()
+        .map(|entity_data| (entity_data.id, entity_data))
+        .collect();
+    let armature_roots: HashMap<u64, u64> = scene_data
+        .entities
+        .iter()
+        .filter(|entity_data| entity_data.bone.is_some())
+        .filter_map(|entity_data| {
+            let mut ancestor = Some(entity_data);
+            // the number of entities bounds the walk, in case the file has a cycle
+            for _ in 0..entities.len() {
+                let ancestor_data = ancestor?;
+                if ancestor_data.bone.as_ref().is_some_and(|bone| bone.is_root) {
+                    return Some((entity_data.id, ancestor_data.id));
+                }
+                ancestor = ancestor_data
+                    .hierarchy
+                    .as_ref()
+                    .and_then(|hierarchy| hierarchy.parent)
+                    .and_then(|parent| entities.get(&parent).copied());
+            }
+            None
+        })
+        .collect();
+    for entity_data in &mut scene_data.entities {
+        if let Some(bone) = &mut entity_data.bone {
+            bone.armature_root = armature_roots.get(&entity_data.id).copied();
+        }
+    }
+}
+
+/// Convert an entity id from a file into a runtime id
+pub(crate) fn remap_file_id(
+    runtime_ids: &HashMap<u64, u64>,
+    file_id: Option<u64>,
+) -> Result<Option<u64>> {
+    match file_id {
+        Some(file_id) => runtime_ids
+            .get(&file_id)
+            .map(|runtime_id| Some(*runtime_id))
+            .ok_or_else(|| anyhow!("Unknown entity id {} in scene file", file_id)),
+        None => Ok(None),
+    }
+}
+
+fn resolve_resource(
+    resource_manager: &ResourceManager,
+    guid: &Option<String>,
+) -> Option<Weak<ResourceHandle>> {
+    let guid = guid.as_ref()?;
+    let resource_handle = resource_manager.get_resource(guid.clone());
+    if resource_handle.is_none() {
+        log::warn!("Unable to find resource with guid {} for scene", guid);
+    }
+    resource_handle
+}
+
+impl Scene {
+    /// Serialize an entity, references to other entities are converted with remap
+    pub(crate) fn entity_to_data(
+        &self,
+        runtime_id: u64,
+        id: u64,
+        remap: &dyn Fn(Option<u64>) -> Option<u64>,
+    ) -> EntityData {
+        EntityData {
+            id,
+            guid: self.get_entity_guid(runtime_id).map(|guid| guid.0),
+            tag: self
+                .get_component_by_id::<Tag>(runtime_id)
+                .map(|tag| tag.name),
+            active: self
+                .get_component_by_id::<Active>(runtime_id)
+                .map(|active| active.active),
+            render_layers: self
+                .get_component_by_id::<RenderLayers>(runtime_id)
+                .map(|render_layers| render_layers.mask),
+            transform: self
+                .get_component_by_id::<Transform>(runtime_id)
+                .map(|transform| TransformData::from(&transform)),
+            hierarchy: self
+                .get_component_by_id::<Hierarchy>(runtime_id)
+                .map(|hierarchy| HierarchyData {
+                    num_children: hierarchy.num_children,
+                    parent: remap(hierarchy.parent_runtime_id),
+                    first_child: remap(hierarchy.first_child_runtime_id),
+                    prev_sibling: remap(hierarchy.prev_sibling_runtime_id),
+                    next_sibling: remap(hierarchy.next_sibling_runtime_id),
+                }),
+            light: self
+                .get_component_by_id::<Light>(runtime_id)
+                .map(|light| LightData::from(&light)),
+            scene_camera: self
+                .get_component_by_id::<SceneCamera>(runtime_id)
+                .map(|_| SceneCameraData::default()),
+            bone: self
+                .get_component_by_id::<Bone>(runtime_id)
+                .map(|bone| BoneData {
+                    is_root: bone.is_root,
+                    node_id: bone.node_id,
+                    bone_id: bone.bone_id,
+                    inverse_bind_pose: bone.inverse_bind_pose.into(),
+                    armature_root: remap(bone.armature_root_runtime_id),
+                }),
+            mesh_renderer: self
+                .get_component_by_id::<MeshRenderer>(runtime_id)
+                .map(|mesh_renderer| MeshRendererData::from(&mesh_renderer)),
+            python_script: self
+                .get_component_by_id::<PythonScript>(runtime_id)
+                .map(|python_script| PythonScriptData::from(&python_script)),
+            animation_player: self
+                .get_component_by_id::<AnimationPlayer>(runtime_id)
+                .map(|animation_player| AnimationPlayerData::from(&animation_player)),
+            animator: self
+                .get_component_by_id::<Animator>(runtime_id)
+                .map(|animator| AnimatorData::from(&animator)),
+            morph_weights: self
+                .get_component_by_id::<MorphWeights>(runtime_id)
+                .map(|morph_weights| morph_weights.weights),
+            prefab_instance: self
+                .get_component_by_id::<PrefabInstance>(runtime_id)
+                .map(|prefab_instance| PrefabInstanceData::from(&prefab_instance)),
+            prefab_entity: self
+                .get_component_by_id::<PrefabEntity>(runtime_id)
+                .map(|prefab_entity| prefab_entity.prefab_entity_id),
+            components: component_registry()
+                .iter_without_dedicated_data()
+                .filter_map(|component| {
+                    component
+                        .to_yaml(self, runtime_id)
+                        .map(|value| (String::from(component.name), value))
+                })
+                .collect(),
+        }
+    }
+
+    /// Add the components described by entity data to an existing entity, references to other
+    /// entities are converted with remap
+    pub(crate) fn add_entity_data_components(
+        &mut self,
+        runtime_id: u64,
+        entity_data: &EntityData,
+        remap: &dyn Fn(Option<u64>) -> Result<Option<u64>>,
+        resource_manager: &ResourceManager,
+    ) -> Result<()> {
+        let entity_id = EntityId::from_inner(runtime_id)
+            .ok_or_else(|| anyhow!("Invalid runtime id {}", runtime_id))?;
+        // entities from files written before guids existed get a new one
+        let guid = entity_data
+            .guid
+            .clone()
+            .map(Guid)
+            .unwrap_or_else(Guid::generate);
+        self.assign_guid(runtime_id, guid);
+        if let Some(name) = &entity_data.tag {
+            self.handle.add_component(entity_id, Tag::new(name.clone()));
+            self.name_index.insert(runtime_id, name.clone());
+        }
+        if let Some(active) = entity_data.active {
+            self.handle.add_component(entity_id, Active::new(active));
+        }
+        if let Some(mask) = entity_data.render_layers {
+            self.handle
+                .add_component(entity_id, RenderLayers::new(mask));
+        }
+        if let Some(transform) = &entity_data.transform {
+            self.handle
+                .add_component(entity_id, Transform::from(transform));
+        }
+        if let Some(hierarchy) = &entity_data.hierarchy {
+            self.handle.add_component(
+                entity_id,
+                Hierarchy {
+                    num_children: hierarchy.num_children,
+                    parent_runtime_id: remap(hierarchy.parent)?,
+                    first_child_runtime_id: remap(hierarchy.first_child)?,
+                    prev_sibling_runtime_id: remap(hierarchy.prev_sibling)?,
+                    next_sibling_runtime_id: remap(hierarchy.next_sibling)?,
+                },
+            );
+        }
+        if let Some(light) = &entity_data.light {
+            self.handle.add_component(entity_id, Light::from(light));
+        }
+        if entity_data.scene_camera.is_some() {
+            self.handle.add_component(entity_id, SceneCamera::default());
+        }
+        if let Some(bone) = &entity_data.bone {
+            self.handle.add_component(
+                entity_id,
+                Bone {
+                    is_root: bone.is_root,
+                    node_id: bone.node_id,
+                    bone_id: bone.bone_id,
+                    inverse_bind_pose: Matrix4::from(bone.inverse_bind_pose),
+                    armature_root_runtime_id: remap(bone.armature_root)?,
+                },
+            );
+        }
+        if let Some(mesh_renderer) = &entity_data.mesh_renderer {
+            self.handle.add_component(
+                entity_id,
+                MeshRenderer::new(
+                    resolve_resource(resource_manager, &mesh_renderer.guid),
+                    mesh_renderer.mesh_idx,
+                ),
+            );
+        }
+        if let Some(python_script) = &entity_data.python_script {
+            self.handle.add_component(
+                entity_id,
+                PythonScript::new(resolve_resource(resource_manager, &python_script.guid)),
+            );
+        }
+        if let Some(animation_player) = &entity_data.animation_player {
+            self.handle.add_component(
+                entity_id,
+                AnimationPlayer {
+                    resource_handle: resolve_resource(resource_manager, &animation_player.guid),
+                    clip: animation_player.clip.clone(),
+                    time: animation_player.time,
+                    speed: animation_player.speed,
+                    playing: animation_player.playing,
+                    looping: animation_player.looping,
+                    fade: animation_player.fade.clone(),
+                    layers: animation_player.layers.clone(),
+                },
+            );
+        }
+        if let Some(animator) = &entity_data.animator {
+            self.handle.add_component(
+                entity_id,
+                Animator {
+                    resource_handle: resolve_resource(resource_manager, &animator.guid),
+                    state: animator.state.clone(),
+                    parameters: animator.parameters.clone(),
+                },
+            );
+        }
+        if let Some(morph_weights) = &entity_data.morph_weights {
+            self.handle
+                .add_component(entity_id, MorphWeights::new(morph_weights.clone()));
+        }
+        if let Some(prefab_instance) = &entity_data.prefab_instance {
+            self.handle.add_component(
+                entity_id,
+                PrefabInstance {
+                    resource_handle: resolve_resource(resource_manager, &prefab_instance.guid),
+                    overrides: prefab_instance.overrides.clone(),
+                },
+            );
+        }
+        if let Some(prefab_entity_id) = entity_data.prefab_entity {
+            self.handle
+                .add_component(entity_id, PrefabEntity { prefab_entity_id });
+        }
+        let registry = component_registry();
+        for (name, value) in &entity_data.components {
+            match registry.get(name) {
+                Some(component) => {
+                    let value = component.parse_yaml(value)?;
+                    component.insert(self, runtime_id, value)?;
+                }
+                None => log::warn!("Skipping unregistered component {}", name),
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize an entity and its descendants, the entity becomes the root of the scene data
+    pub fn subtree_to_scene_data(&self, entity_id: u64) -> SceneData {
+        // runtime ids change between runs, so number entities by their position in the file
+        let file_ids: HashMap<u64, u64> = self
+            .collect_subtree(entity_id)
+            .iter()
+            .enumerate()
+            .map(|(idx, runtime_id)| (*runtime_id, idx as u64))
+            .collect();
+        self.subtree_to_scene_data_with_ids(entity_id, &file_ids)
+    }
+
+    /// Serialize an entity and its descendants using the given map of <runtime id: file id>
+    pub(crate) fn subtree_to_scene_data_with_ids(
+        &self,
+        entity_id: u64,
+        file_ids: &HashMap<u64, u64>,
+    ) -> SceneData {
+        // references to entities outside of the subtree are dropped
+        let remap = |runtime_id: Option<u64>| runtime_id.and_then(|id| file_ids.get(&id).copied());
+        let entities = self
+            .collect_subtree(entity_id)
+            .iter()
+            .map(|runtime_id| self.entity_to_data(*runtime_id, file_ids[runtime_id], &remap))
+            .collect();
+        SceneData {
+            version: SCENE_FILE_VERSION,
+            name: self
+                .get_component_by_id::<Tag>(entity_id)
+                .map(|tag| tag.name)
+                .unwrap_or_default(),
+            root_entity_id: remap(Some(entity_id)),
+            entities,
+        }
+    }
+
+    /// Serialize the scene into its on-disk representation
+    pub fn to_scene_data(&self) -> SceneData {
+        match self.root_entity_runtime_id {
+            Some(root_entity_id) => SceneData {
+                name: self.name.clone(),
+                ..self.subtree_to_scene_data(root_entity_id)
+            },
+            None => SceneData {
+                version: SCENE_FILE_VERSION,
+                name: self.name.clone(),
+                root_entity_id: None,
+                entities: Vec::new(),
+            },
+        }
+    }
+
+    /// Replace the contents of this scene with the entities described by scene data, returns the
+    /// map between ids in the file and the new runtime ids
+    pub fn load_scene_data(
+        &mut self,
+        scene_data: &SceneData,
+        resource_manager: &ResourceManager,
+    ) -> Result<HashMap<u64, u64>> {
+        let scene_data = upgrade_scene_data(scene_data)?;
+
+        // build the new contents aside, so a malformed file leaves this scene untouched
+        let mut loaded = Scene::empty();
+        loaded.guid = self.guid.clone();
+        let runtime_ids = loaded.add_root_scene_data(&scene_data, resource_manager)?;
+        // bring prefab instances up to date with their prefabs, which might have changed
+        loaded.sync_prefab_instances_after_load(resource_manager);
+
+        // the history of the new contents is empty, commands refer to the previous entities
+        self.replace_contents(loaded);
+        Ok(runtime_ids)
+    }
+
+    /// Swap in contents that were built aside, systems are told about the previous entities
+    /// through the destroyed entities
+    pub(crate) fn replace_contents(&mut self, mut contents: Scene) {
+        self.clear_contents();
+        contents.destroyed_entity_runtime_ids =
+            std::mem::take(&mut self.destroyed_entity_runtime_ids);
+        contents.event_collectors = std::mem::take(&mut self.event_collectors);
+        *self = contents;
+    }
+
+    /// Remove all entities, systems are told about them through the destroyed entities
+    pub(crate) fn clear_contents(&mut self) {
+        if let Some(root_entity_id) = self.root_entity_runtime_id {
+            let old_entity_ids = self.collect_subtree(root_entity_id);
+            self.destroyed_entity_runtime_ids.extend(old_entity_ids);
+        }
+        self.handle = shipyard::World::new();
+        self.root_entity_runtime_id = None;
+        self.name_index.clear();
+        self.guid_to_runtime_id.clear();
+        self.component_events.clear();
+        self.dirty_transform_runtime_ids.clear();
+    }
+
+    /// Fill a cleared scene with scene data whose root becomes the root entity of the scene,
+    /// returns the map of <file id: runtime id>
+    pub(crate) fn add_root_scene_data(
+        &mut self,
+        scene_data: &SceneData,
+        resource_manager: &ResourceManager,
+    ) -> Result<HashMap<u64, u64>> {
+        self.name = scene_data.name.clone();
+        let runtime_ids = self.add_scene_data_entities(scene_data, resource_manager)?;
+        self.root_entity_runtime_id = remap_file_id(&runtime_ids, scene_data.root_entity_id)?;
+        if let Some(root_entity_id) = self.root_entity_runtime_id {
+            self.mark_transform_dirty(root_entity_id);
+        }
+        Ok(runtime_ids)
+    }
+
+    /// Create the entities of scene data in this scene without linking them to the existing
+    /// entities, returns the map of <file id: runtime id>
+    pub(crate) fn add_scene_data_entities(
+        &mut self,
+        scene_data: &SceneData,
+        resource_manager: &ResourceManager,
+    ) -> Result<HashMap<u64, u64>> {
+        let mut file_ids = HashSet::new();
+        for entity_data in &scene_data.entities {
+            if !file_ids.insert(entity_data.id) {
+                return Err(anyhow!(
+                    "Duplicate entity id {} in scene file",
+                    entity_data.id
+                ));
+            }
+        }
+
+        // first pass: create entities so every file id has a runtime id
+        let runtime_ids: HashMap<u64, u64> = scene_data
+            .entities
+            .iter()
+            .map(|entity_data| (entity_data.id, self.handle.add_entity(()).inner()))
+            .collect();
+        let remap = |file_id: Option<u64>| remap_file_id(&runtime_ids, file_id);
+
+        // second pass: add components, remapping entity references to runtime ids
+        for entity_data in &scene_data.entities {
+            if let Err(err) = self.add_entity_data_components(
+                runtime_ids[&entity_data.id],
+                entity_data,
+                &remap,
+                resource_manager,
+            ) {
+                // none of the entities are linked to the existing ones yet
+                self.delete_entities(&runtime_ids.values().copied().collect::<Vec<_>>());
+                return Err(err);
+            }
+        }
+        Ok(runtime_ids)
+    }
+
+    /// Move an entity and its descendants to another scene, under new_parent_id or under the root
+    /// entity of the target scene. Returns the runtime id of the entity in the target scene.
+    pub fn move_entity_to_scene(
+        source_scene: Weak<Mutex<Scene>>,
+        entity_id: u64,
+        target_scene: Weak<Mutex<Scene>>,
+        new_parent_id: Option<u64>,
+        resource_manager: &ResourceManager,
+    ) -> Result<u64> {
+        let source_scene_arc = source_scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade source scene when moving entity"))?;
+        let target_scene_arc = target_scene
+            .upgrade()
+            .ok_or_else(|| anyhow!("Unable to upgrade target scene when moving entity"))?;
+        if Arc::ptr_eq(&source_scene_arc, &target_scene_arc) {
+            return Err(anyhow!(
+                "Unable to move entity {} to the scene it is already in",
+                entity_id
+            ));
+        }
+        let subtree_data = {
+            let mut source_scene_mutex_lock = source_scene_arc
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire source scene mutex"))?;
+            if source_scene_mutex_lock.root_entity_runtime_id == Some(entity_id) {
+                return Err(anyhow!("Unable to move the root entity to another scene"));
+            }
+            if !source_scene_mutex_lock.is_alive(entity_id) {
+                return Err(anyhow!("Entity {} does not exist", entity_id));
+            }
+            source_scene_mutex_lock.record_prefab_overrides();
+            source_scene_mutex_lock.subtree_to_scene_data(entity_id)
+        };
+        let (new_entity_id, parent_id) = {
+            let mut target_scene_mutex_lock = target_scene_arc
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire target scene mutex"))?;
+            let parent_id = new_parent_id
+                .or(target_scene_mutex_lock.root_entity_runtime_id)
+                .ok_or_else(|| anyhow!("Target scene has no root entity"))?;
+            if !target_scene_mutex_lock.is_alive(parent_id) {
+                return Err(anyhow!(
+                    "Parent {} does not exist in the target scene",
+                    parent_id
+                ));
+            }
+            // adding the entities is undone by add_scene_data_entities when it fails
+            let runtime_ids =
+                target_scene_mutex_lock.add_scene_data_entities(&subtree_data, resource_manager)?;
+            let new_entity_id = match remap_file_id(&runtime_ids, subtree_data.root_entity_id) {
+                Ok(Some(new_entity_id)) => new_entity_id,
+                _ => {
+                    target_scene_mutex_lock
+                        .delete_entities(&runtime_ids.values().copied().collect::<Vec<_>>());
+                    return Err(anyhow!("Moved entity has no root"));
+                }
+            };
+            (new_entity_id, parent_id)
+        };
+        Scene::add_child_to_entity(target_scene.clone(), new_entity_id, parent_id)?;
+        // the entity keeps its guid, references to it resolve in the target scene from now on
+        if let Err(err) = Scene::destroy_entity(source_scene, entity_id) {
+            Scene::remove_from_parent(target_scene, new_entity_id);
+            let mut target_scene_mutex_lock = target_scene_arc
+                .lock()
+                .map_err(|_| anyhow!("Unable to acquire target scene mutex"))?;
+            let entity_ids = target_scene_mutex_lock.collect_subtree(new_entity_id);
+            target_scene_mutex_lock.delete_entities(&entity_ids);
+            return Err(err);
+        }
+        Ok(new_entity_id)
+    }
+
+    /// Serialize the scene to YAML
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(&self.to_scene_data())
+            .map_err(|err| anyhow!("Unable to serialize scene: {}", err))
+    }
+
+    /// Replace the contents of this scene with a YAML scene
+    pub fn load_yaml(&mut self, yaml: &str, resource_manager: &ResourceManager) -> Result<()> {
+        let scene_data = SceneData::from_yaml(yaml)?;
+        self.load_scene_data(&scene_data, resource_manager)?;
+        Ok(())
+    }
+
+    /// Write the scene to a .scene file
+    pub fn save(&mut self, path: PathBuf) -> Result<()> {
+        self.record_prefab_overrides();
+        let yaml = self.to_yaml()?;
+        log::debug!("Saving scene to {}", path.to_str().unwrap_or("none"));
+        dream_fs::fs::write_binary(path, yaml.into_bytes())
+    }
+
+    /// Create a new scene from a .scene file
+    pub fn load(path: PathBuf, resource_manager: &ResourceManager) -> Result<Arc<Mutex<Scene>>> {
+        let bytes = dream_fs::fs::read_binary(path.clone(), true).map_err(|err| {
+            anyhow!(
+                "Unable to read scene file {}: {}",
+                path.to_str().unwrap_or("none"),
+                err
+            )
+        })?;
+        let yaml = String::from_utf8(bytes).map_err(|_| anyhow!("Scene file is not UTF-8"))?;
+        let scene = Scene::create();
+        scene
+            .lock()
+            .map_err(|_| anyhow!("Unable to acquire scene mutex when loading scene"))?
+            .load_yaml(&yaml, resource_manager)?;
+        Ok(scene)
+    }
+}
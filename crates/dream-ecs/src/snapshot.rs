@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use dream_resource::resource_manager::ResourceManager;
+
+use crate::history::CommandHistory;
+use crate::scene::Scene;
+use crate::serialization::{upgrade_scene_data, SceneData};
+
+/// Full state of a scene, taken when play mode starts so that the scene can be put back exactly
+/// the way it was when play mode stops. The scene is kept as it would be saved, so components
+/// that are neither saved by EntityData nor registered (see reflect.rs) are not kept.
+pub struct SceneSnapshot {
+    guid: Option<String>,
+    scene_data: SceneData,
+    prefabs: HashMap<String, SceneData>,
+    /// Edit history of the scene, commands executed while playing are not kept
+    history: CommandHistory,
+}
+
+impl Scene {
+    /// Capture the state of the scene, the scene starts over with an empty command history until
+    /// the snapshot is restored
+    pub fn take_snapshot(&mut self) -> SceneSnapshot {
+        // entities are numbered by their runtime ids, so restore can report where each one went
+        let scene_data = match self.root_entity_runtime_id {
+            Some(root_entity_id) => {
+                let file_ids: HashMap<u64, u64> = self
+                    .collect_subtree(root_entity_id)
+                    .into_iter()
+                    .map(|runtime_id| (runtime_id, runtime_id))
+                    .collect();
+                SceneData {
+                    name: self.name.clone(),
+                    ..self.subtree_to_scene_data_with_ids(root_entity_id, &file_ids)
+                }
+            }
+            None => self.to_scene_data(),
+        };
+        SceneSnapshot {
+            guid: self.guid.clone(),
+            scene_data,
+            prefabs: self.prefabs.clone(),
+            history: std::mem::take(&mut self.history),
+        }
+    }
+
+    /// Replace the contents of the scene with a snapshot. Prefab instances are not synced to their
+    /// prefabs, so the scene ends up as it was when the snapshot was taken. Returns the map of
+    /// <runtime id when the snapshot was taken: new runtime id>.
+    pub fn restore_snapshot(
+        &mut self,
+        snapshot: SceneSnapshot,
+        resource_manager: &ResourceManager,
+    ) -> Result<HashMap<u64, u64>> {
+        let scene_data = upgrade_scene_data(&snapshot.scene_data)?;
+        // like loading, the scene is only changed once the snapshot was restored completely
+        let mut restored = Scene::empty();
+        restored.guid = snapshot.guid;
+        restored.prefabs = snapshot.prefabs;
+        restored.history = snapshot.history;
+        let runtime_ids = restored.add_root_scene_data(&scene_data, resource_manager)?;
+        self.replace_contents(restored);
+        Ok(runtime_ids)
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex};
+
+use dream_ecs::component::{Active, Light, LightType};
+use dream_ecs::entity::Entity;
+use dream_ecs::history::SetComponent;
+use dream_ecs::scene::Scene;
+use dream_math::Vector3;
+
+fn point_light() -> Light {
+    Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    )
+}
+
+fn set_active(scene: &Arc<Mutex<Scene>>, entity_id: u64, active: bool) {
+    let guid = scene.lock().unwrap().get_entity_guid(entity_id).unwrap();
+    Scene::execute(
+        Arc::downgrade(scene),
+        SetComponent::new(guid, Active::new(active)),
+    )
+    .unwrap();
+}
+
+#[test]
+fn inactive_entities_deactivate_their_whole_subtree() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let player =
+        Scene::create_entity(weak_scene.clone(), Some("Player".into()), None, None).unwrap();
+    let arm =
+        Scene::create_entity(weak_scene.clone(), Some("Arm".into()), Some(player), None).unwrap();
+    let torch =
+        Scene::create_entity(weak_scene.clone(), Some("Torch".into()), Some(arm), None).unwrap();
+    let lamp = Scene::create_entity(weak_scene.clone(), Some("Lamp".into()), None, None).unwrap();
+    Entity::from_handle(torch, weak_scene.clone()).add_component(point_light());
+    Entity::from_handle(lamp, weak_scene.clone()).add_component(point_light());
+    let mut lights = scene
+        .lock()
+        .unwrap()
+        .get_active_entities_with_component::<Light>();
+    lights.sort();
+    let mut all_lights = vec![torch, lamp];
+    all_lights.sort();
+    assert_eq!(lights, all_lights);
+
+    set_active(&scene, player, false);
+    {
+        let scene = scene.lock().unwrap();
+        assert!(!scene.is_active_in_hierarchy(player));
+        // descendants keep their own flag, only their ancestor turned them off
+        assert!(scene.is_active_self(torch));
+        assert!(!scene.is_active_in_hierarchy(arm));
+        assert!(!scene.is_active_in_hierarchy(torch));
+        assert!(scene.is_active_in_hierarchy(lamp));
+        assert_eq!(
+            scene.get_active_entities_with_component::<Light>(),
+            vec![lamp]
+        );
+    }
+
+    // turning the ancestor back on does not turn on a descendant that was turned off itself
+    set_active(&scene, arm, false);
+    set_active(&scene, player, true);
+    {
+        let scene = scene.lock().unwrap();
+        assert!(scene.is_active_in_hierarchy(player));
+        assert!(!scene.is_active_in_hierarchy(torch));
+        assert_eq!(
+            scene.get_active_entities_with_component::<Light>(),
+            vec![lamp]
+        );
+    }
+
+    // undoing both steps leaves only the player turned off
+    Scene::undo(weak_scene.clone()).unwrap();
+    Scene::undo(weak_scene).unwrap();
+    assert!(!scene.lock().unwrap().is_active_in_hierarchy(torch));
+    assert!(!scene.lock().unwrap().is_active_self(player));
+    assert!(scene.lock().unwrap().is_active_self(arm));
+}
@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex};
+
+use dream_ecs::component::{EntityRef, Light, LightType};
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+use dream_math::Vector3;
+use dream_resource::resource_manager::ResourceManager;
+
+/// Resource manager of an empty project, the moved entities use no resources
+fn empty_resource_manager() -> ResourceManager {
+    let project_dir =
+        std::env::temp_dir().join(format!("dream-additive-scenes-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    ResourceManager::default()
+}
+
+/// Player whose arm holds a lamp
+fn create_level() -> Arc<Mutex<Scene>> {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let player =
+        Scene::create_entity(weak_scene.clone(), Some("Player".into()), None, None).unwrap();
+    let arm =
+        Scene::create_entity(weak_scene.clone(), Some("Arm".into()), Some(player), None).unwrap();
+    Entity::from_handle(arm, weak_scene).add_component(Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 1.0, 1.0),
+        2.0,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    ));
+    scene
+}
+
+fn create_empty_level() -> Arc<Mutex<Scene>> {
+    let scene = Scene::create();
+    Scene::create_entity(Arc::downgrade(&scene), Some("Floor".into()), None, None).unwrap();
+    scene
+}
+
+#[test]
+fn moved_entities_keep_their_components_and_guid() {
+    let source = create_level();
+    let target = create_empty_level();
+    let player = source.lock().unwrap().find_by_path("Player").unwrap();
+    let player_guid = source.lock().unwrap().get_entity_guid(player).unwrap();
+    let player_ref = EntityRef::new(player_guid.clone());
+
+    let moved_player = Scene::move_entity_to_scene(
+        Arc::downgrade(&source),
+        player,
+        Arc::downgrade(&target),
+        None,
+        &empty_resource_manager(),
+    )
+    .unwrap();
+
+    assert_eq!(source.lock().unwrap().find_by_path("Player"), None);
+    assert!(!source.lock().unwrap().is_alive(player));
+    assert_eq!(player_ref.resolve(&source.lock().unwrap()), None);
+
+    assert_eq!(
+        target.lock().unwrap().find_by_path("Player"),
+        Some(moved_player)
+    );
+    // references to the entity resolve in the scene it was moved to
+    assert_eq!(
+        target.lock().unwrap().get_entity_guid(moved_player),
+        Some(player_guid)
+    );
+    assert_eq!(
+        player_ref.resolve(&target.lock().unwrap()),
+        Some(moved_player)
+    );
+    let arm = target.lock().unwrap().find_by_path("Player/Arm").unwrap();
+    let light: Light = Entity::from_handle(arm, Arc::downgrade(&target))
+        .get_component()
+        .unwrap();
+    assert_eq!(light.radius, 2.0);
+}
+
+#[test]
+fn moving_under_a_missing_parent_changes_neither_scene() {
+    let source = create_level();
+    let target = create_empty_level();
+    let player = source.lock().unwrap().find_by_path("Player").unwrap();
+    let source_data = source.lock().unwrap().to_scene_data();
+    let target_data = target.lock().unwrap().to_scene_data();
+
+    // the player id is not an entity of the target scene
+    assert!(Scene::move_entity_to_scene(
+        Arc::downgrade(&source),
+        player,
+        Arc::downgrade(&target),
+        Some(player + 100),
+        &empty_resource_manager(),
+    )
+    .is_err());
+
+    assert_eq!(source.lock().unwrap().to_scene_data(), source_data);
+    assert_eq!(target.lock().unwrap().to_scene_data(), target_data);
+    // no entities were left behind outside of the hierarchy of the target scene
+    assert_eq!(target.lock().unwrap().find_by_name("Player"), None);
+    assert!(source.lock().unwrap().take_destroyed_entities().is_empty());
+}
+
+#[test]
+fn root_entities_and_moves_within_a_scene_are_rejected() {
+    let source = create_level();
+    let target = create_empty_level();
+    let root = source.lock().unwrap().root_entity_runtime_id.unwrap();
+    let player = source.lock().unwrap().find_by_path("Player").unwrap();
+    let source_data = source.lock().unwrap().to_scene_data();
+    let target_data = target.lock().unwrap().to_scene_data();
+
+    let resource_manager = empty_resource_manager();
+    assert!(Scene::move_entity_to_scene(
+        Arc::downgrade(&source),
+        root,
+        Arc::downgrade(&target),
+        None,
+        &resource_manager,
+    )
+    .is_err());
+    assert!(Scene::move_entity_to_scene(
+        Arc::downgrade(&source),
+        player,
+        Arc::downgrade(&source),
+        None,
+        &resource_manager,
+    )
+    .is_err());
+
+    assert_eq!(source.lock().unwrap().to_scene_data(), source_data);
+    assert_eq!(target.lock().unwrap().to_scene_data(), target_data);
+}
@@ -0,0 +1,303 @@
+use std::sync::{Arc, Mutex};
+
+use dream_ecs::animation::{AnimationChannel, AnimationPose, AnimationProperty, Interpolation};
+use dream_ecs::animator::AnimationStateMachine;
+use dream_ecs::component::{AnimationPlayer, Animator, GltfNode, MorphWeights, Tag, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+use dream_math::Vector3;
+use dream_resource::resource_manager::ResourceManager;
+
+fn translation_channel(interpolation: Interpolation, values: Vec<f32>) -> AnimationChannel {
+    AnimationChannel {
+        target_node: 0,
+        property: AnimationProperty::Translation,
+        interpolation,
+        times: vec![0.0, 2.0],
+        values,
+        stride: 3,
+    }
+}
+
+#[test]
+fn channels_are_sampled_with_their_interpolation() {
+    let step = translation_channel(Interpolation::Step, vec![0.0, 0.0, 0.0, 4.0, 2.0, 0.0]);
+    assert_eq!(step.sample(1.0), Some(vec![0.0, 0.0, 0.0]));
+
+    let linear = translation_channel(Interpolation::Linear, vec![0.0, 0.0, 0.0, 4.0, 2.0, 0.0]);
+    assert_eq!(linear.sample(1.0), Some(vec![2.0, 1.0, 0.0]));
+    // times outside of the keyframes are clamped
+    assert_eq!(linear.sample(-1.0), Some(vec![0.0, 0.0, 0.0]));
+    assert_eq!(linear.sample(3.0), Some(vec![4.0, 2.0, 0.0]));
+
+    // in-tangent, value and out-tangent per keyframe, flat tangents give a smooth step
+    let cubic = translation_channel(
+        Interpolation::CubicSpline,
+        vec![
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0,
+        ],
+    );
+    assert_eq!(cubic.sample(1.0), Some(vec![2.0, 1.0, 0.0]));
+    assert_eq!(cubic.sample(0.5), Some(vec![0.625, 0.3125, 0.0]));
+}
+
+#[test]
+fn players_loop_or_stop_at_the_end_of_the_clip() {
+    let mut looping = AnimationPlayer::default();
+    looping.advance(2.5, 2.0);
+    assert_eq!(looping.time, 0.5);
+    assert!(looping.playing);
+
+    let mut once = AnimationPlayer {
+        looping: false,
+        speed: 2.0,
+        ..Default::default()
+    };
+    once.advance(1.5, 2.0);
+    assert_eq!(once.time, 2.0);
+    assert!(!once.playing);
+
+    once.pause();
+    once.advance(1.0, 2.0);
+    assert_eq!(once.time, 2.0);
+}
+
+#[test]
+fn state_machine_transitions_follow_parameters() {
+    let state_machine = AnimationStateMachine::from_yaml(
+        br#"
+parameters:
+  speed: 0.0
+states:
+  - name: idle
+    clip: Idle
+  - name: walk
+    clip: Walk
+transitions:
+  - from: idle
+    to: walk
+    duration: 0.25
+    conditions:
+      - parameter: speed
+        op: greater
+        value: 0.1
+  - from: walk
+    to: idle
+    exit_time: 1.0
+    conditions:
+      - parameter: speed
+        op: less
+        value: 0.1
+"#,
+    )
+    .unwrap();
+    let mut animator = Animator::default();
+    assert_eq!(state_machine.get_initial_state().unwrap().name, "idle");
+    assert!(state_machine
+        .find_transition(&animator, "idle", 0.0)
+        .is_none());
+
+    animator.set_parameter("speed".into(), 1.0);
+    let transition = state_machine
+        .find_transition(&animator, "idle", 0.0)
+        .unwrap();
+    assert_eq!(transition.to, "walk");
+
+    // walk only goes back to idle once its clip finished
+    animator.set_parameter("speed".into(), 0.0);
+    assert!(state_machine
+        .find_transition(&animator, "walk", 0.5)
+        .is_none());
+    assert!(state_machine
+        .find_transition(&animator, "walk", 1.0)
+        .is_some());
+}
+
+#[test]
+fn cross_fades_blend_between_poses() {
+    let mut player = AnimationPlayer::new(None, "Idle".into());
+    player.cross_fade("Walk".into(), 0.5);
+    assert_eq!(player.clip, "Walk");
+    assert_eq!(player.fade.as_ref().unwrap().clip, "Idle");
+
+    let key = (1, AnimationProperty::Translation);
+    let mut pose = AnimationPose::default();
+    pose.values.insert(key, vec![0.0, 0.0, 0.0]);
+    let mut other = AnimationPose::default();
+    other.values.insert(key, vec![2.0, 4.0, 0.0]);
+    pose.blend(&other, 0.25);
+    assert_eq!(pose.values[&key], vec![0.5, 1.0, 0.0]);
+
+    let mut reference = AnimationPose::default();
+    reference.values.insert(key, vec![1.0, 1.0, 0.0]);
+    pose.add(&other, &reference, 1.0);
+    assert_eq!(pose.values[&key], vec![1.5, 4.0, 0.0]);
+}
+
+#[test]
+fn morph_weights_grow_to_fit_targets() {
+    let mut morph_weights = MorphWeights::new(vec![0.5]);
+    assert_eq!(morph_weights.get_weight(0), 0.5);
+    assert_eq!(morph_weights.get_weight(2), 0.0);
+
+    morph_weights.set_weight(2, 1.0);
+    assert_eq!(morph_weights.weights, vec![0.5, 0.0, 1.0]);
+}
+
+#[test]
+fn poses_are_applied_to_the_entities_of_their_nodes() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Model".into()), None, None).unwrap();
+    let hips =
+        Scene::create_entity(weak_scene.clone(), Some("Hips".into()), Some(model), None).unwrap();
+    let mesh =
+        Scene::create_entity(weak_scene.clone(), Some("Mesh".into()), Some(hips), None).unwrap();
+    // a model below that is animated by its own player
+    let other_model =
+        Scene::create_entity(weak_scene.clone(), Some("Other".into()), Some(model), None).unwrap();
+    let other_hips = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Hips".into()),
+        Some(other_model),
+        None,
+    )
+    .unwrap();
+    for (entity_id, node_index) in [(model, 0), (hips, 1), (other_model, 0), (other_hips, 1)] {
+        Entity::from_handle(entity_id, weak_scene.clone()).add_component(GltfNode { node_index });
+    }
+    Entity::from_handle(other_model, weak_scene.clone()).add_component(AnimationPlayer::default());
+    Entity::from_handle(mesh, weak_scene.clone()).add_component(MorphWeights::new(vec![0.0]));
+    // renaming an entity does not change what it is animated by
+    Entity::from_handle(hips, weak_scene.clone()).add_component(Tag::new("Pelvis".into()));
+
+    let mut pose = AnimationPose::default();
+    pose.values
+        .insert((1, AnimationProperty::Translation), vec![0.0, 2.0, 0.0]);
+    pose.values
+        .insert((1, AnimationProperty::Weights), vec![0.5, 1.0]);
+    // nodes without an entity are skipped
+    pose.values
+        .insert((7, AnimationProperty::Scale), vec![2.0, 2.0, 2.0]);
+    scene.lock().unwrap().apply_animation_pose(model, &pose);
+
+    let transform: Transform = Entity::from_handle(hips, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(transform.position, Vector3::new(0.0, 2.0, 0.0));
+    let morph_weights: MorphWeights = Entity::from_handle(mesh, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(morph_weights.weights, vec![0.5, 1.0]);
+    let other_transform: Transform = Entity::from_handle(other_hips, weak_scene)
+        .get_component()
+        .unwrap_or_default();
+    assert_eq!(other_transform.position, Vector3::zeros());
+}
+
+/// Animator and AnimationPlayer of an entity after the animation players were updated once
+fn update_animator(scene: &Arc<Mutex<Scene>>, entity_id: u64) -> (Animator, AnimationPlayer) {
+    scene.lock().unwrap().update_animation_players(0.1);
+    let entity = Entity::from_handle(entity_id, Arc::downgrade(scene));
+    (
+        entity.get_component().unwrap(),
+        entity.get_component().unwrap(),
+    )
+}
+
+#[test]
+fn animators_cross_fade_their_player_to_the_clip_of_the_new_state() {
+    let project_dir = std::env::temp_dir().join(format!("dream-animation-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    let state_machine_path = project_dir.join("knight.animator");
+    std::fs::write(
+        &state_machine_path,
+        br#"
+parameters:
+  speed: 0.0
+  lean: 0.25
+initial_state: idle
+states:
+  - name: walk
+    clip: Walk
+  - name: idle
+    clip: Idle
+  - name: die
+    clip: Die
+    looping: false
+transitions:
+  - from: idle
+    to: walk
+    duration: 0.25
+    conditions:
+      - parameter: speed
+        op: greater
+        value: 0.1
+  - to: die
+    duration: 0.5
+    conditions:
+      - parameter: dead
+        op: equals
+        value: 1.0
+layers:
+  - clip: Lean
+    weight_parameter: lean
+    additive: true
+"#,
+    )
+    .unwrap();
+    let resource_manager = ResourceManager::default();
+    let guid = resource_manager
+        .get_guid_for_path(&state_machine_path)
+        .unwrap();
+
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let knight =
+        Scene::create_entity(weak_scene.clone(), Some("Knight".into()), None, None).unwrap();
+    let entity = Entity::from_handle(knight, weak_scene);
+    entity.add_component(Animator::new(resource_manager.get_resource(guid)));
+    entity.add_component(AnimationPlayer::new(None, String::new()));
+
+    // the initial state is entered without a fade
+    let (animator, player) = update_animator(&scene, knight);
+    assert_eq!(animator.state, "idle");
+    assert_eq!(player.clip, "Idle");
+    assert_eq!(player.fade, None);
+    assert_eq!(player.layers.len(), 1);
+    assert_eq!(player.layers[0].clip, "Lean");
+    assert_eq!(player.layers[0].weight, 0.25);
+    assert!(player.layers[0].additive);
+
+    // nothing changes while no condition is met
+    let (animator, player) = update_animator(&scene, knight);
+    assert_eq!(animator.state, "idle");
+    assert_eq!(player.fade, None);
+
+    let mut animator = animator;
+    animator.set_parameter("speed".into(), 1.0);
+    animator.set_parameter("lean".into(), 0.75);
+    entity.add_component(animator);
+    let (animator, player) = update_animator(&scene, knight);
+    assert_eq!(animator.state, "walk");
+    assert_eq!(player.clip, "Walk");
+    let fade = player.fade.unwrap();
+    assert_eq!(fade.clip, "Idle");
+    assert_eq!(fade.duration, 0.25);
+    assert_eq!(player.layers[0].weight, 0.75);
+
+    // transitions from any state are taken once and do not restart their state
+    let mut animator = animator;
+    animator.set_parameter("dead".into(), 1.0);
+    entity.add_component(animator);
+    let (animator, player) = update_animator(&scene, knight);
+    assert_eq!(animator.state, "die");
+    assert_eq!(player.clip, "Die");
+    assert!(!player.looping);
+    assert_eq!(player.fade.as_ref().unwrap().clip, "Walk");
+    let (animator, player_after) = update_animator(&scene, knight);
+    assert_eq!(animator.state, "die");
+    assert_eq!(player_after.fade, player.fade);
+}
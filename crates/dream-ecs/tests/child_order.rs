@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::scene::Scene;
+
+fn create_children(scene: Weak<Mutex<Scene>>, parent: u64, names: &[&str]) -> Vec<u64> {
+    names
+        .iter()
+        .map(|name| {
+            Scene::create_entity(scene.clone(), Some((*name).into()), Some(parent), None).unwrap()
+        })
+        .collect()
+}
+
+fn create_parent(scene: Weak<Mutex<Scene>>) -> u64 {
+    Scene::create_entity(scene, Some("Parent".into()), None, None).unwrap()
+}
+
+#[test]
+fn children_keep_creation_order() {
+    let scene = Scene::create();
+    let parent = create_parent(Arc::downgrade(&scene));
+    let children = create_children(Arc::downgrade(&scene), parent, &["A", "B", "C"]);
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), parent),
+        children
+    );
+}
+
+#[test]
+fn insert_child_at_index() {
+    let scene = Scene::create();
+    let parent = create_parent(Arc::downgrade(&scene));
+    let children = create_children(Arc::downgrade(&scene), parent, &["A", "B"]);
+    let other_parent = create_parent(Arc::downgrade(&scene));
+    let new_child =
+        Scene::create_entity(Arc::downgrade(&scene), None, Some(other_parent), None).unwrap();
+
+    Scene::insert_child_at(Arc::downgrade(&scene), new_child, parent, 1).unwrap();
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), parent),
+        vec![children[0], new_child, children[1]]
+    );
+    assert!(Scene::get_children_for_entity(Arc::downgrade(&scene), other_parent).is_empty());
+
+    // an index past the end appends
+    Scene::insert_child_at(Arc::downgrade(&scene), new_child, parent, 10).unwrap();
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), parent),
+        vec![children[0], children[1], new_child]
+    );
+    assert_eq!(
+        Scene::get_child_index(Arc::downgrade(&scene), new_child),
+        Some(2)
+    );
+}
+
+#[test]
+fn move_siblings() {
+    let scene = Scene::create();
+    let parent = create_parent(Arc::downgrade(&scene));
+    let children = create_children(Arc::downgrade(&scene), parent, &["A", "B", "C"]);
+
+    assert!(!Scene::move_sibling_up(Arc::downgrade(&scene), children[0]));
+    assert!(!Scene::move_sibling_down(
+        Arc::downgrade(&scene),
+        children[2]
+    ));
+
+    assert!(Scene::move_sibling_up(Arc::downgrade(&scene), children[2]));
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), parent),
+        vec![children[0], children[2], children[1]]
+    );
+
+    assert!(Scene::move_sibling_down(
+        Arc::downgrade(&scene),
+        children[0]
+    ));
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), parent),
+        vec![children[2], children[0], children[1]]
+    );
+}
+
+#[test]
+fn insert_child_under_itself_is_rejected() {
+    let scene = Scene::create();
+    let parent = create_parent(Arc::downgrade(&scene));
+    let children = create_children(Arc::downgrade(&scene), parent, &["A"]);
+    let grandchildren = create_children(Arc::downgrade(&scene), children[0], &["B"]);
+
+    assert!(Scene::insert_child_at(Arc::downgrade(&scene), parent, grandchildren[0], 0).is_err());
+    assert!(Scene::insert_child_at(Arc::downgrade(&scene), children[0], children[0], 0).is_err());
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), parent),
+        children
+    );
+    assert_eq!(
+        Scene::get_children_for_entity(Arc::downgrade(&scene), children[0]),
+        grandchildren
+    );
+    assert!(Scene::get_children_for_entity(Arc::downgrade(&scene), grandchildren[0]).is_empty());
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::Hierarchy;
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+
+/// Create a parent with three children, returns (parent, [first, middle, last]) where the
+/// children are listed in the order they appear in the hierarchy
+fn create_family(scene: Weak<Mutex<Scene>>) -> (u64, [u64; 3]) {
+    let parent = Scene::create_entity(scene.clone(), Some("Parent".into()), None, None).unwrap();
+    let mut children = Vec::new();
+    for name in ["A", "B", "C"] {
+        let child =
+            Scene::create_entity(scene.clone(), Some(name.into()), Some(parent), None).unwrap();
+        children.push(child);
+    }
+    // new children are appended, so they are listed in creation order
+    (parent, [children[0], children[1], children[2]])
+}
+
+fn hierarchy(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Hierarchy {
+    Entity::from_handle(entity_id, scene)
+        .get_component()
+        .expect("No hierarchy component")
+}
+
+/// Check that the linked list of children of the parent is consistent and matches expected
+fn assert_children(scene: Weak<Mutex<Scene>>, parent: u64, expected: &[u64]) {
+    assert_eq!(
+        Scene::get_children_for_entity(scene.clone(), parent),
+        expected
+    );
+    let parent_hierarchy = hierarchy(scene.clone(), parent);
+    assert_eq!(parent_hierarchy.num_children, expected.len());
+    assert_eq!(
+        parent_hierarchy.first_child_runtime_id,
+        expected.first().copied()
+    );
+    for (idx, child) in expected.iter().enumerate() {
+        let child_hierarchy = hierarchy(scene.clone(), *child);
+        assert_eq!(child_hierarchy.parent_runtime_id, Some(parent));
+        let prev = if idx == 0 {
+            None
+        } else {
+            Some(expected[idx - 1])
+        };
+        assert_eq!(child_hierarchy.prev_sibling_runtime_id, prev);
+        assert_eq!(
+            child_hierarchy.next_sibling_runtime_id,
+            expected.get(idx + 1).copied()
+        );
+    }
+}
+
+fn is_alive(scene: &Arc<Mutex<Scene>>, entity_id: u64) -> bool {
+    scene.lock().unwrap().is_alive(entity_id)
+}
+
+#[test]
+fn destroy_first_child() {
+    let scene = Scene::create();
+    let (parent, [first, middle, last]) = create_family(Arc::downgrade(&scene));
+    Scene::destroy_entity(Arc::downgrade(&scene), first).unwrap();
+    assert!(!is_alive(&scene, first));
+    assert_children(Arc::downgrade(&scene), parent, &[middle, last]);
+}
+
+#[test]
+fn destroy_middle_child() {
+    let scene = Scene::create();
+    let (parent, [first, middle, last]) = create_family(Arc::downgrade(&scene));
+    Scene::destroy_entity(Arc::downgrade(&scene), middle).unwrap();
+    assert!(!is_alive(&scene, middle));
+    assert_children(Arc::downgrade(&scene), parent, &[first, last]);
+}
+
+#[test]
+fn destroy_last_child() {
+    let scene = Scene::create();
+    let (parent, [first, middle, last]) = create_family(Arc::downgrade(&scene));
+    Scene::destroy_entity(Arc::downgrade(&scene), last).unwrap();
+    assert!(!is_alive(&scene, last));
+    assert_children(Arc::downgrade(&scene), parent, &[first, middle]);
+}
+
+#[test]
+fn destroy_only_child() {
+    let scene = Scene::create();
+    let parent =
+        Scene::create_entity(Arc::downgrade(&scene), Some("Parent".into()), None, None).unwrap();
+    let child = Scene::create_entity(Arc::downgrade(&scene), None, Some(parent), None).unwrap();
+    Scene::destroy_entity(Arc::downgrade(&scene), child).unwrap();
+    assert_children(Arc::downgrade(&scene), parent, &[]);
+}
+
+#[test]
+fn destroy_subtree() {
+    let scene = Scene::create();
+    let (parent, [first, middle, last]) = create_family(Arc::downgrade(&scene));
+    let grandchild =
+        Scene::create_entity(Arc::downgrade(&scene), None, Some(middle), None).unwrap();
+    let great_grandchild =
+        Scene::create_entity(Arc::downgrade(&scene), None, Some(grandchild), None).unwrap();
+    Scene::destroy_entity(Arc::downgrade(&scene), parent).unwrap();
+    for entity_id in [parent, first, middle, last, grandchild, great_grandchild] {
+        assert!(!is_alive(&scene, entity_id));
+    }
+    let mut destroyed = scene.lock().unwrap().take_destroyed_entities();
+    destroyed.sort();
+    let mut expected = vec![parent, first, middle, last, grandchild, great_grandchild];
+    expected.sort();
+    assert_eq!(destroyed, expected);
+    assert!(scene.lock().unwrap().take_destroyed_entities().is_empty());
+}
+
+#[test]
+fn destroy_root_fails() {
+    let scene = Scene::create();
+    Scene::create_entity(Arc::downgrade(&scene), None, None, None).unwrap();
+    let root = scene.lock().unwrap().root_entity_runtime_id.unwrap();
+    assert!(Scene::destroy_entity(Arc::downgrade(&scene), root).is_err());
+    assert!(is_alive(&scene, root));
+}
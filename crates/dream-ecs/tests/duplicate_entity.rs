@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::{Bone, Hierarchy};
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+use dream_math::Matrix4;
+
+/// Model with a mesh and an armature of two bones, returns (model, hips, spine)
+fn create_skinned_model(scene: Weak<Mutex<Scene>>) -> (u64, u64, u64) {
+    let model = Scene::create_entity(scene.clone(), Some("Model".into()), None, None).unwrap();
+    Scene::create_entity(scene.clone(), Some("Mesh".into()), Some(model), None).unwrap();
+    let armature =
+        Scene::create_entity(scene.clone(), Some("Armature".into()), Some(model), None).unwrap();
+    let hips =
+        Scene::create_entity(scene.clone(), Some("Hips".into()), Some(armature), None).unwrap();
+    let spine =
+        Scene::create_entity(scene.clone(), Some("Spine".into()), Some(hips), None).unwrap();
+    for (bone_id, entity_id) in [hips, spine].into_iter().enumerate() {
+        Entity::from_handle(entity_id, scene.clone()).add_component(Bone {
+            is_root: entity_id == hips,
+            node_id: entity_id as u32,
+            bone_id: bone_id as u32,
+            inverse_bind_pose: Matrix4::identity(),
+            armature_root_runtime_id: Some(hips),
+        });
+    }
+    (model, hips, spine)
+}
+
+fn hierarchy(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Hierarchy {
+    Entity::from_handle(entity_id, scene)
+        .get_component()
+        .expect("No hierarchy component")
+}
+
+fn bone(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Bone {
+    Entity::from_handle(entity_id, scene)
+        .get_component()
+        .expect("No bone component")
+}
+
+#[test]
+fn duplicated_armatures_are_posed_by_their_own_root_bone() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let (model, hips, spine) = create_skinned_model(weak_scene.clone());
+
+    let copy = Scene::duplicate_entity(weak_scene.clone(), model, None).unwrap();
+    let copy_hips = scene
+        .lock()
+        .unwrap()
+        .find_all_by_path("Model/Armature/Hips")
+        .into_iter()
+        .find(|entity_id| *entity_id != hips)
+        .unwrap();
+    let copy_spine = Scene::get_children_for_entity(weak_scene.clone(), copy_hips)[0];
+    assert_ne!(copy_spine, spine);
+
+    // the copy is linked to copies only, in the same order as the original
+    let copy_children = Scene::get_children_for_entity(weak_scene.clone(), copy);
+    assert_eq!(copy_children.len(), 2);
+    let copy_armature = copy_children[1];
+    assert_eq!(
+        hierarchy(weak_scene.clone(), copy_children[0]).parent_runtime_id,
+        Some(copy)
+    );
+    assert_eq!(
+        hierarchy(weak_scene.clone(), copy_armature).prev_sibling_runtime_id,
+        Some(copy_children[0])
+    );
+    assert_eq!(
+        hierarchy(weak_scene.clone(), copy_hips).parent_runtime_id,
+        Some(copy_armature)
+    );
+    assert_eq!(
+        hierarchy(weak_scene.clone(), copy_spine).parent_runtime_id,
+        Some(copy_hips)
+    );
+
+    for bone_id in [copy_hips, copy_spine] {
+        assert_eq!(
+            bone(weak_scene.clone(), bone_id).armature_root_runtime_id,
+            Some(copy_hips)
+        );
+    }
+    // the original armature is untouched
+    assert_eq!(
+        bone(weak_scene.clone(), spine).armature_root_runtime_id,
+        Some(hips)
+    );
+    assert_eq!(hierarchy(weak_scene, spine).parent_runtime_id, Some(hips));
+}
+
+#[test]
+fn duplicating_under_a_missing_parent_creates_nothing() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let (model, _, spine) = create_skinned_model(weak_scene.clone());
+    let scene_data = scene.lock().unwrap().to_scene_data();
+
+    assert!(Scene::duplicate_entity(weak_scene, model, Some(spine + 100)).is_err());
+
+    assert_eq!(scene.lock().unwrap().to_scene_data(), scene_data);
+    assert_eq!(scene.lock().unwrap().find_all_by_name("Hips").len(), 1);
+}
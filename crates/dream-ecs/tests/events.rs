@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use dream_ecs::component::{Light, LightType};
+use dream_ecs::entity::Entity;
+use dream_ecs::reflect::{component_registry, register_component, ComponentRegistration};
+use dream_ecs::scene::Scene;
+use dream_math::Vector3;
+
+/// Component defined outside of dream-ecs whose events are recorded once it is registered
+#[derive(shipyard::Component, Debug, Clone, Default, PartialEq)]
+#[track(All)]
+struct Fuel {
+    liters: f32,
+}
+
+fn light(intensity: f32) -> Light {
+    Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 1.0, 1.0),
+        intensity,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    )
+}
+
+#[test]
+fn events_are_reported_for_one_frame() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let added = Scene::create_entity(weak_scene.clone(), Some("Added".into()), None, None).unwrap();
+    let changed =
+        Scene::create_entity(weak_scene.clone(), Some("Changed".into()), None, None).unwrap();
+    let removed =
+        Scene::create_entity(weak_scene.clone(), Some("Removed".into()), None, None).unwrap();
+    Entity::from_handle(changed, weak_scene.clone()).add_component(light(1.0));
+    Entity::from_handle(removed, weak_scene.clone()).add_component(light(1.0));
+    scene.lock().unwrap().collect_component_events();
+
+    Entity::from_handle(added, weak_scene.clone()).add_component(light(1.0));
+    Entity::from_handle(changed, weak_scene.clone()).add_component(light(2.0));
+    Entity::from_handle(removed, weak_scene).remove_component::<Light>();
+    scene.lock().unwrap().collect_component_events();
+    {
+        let scene = scene.lock().unwrap();
+        assert_eq!(scene.added::<Light>(), [added]);
+        assert_eq!(scene.changed::<Light>(), [changed]);
+        assert_eq!(scene.removed::<Light>(), [removed]);
+    }
+
+    // nothing happened during the next frame
+    scene.lock().unwrap().collect_component_events();
+    let scene = scene.lock().unwrap();
+    assert!(scene.get_component_events::<Light>().is_none());
+    assert!(scene.added::<Light>().is_empty());
+    assert!(scene.changed::<Light>().is_empty());
+    assert!(scene.removed::<Light>().is_empty());
+}
+
+#[test]
+fn events_of_tracked_registered_components_are_reported() {
+    register_component(
+        ComponentRegistration::<Fuel>::new("Fuel")
+            .field(
+                "liters",
+                |fuel| fuel.liters,
+                |fuel, liters| fuel.liters = liters,
+            )
+            .tracked(),
+    );
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let car = Scene::create_entity(weak_scene, Some("Car".into()), None, None).unwrap();
+    scene.lock().unwrap().collect_component_events();
+
+    let mut scene = scene.lock().unwrap();
+    {
+        let registry = component_registry();
+        registry
+            .get("Fuel")
+            .unwrap()
+            .add_default(&mut scene, car)
+            .unwrap();
+    }
+    scene.collect_component_events();
+    assert_eq!(scene.added::<Fuel>(), [car]);
+}
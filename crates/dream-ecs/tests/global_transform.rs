@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::{GlobalTransform, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+use dream_math::{Matrix4, UnitQuaternion, Vector3};
+
+fn translation(x: f32, y: f32, z: f32) -> Transform {
+    Transform::new(
+        Vector3::new(x, y, z),
+        UnitQuaternion::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    )
+}
+
+fn global_position(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Vector3<f32> {
+    let global_transform: GlobalTransform = Entity::from_handle(entity_id, scene)
+        .get_component()
+        .expect("No global transform");
+    global_transform.matrix.column(3).xyz()
+}
+
+#[test]
+fn changed_parents_update_their_children_and_clean_subtrees_are_skipped() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let parent = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Parent".into()),
+        None,
+        Some(translation(1.0, 0.0, 0.0)),
+    )
+    .unwrap();
+    let child = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Child".into()),
+        Some(parent),
+        Some(translation(0.0, 2.0, 0.0)),
+    )
+    .unwrap();
+    let other = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Other".into()),
+        None,
+        Some(translation(0.0, 0.0, 3.0)),
+    )
+    .unwrap();
+    scene.lock().unwrap().propagate_transforms();
+    assert_eq!(
+        global_position(weak_scene.clone(), child),
+        Vector3::new(1.0, 2.0, 0.0)
+    );
+    assert_eq!(
+        global_position(weak_scene.clone(), other),
+        Vector3::new(0.0, 0.0, 3.0)
+    );
+
+    // a stale matrix in the clean subtree shows whether it is recomputed
+    let stale = GlobalTransform {
+        matrix: Matrix4::new_translation(&Vector3::new(9.0, 9.0, 9.0)),
+    };
+    Entity::from_handle(other, weak_scene.clone()).add_component(stale);
+    Entity::from_handle(parent, weak_scene.clone()).add_component(translation(5.0, 0.0, 0.0));
+    scene.lock().unwrap().propagate_transforms();
+
+    assert_eq!(
+        global_position(weak_scene.clone(), parent),
+        Vector3::new(5.0, 0.0, 0.0)
+    );
+    assert_eq!(
+        global_position(weak_scene.clone(), child),
+        Vector3::new(5.0, 2.0, 0.0)
+    );
+    assert_eq!(
+        global_position(weak_scene, other),
+        Vector3::new(9.0, 9.0, 9.0)
+    );
+}
@@ -0,0 +1,315 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use dream_ecs::component::{Bone, Hierarchy, Light, LightType, MeshRenderer, Tag, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::gltf_export::glb_file_name;
+use dream_ecs::scene::Scene;
+use dream_math::{Matrix4, UnitQuaternion, Vector3};
+use dream_resource::resource_manager::ResourceManager;
+
+/// Name, number of children, bone and light of an entity
+type EntityDescription = (String, usize, Option<(bool, u32)>, Option<LightType>);
+
+/// Project directory that is used as the file system root, the tests of this file share it
+fn create_project() -> PathBuf {
+    let project_dir =
+        std::env::temp_dir().join(format!("dream-gltf-export-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    project_dir
+}
+
+/// Description of every entity below the given one
+fn describe_descendants(scene: &Arc<Mutex<Scene>>, entity_id: u64) -> Vec<EntityDescription> {
+    let subtree = scene.lock().unwrap().collect_subtree(entity_id);
+    subtree
+        .into_iter()
+        .skip(1)
+        .map(|descendant_id| {
+            let entity = Entity::from_handle(descendant_id, Arc::downgrade(scene));
+            (
+                entity.get_component::<Tag>().unwrap().name,
+                entity.get_component::<Hierarchy>().unwrap().num_children,
+                entity
+                    .get_component::<Bone>()
+                    .map(|bone| (bone.is_root, bone.bone_id)),
+                entity
+                    .get_component::<Light>()
+                    .map(|light| light.light_type),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn exported_subtree_is_imported_with_the_same_entities() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Model".into()), None, None).unwrap();
+    let armature = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Armature".into()),
+        Some(model),
+        Some(Transform::new(
+            Vector3::new(1.0, 0.0, 0.0),
+            UnitQuaternion::identity(),
+            Vector3::new(1.0, 1.0, 1.0),
+        )),
+    )
+    .unwrap();
+    Entity::from_handle(armature, weak_scene.clone()).add_component(Bone {
+        is_root: true,
+        node_id: 0,
+        bone_id: 0,
+        inverse_bind_pose: Matrix4::identity(),
+        armature_root_runtime_id: Some(armature),
+    });
+    let joint = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Joint".into()),
+        Some(armature),
+        None,
+    )
+    .unwrap();
+    let joint_inverse_bind_pose = Matrix4::new_translation(&Vector3::new(0.0, -1.0, 0.0));
+    Entity::from_handle(joint, weak_scene.clone()).add_component(Bone {
+        is_root: false,
+        node_id: 1,
+        bone_id: 1,
+        inverse_bind_pose: joint_inverse_bind_pose,
+        armature_root_runtime_id: Some(armature),
+    });
+    let sun = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Sun".into()),
+        Some(model),
+        Some(Transform::new(
+            Vector3::zeros(),
+            UnitQuaternion::from_euler_angles(-1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        )),
+    )
+    .unwrap();
+    Entity::from_handle(sun, weak_scene.clone()).add_component(Light::new(
+        LightType::DIRECTIONAL,
+        Vector3::new(2.0, 1.0, 1.0),
+        1.0,
+        Vector3::new(0.0, 0.0, -1.0),
+        true,
+    ));
+
+    let project_dir = create_project();
+    let glb_path = project_dir.join("model.glb");
+    scene
+        .lock()
+        .unwrap()
+        .export_glb(model, glb_path.clone())
+        .unwrap();
+    let resource_manager = ResourceManager::default();
+    let guid = resource_manager.get_guid_for_path(&glb_path).unwrap();
+    let imported =
+        Scene::create_entity(weak_scene.clone(), Some("Imported".into()), None, None).unwrap();
+    Scene::add_gltf_scene(weak_scene.clone(), imported, &resource_manager, guid).unwrap();
+
+    assert_eq!(
+        describe_descendants(&scene, imported),
+        describe_descendants(&scene, model)
+    );
+    let imported_joint = scene.lock().unwrap().collect_subtree(imported)[2];
+    let imported_bone: Bone = Entity::from_handle(imported_joint, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert!((imported_bone.inverse_bind_pose - joint_inverse_bind_pose).norm() < 1e-5);
+    let imported_sun = scene.lock().unwrap().collect_subtree(imported)[3];
+    let imported_light: Light = Entity::from_handle(imported_sun, weak_scene)
+        .get_component()
+        .unwrap();
+    assert!((imported_light.color - Vector3::new(2.0, 1.0, 1.0)).norm() < 1e-5);
+}
+
+/// Semantic, number of elements, component type and dimensions of every attribute of every
+/// primitive, and the number of indices
+fn describe_primitives(
+    gltf: &gltf::Gltf,
+) -> Vec<
+    Vec<(
+        String,
+        usize,
+        gltf::accessor::DataType,
+        gltf::accessor::Dimensions,
+    )>,
+> {
+    gltf.meshes()
+        .flat_map(|mesh| mesh.primitives())
+        .map(|primitive| {
+            let mut attributes: Vec<_> = primitive
+                .attributes()
+                .map(|(semantic, accessor)| {
+                    (
+                        semantic.to_string(),
+                        accessor.count(),
+                        accessor.data_type(),
+                        accessor.dimensions(),
+                    )
+                })
+                .collect();
+            attributes.sort_by(|a, b| a.0.cmp(&b.0));
+            let indices = primitive.indices().unwrap();
+            attributes.push((
+                String::from("indices"),
+                indices.count(),
+                indices.data_type(),
+                indices.dimensions(),
+            ));
+            attributes
+        })
+        .collect()
+}
+
+/// Number of entities below the given one that draw a mesh
+fn count_meshes(scene: &Arc<Mutex<Scene>>, entity_id: u64) -> usize {
+    let subtree = scene.lock().unwrap().collect_subtree(entity_id);
+    subtree
+        .into_iter()
+        .filter(|&descendant_id| {
+            Entity::from_handle(descendant_id, Arc::downgrade(scene))
+                .get_component::<MeshRenderer>()
+                .map_or(false, |mesh_renderer| mesh_renderer.mesh_idx.is_some())
+        })
+        .count()
+}
+
+fn read_positions(gltf: &gltf::Gltf, bin: &[u8]) -> Vec<[f32; 3]> {
+    let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+    primitive
+        .reader(|_| Some(bin))
+        .read_positions()
+        .unwrap()
+        .collect()
+}
+
+/// glTF document with an untextured box mesh and its material, the buffer is a separate
+/// .bin file. Returns the path of the document and the buffer.
+fn write_crate_model(project_dir: &std::path::Path) -> (PathBuf, Vec<u8>) {
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let normals: [f32; 9] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    let tex_coords: [f32; 6] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    let indices: [u32; 3] = [0, 1, 2];
+    let mut bin = Vec::new();
+    for value in positions.iter().chain(&normals).chain(&tex_coords) {
+        bin.extend_from_slice(&value.to_le_bytes());
+    }
+    for index in indices {
+        bin.extend_from_slice(&index.to_le_bytes());
+    }
+    std::fs::write(project_dir.join("crate.bin"), &bin).unwrap();
+
+    let document = serde_json::json!({
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "name": "Crate", "children": [1] },
+            { "name": "Box", "mesh": 0, "translation": [0.0, 1.0, 0.0] },
+        ],
+        "meshes": [{
+            "name": "BoxMesh",
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                "indices": 3,
+                "material": 0,
+            }],
+        }],
+        "materials": [{
+            "name": "Wood",
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [0.5, 0.25, 0.125, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 0.75,
+            },
+        }],
+        "buffers": [{ "uri": "crate.bin", "byteLength": bin.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 72, "byteLength": 24 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 12 },
+        ],
+        "accessors": [
+            {
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0],
+            },
+            { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+            { "bufferView": 3, "componentType": 5125, "count": 3, "type": "SCALAR" },
+        ],
+    });
+    let gltf_path = project_dir.join("crate.gltf");
+    std::fs::write(&gltf_path, serde_json::to_vec(&document).unwrap()).unwrap();
+    (gltf_path, bin)
+}
+
+#[test]
+fn exported_meshes_and_materials_are_imported_again() {
+    let project_dir = create_project();
+    let (gltf_path, bin) = write_crate_model(&project_dir);
+    let mut resource_manager = ResourceManager::default();
+    let guid = resource_manager.get_guid_for_path(&gltf_path).unwrap();
+
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Crate".into()), None, None).unwrap();
+    MeshRenderer::add_to_entity(
+        weak_scene.clone(),
+        model,
+        &resource_manager,
+        guid,
+        true,
+        None,
+    );
+
+    let glb_path = project_dir.join("crate.glb");
+    scene
+        .lock()
+        .unwrap()
+        .export_glb(model, glb_path.clone())
+        .unwrap();
+    let exported_guid = resource_manager.register_resource(glb_path.clone());
+    let imported =
+        Scene::create_entity(weak_scene.clone(), Some("Imported".into()), None, None).unwrap();
+    Scene::add_gltf_scene(weak_scene, imported, &resource_manager, exported_guid).unwrap();
+
+    assert_eq!(
+        describe_descendants(&scene, imported),
+        describe_descendants(&scene, model)
+    );
+    assert_eq!(count_meshes(&scene, imported), 1);
+    assert_eq!(count_meshes(&scene, model), 1);
+    let source = gltf::Gltf::from_slice(&std::fs::read(&gltf_path).unwrap()).unwrap();
+    let exported = gltf::Gltf::from_slice(&std::fs::read(&glb_path).unwrap()).unwrap();
+    assert_eq!(exported.meshes().count(), source.meshes().count());
+    assert_eq!(exported.materials().count(), source.materials().count());
+    assert_eq!(describe_primitives(&exported), describe_primitives(&source));
+    assert_eq!(
+        read_positions(&exported, exported.blob.as_deref().unwrap()),
+        read_positions(&source, &bin)
+    );
+    let material = exported.materials().next().unwrap();
+    assert_eq!(material.name(), Some("Wood"));
+    let pbr_properties = material.pbr_metallic_roughness();
+    assert_eq!(pbr_properties.base_color_factor(), [0.5, 0.25, 0.125, 1.0]);
+    assert_eq!(pbr_properties.roughness_factor(), 0.75);
+}
+
+#[test]
+fn export_file_names_stay_in_the_project_folder() {
+    assert_eq!(glb_file_name("Knight"), "Knight.glb");
+    assert_eq!(glb_file_name("Red Knight_2"), "Red Knight_2.glb");
+    assert_eq!(glb_file_name("../../etc/passwd"), "______etc_passwd.glb");
+    assert_eq!(glb_file_name("/tmp/knight"), "_tmp_knight.glb");
+    assert_eq!(glb_file_name("C:\\knight"), "C__knight.glb");
+    assert_eq!(glb_file_name(".."), "__.glb");
+    assert_eq!(glb_file_name("  "), "Entity.glb");
+}
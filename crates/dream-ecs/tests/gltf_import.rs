@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use dream_ecs::component::{Bone, Hierarchy, Light, LightType};
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+use dream_math::Vector3;
+use dream_resource::resource_manager::ResourceManager;
+
+// an armature with two joints, the skin has one inverse bind matrix when the accessor is
+// given, one zeroed 4x4 float matrix in a data uri buffer
+const SKINNED_MODEL: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "name": "Armature", "children": [1] },
+        { "name": "Hips", "children": [2] },
+        { "name": "Spine" }
+    ],
+    "skins": [{ "skeleton": 1, "joints": [1, 2] INVERSE_BIND_MATRICES }],
+    "buffers": [{
+        "byteLength": 64,
+        "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=="
+    }],
+    "bufferViews": [{ "buffer": 0, "byteLength": 64 }],
+    "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 1, "type": "MAT4" }]
+}"#;
+
+fn project_with_model(file_name: &str, contents: &[u8]) -> (ResourceManager, String) {
+    let project_dir =
+        std::env::temp_dir().join(format!("dream-gltf-import-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    let model_path = project_dir.join(file_name);
+    std::fs::write(&model_path, contents).unwrap();
+    let resource_manager = ResourceManager::default();
+    let guid = resource_manager.get_guid_for_path(&model_path).unwrap();
+    (resource_manager, guid)
+}
+
+#[test]
+fn unreadable_models_leave_the_scene_untouched() {
+    let (resource_manager, guid) = project_with_model("broken.glb", b"glTF but not really");
+
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Model".into()), None, None).unwrap();
+    assert!(Scene::add_gltf_scene(weak_scene.clone(), model, &resource_manager, guid).is_err());
+
+    let hierarchy: Hierarchy = Entity::from_handle(model, weak_scene)
+        .get_component()
+        .unwrap();
+    assert_eq!(hierarchy.num_children, 0);
+}
+
+#[test]
+fn skins_without_a_matrix_for_every_joint_leave_the_scene_untouched() {
+    let model = SKINNED_MODEL.replace("INVERSE_BIND_MATRICES", r#", "inverseBindMatrices": 0"#);
+    let (resource_manager, guid) = project_with_model("short_skin.gltf", model.as_bytes());
+
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Model".into()), None, None).unwrap();
+    let err = Scene::add_gltf_scene(weak_scene.clone(), model, &resource_manager, guid)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("no inverse bind matrix for joint 1"),
+        "{}",
+        err
+    );
+
+    assert!(Scene::get_children_for_entity(weak_scene, model).is_empty());
+}
+
+#[test]
+fn skins_without_inverse_bind_matrices_bind_with_the_identity() {
+    let model = SKINNED_MODEL.replace("INVERSE_BIND_MATRICES", "");
+    let (resource_manager, guid) = project_with_model("identity_skin.gltf", model.as_bytes());
+
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Model".into()), None, None).unwrap();
+    Scene::add_gltf_scene(weak_scene.clone(), model, &resource_manager, guid).unwrap();
+
+    let hips = Scene::get_children_for_entity(weak_scene.clone(), model)[0];
+    let spine = Scene::get_children_for_entity(weak_scene.clone(), hips)[0];
+    let bone: Bone = Entity::from_handle(spine, weak_scene)
+        .get_component()
+        .unwrap();
+    assert_eq!(bone.bone_id, 1);
+    assert_eq!(bone.armature_root_runtime_id, Some(hips));
+    assert_eq!(bone.inverse_bind_pose, dream_math::Matrix4::identity());
+}
+
+// a spot light on a node turned to face down and a sun, below a root node turned a quarter around
+// z, the root node becomes the entity the model is added to
+const LIGHTS_MODEL: &str = r#"{
+    "asset": { "version": "2.0" },
+    "extensionsUsed": ["KHR_lights_punctual"],
+    "extensions": {
+        "KHR_lights_punctual": {
+            "lights": [
+                {
+                    "type": "spot",
+                    "color": [1.0, 0.5, 0.0],
+                    "intensity": 108.70141,
+                    "spot": { "innerConeAngle": 0.2, "outerConeAngle": 0.6 }
+                },
+                { "type": "directional", "intensity": 3.0 }
+            ]
+        }
+    },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "name": "Rig", "rotation": [0.0, 0.0, 0.70710677, 0.70710677], "children": [1, 2] },
+        {
+            "name": "Spot",
+            "rotation": [-0.70710677, 0.0, 0.0, 0.70710677],
+            "extensions": { "KHR_lights_punctual": { "light": 0 } }
+        },
+        { "name": "Sun", "extensions": { "KHR_lights_punctual": { "light": 1 } } }
+    ]
+}"#;
+
+fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!(
+        (actual - expected).norm() < 1e-4,
+        "{actual:?} is not {expected:?}"
+    );
+}
+
+#[test]
+fn spot_lights_keep_their_cones_and_shine_along_their_node() {
+    let (resource_manager, guid) = project_with_model("lights.gltf", LIGHTS_MODEL.as_bytes());
+
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let model = Scene::create_entity(weak_scene.clone(), Some("Model".into()), None, None).unwrap();
+    Scene::add_gltf_scene(weak_scene.clone(), model, &resource_manager, guid).unwrap();
+
+    let light_of = |path: &str| -> Light {
+        let entity_id = scene.lock().unwrap().find_by_path(path).unwrap();
+        Entity::from_handle(entity_id, weak_scene.clone())
+            .get_component()
+            .unwrap()
+    };
+    let spot = light_of("Model/Spot");
+    assert_eq!(spot.light_type, LightType::SPOT);
+    assert_eq!(spot.inner_cone_angle, 0.2);
+    assert_eq!(spot.outer_cone_angle, 0.6);
+    // the candela of the file are the 2 kW of the lamp the model was exported from
+    assert_close(spot.color, Vector3::new(2.0, 1.0, 0.0));
+    // facing down in the rig, which turns it to +x
+    assert_close(spot.direction, Vector3::new(1.0, 0.0, 0.0));
+    assert!(!spot.cast_shadow);
+
+    let sun = light_of("Model/Sun");
+    assert_eq!(sun.light_type, LightType::DIRECTIONAL);
+    assert_close(sun.color, Vector3::new(3.0, 3.0, 3.0));
+    assert_close(sun.direction, Vector3::new(0.0, 0.0, -1.0));
+}
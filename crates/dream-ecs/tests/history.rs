@@ -0,0 +1,269 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::{Guid, Light, LightType, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::history::{
+    CreateEntity, DestroyEntity, ReparentEntity, SetComponent, MAX_UNDO_DEPTH,
+};
+use dream_ecs::scene::Scene;
+use dream_ecs::serialization::SceneData;
+use dream_math::{UnitQuaternion, Vector3};
+
+fn translation(position: Vector3<f32>) -> Transform {
+    Transform::new(
+        position,
+        UnitQuaternion::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    )
+}
+
+fn guid(scene: &Arc<Mutex<Scene>>, path: &str) -> Guid {
+    let scene = scene.lock().unwrap();
+    let entity_id = scene
+        .find_by_path(path)
+        .unwrap_or_else(|| panic!("No entity at {}", path));
+    scene.get_entity_guid(entity_id).unwrap()
+}
+
+fn scene_data(scene: &Arc<Mutex<Scene>>) -> SceneData {
+    scene.lock().unwrap().to_scene_data()
+}
+
+fn undo(scene: Weak<Mutex<Scene>>) {
+    assert!(Scene::undo(scene).unwrap());
+}
+
+fn redo(scene: Weak<Mutex<Scene>>) {
+    assert!(Scene::redo(scene).unwrap());
+}
+
+/// Table with two legs and a lamp on it, next to a shelf
+fn create_scene() -> Arc<Mutex<Scene>> {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let table = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Table".into()),
+        None,
+        Some(translation(Vector3::new(1.0, 0.0, 0.0))),
+    )
+    .unwrap();
+    for name in ["Leg.L", "Leg.R"] {
+        Scene::create_entity(weak_scene.clone(), Some(name.into()), Some(table), None).unwrap();
+    }
+    let lamp = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Lamp".into()),
+        Some(table),
+        Some(translation(Vector3::new(0.0, 1.0, 0.0))),
+    )
+    .unwrap();
+    Entity::from_handle(lamp, weak_scene.clone()).add_component(Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    ));
+    Scene::create_entity(
+        weak_scene,
+        Some("Shelf".into()),
+        None,
+        Some(translation(Vector3::new(-2.0, 0.0, 0.0))),
+    )
+    .unwrap();
+    scene
+}
+
+#[test]
+fn set_component_is_undone_and_redone() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let before = scene_data(&scene);
+    Scene::execute(
+        weak_scene.clone(),
+        SetComponent::new(
+            guid(&scene, "Table"),
+            translation(Vector3::new(5.0, 0.0, 0.0)),
+        ),
+    )
+    .unwrap();
+    Scene::execute(
+        weak_scene.clone(),
+        SetComponent::<Light>::remove(guid(&scene, "Table/Lamp")),
+    )
+    .unwrap();
+    let after = scene_data(&scene);
+
+    undo(weak_scene.clone());
+    undo(weak_scene.clone());
+    assert_eq!(scene_data(&scene), before);
+    redo(weak_scene.clone());
+    redo(weak_scene.clone());
+    assert_eq!(scene_data(&scene), after);
+    assert!(!Scene::redo(weak_scene).unwrap());
+}
+
+#[test]
+fn created_entity_is_undone_and_redone_with_its_guid() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let before = scene_data(&scene);
+    let create_entity = CreateEntity::new(
+        Some("Vase".into()),
+        Some(guid(&scene, "Shelf")),
+        Some(translation(Vector3::new(0.0, 2.0, 0.0))),
+    );
+    let vase_guid = create_entity.guid.clone();
+    Scene::execute(weak_scene.clone(), create_entity).unwrap();
+    assert_eq!(guid(&scene, "Shelf/Vase"), vase_guid);
+    let after = scene_data(&scene);
+
+    undo(weak_scene.clone());
+    assert_eq!(scene_data(&scene), before);
+    assert!(scene
+        .lock()
+        .unwrap()
+        .get_entity_by_guid(&vase_guid)
+        .is_none());
+    redo(weak_scene);
+    assert_eq!(scene_data(&scene), after);
+    assert_eq!(guid(&scene, "Shelf/Vase"), vase_guid);
+}
+
+#[test]
+fn destroyed_entity_comes_back_with_its_descendants() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let before = scene_data(&scene);
+    Scene::execute(
+        weak_scene.clone(),
+        DestroyEntity::new(guid(&scene, "Table")),
+    )
+    .unwrap();
+    let after = scene_data(&scene);
+    assert!(scene.lock().unwrap().find_by_name("Leg.L").is_none());
+
+    // the descendants come back in the same order, with their guids and components
+    undo(weak_scene.clone());
+    assert_eq!(scene_data(&scene), before);
+    let lamp_id = scene.lock().unwrap().find_by_path("Table/Lamp").unwrap();
+    let lamp: Light = Entity::from_handle(lamp_id, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(lamp.radius, 1.0);
+    redo(weak_scene);
+    assert_eq!(scene_data(&scene), after);
+}
+
+#[test]
+fn reparented_entity_gets_its_child_index_and_transform_back() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let before = scene_data(&scene);
+    // the middle child moves, so restoring its index is not the same as appending it
+    let leg_guid = guid(&scene, "Table/Leg.R");
+    Scene::execute(
+        weak_scene.clone(),
+        ReparentEntity::new(leg_guid.clone(), guid(&scene, "Shelf"), true),
+    )
+    .unwrap();
+    let after = scene_data(&scene);
+    let leg_id = scene.lock().unwrap().get_entity_by_guid(&leg_guid).unwrap();
+    let moved: Transform = Entity::from_handle(leg_id, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    // the world position is kept, so the local position changed
+    assert_eq!(moved.position, Vector3::new(3.0, 0.0, 0.0));
+
+    undo(weak_scene.clone());
+    assert_eq!(scene_data(&scene), before);
+    assert_eq!(Scene::get_child_index(weak_scene.clone(), leg_id), Some(1));
+    let restored: Transform = Entity::from_handle(leg_id, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(restored.position, Vector3::zeros());
+    redo(weak_scene);
+    assert_eq!(scene_data(&scene), after);
+}
+
+#[test]
+fn grouped_commands_are_undone_and_redone_as_one() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let before = scene_data(&scene);
+    scene.lock().unwrap().history.begin_group("Drag");
+    for x in 1..=3 {
+        Scene::execute(
+            weak_scene.clone(),
+            SetComponent::new(
+                guid(&scene, "Shelf"),
+                translation(Vector3::new(x as f32, 0.0, 0.0)),
+            ),
+        )
+        .unwrap();
+    }
+    // nested groups are part of the outer group
+    scene.lock().unwrap().history.begin_group("Nested");
+    Scene::execute(
+        weak_scene.clone(),
+        DestroyEntity::new(guid(&scene, "Table")),
+    )
+    .unwrap();
+    scene.lock().unwrap().history.end_group();
+    scene.lock().unwrap().history.end_group();
+    let after = scene_data(&scene);
+    assert_eq!(
+        scene.lock().unwrap().history.get_undo_name().as_deref(),
+        Some("Drag")
+    );
+
+    undo(weak_scene.clone());
+    assert_eq!(scene_data(&scene), before);
+    assert!(!scene.lock().unwrap().history.can_undo());
+    redo(weak_scene);
+    assert_eq!(scene_data(&scene), after);
+    assert!(!scene.lock().unwrap().history.can_redo());
+}
+
+#[test]
+fn oldest_commands_are_dropped_beyond_the_undo_depth() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let shelf_guid = guid(&scene, "Shelf");
+    let set_x = |x: usize| {
+        Scene::execute(
+            weak_scene.clone(),
+            SetComponent::new(
+                shelf_guid.clone(),
+                translation(Vector3::new(x as f32, 0.0, 0.0)),
+            ),
+        )
+        .unwrap();
+    };
+    for x in 1..=MAX_UNDO_DEPTH + 2 {
+        set_x(x);
+    }
+
+    let mut undone = 0;
+    while Scene::undo(weak_scene.clone()).unwrap() {
+        undone += 1;
+    }
+    assert_eq!(undone, MAX_UNDO_DEPTH);
+    // the first two commands can no longer be undone
+    let shelf_id = scene
+        .lock()
+        .unwrap()
+        .get_entity_by_guid(&shelf_guid)
+        .unwrap();
+    let shelf: Transform = Entity::from_handle(shelf_id, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(shelf.position, Vector3::new(2.0, 0.0, 0.0));
+
+    let mut redone = 0;
+    while Scene::redo(weak_scene.clone()).unwrap() {
+        redone += 1;
+    }
+    assert_eq!(redone, MAX_UNDO_DEPTH);
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::Transform;
+use dream_ecs::scene::Scene;
+use dream_math::{UnitQuaternion, Vector3};
+
+fn create_entity(
+    scene: Weak<Mutex<Scene>>,
+    name: &str,
+    parent: Option<u64>,
+    transform: Transform,
+) -> u64 {
+    Scene::create_entity(scene, Some(name.into()), parent, Some(transform)).unwrap()
+}
+
+fn translation(x: f32, y: f32, z: f32) -> Transform {
+    Transform::new(
+        Vector3::new(x, y, z),
+        UnitQuaternion::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    )
+}
+
+#[test]
+fn moving_under_a_descendant_is_rejected() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let parent = create_entity(weak_scene.clone(), "Parent", None, Transform::default());
+    let child = create_entity(
+        weak_scene.clone(),
+        "Child",
+        Some(parent),
+        Transform::default(),
+    );
+    let grandchild = create_entity(
+        weak_scene.clone(),
+        "Grandchild",
+        Some(child),
+        Transform::default(),
+    );
+
+    assert!(Scene::move_entity(weak_scene.clone(), parent, grandchild, true).is_err());
+    assert!(Scene::move_entity(weak_scene.clone(), child, child, false).is_err());
+
+    assert_eq!(
+        Scene::get_children_for_entity(weak_scene.clone(), parent),
+        vec![child]
+    );
+    assert_eq!(
+        Scene::get_children_for_entity(weak_scene, child),
+        vec![grandchild]
+    );
+}
+
+#[test]
+fn moved_entities_are_appended_to_the_children_of_their_new_parent() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let first = create_entity(weak_scene.clone(), "First", None, Transform::default());
+    let second = create_entity(weak_scene.clone(), "Second", None, Transform::default());
+    let a = create_entity(weak_scene.clone(), "A", Some(first), Transform::default());
+    let b = create_entity(weak_scene.clone(), "B", Some(first), Transform::default());
+    let c = create_entity(weak_scene.clone(), "C", Some(first), Transform::default());
+    let d = create_entity(weak_scene.clone(), "D", Some(second), Transform::default());
+
+    Scene::move_entity(weak_scene.clone(), b, second, false).unwrap();
+
+    // the old siblings close the gap, the moved entity goes last
+    assert_eq!(
+        Scene::get_children_for_entity(weak_scene.clone(), first),
+        vec![a, c]
+    );
+    assert_eq!(
+        Scene::get_children_for_entity(weak_scene.clone(), second),
+        vec![d, b]
+    );
+    assert_eq!(Scene::get_child_index(weak_scene, b), Some(1));
+}
+
+#[test]
+fn moved_entities_keep_their_world_transform_when_asked() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let first = create_entity(
+        weak_scene.clone(),
+        "First",
+        None,
+        translation(1.0, 0.0, 0.0),
+    );
+    let second = create_entity(
+        weak_scene.clone(),
+        "Second",
+        None,
+        Transform::new(
+            Vector3::new(0.0, 5.0, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, std::f32::consts::FRAC_PI_2, 0.0),
+            Vector3::new(2.0, 2.0, 2.0),
+        ),
+    );
+    let kept = create_entity(
+        weak_scene.clone(),
+        "Kept",
+        Some(first),
+        translation(0.0, 0.0, 3.0),
+    );
+    let local = create_entity(
+        weak_scene.clone(),
+        "Local",
+        Some(first),
+        translation(0.0, 0.0, 3.0),
+    );
+    let world_position = |entity_id| {
+        Scene::get_world_matrix(weak_scene.clone(), entity_id)
+            .column(3)
+            .xyz()
+    };
+    let kept_position = world_position(kept);
+
+    Scene::move_entity(weak_scene.clone(), kept, second, true).unwrap();
+    Scene::move_entity(weak_scene.clone(), local, second, false).unwrap();
+
+    assert!((world_position(kept) - kept_position).norm() < 1e-4);
+    // without keeping it, the local transform is kept and the entity moves with its new parent
+    let expected = Scene::get_world_matrix(weak_scene.clone(), second)
+        .transform_point(&dream_math::Point3::new(0.0, 0.0, 3.0))
+        .coords;
+    assert!((world_position(local) - expected).norm() < 1e-4);
+}
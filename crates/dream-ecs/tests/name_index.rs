@@ -0,0 +1,91 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::Tag;
+use dream_ecs::entity::Entity;
+use dream_ecs::name_index::NameIndex;
+use dream_ecs::scene::Scene;
+
+fn create(scene: Weak<Mutex<Scene>>, name: &str, parent: Option<u64>) -> u64 {
+    Scene::create_entity(scene, Some(name.into()), parent, None).unwrap()
+}
+
+fn sorted(mut ids: Vec<u64>) -> Vec<u64> {
+    ids.sort_unstable();
+    ids
+}
+
+#[test]
+fn glob_patterns_match_names() {
+    let mut index = NameIndex::default();
+    for (id, name) in ["Cube", "Cube.001", "Cone", "Sphere", ""]
+        .iter()
+        .enumerate()
+    {
+        index.insert(id as u64, String::from(*name));
+    }
+    assert_eq!(index.get_matching("Cube"), vec![0]);
+    assert_eq!(index.get_matching("Cube*"), vec![0, 1]);
+    assert_eq!(index.get_matching("C*e"), vec![0, 2]);
+    assert_eq!(index.get_matching("Co?e"), vec![2]);
+    assert_eq!(index.get_matching("?"), Vec::<u64>::new());
+    assert_eq!(index.get_matching("*"), vec![0, 1, 2, 3, 4]);
+    assert_eq!(index.get_matching("*.00?"), vec![1]);
+    // a * can swallow characters that the rest of the pattern also matches
+    assert_eq!(index.get_matching("*e*e"), vec![3]);
+}
+
+#[test]
+fn path_patterns_match_hierarchies() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let knight = create(weak_scene.clone(), "Knight", None);
+    let armature = create(weak_scene.clone(), "Armature", Some(knight));
+    let hips = create(weak_scene.clone(), "Hips", Some(armature));
+    let left_hand = create(weak_scene.clone(), "Hand.L", Some(hips));
+    let right_hand = create(weak_scene.clone(), "Hand.R", Some(armature));
+    let other_hips = create(weak_scene, "Hips", None);
+
+    let scene = scene.lock().unwrap();
+    assert_eq!(scene.find_by_path("Knight/Armature/Hips"), Some(hips));
+    // separators at the start and the end are ignored
+    assert_eq!(scene.find_by_path("/Knight/Armature/Hips/"), Some(hips));
+    assert_eq!(scene.find_by_path("Hips"), Some(other_hips));
+    assert_eq!(scene.find_by_path("Armature/Hips"), None);
+    assert_eq!(scene.find_by_path(""), None);
+    assert_eq!(scene.find_by_path("/"), None);
+    assert_eq!(
+        sorted(scene.find_all_by_path("Knight/**/Hand.?")),
+        sorted(vec![left_hand, right_hand])
+    );
+    // ** also matches no segment at all
+    assert_eq!(
+        sorted(scene.find_all_by_path("**/Hips")),
+        sorted(vec![hips, other_hips])
+    );
+    assert_eq!(scene.find_all_by_path("Knight/*/Hand*"), vec![right_hand]);
+    assert_eq!(
+        sorted(scene.find_all_by_path("Knight/**")),
+        sorted(vec![knight, armature, hips, left_hand, right_hand])
+    );
+}
+
+#[test]
+fn renamed_entities_are_found_by_their_new_name() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let parent = create(weak_scene.clone(), "Parent", None);
+    let child = create(weak_scene.clone(), "Child", Some(parent));
+
+    Entity::from_handle(parent, weak_scene.clone()).add_component(Tag::new("Renamed".into()));
+    let scene_mutex_lock = scene.lock().unwrap();
+    assert_eq!(scene_mutex_lock.find_by_name("Parent"), None);
+    assert_eq!(scene_mutex_lock.find_by_name("Renamed"), Some(parent));
+    assert_eq!(scene_mutex_lock.find_by_path("Renamed/Child"), Some(child));
+    assert_eq!(scene_mutex_lock.find_by_path("Parent/Child"), None);
+    drop(scene_mutex_lock);
+
+    Entity::from_handle(child, weak_scene.clone()).remove_component::<Tag>();
+    assert_eq!(scene.lock().unwrap().find_by_name("Child"), None);
+    Scene::destroy_entity(weak_scene, parent).unwrap();
+    assert_eq!(scene.lock().unwrap().find_by_name("Renamed"), None);
+}
@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+
+use dream_ecs::component::{Tag, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::scene::Scene;
+use dream_ecs::serialization::SceneData;
+use dream_math::{UnitQuaternion, Vector3};
+use dream_resource::resource_manager::ResourceManager;
+
+/// Project directory that is used as the file system root, the tests of this file share it
+fn create_project() -> PathBuf {
+    let project_dir = std::env::temp_dir().join(format!("dream-prefab-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    project_dir
+}
+
+fn translation(position: Vector3<f32>) -> Transform {
+    Transform::new(
+        position,
+        UnitQuaternion::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    )
+}
+
+fn transform(scene: Weak<Mutex<Scene>>, entity_id: u64) -> Transform {
+    Entity::from_handle(entity_id, scene)
+        .get_component()
+        .expect("No transform component")
+}
+
+fn find(scene: &Arc<Mutex<Scene>>, path: &str) -> u64 {
+    scene
+        .lock()
+        .unwrap()
+        .find_by_path(path)
+        .unwrap_or_else(|| panic!("No entity at {}", path))
+}
+
+/// Scene with a crate that has a lid, the crate is turned into a prefab, returns the guid of the
+/// prefab and the runtime id of the crate
+fn create_crate_prefab(
+    scene: &Arc<Mutex<Scene>>,
+    prefab_path: PathBuf,
+    resource_manager: &mut ResourceManager,
+) -> (String, u64) {
+    let weak_scene = Arc::downgrade(scene);
+    let crate_id = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Crate".into()),
+        None,
+        Some(translation(Vector3::zeros())),
+    )
+    .unwrap();
+    Scene::create_entity(
+        weak_scene,
+        Some("Lid".into()),
+        Some(crate_id),
+        Some(translation(Vector3::new(0.0, 1.0, 0.0))),
+    )
+    .unwrap();
+    let guid = scene
+        .lock()
+        .unwrap()
+        .create_prefab(crate_id, prefab_path, resource_manager)
+        .unwrap();
+    (guid, crate_id)
+}
+
+#[test]
+fn overrides_survive_prefab_edits_and_reloads() {
+    let project_dir = create_project();
+    let mut resource_manager = ResourceManager::default();
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let prefab_path = project_dir.join("edited.prefab");
+    let (guid, _) = create_crate_prefab(&scene, prefab_path.clone(), &mut resource_manager);
+
+    // a renamed instance with a raised lid
+    let copy_id =
+        Scene::instantiate_prefab(weak_scene.clone(), guid.clone(), None, &resource_manager)
+            .unwrap();
+    Entity::from_handle(copy_id, weak_scene.clone()).add_component(Tag::new("Copy".into()));
+    let copy_lid = find(&scene, "Copy/Lid");
+    Entity::from_handle(copy_lid, weak_scene.clone())
+        .add_component(translation(Vector3::new(0.0, 2.0, 0.0)));
+
+    // scale the lid in the prefab file
+    let mut prefab_data: SceneData =
+        serde_yaml::from_slice(&std::fs::read(&prefab_path).unwrap()).unwrap();
+    for entity_data in &mut prefab_data.entities {
+        if entity_data.tag.as_deref() == Some("Lid") {
+            entity_data.transform.as_mut().unwrap().scale = [3.0, 3.0, 3.0];
+        }
+    }
+    std::fs::write(&prefab_path, serde_yaml::to_string(&prefab_data).unwrap()).unwrap();
+    scene
+        .lock()
+        .unwrap()
+        .update_prefab_instances(&guid, &resource_manager)
+        .unwrap();
+
+    // the instance keeps its overrides and picks up the properties it did not override
+    let lid = transform(weak_scene.clone(), find(&scene, "Crate/Lid"));
+    assert_eq!(lid.position, Vector3::new(0.0, 1.0, 0.0));
+    assert_eq!(lid.scale, Vector3::new(3.0, 3.0, 3.0));
+    let copy_lid = transform(weak_scene, find(&scene, "Copy/Lid"));
+    assert_eq!(copy_lid.position, Vector3::new(0.0, 2.0, 0.0));
+    assert_eq!(copy_lid.scale, Vector3::new(3.0, 3.0, 3.0));
+
+    let scene_path = project_dir.join("edited.scene");
+    scene.lock().unwrap().save(scene_path.clone()).unwrap();
+    let loaded = Scene::load(scene_path, &resource_manager).unwrap();
+    assert_eq!(loaded.lock().unwrap().get_prefab_instances(&guid).len(), 2);
+    let copy_lid = transform(Arc::downgrade(&loaded), find(&loaded, "Copy/Lid"));
+    assert_eq!(copy_lid.position, Vector3::new(0.0, 2.0, 0.0));
+    assert_eq!(copy_lid.scale, Vector3::new(3.0, 3.0, 3.0));
+}
+
+#[test]
+fn saving_a_prefab_again_keeps_instances_linked() {
+    let project_dir = create_project();
+    let mut resource_manager = ResourceManager::default();
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let prefab_path = project_dir.join("resaved.prefab");
+    let (guid, crate_id) = create_crate_prefab(&scene, prefab_path.clone(), &mut resource_manager);
+    let copy_id =
+        Scene::instantiate_prefab(weak_scene, guid.clone(), None, &resource_manager).unwrap();
+
+    let resaved_guid = scene
+        .lock()
+        .unwrap()
+        .create_prefab(crate_id, prefab_path, &mut resource_manager)
+        .unwrap();
+
+    assert_eq!(resaved_guid, guid);
+    let mut instances = scene.lock().unwrap().get_prefab_instances(&guid);
+    instances.sort();
+    let mut expected = vec![crate_id, copy_id];
+    expected.sort();
+    assert_eq!(instances, expected);
+}
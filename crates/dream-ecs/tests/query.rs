@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use dream_ecs::component::{GlobalTransform, Light, LightType, Tag, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::query::Without;
+use dream_ecs::scene::Scene;
+use dream_math::{UnitQuaternion, Vector3};
+
+#[test]
+fn writes_through_queries_reach_the_scene() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let lamp = Scene::create_entity(weak_scene.clone(), Some("Lamp".into()), None, None).unwrap();
+    Entity::from_handle(lamp, weak_scene.clone()).add_component(Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    ));
+    let crate_id =
+        Scene::create_entity(weak_scene.clone(), Some("Crate".into()), None, None).unwrap();
+    scene.lock().unwrap().propagate_transforms();
+    // the events of creating the entities are not of interest
+    scene.lock().unwrap().collect_component_events();
+
+    let mut scene_mutex_lock = scene.lock().unwrap();
+    scene_mutex_lock
+        .query::<(&Tag, &mut Light)>()
+        .for_each(|_, (_, mut light)| light.radius = 2.0);
+    *scene_mutex_lock
+        .query_filtered::<&mut Transform, Without<Light>>()
+        .get(crate_id)
+        .unwrap() = Transform::new(
+        Vector3::new(0.0, 3.0, 0.0),
+        UnitQuaternion::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    );
+    assert!(scene_mutex_lock
+        .query_filtered::<&mut Transform, Without<Light>>()
+        .get(lamp)
+        .is_none());
+    scene_mutex_lock.on_component_written::<Transform>(crate_id);
+    scene_mutex_lock.propagate_transforms();
+    scene_mutex_lock.collect_component_events();
+    // writes through &mut are recorded as changes
+    assert_eq!(scene_mutex_lock.changed::<Light>(), [lamp]);
+    drop(scene_mutex_lock);
+
+    let light: Light = Entity::from_handle(lamp, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(light.radius, 2.0);
+    let global_transform: GlobalTransform = Entity::from_handle(crate_id, weak_scene)
+        .get_component()
+        .unwrap();
+    assert_eq!(global_transform.get_position(), Vector3::new(0.0, 3.0, 0.0));
+}
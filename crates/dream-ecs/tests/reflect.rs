@@ -0,0 +1,286 @@
+use std::sync::{Arc, Mutex};
+
+use dream_ecs::component::{Camera, CameraProjection, EntityRef, Tag};
+use dream_ecs::entity::Entity;
+use dream_ecs::history::{DestroyEntity, SetComponentField, SetRegisteredComponent};
+use dream_ecs::reflect::{
+    component_registry, register_component, ComponentRegistration, FieldValue,
+};
+use dream_ecs::scene::Scene;
+use dream_resource::resource_manager::ResourceManager;
+
+/// Component defined outside of dream-ecs, like one from a user crate
+#[derive(shipyard::Component, Debug, Clone, Default, PartialEq)]
+struct Health {
+    hp: f32,
+}
+
+fn register_health() {
+    register_component(ComponentRegistration::<Health>::new("Health").field(
+        "hp",
+        |health| health.hp,
+        |health, hp| health.hp = hp,
+    ));
+}
+
+/// Component that refers to other entities, like a camera follow target
+#[derive(shipyard::Component, Debug, Clone, Default, PartialEq)]
+struct Follow {
+    target: EntityRef,
+}
+
+fn register_follow() {
+    register_component(ComponentRegistration::<Follow>::new("Follow").field(
+        "target",
+        |follow| follow.target.clone(),
+        |follow, target| follow.target = target,
+    ));
+}
+
+/// Runtime id of the entity followed by an entity
+fn follow_target(scene: &Arc<Mutex<Scene>>, path: &str) -> Option<u64> {
+    let entity_id = scene.lock().unwrap().find_by_path(path)?;
+    let follow: Follow = Entity::from_handle(entity_id, Arc::downgrade(scene)).get_component()?;
+    follow.target.resolve(&scene.lock().unwrap())
+}
+
+fn find(scene: &Arc<Mutex<Scene>>, path: &str) -> Option<u64> {
+    scene.lock().unwrap().find_by_path(path)
+}
+
+fn get_hp(scene: &Arc<Mutex<Scene>>, entity_id: u64) -> Option<FieldValue> {
+    let scene = scene.lock().unwrap();
+    component_registry()
+        .get("Health")
+        .unwrap()
+        .get_field(&scene, entity_id, "hp")
+}
+
+#[test]
+fn registered_component_edits_can_be_undone() {
+    register_health();
+    let scene = Scene::create();
+    let entity_id =
+        Scene::create_entity(Arc::downgrade(&scene), Some("Player".into()), None, None).unwrap();
+    let guid = scene.lock().unwrap().get_entity_guid(entity_id).unwrap();
+
+    Scene::execute(
+        Arc::downgrade(&scene),
+        SetRegisteredComponent::add_default(guid.clone(), "Health"),
+    )
+    .unwrap();
+    assert_eq!(get_hp(&scene, entity_id), Some(FieldValue::F32(0.0)));
+
+    Scene::execute(
+        Arc::downgrade(&scene),
+        SetComponentField::new(guid, "Health", "hp", FieldValue::F32(5.0)),
+    )
+    .unwrap();
+    assert_eq!(get_hp(&scene, entity_id), Some(FieldValue::F32(5.0)));
+
+    Scene::undo(Arc::downgrade(&scene)).unwrap();
+    assert_eq!(get_hp(&scene, entity_id), Some(FieldValue::F32(0.0)));
+    Scene::undo(Arc::downgrade(&scene)).unwrap();
+    assert_eq!(get_hp(&scene, entity_id), None);
+}
+
+#[test]
+fn registered_component_is_saved_by_name() {
+    register_health();
+    let scene = Scene::create();
+    let entity_id =
+        Scene::create_entity(Arc::downgrade(&scene), Some("Player".into()), None, None).unwrap();
+    let mut scene_mutex_lock = scene.lock().unwrap();
+    {
+        // serializing reads the registry again, so the guard is dropped first
+        let registry = component_registry();
+        let health = registry.get("Health").unwrap();
+        health
+            .add_default(&mut scene_mutex_lock, entity_id)
+            .unwrap();
+        health
+            .set_field(&mut scene_mutex_lock, entity_id, "hp", FieldValue::F32(3.0))
+            .unwrap();
+    }
+
+    let scene_data = scene_mutex_lock.subtree_to_scene_data(entity_id);
+    let yaml = serde_yaml::to_string(&scene_data.entities[0]).unwrap();
+    assert!(yaml.contains("Health:"));
+    assert!(yaml.contains("hp: 3.0"));
+}
+
+#[test]
+fn imported_cameras_are_saved_with_their_projection() {
+    let scene = Scene::create();
+    let entity_id =
+        Scene::create_entity(Arc::downgrade(&scene), Some("Camera".into()), None, None).unwrap();
+    Entity::from_handle(entity_id, Arc::downgrade(&scene)).add_component(Camera {
+        projection: CameraProjection::ORTHOGRAPHIC,
+        ymag: 5.0,
+        ..Default::default()
+    });
+
+    let scene_mutex_lock = scene.lock().unwrap();
+    let scene_data = scene_mutex_lock.subtree_to_scene_data(entity_id);
+    let yaml = serde_yaml::to_string(&scene_data.entities[0]).unwrap();
+    assert!(yaml.contains("Camera:"));
+    assert!(yaml.contains("projection: ORTHOGRAPHIC"));
+    assert!(yaml.contains("ymag: 5.0"));
+}
+
+#[test]
+fn entity_refs_survive_save_load_and_duplication() {
+    register_follow();
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let player =
+        Scene::create_entity(weak_scene.clone(), Some("Player".into()), None, None).unwrap();
+    let camera =
+        Scene::create_entity(weak_scene.clone(), Some("Camera".into()), None, None).unwrap();
+    let arm =
+        Scene::create_entity(weak_scene.clone(), Some("Arm".into()), Some(player), None).unwrap();
+    let hand =
+        Scene::create_entity(weak_scene.clone(), Some("Hand".into()), Some(arm), None).unwrap();
+    let player_ref = EntityRef::from_entity(&scene.lock().unwrap(), player);
+    let hand_ref = EntityRef::from_entity(&scene.lock().unwrap(), hand);
+    Entity::from_handle(camera, weak_scene.clone()).add_component(Follow { target: player_ref });
+    Entity::from_handle(arm, weak_scene.clone()).add_component(Follow { target: hand_ref });
+
+    // a copy follows its own hand, references outside of the copy are kept
+    let player_copy = Scene::duplicate_entity(weak_scene.clone(), player, None).unwrap();
+    Entity::from_handle(player_copy, weak_scene.clone()).add_component(Tag::new("Copy".into()));
+    let camera_copy = Scene::duplicate_entity(weak_scene.clone(), camera, None).unwrap();
+    Entity::from_handle(camera_copy, weak_scene).add_component(Tag::new("Camera.001".into()));
+    assert_eq!(follow_target(&scene, "Player/Arm"), Some(hand));
+    assert_eq!(
+        follow_target(&scene, "Copy/Arm"),
+        find(&scene, "Copy/Arm/Hand")
+    );
+    assert_eq!(follow_target(&scene, "Camera.001"), Some(player));
+
+    // runtime ids change on load, the references resolve to the loaded entities
+    let project_dir = std::env::temp_dir().join(format!("dream-reflect-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    let yaml = scene.lock().unwrap().to_yaml().unwrap();
+    let loaded = Scene::create();
+    loaded
+        .lock()
+        .unwrap()
+        .load_yaml(&yaml, &ResourceManager::default())
+        .unwrap();
+    assert!(follow_target(&loaded, "Camera").is_some());
+    assert_eq!(follow_target(&loaded, "Camera"), find(&loaded, "Player"));
+    assert_eq!(
+        follow_target(&loaded, "Player/Arm"),
+        find(&loaded, "Player/Arm/Hand")
+    );
+    assert_eq!(
+        follow_target(&loaded, "Copy/Arm"),
+        find(&loaded, "Copy/Arm/Hand")
+    );
+}
+
+#[test]
+fn managed_components_are_shown_but_not_added_removed_or_copied() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let player =
+        Scene::create_entity(weak_scene.clone(), Some("Player".into()), None, None).unwrap();
+    Scene::create_entity(weak_scene.clone(), Some("Arm".into()), Some(player), None).unwrap();
+    let guid = scene.lock().unwrap().get_entity_guid(player).unwrap();
+    {
+        let scene_mutex_lock = scene.lock().unwrap();
+        let registry = component_registry();
+        let guid_component = registry.get("Guid").unwrap();
+        assert!(guid_component.is_managed());
+        assert_eq!(
+            guid_component.get_field(&scene_mutex_lock, player, "guid"),
+            Some(FieldValue::String(guid.0.clone()))
+        );
+        assert_eq!(
+            registry
+                .get("Hierarchy")
+                .unwrap()
+                .get_field(&scene_mutex_lock, player, "num_children"),
+            Some(FieldValue::U32(1))
+        );
+        assert!(registry
+            .iter_unmanaged()
+            .all(|component| !component.is_managed()));
+    }
+
+    assert!(Scene::execute(
+        weak_scene.clone(),
+        SetRegisteredComponent::remove(guid.clone(), "Hierarchy"),
+    )
+    .is_err());
+    assert!(Scene::execute(
+        weak_scene.clone(),
+        SetComponentField::new(guid.clone(), "Guid", "guid", FieldValue::String("x".into())),
+    )
+    .is_err());
+    assert!(find(&scene, "Player/Arm").is_some());
+    assert_eq!(
+        scene.lock().unwrap().get_entity_guid(player),
+        Some(guid.clone())
+    );
+
+    // the copy keeps its own guid and links
+    let player_copy = Scene::duplicate_entity(weak_scene, player, None).unwrap();
+    let copy_guid = scene.lock().unwrap().get_entity_guid(player_copy).unwrap();
+    assert_ne!(copy_guid, guid);
+    assert_eq!(
+        scene.lock().unwrap().get_entity_by_guid(&copy_guid),
+        Some(player_copy)
+    );
+}
+
+#[test]
+fn entity_refs_resolve_after_reload_undo_and_snapshot_restore() {
+    register_follow();
+    let project_dir = std::env::temp_dir().join(format!("dream-reflect-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    let resource_manager = ResourceManager::default();
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let player =
+        Scene::create_entity(weak_scene.clone(), Some("Player".into()), None, None).unwrap();
+    let camera =
+        Scene::create_entity(weak_scene.clone(), Some("Camera".into()), None, None).unwrap();
+    let player_ref = EntityRef::from_entity(&scene.lock().unwrap(), player);
+    let player_guid = player_ref.guid.clone().unwrap();
+    Entity::from_handle(camera, weak_scene.clone()).add_component(Follow { target: player_ref });
+
+    // reading the file again gives every entity a new runtime id
+    let scene_path = project_dir.join("follow.scene");
+    scene.lock().unwrap().save(scene_path.clone()).unwrap();
+    let reloaded = Scene::load(scene_path, &resource_manager).unwrap();
+    assert!(follow_target(&reloaded, "Camera").is_some());
+    assert_eq!(
+        follow_target(&reloaded, "Camera"),
+        find(&reloaded, "Player")
+    );
+
+    // the target is gone while it is destroyed, undo brings it back under a new runtime id
+    Scene::execute(weak_scene.clone(), DestroyEntity::new(player_guid.clone())).unwrap();
+    assert_eq!(follow_target(&scene, "Camera"), None);
+    Scene::undo(weak_scene.clone()).unwrap();
+    let restored_player = find(&scene, "Player").unwrap();
+    assert_eq!(follow_target(&scene, "Camera"), Some(restored_player));
+
+    // the same for entities destroyed while playing
+    let snapshot = scene.lock().unwrap().take_snapshot();
+    Scene::execute(weak_scene, DestroyEntity::new(player_guid)).unwrap();
+    assert_eq!(follow_target(&scene, "Camera"), None);
+    let runtime_ids = scene
+        .lock()
+        .unwrap()
+        .restore_snapshot(snapshot, &resource_manager)
+        .unwrap();
+    assert_eq!(
+        follow_target(&scene, "Camera"),
+        Some(runtime_ids[&restored_player])
+    );
+}
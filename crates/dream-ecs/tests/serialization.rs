@@ -0,0 +1,174 @@
+use std::sync::{Arc, Mutex};
+
+use dream_ecs::component::{Bone, Light, LightType, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::history::SetComponent;
+use dream_ecs::scene::Scene;
+use dream_ecs::serialization::SCENE_FILE_VERSION;
+use dream_math::{UnitQuaternion, Vector3};
+use dream_resource::resource_manager::ResourceManager;
+
+/// Resource manager of an empty project, scenes without models or prefabs need no resources
+fn empty_resource_manager() -> ResourceManager {
+    let project_dir =
+        std::env::temp_dir().join(format!("dream-serialization-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    ResourceManager::default()
+}
+
+/// Root with a lit child, which has a child of its own
+fn create_scene() -> Arc<Mutex<Scene>> {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let root = Scene::create_entity(weak_scene.clone(), Some("Root".into()), None, None).unwrap();
+    let lamp = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Lamp".into()),
+        Some(root),
+        Some(Transform::new(
+            Vector3::new(1.0, 2.0, 3.0),
+            UnitQuaternion::from_euler_angles(0.5, 0.0, 0.0),
+            Vector3::new(2.0, 2.0, 2.0),
+        )),
+    )
+    .unwrap();
+    Entity::from_handle(lamp, weak_scene.clone()).add_component(Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 0.5, 0.25),
+        4.0,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    ));
+    Scene::create_entity(weak_scene.clone(), Some("Bulb".into()), Some(lamp), None).unwrap();
+    Scene::create_entity(weak_scene, Some("Floor".into()), Some(root), None).unwrap();
+    scene
+}
+
+#[test]
+fn saved_scene_loads_with_the_same_contents() {
+    let scene = create_scene();
+    let yaml = scene.lock().unwrap().to_yaml().unwrap();
+
+    let loaded = Scene::create();
+    loaded
+        .lock()
+        .unwrap()
+        .load_yaml(&yaml, &empty_resource_manager())
+        .unwrap();
+
+    // entities keep their guids, so the scene data matches exactly
+    let loaded = loaded.lock().unwrap();
+    assert_eq!(
+        loaded.to_scene_data(),
+        scene.lock().unwrap().to_scene_data()
+    );
+    // the name index is rebuilt for the loaded entities
+    assert!(loaded.find_by_path("Root/Lamp/Bulb").is_some());
+}
+
+#[test]
+fn malformed_scene_leaves_the_scene_untouched() {
+    let scene = create_scene();
+    let weak_scene = Arc::downgrade(&scene);
+    let floor = scene.lock().unwrap().find_by_name("Floor").unwrap();
+    let floor_guid = scene.lock().unwrap().get_entity_guid(floor).unwrap();
+    Scene::execute(
+        weak_scene.clone(),
+        SetComponent::new(
+            floor_guid,
+            Transform::new(
+                Vector3::new(0.0, -1.0, 0.0),
+                UnitQuaternion::identity(),
+                Vector3::new(1.0, 1.0, 1.0),
+            ),
+        ),
+    )
+    .unwrap();
+    let scene_data = scene.lock().unwrap().to_scene_data();
+
+    // the root entity is not in the file
+    let yaml = "version: 1\nname: broken\nroot_entity_id: 7\nentities:\n  - id: 0\n    tag: Root\n";
+    assert!(scene
+        .lock()
+        .unwrap()
+        .load_yaml(yaml, &empty_resource_manager())
+        .is_err());
+
+    assert_eq!(scene.lock().unwrap().to_scene_data(), scene_data);
+    assert!(scene.lock().unwrap().take_destroyed_entities().is_empty());
+    assert!(Scene::undo(weak_scene).unwrap());
+}
+
+#[test]
+fn saving_to_an_unwritable_path_returns_an_error() {
+    let scene = create_scene();
+    let path = std::env::temp_dir()
+        .join(format!(
+            "dream-serialization-missing-{}",
+            std::process::id()
+        ))
+        .join("main.scene");
+    let err = scene.lock().unwrap().save(path).unwrap_err();
+    assert!(format!("{:#}", err).contains("main.scene"), "{:#}", err);
+}
+
+#[test]
+fn version_1_bones_are_given_the_armature_of_their_closest_root_bone() {
+    // written before bones stored their armature
+    let yaml = r#"
+version: 1
+name: old
+root_entity_id: 0
+entities:
+  - id: 0
+    tag: Root
+    hierarchy: { num_children: 1, parent: null, first_child: 1, prev_sibling: null, next_sibling: null }
+  - id: 1
+    tag: Hips
+    hierarchy: { num_children: 1, parent: 0, first_child: 2, prev_sibling: null, next_sibling: null }
+    bone: { is_root: true, node_id: 1, bone_id: 0, inverse_bind_pose: [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]] }
+  - id: 2
+    tag: Spine
+    hierarchy: { num_children: 0, parent: 1, first_child: null, prev_sibling: null, next_sibling: null }
+    bone: { is_root: false, node_id: 2, bone_id: 1, inverse_bind_pose: [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]] }
+"#;
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    scene
+        .lock()
+        .unwrap()
+        .load_yaml(yaml, &empty_resource_manager())
+        .unwrap();
+
+    let hips = scene.lock().unwrap().find_by_name("Hips").unwrap();
+    let spine = scene.lock().unwrap().find_by_name("Spine").unwrap();
+    for bone_id in [hips, spine] {
+        let bone: Bone = Entity::from_handle(bone_id, weak_scene.clone())
+            .get_component()
+            .unwrap();
+        assert_eq!(bone.armature_root_runtime_id, Some(hips));
+    }
+    assert_eq!(
+        scene.lock().unwrap().to_scene_data().version,
+        SCENE_FILE_VERSION
+    );
+}
+
+#[test]
+fn unknown_scene_file_versions_are_rejected() {
+    let scene = create_scene();
+    let scene_data = scene.lock().unwrap().to_scene_data();
+    for version in [0, SCENE_FILE_VERSION + 1] {
+        let yaml = format!(
+            "version: {}\nname: future\nroot_entity_id: null\nentities: []\n",
+            version
+        );
+        assert!(scene
+            .lock()
+            .unwrap()
+            .load_yaml(&yaml, &empty_resource_manager())
+            .is_err());
+    }
+    assert_eq!(scene.lock().unwrap().to_scene_data(), scene_data);
+}
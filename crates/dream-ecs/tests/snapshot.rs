@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use dream_ecs::component::{AnimationPlayer, Light, LightType, Transform};
+use dream_ecs::entity::Entity;
+use dream_ecs::history::{DestroyEntity, SetChildIndex, SetComponent};
+use dream_ecs::scene::Scene;
+use dream_math::{UnitQuaternion, Vector3};
+use dream_resource::resource_manager::ResourceManager;
+
+fn empty_resource_manager() -> ResourceManager {
+    let project_dir = std::env::temp_dir().join(format!("dream-snapshot-{}", std::process::id()));
+    std::fs::create_dir_all(&project_dir).unwrap();
+    dream_fs::fs::set_fs_root(project_dir.to_str().unwrap());
+    ResourceManager::default()
+}
+
+fn translation(position: Vector3<f32>) -> Transform {
+    Transform::new(
+        position,
+        UnitQuaternion::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    )
+}
+
+#[test]
+fn restored_snapshot_matches_the_scene_before_playing() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let room = Scene::create_entity(weak_scene.clone(), Some("Room".into()), None, None).unwrap();
+    let lamp = Scene::create_entity(
+        weak_scene.clone(),
+        Some("Lamp".into()),
+        Some(room),
+        Some(translation(Vector3::new(0.0, 2.0, 0.0))),
+    )
+    .unwrap();
+    Entity::from_handle(lamp, weak_scene.clone()).add_component(Light::new(
+        LightType::POINT,
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        Vector3::new(0.0, -1.0, 0.0),
+        false,
+    ));
+    let chair =
+        Scene::create_entity(weak_scene.clone(), Some("Chair".into()), Some(room), None).unwrap();
+    let chair_guid = scene.lock().unwrap().get_entity_guid(chair).unwrap();
+    Scene::execute(
+        weak_scene.clone(),
+        SetComponent::new(chair_guid.clone(), translation(Vector3::new(1.0, 0.0, 0.0))),
+    )
+    .unwrap();
+    let lamp_guid = scene.lock().unwrap().get_entity_guid(lamp).unwrap();
+    let before = scene.lock().unwrap().to_scene_data();
+    let snapshot = scene.lock().unwrap().take_snapshot();
+
+    // play mode moves, reorders, destroys and creates entities
+    Scene::execute(weak_scene.clone(), SetChildIndex::new(chair_guid, 0)).unwrap();
+    Scene::execute(weak_scene.clone(), DestroyEntity::new(lamp_guid.clone())).unwrap();
+    Scene::create_entity(weak_scene.clone(), Some("Ball".into()), Some(room), None).unwrap();
+    Entity::from_handle(chair, weak_scene.clone())
+        .add_component(translation(Vector3::new(5.0, 0.0, 0.0)));
+
+    let runtime_ids = scene
+        .lock()
+        .unwrap()
+        .restore_snapshot(snapshot, &empty_resource_manager())
+        .unwrap();
+
+    // components, guids and the order of the children are back
+    assert_eq!(scene.lock().unwrap().to_scene_data(), before);
+    let room = runtime_ids[&room];
+    let children = Scene::get_children_for_entity(weak_scene.clone(), room);
+    assert_eq!(children, vec![runtime_ids[&lamp], runtime_ids[&chair]]);
+    assert_eq!(
+        scene.lock().unwrap().get_entity_by_guid(&lamp_guid),
+        Some(runtime_ids[&lamp])
+    );
+    let light: Light = Entity::from_handle(runtime_ids[&lamp], weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(light.radius, 1.0);
+
+    // the history is the one from before playing, commands executed while playing are gone
+    assert_eq!(
+        scene.lock().unwrap().history.get_undo_name().as_deref(),
+        Some("Set Transform")
+    );
+    assert!(!scene.lock().unwrap().history.can_redo());
+    assert!(Scene::undo(weak_scene.clone()).unwrap());
+    let chair_transform: Transform = Entity::from_handle(runtime_ids[&chair], weak_scene.clone())
+        .get_component()
+        .unwrap();
+    assert_eq!(chair_transform.position, Vector3::zeros());
+    assert!(!Scene::undo(weak_scene).unwrap());
+}
+
+#[test]
+fn animation_players_stop_playing_where_they_were_when_play_mode_started() {
+    let scene = Scene::create();
+    let weak_scene = Arc::downgrade(&scene);
+    let knight =
+        Scene::create_entity(weak_scene.clone(), Some("Knight".into()), None, None).unwrap();
+    // previewed in the editor halfway through a fade from walking to running
+    let mut animation_player = AnimationPlayer {
+        clip: "walk".into(),
+        time: 0.25,
+        ..Default::default()
+    };
+    animation_player.cross_fade("run".into(), 0.5);
+    animation_player.fade.as_mut().unwrap().elapsed = 0.2;
+    Entity::from_handle(knight, weak_scene.clone()).add_component(animation_player.clone());
+    let snapshot = scene.lock().unwrap().take_snapshot();
+
+    // playing finishes the fade and starts another one
+    let mut playing: AnimationPlayer = Entity::from_handle(knight, weak_scene.clone())
+        .get_component()
+        .unwrap();
+    playing.fade = None;
+    playing.time = 3.0;
+    playing.cross_fade("jump".into(), 0.1);
+    Entity::from_handle(knight, weak_scene.clone()).add_component(playing);
+
+    let runtime_ids = scene
+        .lock()
+        .unwrap()
+        .restore_snapshot(snapshot, &empty_resource_manager())
+        .unwrap();
+
+    let restored: AnimationPlayer = Entity::from_handle(runtime_ids[&knight], weak_scene)
+        .get_component()
+        .unwrap();
+    assert_eq!(restored, animation_player);
+    assert_eq!(restored.fade.unwrap().clip, "walk");
+}
@@ -24,7 +24,9 @@ impl AssetsPanel {
     ) -> Self {
         let file_icon_texture_bytes = include_bytes!("icons/FileIcon.png");
         let mut file_icon_image = Image::default();
-        file_icon_image.load_from_bytes(file_icon_texture_bytes, "icons/FileIcon.png", None);
+        file_icon_image
+            .load_from_bytes(file_icon_texture_bytes, "icons/FileIcon.png", None)
+            .expect("Unable to decode file icon");
         let rgba = file_icon_image.to_rgba8();
         let file_icon_texture = texture::Texture::new(
             &renderer.device,
@@ -39,11 +41,13 @@ impl AssetsPanel {
 
         let directory_icon_texture_bytes = include_bytes!("icons/DirectoryIcon.png");
         let mut directory_icon_image = Image::default();
-        directory_icon_image.load_from_bytes(
-            directory_icon_texture_bytes,
-            "icons/DirectoryIcon.png",
-            None,
-        );
+        directory_icon_image
+            .load_from_bytes(
+                directory_icon_texture_bytes,
+                "icons/DirectoryIcon.png",
+                None,
+            )
+            .expect("Unable to decode directory icon");
         let rgba = directory_icon_image.to_rgba8();
         let directory_icon_texture = texture::Texture::new(
             &renderer.device,
@@ -96,7 +96,7 @@ impl EditorEguiWgpu {
             depth_texture_egui,
             renderer_panel,
             panels: vec![
-                Arc::new(Mutex::new(MenuBarPanel::default())),
+                Arc::new(Mutex::new(MenuBarPanel::new(Arc::downgrade(&app.scene)))),
                 inspector_panel,
                 assets_panel,
                 scene_hierarchy_panel,
@@ -3,8 +3,13 @@ use std::sync::{Mutex, Weak};
 
 use crossbeam_channel::Receiver;
 
-use dream_ecs::component::{Bone, Light, MeshRenderer, PythonScript, Tag, Transform};
+use dream_app::asset_errors::get_asset_load_error;
+use dream_ecs::component::{
+    Active, Guid, Light, LightType, MeshRenderer, PythonScript, RenderLayers, Tag, Transform,
+};
 use dream_ecs::entity::Entity;
+use dream_ecs::history::{Command, SetComponent, SetComponentField, SetRegisteredComponent};
+use dream_ecs::reflect::{component_registry, FieldType, FieldValue};
 use dream_ecs::scene::Scene;
 use dream_math::{degrees, pi, radians};
 
@@ -13,7 +18,9 @@ use crate::editor::{EditorEvent, EditorEventType, Panel};
 pub struct InspectorPanel {
     rx: Receiver<EditorEvent>,
     scene: Weak<Mutex<Scene>>,
-    selected_entity_id: Option<u64>,
+    /// Kept by guid, since the runtime id changes when play mode stops or undo brings the entity
+    /// back
+    selected_entity: Option<Guid>,
 }
 
 impl InspectorPanel {
@@ -21,7 +28,231 @@ impl InspectorPanel {
         Self {
             rx,
             scene,
-            selected_entity_id: None,
+            selected_entity: None,
+        }
+    }
+}
+
+/// Run an edit made in the inspector through the history of the scene, so it can be undone
+fn execute(scene: &Weak<Mutex<Scene>>, command: impl Command + 'static) {
+    let command_name = command.name();
+    if let Err(err) = Scene::execute(scene.clone(), command) {
+        log::error!("Unable to apply {}: {}", command_name, err);
+    }
+}
+
+/// Registered components that have a hand-made section in the inspector
+const COMPONENTS_WITH_SECTION: &[&str] = &[
+    "Tag",
+    "Active",
+    "RenderLayers",
+    "Transform",
+    "Light",
+    "MeshRenderer",
+    "PythonScript",
+];
+
+/// Registered components with a hand-made section that are not offered in the Add Component menu,
+/// every entity has them or they are added by dropping an asset
+const COMPONENTS_NOT_IN_ADD_MENU: &[&str] = &[
+    "Tag",
+    "Active",
+    "Transform",
+    "Light",
+    "MeshRenderer",
+    "PythonScript",
+];
+
+/// A reflected field of the selected entity, read before drawing since commands lock the scene
+struct FieldState {
+    name: &'static str,
+    field_type: FieldType,
+    read_only: bool,
+    value: FieldValue,
+}
+
+/// Draw a section for every other registered component of the entity, and a menu to add the
+/// registered components it does not have. Components managed by the scene are only shown.
+fn draw_registered_components(
+    ui: &mut egui::Ui,
+    scene: &Weak<Mutex<Scene>>,
+    entity_id: u64,
+    entity_guid: Option<Guid>,
+) {
+    let (Some(scene_arc), Some(entity_guid)) = (scene.upgrade(), entity_guid) else {
+        return;
+    };
+    let mut components: Vec<(&'static str, bool, Vec<FieldState>)> = Vec::new();
+    let mut missing_components: Vec<&'static str> = Vec::new();
+    {
+        let scene = scene_arc
+            .lock()
+            .expect("Unable to lock scene for inspector");
+        let registry = component_registry();
+        for component in registry.iter() {
+            if COMPONENTS_WITH_SECTION.contains(&component.name) {
+                if !COMPONENTS_NOT_IN_ADD_MENU.contains(&component.name)
+                    && !component.has(&scene, entity_id)
+                {
+                    missing_components.push(component.name);
+                }
+                continue;
+            }
+            match component.get(&scene, entity_id) {
+                Some(value) => components.push((
+                    component.name,
+                    component.is_managed(),
+                    component
+                        .fields
+                        .iter()
+                        .filter_map(|field| {
+                            field.get(&*value).map(|field_value| FieldState {
+                                name: field.name,
+                                field_type: field.field_type,
+                                read_only: field.is_read_only(),
+                                value: field_value,
+                            })
+                        })
+                        .collect(),
+                )),
+                None if !component.is_managed() => missing_components.push(component.name),
+                None => {}
+            }
+        }
+    }
+
+    for (component_name, managed, fields) in components {
+        egui::collapsing_header::CollapsingState::load_with_default_open(
+            ui.ctx(),
+            ui.make_persistent_id(component_name),
+            true,
+        )
+        .show_header(ui, |ui| {
+            ui.strong(component_name);
+            if !managed && ui.small_button("Remove").clicked() {
+                execute(
+                    scene,
+                    SetRegisteredComponent::remove(entity_guid.clone(), component_name),
+                );
+            }
+        })
+        .body(|ui| {
+            for mut field in fields {
+                ui.strong(field.name);
+                let id_source = format!("{component_name}.{}", field.name);
+                let changed = ui
+                    .add_enabled_ui(!field.read_only, |ui| {
+                        field_value_widget(ui, &id_source, field.field_type, &mut field.value)
+                    })
+                    .inner;
+                if changed {
+                    execute(
+                        scene,
+                        SetComponentField::new(
+                            entity_guid.clone(),
+                            component_name,
+                            field.name,
+                            field.value,
+                        ),
+                    );
+                }
+            }
+        });
+    }
+
+    if !missing_components.is_empty() {
+        ui.menu_button("Add Component", |ui| {
+            for component_name in missing_components {
+                if ui.button(component_name).clicked() {
+                    execute(
+                        scene,
+                        SetRegisteredComponent::add_default(entity_guid.clone(), component_name),
+                    );
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+}
+
+/// Widget for a reflected field, returns whether the value was changed
+fn field_value_widget(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    field_type: FieldType,
+    value: &mut FieldValue,
+) -> bool {
+    match value {
+        FieldValue::Bool(value) => ui.checkbox(value, "").changed(),
+        FieldValue::U32(value) => ui.add(egui::DragValue::new(value)).changed(),
+        FieldValue::F32(value) => ui
+            .add(egui::DragValue::new(value).speed(0.1).max_decimals(5))
+            .changed(),
+        FieldValue::String(value) => ui.text_edit_singleline(value).changed(),
+        FieldValue::Vec3(value) => {
+            ui.horizontal(|ui| {
+                let mut changed = false;
+                ui.strong("x");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut value.x)
+                            .speed(0.1)
+                            .max_decimals(5),
+                    )
+                    .changed();
+                ui.strong("y");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut value.y)
+                            .speed(0.1)
+                            .max_decimals(5),
+                    )
+                    .changed();
+                ui.strong("z");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut value.z)
+                            .speed(0.1)
+                            .max_decimals(5),
+                    )
+                    .changed();
+                changed
+            })
+            .inner
+        }
+        // rotations are only shown, as euler angles in degrees
+        FieldValue::Quat(value) => {
+            let (roll, pitch, yaw) = value.euler_angles();
+            ui.label(format!(
+                "{:.2} {:.2} {:.2}",
+                degrees(roll),
+                degrees(pitch),
+                degrees(yaw)
+            ));
+            false
+        }
+        FieldValue::Enum(idx) => {
+            let FieldType::Enum(variants) = field_type else {
+                return false;
+            };
+            let mut changed = false;
+            egui::ComboBox::from_id_source(id_source)
+                .selected_text(variants.get(*idx).copied().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (variant_idx, variant) in variants.iter().enumerate() {
+                        changed |= ui.selectable_value(idx, variant_idx, *variant).changed();
+                    }
+                });
+            changed
+        }
+        FieldValue::Entity(entity_ref) => {
+            ui.label(
+                entity_ref
+                    .guid
+                    .as_ref()
+                    .map_or("None", |guid| guid.0.as_str()),
+            );
+            false
         }
     }
 }
@@ -37,17 +268,39 @@ impl Panel for InspectorPanel {
                 if let Some(editor_event) = self.rx.try_iter().last() {
                     match editor_event.event_type {
                         EditorEventType::ShowEntityInInspector => {
-                            let entity_id = editor_event.event_data;
-                            self.selected_entity_id = Some(
-                                entity_id
-                                    .parse()
-                                    .expect("Inspector did not receive a u64 for entity ID"),
-                            );
+                            let entity_id: u64 = editor_event
+                                .event_data
+                                .parse()
+                                .expect("Inspector did not receive a u64 for entity ID");
+                            self.selected_entity = Entity::from_handle(entity_id, self.scene.clone())
+                                .get_component::<Guid>();
                         }
                     }
                 }
 
-                if let Some(entity_id) = self.selected_entity_id {
+                // every change made while a value is dragged becomes one undo step
+                let (pointer_pressed, pointer_released) = ui.input(|i| {
+                    (i.pointer.primary_pressed(), i.pointer.primary_released())
+                });
+                if let Some(scene) = self.scene.upgrade() {
+                    let mut scene = scene.lock().expect("Unable to lock scene for inspector");
+                    if pointer_pressed {
+                        scene.history.begin_group("Edit Component");
+                    }
+                    if pointer_released {
+                        scene.history.end_group();
+                    }
+                }
+
+                let selected_entity_id = self.selected_entity.as_ref().and_then(|guid| {
+                    self.scene.upgrade().and_then(|scene| {
+                        scene
+                            .lock()
+                            .expect("Unable to lock scene for inspector")
+                            .get_entity_by_guid(guid)
+                    })
+                });
+                if let Some(entity_id) = selected_entity_id {
                     let entity = Entity::from_handle(entity_id, self.scene.clone());
 
                     let tag_component: Option<Tag> = entity.get_component();
@@ -55,12 +308,23 @@ impl Panel for InspectorPanel {
                     let mesh_renderer_component: Option<MeshRenderer> = entity.get_component();
                     let python_script_component: Option<PythonScript> = entity.get_component();
                     let light_component: Option<Light> = entity.get_component();
-                    let bone_component: Option<Bone> = entity.get_component();
+                    let active_component: Option<Active> = entity.get_component();
+                    let render_layers_component: Option<RenderLayers> = entity.get_component();
+                    let entity_guid: Option<Guid> = entity.get_component();
+                    let transform_component_before = transform_component.clone();
+                    let light_component_before = light_component.clone();
 
                     if let Some(tag_component) = tag_component {
                         ui.strong(tag_component.name);
                     }
 
+                    let mut active = active_component.map_or(true, |active| active.active);
+                    if ui.checkbox(&mut active, "Active").changed() {
+                        if let Some(entity_guid) = entity_guid.clone() {
+                            execute(&self.scene, SetComponent::new(entity_guid, Active::new(active)));
+                        }
+                    }
+
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         egui::ScrollArea::horizontal().show(ui, |ui| {
                             if let Some(mut transform_component) = transform_component {
@@ -98,6 +362,7 @@ impl Panel for InspectorPanel {
                                         let mut roll = degrees(roll_pitch_yaw.0);
                                         let mut pitch = degrees(roll_pitch_yaw.1);
                                         let mut yaw = degrees(roll_pitch_yaw.2);
+                                        let displayed_angles = (roll, pitch, yaw);
                                         ui.strong("Rotation");
                                         ui.strong("roll");
                                         ui.add(
@@ -117,10 +382,14 @@ impl Panel for InspectorPanel {
                                                 .speed(pi() / 10.0)
                                                 .max_decimals(2),
                                         );
-                                        roll = radians(roll);
-                                        pitch = radians(pitch);
-                                        yaw = radians(yaw);
-                                        transform_component.set_euler_angles(roll, pitch, yaw);
+                                        // converting back and forth is lossy, so only touch the
+                                        // rotation when it was edited
+                                        if (roll, pitch, yaw) != displayed_angles {
+                                            roll = radians(roll);
+                                            pitch = radians(pitch);
+                                            yaw = radians(yaw);
+                                            transform_component.set_euler_angles(roll, pitch, yaw);
+                                        }
 
                                         ui.strong("Scale");
                                         ui.strong("x");
@@ -142,7 +411,14 @@ impl Panel for InspectorPanel {
                                                 .max_decimals(5),
                                         );
 
-                                        entity.add_component(transform_component);
+                                        if transform_component_before.as_ref() != Some(&transform_component) {
+                                            if let Some(entity_guid) = entity_guid.clone() {
+                                                execute(
+                                                    &self.scene,
+                                                    SetComponent::new(entity_guid, transform_component),
+                                                );
+                                            }
+                                        }
                                         // ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
                                         //     ui.strong("x");
                                         //     ui.add(
@@ -179,8 +455,11 @@ impl Panel for InspectorPanel {
                                     .body(|ui| {
                                         ui.strong("Path");
                                         if let Some(resource_handle) = mesh_renderer_component.resource_handle {
-                                            let path = resource_handle.upgrade().expect("Unable to upgrade resource handle for inspector for mesh renderer").path.clone();
-                                            ui.label(path.to_str().expect("Unable to convert path to string"));
+                                            let resource_handle = resource_handle.upgrade().expect("Unable to upgrade resource handle for inspector for mesh renderer");
+                                            ui.label(resource_handle.get_path().to_str().expect("Unable to convert path to string"));
+                                            if let Some(error) = get_asset_load_error(&resource_handle.key) {
+                                                ui.colored_label(egui::Color32::RED, format!("Failed to load: {error}"));
+                                            }
                                         } else {
                                             ui.label("None");
                                         }
@@ -203,7 +482,7 @@ impl Panel for InspectorPanel {
                                     .body(|ui| {
                                         ui.strong("Path");
                                         if let Some(resource_handle) = python_script_component.resource_handle {
-                                            let path = resource_handle.upgrade().expect("Unable to upgrade resource handle for inspector for python script").path.clone();
+                                            let path = resource_handle.upgrade().expect("Unable to upgrade resource handle for inspector for python script").get_path();
                                             ui.label(path.to_str().expect("Unable to convert path to string"));
                                         } else {
                                             ui.label("None");
@@ -211,6 +490,33 @@ impl Panel for InspectorPanel {
                                     });
                             }
 
+                            if let Some(render_layers_component) = render_layers_component {
+                                egui::collapsing_header::CollapsingState::load_with_default_open(
+                                    ui.ctx(),
+                                    ui.make_persistent_id("RenderLayersComponent"),
+                                    true,
+                                )
+                                    .show_header(ui, |ui| {
+                                        ui.strong("Render Layers");
+                                    })
+                                    .body(|ui| {
+                                        let mut mask = render_layers_component.mask;
+                                        ui.horizontal_wrapped(|ui| {
+                                            for layer in 0..u32::BITS {
+                                                let mut on_layer = render_layers_component.contains(layer);
+                                                if ui.checkbox(&mut on_layer, format!("{layer}")).changed() {
+                                                    mask ^= 1 << layer;
+                                                }
+                                            }
+                                        });
+                                        if mask != render_layers_component.mask {
+                                            if let Some(entity_guid) = entity_guid.clone() {
+                                                execute(&self.scene, SetComponent::new(entity_guid, RenderLayers::new(mask)));
+                                            }
+                                        }
+                                    });
+                            }
+
                             if let Some(mut light_component) = light_component {
                                 egui::collapsing_header::CollapsingState::load_with_default_open(
                                     ui.ctx(),
@@ -272,29 +578,36 @@ impl Panel for InspectorPanel {
                                                 .max_decimals(5)
                                                 .clamp_range(RangeInclusive::new(-1.0, 1.0))
                                         );
+                                        if light_component.light_type == LightType::SPOT {
+                                            ui.strong("Cone");
+                                            ui.strong("inner");
+                                            ui.add(
+                                                egui::DragValue::new(&mut light_component.inner_cone_angle)
+                                                    .speed(0.01)
+                                                    .max_decimals(3)
+                                                    .clamp_range(RangeInclusive::new(0.0, std::f32::consts::FRAC_PI_2))
+                                            );
+                                            ui.strong("outer");
+                                            ui.add(
+                                                egui::DragValue::new(&mut light_component.outer_cone_angle)
+                                                    .speed(0.01)
+                                                    .max_decimals(3)
+                                                    .clamp_range(RangeInclusive::new(0.0, std::f32::consts::FRAC_PI_2))
+                                            );
+                                        }
 
-                                        entity.add_component(light_component);
+                                        if light_component_before.as_ref() != Some(&light_component) {
+                                            if let Some(entity_guid) = entity_guid.clone() {
+                                                execute(
+                                                    &self.scene,
+                                                    SetComponent::new(entity_guid, light_component),
+                                                );
+                                            }
+                                        }
                                     });
                             }
 
-                            if let Some(bone_component) = bone_component {
-                                egui::collapsing_header::CollapsingState::load_with_default_open(
-                                    ui.ctx(),
-                                    ui.make_persistent_id("BoneComponent"),
-                                    true,
-                                )
-                                    .show_header(ui, |ui| {
-                                        ui.strong("Bone");
-                                    })
-                                    .body(|ui| {
-                                        ui.strong("Armature Root");
-                                        ui.label(format!("{:?}", bone_component.is_root).as_str());
-                                        ui.strong("Node ID");
-                                        ui.label(format!("{:?}", bone_component.node_id).as_str());
-                                        ui.strong("Bone ID");
-                                        ui.label(format!("{:?}", bone_component.bone_id).as_str());
-                                    });
-                            }
+                            draw_registered_components(ui, &self.scene, entity_id, entity_guid.clone());
                         });
                     });
                 }
@@ -1,7 +1,41 @@
+use std::sync::{Mutex, Weak};
+
+use dream_app::app::request_scene_save;
+use dream_app::play_mode::is_play_requested;
+use dream_ecs::scene::Scene;
+
 use crate::editor::Panel;
 
-#[derive(Default)]
-pub struct MenuBarPanel {}
+pub struct MenuBarPanel {
+    scene: Weak<Mutex<Scene>>,
+}
+
+impl MenuBarPanel {
+    pub fn new(scene: Weak<Mutex<Scene>>) -> Self {
+        Self { scene }
+    }
+
+    /// The app saves the scene on its next update, to the file the scene was loaded from
+    fn save_scene(&self) {
+        if is_play_requested() {
+            log::warn!("Unable to save scene while playing, stop playing first");
+            return;
+        }
+        request_scene_save();
+    }
+
+    fn undo(&self) {
+        if let Err(err) = Scene::undo(self.scene.clone()) {
+            log::error!("Unable to undo: {}", err);
+        }
+    }
+
+    fn redo(&self) {
+        if let Err(err) = Scene::redo(self.scene.clone()) {
+            log::error!("Unable to redo: {}", err);
+        }
+    }
+}
 
 impl Panel for MenuBarPanel {
     fn draw(&mut self, egui_context: &egui::Context) {
@@ -10,10 +44,22 @@ impl Panel for MenuBarPanel {
                 let save_shortcut =
                     egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
 
+                let undo_shortcut =
+                    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+                let redo_shortcut = egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::Z,
+                );
+
                 if ui.input_mut(|i| i.consume_shortcut(&save_shortcut)) {
-                    // TODO: allow saving
-                    log::warn!("TODO: save");
-                    todo!();
+                    self.save_scene();
+                }
+                // check redo first, since the undo shortcut also matches with shift held
+                if ui.input_mut(|i| i.consume_shortcut(&redo_shortcut)) {
+                    self.redo();
+                }
+                if ui.input_mut(|i| i.consume_shortcut(&undo_shortcut)) {
+                    self.undo();
                 }
 
                 ui.menu_button("File", |ui| {
@@ -27,10 +73,43 @@ impl Panel for MenuBarPanel {
                         )
                         .clicked()
                     {
-                        // TODO: allow saving
-                        log::warn!("TODO: save");
+                        self.save_scene();
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("Edit", |ui| {
+                    ui.set_min_width(100.0);
+                    ui.style_mut().wrap = Some(false);
+
+                    let (undo_name, redo_name) = match self.scene.upgrade() {
+                        Some(scene) => {
+                            let scene = scene.lock().expect("Unable to lock scene for menu bar");
+                            (scene.history.get_undo_name(), scene.history.get_redo_name())
+                        }
+                        None => (None, None),
+                    };
+                    if ui
+                        .add_enabled(
+                            undo_name.is_some(),
+                            egui::Button::new(format!("Undo {}", undo_name.unwrap_or_default()))
+                                .shortcut_text(ui.ctx().format_shortcut(&undo_shortcut)),
+                        )
+                        .clicked()
+                    {
+                        self.undo();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            redo_name.is_some(),
+                            egui::Button::new(format!("Redo {}", redo_name.unwrap_or_default()))
+                                .shortcut_text(ui.ctx().format_shortcut(&redo_shortcut)),
+                        )
+                        .clicked()
+                    {
+                        self.redo();
                         ui.close_menu();
-                        todo!();
                     }
                 });
             });
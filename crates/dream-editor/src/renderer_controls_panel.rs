@@ -2,6 +2,7 @@ use egui::load::SizedTexture;
 use egui::Widget;
 use egui_wgpu::Renderer;
 
+use dream_app::play_mode::{is_play_requested, set_play_requested};
 use dream_renderer::image::Image;
 use dream_renderer::texture;
 
@@ -18,7 +19,9 @@ impl RendererControlsPanel {
     ) -> Self {
         let play_icon_texture_bytes = include_bytes!("icons/PlayIcon.png");
         let mut play_icon_image = Image::default();
-        play_icon_image.load_from_bytes(play_icon_texture_bytes, "icons/PlayIcon.png", None);
+        play_icon_image
+            .load_from_bytes(play_icon_texture_bytes, "icons/PlayIcon.png", None)
+            .expect("Unable to decode play icon");
         let rgba = play_icon_image.to_rgba8();
         let play_icon_texture = texture::Texture::new(
             &renderer.device,
@@ -56,8 +59,16 @@ impl Panel for RendererControlsPanel {
                         id: self.play_icon_epaint_texture_id,
                         size: egui::vec2(15.5, 15.5),
                     };
-                    let btn = egui::ImageButton::new(image);
-                    btn.ui(ui);
+                    let playing = is_play_requested();
+                    let btn = egui::ImageButton::new(image).selected(playing);
+                    let response = btn
+                        .ui(ui)
+                        .on_hover_text(if playing { "Stop" } else { "Play" });
+                    if response.clicked() {
+                        // the app snapshots the scenes when it starts playing and restores them
+                        // when it stops
+                        set_play_requested(!playing);
+                    }
                 });
             });
     }
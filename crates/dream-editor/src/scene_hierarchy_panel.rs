@@ -3,8 +3,10 @@ use std::sync::{Mutex, Weak};
 use crossbeam_channel::Sender;
 use egui::{vec2, Color32, Rounding, Sense, Stroke, Ui};
 
-use dream_ecs::component::Tag;
+use dream_ecs::component::{Guid, Hierarchy, Tag};
 use dream_ecs::entity::Entity;
+use dream_ecs::gltf_export::glb_file_name;
+use dream_ecs::history::SetChildIndex;
 use dream_ecs::scene::Scene;
 use dream_math::max;
 
@@ -13,7 +15,8 @@ use crate::editor::{EditorEvent, EditorEventType, Panel};
 pub struct SceneHierarchyPanel {
     sx: Sender<EditorEvent>,
     scene: Weak<Mutex<Scene>>,
-    selected_entity: Option<u64>,
+    /// Kept by guid, like the selection of the inspector
+    selected_entity: Option<Guid>,
 }
 
 impl SceneHierarchyPanel {
@@ -55,6 +58,58 @@ impl Panel for SceneHierarchyPanel {
 }
 
 impl SceneHierarchyPanel {
+    /// Position of an entity among its siblings and the number of siblings including itself
+    fn get_sibling_position(&self, entity_id: u64) -> Option<(usize, usize)> {
+        let parent_id = Entity::from_handle(entity_id, self.scene.clone())
+            .get_component::<Hierarchy>()
+            .and_then(|hierarchy| hierarchy.parent_runtime_id)?;
+        let siblings = Scene::get_children_for_entity(self.scene.clone(), parent_id);
+        let index = siblings
+            .iter()
+            .position(|sibling_id| *sibling_id == entity_id)?;
+        Some((index, siblings.len()))
+    }
+
+    /// Write an entity and its descendants to a .glb file named after it in the project folder,
+    /// existing files are not overwritten
+    fn export_gltf(&self, entity_id: u64) {
+        let name = Entity::from_handle(entity_id, self.scene.clone())
+            .get_component::<Tag>()
+            .map(|tag| tag.name)
+            .unwrap_or(String::from("Entity"));
+        let path = dream_fs::fs::get_fs_root().join(glb_file_name(&name));
+        if path.exists() {
+            log::error!(
+                "Unable to export glTF: {} already exists",
+                path.to_str().unwrap_or("none")
+            );
+            return;
+        }
+        let Some(scene) = self.scene.upgrade() else {
+            log::error!("Unable to export glTF: the scene is no longer loaded");
+            return;
+        };
+        let res = scene
+            .lock()
+            .expect("Unable to lock scene for export")
+            .export_glb(entity_id, path.clone());
+        match res {
+            Ok(()) => log::info!("Exported glTF to {}", path.to_str().unwrap_or("none")),
+            Err(err) => log::error!("Unable to export glTF: {}", err),
+        }
+    }
+
+    /// Move an entity to another position among its siblings, so that it can be undone
+    fn set_child_index(&self, entity_id: u64, index: usize) {
+        let Some(guid) = Entity::from_handle(entity_id, self.scene.clone()).get_component::<Guid>()
+        else {
+            return;
+        };
+        if let Err(err) = Scene::execute(self.scene.clone(), SetChildIndex::new(guid, index)) {
+            log::error!("Unable to reorder entity: {}", err);
+        }
+    }
+
     fn draw_scene_hierarchy_entity(&mut self, entity_id: u64, ui: &mut Ui) {
         let id_str = format!("scene_panel_entity_{entity_id}");
         let mut collapsing_state = egui::collapsing_header::CollapsingState::load_with_default_open(
@@ -95,7 +150,9 @@ impl SceneHierarchyPanel {
             let name = entity.get_component::<Tag>().unwrap().name;
             // draw background
             let indent_size = ui.spacing().indent;
-            if self.selected_entity.is_some() && self.selected_entity.unwrap() == entity.handle {
+            if self.selected_entity.is_some()
+                && self.selected_entity == entity.get_component::<Guid>()
+            {
                 let text_to_be_drawn = ui.painter().layout_no_wrap(
                     name.clone(),
                     Default::default(),
@@ -124,12 +181,21 @@ impl SceneHierarchyPanel {
             // draw label text
             {
                 let available_width = ui.available_width();
-                let label_response = ui.colored_label(egui::Color32::WHITE, name);
+                // inactive entities are greyed out
+                let active = self.scene.upgrade().map_or(true, |scene| {
+                    scene.lock().unwrap().is_active_in_hierarchy(entity_id)
+                });
+                let label_color = if active {
+                    egui::Color32::WHITE
+                } else {
+                    egui::Color32::GRAY
+                };
+                let label_response = ui.colored_label(label_color, name);
                 let mut label_rect = label_response.rect;
                 label_rect.set_width(max!(label_response.rect.width(), available_width));
                 let response = ui.allocate_rect(label_rect, Sense::click());
                 if response.clicked() {
-                    self.selected_entity = Some(entity_id);
+                    self.selected_entity = entity.get_component::<Guid>();
                     self.sx
                         .send(EditorEvent {
                             event_type: EditorEventType::ShowEntityInInspector,
@@ -137,6 +203,28 @@ impl SceneHierarchyPanel {
                         })
                         .expect("Unable to transmit show entity event");
                 }
+                response.context_menu(|ui| {
+                    let (index, num_siblings) =
+                        self.get_sibling_position(entity_id).unwrap_or((0, 1));
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("Move Up"))
+                        .clicked()
+                    {
+                        self.set_child_index(entity_id, index - 1);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(index + 1 < num_siblings, egui::Button::new("Move Down"))
+                        .clicked()
+                    {
+                        self.set_child_index(entity_id, index + 1);
+                        ui.close_menu();
+                    }
+                    if ui.button("Export as glTF").clicked() {
+                        self.export_gltf(entity_id);
+                        ui.close_menu();
+                    }
+                });
             }
         });
 
@@ -1,7 +1,8 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 use anyhow::*;
+use base64::Engine;
 use cfg_if::cfg_if;
 
 static FS_ROOT: Mutex<Option<String>> = Mutex::new(None);
@@ -71,6 +72,39 @@ pub fn read_binary(file_path: PathBuf, absolute: bool) -> Result<Vec<u8>> {
     Ok(data)
 }
 
+/// Reads the data behind a uri referenced by a file such as a glTF document.
+/// `data:` uris are decoded in place, anything else is a percent-encoded path
+/// relative to `base_dir`. Returns the bytes and the mime type if one was given.
+pub fn read_uri(uri: &str, base_dir: &Path) -> Result<(Vec<u8>, Option<String>)> {
+    if let Some(data_uri) = uri.strip_prefix("data:") {
+        let (header, data) = data_uri
+            .split_once(',')
+            .ok_or_else(|| anyhow!("Malformed data uri, missing ',' separator"))?;
+        let (mime_type, is_base64) = match header.strip_suffix(";base64") {
+            Some(mime_type) => (mime_type, true),
+            None => (header, false),
+        };
+        let bytes = if is_base64 {
+            base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .with_context(|| "Unable to decode base64 data uri")?
+        } else {
+            urlencoding::decode_binary(data.as_bytes()).into_owned()
+        };
+        let mime_type = if mime_type.is_empty() {
+            None
+        } else {
+            Some(String::from(mime_type))
+        };
+        return Ok((bytes, mime_type));
+    }
+    let relative_path = urlencoding::decode(uri).with_context(|| format!("Invalid uri {}", uri))?;
+    let path = base_dir.join(relative_path.as_ref());
+    let bytes = read_binary(path.clone(), true)
+        .with_context(|| format!("Unable to read {}", path.to_str().unwrap_or(uri)))?;
+    Ok((bytes, None))
+}
+
 pub fn read_dir(file_path: PathBuf) -> Result<Vec<ReadDir>> {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
@@ -92,17 +126,18 @@ pub fn read_dir(file_path: PathBuf) -> Result<Vec<ReadDir>> {
     Ok(files_in_directory)
 }
 
-pub fn write_binary(file_path: PathBuf, content: Vec<u8>) {
+pub fn write_binary(file_path: PathBuf, content: Vec<u8>) -> Result<()> {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             crate::js_fs::write_all_to_web_storage(file_path, content);
         } else {
             use std::io::Write;
-            let mut file = std::fs::File::create(file_path.clone()).unwrap_or_else(|_| panic!("Unable to create file {}", file_path.to_str().unwrap()));
+            let mut file = std::fs::File::create(file_path.clone()).with_context(|| format!("Unable to create file {}", file_path.to_str().unwrap_or("none")))?;
             let c: &[u8] = &content;
-            file.write_all(c).unwrap_or_else(|_| panic!("Unable to write all to file {}", file_path.to_str().unwrap()));
+            file.write_all(c).with_context(|| format!("Unable to write all to file {}", file_path.to_str().unwrap_or("none")))?;
         }
     }
+    Ok(())
 }
 
 pub fn exists(file_path: PathBuf) -> bool {
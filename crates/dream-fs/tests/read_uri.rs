@@ -0,0 +1,47 @@
+use dream_fs::fs::read_uri;
+
+#[test]
+fn base64_data_uris_are_decoded_with_their_mime_type() {
+    let base_dir = std::env::temp_dir();
+    let (bytes, mime_type) =
+        read_uri("data:application/octet-stream;base64,AAEC/w==", &base_dir).unwrap();
+    assert_eq!(bytes, vec![0, 1, 2, 255]);
+    assert_eq!(mime_type.as_deref(), Some("application/octet-stream"));
+
+    // the mime type is optional
+    let (bytes, mime_type) = read_uri("data:;base64,aGk=", &base_dir).unwrap();
+    assert_eq!(bytes, b"hi");
+    assert_eq!(mime_type, None);
+
+    assert!(read_uri("data:image/png;base64,not base64!", &base_dir).is_err());
+}
+
+#[test]
+fn other_data_uris_are_percent_decoded() {
+    let (bytes, mime_type) =
+        read_uri("data:text/plain,hello%20world%FF", &std::env::temp_dir()).unwrap();
+    assert_eq!(bytes, b"hello world\xff");
+    assert_eq!(mime_type.as_deref(), Some("text/plain"));
+}
+
+#[test]
+fn data_uris_without_a_comma_are_rejected() {
+    assert!(read_uri("data:text/plain;base64", &std::env::temp_dir()).is_err());
+}
+
+#[test]
+fn relative_paths_are_percent_decoded_and_read_from_the_base_dir() {
+    let base_dir = std::env::temp_dir().join(format!("dream-read-uri-{}", std::process::id()));
+    std::fs::create_dir_all(base_dir.join("model buffers")).unwrap();
+    std::fs::write(
+        base_dir.join("model buffers").join("mesh #1.bin"),
+        [7, 8, 9],
+    )
+    .unwrap();
+
+    let (bytes, mime_type) = read_uri("model%20buffers/mesh%20%231.bin", &base_dir).unwrap();
+    assert_eq!(bytes, vec![7, 8, 9]);
+    assert_eq!(mime_type, None);
+
+    assert!(read_uri("model%20buffers/missing.bin", &base_dir).is_err());
+}
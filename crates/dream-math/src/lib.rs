@@ -6,6 +6,7 @@ pub type Vector4<T> = nalgebra::Vector4<T>;
 pub type UnitVector3<T> = nalgebra::UnitVector3<T>;
 pub type Point3<T> = nalgebra::Point3<T>;
 pub type Point4<T> = nalgebra::Point4<T>;
+pub type Matrix3<T> = nalgebra::Matrix3<T>;
 pub type Matrix4<T> = nalgebra::Matrix4<T>;
 pub type Rotation3<T> = nalgebra::Rotation3<T>;
 
@@ -2,6 +2,8 @@ use wgpu::util::DeviceExt;
 
 use dream_math::{Matrix4, Point3, UnitQuaternion, Vector3};
 
+use crate::render_storage::ALL_RENDER_LAYERS;
+
 // #[rustfmt::skip]
 // pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
 //     1.0, 0.0, 0.0, 0.0,
@@ -45,6 +47,8 @@ pub struct Camera {
     pub znear: f32,
     pub zfar: f32,
     camera_type: CameraType,
+    /// Render layers of the meshes seen by this camera
+    pub layer_mask: u32,
     pub(crate) camera_uniform: CameraUniform,
     pub(crate) camera_buffer: wgpu::Buffer,
     pub(crate) camera_bind_group: wgpu::BindGroup,
@@ -107,6 +111,7 @@ impl Camera {
             camera_buffer,
             camera_bind_group,
             camera_type: CameraType::Perspective,
+            layer_mask: ALL_RENDER_LAYERS,
             camera_bind_group_layout,
             left: -10.0,
             right: 10.0,
@@ -172,6 +177,7 @@ impl Camera {
             camera_bind_group,
             camera_bind_group_layout,
             camera_type: CameraType::Orthographic,
+            layer_mask: ALL_RENDER_LAYERS,
             left: camera_params.left,
             right: camera_params.right,
             bottom: camera_params.bottom,
@@ -321,6 +321,7 @@ impl DeferredRenderingTech {
         render_storage: &RenderStorage,
         camera_bones_lights_bind_group: &CameraLightBindGroup,
         filter_func: fn(&Material) -> bool,
+        layer_mask: u32,
     ) {
         // render to gbuffers
         // define render pass to write to GBuffers
@@ -407,7 +408,7 @@ impl DeferredRenderingTech {
         // render_pass_write_g_buffers.set_bind_group(2, &skinning_bind_group.bind_group, &[]);
 
         // iterate through all meshes that should be instanced drawn
-        for (render_map_key, transforms) in render_storage.render_map.iter() {
+        for (render_map_key, transforms) in render_storage.meshes_in_layers(layer_mask) {
             let model_map = &render_storage.model_guids;
             // get the mesh to be instance drawn
             let model_guid = render_map_key.model_guid.clone();
@@ -428,7 +429,7 @@ impl DeferredRenderingTech {
                 .get(render_map_key)
                 .expect("No instance buffer found in map");
             render_pass_write_g_buffers.set_vertex_buffer(1, instance_buffer.slice(..));
-            for primitive in &mesh.primitives {
+            for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
                 // get the material and set it in the bind group
                 let material = model
                     .materials
@@ -441,8 +442,11 @@ impl DeferredRenderingTech {
                         &[],
                     );
                     // draw the mesh
-                    render_pass_write_g_buffers
-                        .draw_primitive_instanced(&primitive, 0..transforms.len() as u32);
+                    render_pass_write_g_buffers.draw_primitive_instanced(
+                        primitive,
+                        render_storage.get_skinned_vertices(render_map_key, primitive_index),
+                        0..transforms.len() as u32,
+                    );
                 }
             }
         }
@@ -106,6 +106,7 @@ impl ForwardRenderingTech {
         camera_bones_lights_bind_group: &CameraLightBindGroup,
         shadow_tech: &ShadowTech,
         filter_func: fn(&Material) -> bool,
+        layer_mask: u32,
     ) {
         // define render pass
         let mut render_pass_forward_rendering =
@@ -152,7 +153,7 @@ impl ForwardRenderingTech {
         // render_pass_forward_rendering.set_bind_group(3, &skinning_bind_group.bind_group, &[]);
 
         // iterate through all meshes that should be instanced drawn
-        for (render_map_key, transforms) in render_storage.render_map.iter() {
+        for (render_map_key, transforms) in render_storage.meshes_in_layers(layer_mask) {
             let model_map = &render_storage.model_guids;
             // get the mesh to be instance drawn
             let model_guid = render_map_key.model_guid.clone();
@@ -173,7 +174,7 @@ impl ForwardRenderingTech {
                 .get(render_map_key)
                 .expect("No instance buffer found in map");
             render_pass_forward_rendering.set_vertex_buffer(1, instance_buffer.slice(..));
-            for primitive in &mesh.primitives {
+            for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
                 // get the material and set it in the bind group
                 let material = model
                     .materials
@@ -187,8 +188,11 @@ impl ForwardRenderingTech {
                         &[],
                     );
                     // draw the mesh
-                    render_pass_forward_rendering
-                        .draw_primitive_instanced(&primitive, 0..transforms.len() as u32);
+                    render_pass_forward_rendering.draw_primitive_instanced(
+                        primitive,
+                        render_storage.get_skinned_vertices(render_map_key, primitive_index),
+                        0..transforms.len() as u32,
+                    );
                 }
             }
         }
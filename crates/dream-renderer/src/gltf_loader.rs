@@ -4,36 +4,83 @@ use gltf::buffer::Source;
 use gltf::Mesh;
 use wgpu::util::DeviceExt;
 
-use dream_fs::fs::read_binary;
+use dream_fs::fs::{read_binary, read_uri};
 use dream_math::Vector3;
 
 use crate::material::Material;
-use crate::model::{Model, ModelVertex, PrimitiveInfo};
+use crate::model::{Model, ModelVertex, MorphTargetDelta, PrimitiveInfo};
+use crate::model_load_error::ModelLoadError;
 
-pub fn read_gltf<'a>(
+pub fn read_gltf(
     path: &str,
     device: &wgpu::Device,
     pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Result<Model, ModelLoadError> {
+    let bytes =
+        read_binary(std::path::PathBuf::from(path), true).map_err(|err| ModelLoadError::Io {
+            path: String::from(path),
+            message: err.to_string(),
+        })?;
+    // external buffers and images are relative to the folder of the document
+    let base_dir = std::path::Path::new(path)
+        .parent()
+        .unwrap_or(std::path::Path::new(""));
+    read_gltf_from_slice(
+        &bytes,
+        base_dir,
+        device,
+        pbr_material_factors_bind_group_layout,
+    )
+}
+
+/// Model drawn in place of models that failed to load
+pub(crate) fn read_placeholder_gltf(
+    device: &wgpu::Device,
+    pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
 ) -> Model {
-    let gltf = gltf::Gltf::from_slice(
-        &read_binary(std::path::PathBuf::from(path), true)
-            .unwrap_or_else(|_| panic!("Error loading binary for glb {}", path)),
+    read_gltf_from_slice(
+        include_bytes!("placeholder.gltf"),
+        std::path::Path::new(""),
+        device,
+        pbr_material_factors_bind_group_layout,
     )
-    .expect("Error loading from slice for glb");
+    .expect("Unable to load placeholder model")
+}
+
+fn read_gltf_from_slice(
+    bytes: &[u8],
+    base_dir: &std::path::Path,
+    device: &wgpu::Device,
+    pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Result<Model, ModelLoadError> {
+    let gltf =
+        gltf::Gltf::from_slice(bytes).map_err(|err| ModelLoadError::Parse(err.to_string()))?;
     let mut buffer_data = Vec::new();
     for buffer in gltf.buffers() {
-        match buffer.source() {
-            Source::Bin => {
-                if let Some(blob) = gltf.blob.as_deref() {
-                    buffer_data.push(Vec::from(blob));
-                };
-            }
+        let data = match buffer.source() {
+            Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or_else(|| ModelLoadError::InvalidData(String::from("missing binary chunk")))?,
             Source::Uri(uri) => {
-                let bin = read_binary(std::path::PathBuf::from(uri), false)
-                    .unwrap_or_else(|_| panic!("unable to load binary at uri {}", uri));
-                buffer_data.push(bin);
+                read_uri(uri, base_dir)
+                    .map_err(|err| ModelLoadError::Io {
+                        path: String::from(uri),
+                        message: format!("{:#}", err),
+                    })?
+                    .0
             }
+        };
+        // accessors are read without bounds checks, so buffers must be as long as declared
+        if data.len() < buffer.length() {
+            return Err(ModelLoadError::InvalidData(format!(
+                "buffer {} holds {} bytes but declares {}",
+                buffer.index(),
+                data.len(),
+                buffer.length()
+            )));
         }
+        buffer_data.push(data);
     }
 
     // let mut mesh_info = Vec::new();
@@ -46,7 +93,8 @@ pub fn read_gltf<'a>(
             device,
             pbr_material_factors_bind_group_layout,
             &buffer_data,
-        )));
+            base_dir,
+        )?));
     }
 
     let mut mesh_list = Vec::new();
@@ -63,16 +111,24 @@ pub fn read_gltf<'a>(
         let idx = mesh.index();
         let mesh = crate::model::Mesh {
             name: mesh.name().unwrap_or("mesh").to_string(),
-            primitives: get_dream_primitives_from_gltf_mesh(device, mesh, &buffer_data),
+            primitives: get_dream_primitives_from_gltf_mesh(
+                device,
+                mesh,
+                &buffer_data,
+                materials.len(),
+            )?,
         };
         mesh_map.insert(idx, mesh);
     }
     let mut meshes = Vec::new();
     for i in 0..mesh_map.len() {
-        meshes.push(mesh_map.remove(&i).unwrap());
+        let mesh = mesh_map.remove(&i).ok_or_else(|| {
+            ModelLoadError::InvalidData(format!("mesh {} is not used by any node", i))
+        })?;
+        meshes.push(mesh);
     }
 
-    Model::new(meshes, materials)
+    Ok(Model::new(meshes, materials))
 }
 
 fn process_gltf_child_node<'a>(child_node: gltf::Node<'a>, mesh_list: &mut Vec<Mesh<'a>>) {
@@ -132,63 +188,73 @@ impl mikktspace::Geometry for MeshVerticesAndIndicesContainer {
 fn get_dream_primitives_from_gltf_mesh(
     device: &wgpu::Device,
     mesh: Mesh,
-    buffer_data: &Vec<Vec<u8>>,
-) -> Vec<crate::model::Primitive> {
+    buffer_data: &[Vec<u8>],
+    num_materials: usize,
+) -> Result<Vec<crate::model::Primitive>, ModelLoadError> {
     let mut primitives_result = Vec::new();
     let primitives = mesh.primitives();
+    let mesh_name = mesh.name().unwrap_or("mesh");
     // log::debug!("Number of primitives is {}", primitives.len());
-    primitives.for_each(|primitive| {
+    for primitive in primitives {
         let mut mesh_vertices_and_indices = MeshVerticesAndIndicesContainer {
             vertices: Vec::new(),
             indices: Vec::new(),
         };
 
-        let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
-        if let Some(vertex_attribute) = reader.read_positions() {
-            vertex_attribute.for_each(|vertex| {
-                mesh_vertices_and_indices
-                    .vertices
-                    .push(crate::model::ModelVertex {
-                        position: vertex,
-                        tex_coords: Default::default(),
-                        normal: Default::default(),
-                        tangent: [0.0, 0.0, 0.0, 0.0],
-                        bone_ids: [0, 0, 0, 0],
-                        bone_weights: [0., 0., 0., 0.],
-                    })
-            });
-            log::debug!(
-                "Number of vertices: {:?}",
-                mesh_vertices_and_indices.vertices.len()
-            );
-        }
+        let reader = primitive.reader(|buffer| buffer_data.get(buffer.index()).map(Vec::as_slice));
+        let Some(vertex_attribute) = reader.read_positions() else {
+            return Err(ModelLoadError::InvalidData(format!(
+                "primitive {} of mesh {} has no positions",
+                primitive.index(),
+                mesh_name
+            )));
+        };
+        vertex_attribute.for_each(|vertex| {
+            mesh_vertices_and_indices
+                .vertices
+                .push(crate::model::ModelVertex {
+                    position: vertex,
+                    tex_coords: Default::default(),
+                    normal: Default::default(),
+                    tangent: [0.0, 0.0, 0.0, 0.0],
+                    bone_ids: [0, 0, 0, 0],
+                    bone_weights: [0., 0., 0., 0.],
+                })
+        });
+        log::debug!(
+            "Number of vertices: {:?}",
+            mesh_vertices_and_indices.vertices.len()
+        );
 
         let mut manually_compute_tangents = false;
         if let Some(tangent_attribute) = reader.read_tangents() {
-            let mut tangent_index = 0;
-            tangent_attribute.for_each(|tangent| {
-                mesh_vertices_and_indices.vertices[tangent_index].tangent = tangent;
-
-                tangent_index += 1;
-            });
+            for (vertex, tangent) in mesh_vertices_and_indices
+                .vertices
+                .iter_mut()
+                .zip(tangent_attribute)
+            {
+                vertex.tangent = tangent;
+            }
         } else {
             manually_compute_tangents = true;
         }
         if let Some(normal_attribute) = reader.read_normals() {
-            let mut normal_index = 0;
-            normal_attribute.for_each(|normal| {
-                mesh_vertices_and_indices.vertices[normal_index].normal = normal;
-
-                normal_index += 1;
-            });
+            for (vertex, normal) in mesh_vertices_and_indices
+                .vertices
+                .iter_mut()
+                .zip(normal_attribute)
+            {
+                vertex.normal = normal;
+            }
         }
         if let Some(tex_coord_attribute) = reader.read_tex_coords(0).map(|v| v.into_f32()) {
-            let mut tex_coord_index = 0;
-            tex_coord_attribute.for_each(|tex_coord| {
-                mesh_vertices_and_indices.vertices[tex_coord_index].tex_coords = tex_coord;
-
-                tex_coord_index += 1;
-            });
+            for (vertex, tex_coord) in mesh_vertices_and_indices
+                .vertices
+                .iter_mut()
+                .zip(tex_coord_attribute)
+            {
+                vertex.tex_coords = tex_coord;
+            }
         }
 
         if let Some(indices_raw) = reader.read_indices() {
@@ -196,42 +262,86 @@ fn get_dream_primitives_from_gltf_mesh(
                 mesh_vertices_and_indices.indices.push(idx);
             }
         }
+        let num_vertices = mesh_vertices_and_indices.vertices.len();
+        if mesh_vertices_and_indices.indices.len() % 3 != 0
+            || mesh_vertices_and_indices
+                .indices
+                .iter()
+                .any(|idx| *idx as usize >= num_vertices)
+        {
+            return Err(ModelLoadError::InvalidData(format!(
+                "primitive {} of mesh {} has indices that do not form triangles of its {} vertices",
+                primitive.index(),
+                mesh_name,
+                num_vertices
+            )));
+        }
 
         // joints and weights for vertex skinning / skeletal animation
         // TODO: I'm passing wrong thing as index / set field of read_
-        // let mut is_skinned = false;
+        let mut is_skinned = false;
         if let Some(joints) = reader.read_joints(0) {
-            // is_skinned = true;
-            let mut joint_index = 0;
-            joints.into_u16().for_each(|joint| {
-                mesh_vertices_and_indices.vertices[joint_index].bone_ids = [
+            is_skinned = true;
+            for (vertex, joint) in mesh_vertices_and_indices
+                .vertices
+                .iter_mut()
+                .zip(joints.into_u16())
+            {
+                vertex.bone_ids = [
                     joint[0] as u32,
                     joint[1] as u32,
                     joint[2] as u32,
                     joint[3] as u32,
                 ];
-                joint_index += 1;
-            });
+            }
         }
 
         // TODO: I'm passing wrong thing as index / set field of read_
         if let Some(weights) = reader.read_weights(0) {
-            let mut weight_index = 0;
-            weights.into_f32().for_each(|weight| {
+            for (vertex, weight) in mesh_vertices_and_indices
+                .vertices
+                .iter_mut()
+                .zip(weights.into_f32())
+            {
                 let w1 = weight[0];
                 let w2 = weight[1];
                 let w3 = weight[2];
                 let w4 = weight[3];
                 let w_sum = w1 + w2 + w3 + w4;
                 if w_sum > 0.0 {
-                    mesh_vertices_and_indices.vertices[weight_index].bone_weights =
-                        [w1 / w_sum, w2 / w_sum, w3 / w_sum, w4 / w_sum];
+                    vertex.bone_weights = [w1 / w_sum, w2 / w_sum, w3 / w_sum, w4 / w_sum];
                 } else {
-                    mesh_vertices_and_indices.vertices[weight_index].bone_weights =
-                        [w1, w2, w3, w4];
+                    vertex.bone_weights = [w1, w2, w3, w4];
                 }
-                weight_index += 1;
-            });
+            }
+        }
+
+        // morph targets, the deltas of each target are stored after the deltas of the previous one
+        let mut morph_target_deltas = Vec::new();
+        let mut num_morph_targets = 0;
+        for (positions, normals, tangents) in reader.read_morph_targets() {
+            let mut deltas = vec![MorphTargetDelta::default(); num_vertices];
+            if let Some(positions) = positions {
+                for (delta, position) in deltas.iter_mut().zip(positions) {
+                    delta.position = position;
+                }
+            }
+            if let Some(normals) = normals {
+                for (delta, normal) in deltas.iter_mut().zip(normals) {
+                    delta.normal = normal;
+                }
+            }
+            if let Some(tangents) = tangents {
+                for (delta, tangent) in deltas.iter_mut().zip(tangents) {
+                    delta.tangent = tangent;
+                }
+            }
+            morph_target_deltas.extend(deltas);
+            num_morph_targets += 1;
+        }
+        if morph_target_deltas.is_empty() {
+            // storage buffers cannot be empty
+            morph_target_deltas.push(MorphTargetDelta::default());
         }
 
         let use_mikktspace_algorithm = false;
@@ -279,7 +389,14 @@ fn get_dream_primitives_from_gltf_mesh(
             }
         }
 
-        let mesh_name = mesh.name().expect("No mesh name found");
+        let material = primitive.material().index().unwrap_or(0);
+        if material >= num_materials {
+            return Err(ModelLoadError::InvalidData(format!(
+                "primitive {} of mesh {} has no material",
+                primitive.index(),
+                mesh_name
+            )));
+        }
 
         // TODO: create a new() method for Primitive struct to do all this logic
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -299,9 +416,15 @@ fn get_dream_primitives_from_gltf_mesh(
             label: Some("Primitive Info"),
             contents: bytemuck::cast_slice(&[PrimitiveInfo {
                 num_vertices: mesh_vertices_and_indices.vertices.len() as u32,
+                num_morph_targets,
             }]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+        let morph_target_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{mesh_name} Morph Target Buffer")),
+            contents: bytemuck::cast_slice(&morph_target_deltas),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
         let input: &[u8] = bytemuck::cast_slice(&mesh_vertices_and_indices.vertices);
         let primitive_info_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -328,70 +451,56 @@ fn get_dream_primitives_from_gltf_mesh(
         let vertices_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    count: None,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        },
                     },
-                }],
-            });
-        let skinned_vertices_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    count: None,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        },
                     },
-                }],
+                ],
             });
         let vertex_buffer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("vertices buffer bind group"),
             layout: &vertices_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: vertex_buffer.as_entire_binding(),
-            }],
-        });
-        let mut skinned_vertex_buffer = Some(device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertices Input"),
-                contents: bytemuck::cast_slice(input.clone()),
-                usage: wgpu::BufferUsages::STORAGE
-                    | wgpu::BufferUsages::VERTEX
-                    | wgpu::BufferUsages::COPY_DST
-                    | wgpu::BufferUsages::COPY_SRC,
-            },
-        ));
-        let skinned_vertices_buffer_bind_group =
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("skinning vertices buffer bind group"),
-                layout: &skinned_vertices_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
+            entries: &[
+                wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: skinned_vertex_buffer.as_mut().unwrap().as_entire_binding(),
-                }],
-            });
+                    resource: vertex_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: morph_target_buffer.as_entire_binding(),
+                },
+            ],
+        });
         primitives_result.push(crate::model::Primitive {
             vertex_buffer,
             vertex_buffer_bind_group,
-            skinned_vertex_buffer,
-            skinned_vertices_buffer_bind_group,
+            is_skinned,
+            num_morph_targets,
+            morph_target_buffer,
             primitive_info_buffer,
             primitive_info_bind_group,
             index_buffer,
             num_elements: mesh_vertices_and_indices.indices.len() as u32,
-            material: primitive.material().index().unwrap_or(0),
+            material,
             buffer_length: input.len() as u32,
         });
-    });
-    primitives_result
+    }
+    Ok(primitives_result)
 }
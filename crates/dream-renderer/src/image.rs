@@ -1,6 +1,8 @@
 use crossbeam_channel::{unbounded, Receiver};
 use image::{DynamicImage, ImageFormat, RgbaImage};
 
+use crate::model_load_error::ModelLoadError;
+
 // use dream_tasks::task_pool::get_async_task_pool;
 
 #[derive(Clone, Default)]
@@ -10,56 +12,67 @@ pub struct Image {
     receiver: Option<Receiver<(DynamicImage, RgbaImage)>>,
 }
 
-fn dynamic_image_from_bytes(bytes: &[u8], _label: &str, mime_type: Option<String>) -> DynamicImage {
-    if mime_type.is_none() {
-        image::load_from_memory(bytes).expect("Unable to load image from memory")
-    } else {
-        let mime_type = mime_type.unwrap();
-        if mime_type == "image/png" {
-            // log::warn!("TODO: use png crate for faster image loading");
-            image::load_from_memory_with_format(bytes, ImageFormat::Png)
-                .expect("Unable to decode png image")
-        } else if mime_type == "image/jpeg" {
-            image::load_from_memory_with_format(bytes, ImageFormat::Jpeg)
-                .expect("Unable to decode jpg image")
-        } else {
-            panic!("Unsupported mime_type provided: {}", mime_type);
+fn dynamic_image_from_bytes(
+    bytes: &[u8],
+    label: &str,
+    mime_type: Option<String>,
+) -> Result<DynamicImage, ModelLoadError> {
+    let dynamic_image = match mime_type.as_deref() {
+        None => image::load_from_memory(bytes),
+        // log::warn!("TODO: use png crate for faster image loading");
+        Some("image/png") => image::load_from_memory_with_format(bytes, ImageFormat::Png),
+        Some("image/jpeg") => image::load_from_memory_with_format(bytes, ImageFormat::Jpeg),
+        Some(mime_type) => {
+            return Err(ModelLoadError::InvalidData(format!(
+                "image {} has unsupported mime type {}",
+                label, mime_type
+            )))
         }
-    }
+    };
+    dynamic_image.map_err(|err| {
+        ModelLoadError::InvalidData(format!("unable to decode image {}: {}", label, err))
+    })
 }
 
 pub fn get_texture_bytes_info_from_gltf<'a>(
     texture: gltf::Texture<'a>,
     buffer_data: &[Vec<u8>],
-) -> (Vec<u8>, String, Option<String>) {
+    base_dir: &std::path::Path,
+) -> Result<(Vec<u8>, String, Option<String>), ModelLoadError> {
     let texture_name = texture.name().unwrap_or("No texture name");
     let texture_source = texture.source().source();
     match texture_source {
         gltf::image::Source::View { view, mime_type } => {
-            let parent_buffer_data = &buffer_data[view.buffer().index()];
             let begin = view.offset();
             let end = view.offset() + view.length();
-            let buf_dat = &parent_buffer_data[begin..end];
+            let buf_dat = buffer_data
+                .get(view.buffer().index())
+                .and_then(|parent_buffer_data| parent_buffer_data.get(begin..end))
+                .ok_or_else(|| {
+                    ModelLoadError::InvalidData(format!(
+                        "image of texture {} is outside of its buffer",
+                        texture_name
+                    ))
+                })?;
             let mime_type = Some(mime_type.to_string());
-            (
-                buf_dat.to_vec(),
-                String::from(texture_name),
-                mime_type.clone(),
-            )
+            Ok((buf_dat.to_vec(), String::from(texture_name), mime_type))
         }
-        gltf::image::Source::Uri {
-            uri,
-            mime_type: _mime_type,
-        } => {
-            let bin = dream_fs::fs::read_binary(std::path::PathBuf::from(uri), false)
-                .expect("unable to load binary");
-            let buf_dat: &[u8] = &bin;
-            (buf_dat.to_vec(), String::from(texture_name), None)
+        gltf::image::Source::Uri { uri, mime_type } => {
+            let (bin, uri_mime_type) =
+                dream_fs::fs::read_uri(uri, base_dir).map_err(|err| ModelLoadError::Io {
+                    path: String::from(uri),
+                    message: format!("{:#}", err),
+                })?;
+            // prefer the mime type of a data uri over the one declared by the image
+            let mime_type = uri_mime_type.or(mime_type.map(String::from));
+            Ok((bin, String::from(texture_name), mime_type))
         }
     }
 }
 
 impl Image {
+    /// Decode an image on the task pool, for images that are known to decode such as the
+    /// built-in defaults. An image that cannot be decoded is never loaded.
     pub fn load_from_bytes_threaded(
         &mut self,
         bytes: &[u8],
@@ -72,10 +85,14 @@ impl Image {
         let (sx, rx) = unbounded();
 
         dream_tasks::task_pool::spawn(move || {
-            let dynamic_image = dynamic_image_from_bytes(&bytes, label.as_str(), mime_type);
-            let rgba8 = dynamic_image.to_rgba8();
-            sx.send((dynamic_image, rgba8))
-                .expect("Unable to send dynamic image contents");
+            match dynamic_image_from_bytes(&bytes, label.as_str(), mime_type) {
+                Ok(dynamic_image) => {
+                    let rgba8 = dynamic_image.to_rgba8();
+                    sx.send((dynamic_image, rgba8))
+                        .expect("Unable to send dynamic image contents");
+                }
+                Err(err) => log::error!("{}", err),
+            }
         });
 
         // rayon::spawn(move || {
@@ -88,16 +105,6 @@ impl Image {
         self.receiver = Some(rx);
     }
 
-    pub fn load_from_gltf_texture_threaded(
-        &mut self,
-        texture: gltf::Texture,
-        buffer_data: &[Vec<u8>],
-    ) {
-        let texture = texture.clone();
-        let (bytes, label, mime_type) = get_texture_bytes_info_from_gltf(texture, buffer_data);
-        self.load_from_bytes_threaded(&bytes, label.as_str(), mime_type);
-    }
-
     pub fn update(&mut self) {
         if self.receiver.is_some() {
             if let Some((dynamic_image, rgba8)) = self.receiver.clone().unwrap().try_iter().last() {
@@ -108,19 +115,28 @@ impl Image {
         }
     }
 
-    pub fn load_from_bytes(&mut self, bytes: &[u8], label: &str, mime_type: Option<String>) {
-        self.dynamic_image = Some(dynamic_image_from_bytes(bytes, label, mime_type));
+    pub fn load_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        label: &str,
+        mime_type: Option<String>,
+    ) -> Result<(), ModelLoadError> {
+        self.dynamic_image = Some(dynamic_image_from_bytes(bytes, label, mime_type)?);
         self.update_rgba();
+        Ok(())
     }
 
-    pub async fn load_from_gltf_texture<'a>(
+    /// Decode the image of a texture right away, so that a corrupt image fails the model load
+    pub fn load_from_gltf_texture<'a>(
         &mut self,
         texture: gltf::Texture<'a>,
         buffer_data: &[Vec<u8>],
-    ) {
+        base_dir: &std::path::Path,
+    ) -> Result<(), ModelLoadError> {
         let texture = texture.clone();
-        let (bytes, label, mime_type) = get_texture_bytes_info_from_gltf(texture, buffer_data);
-        self.load_from_bytes(&bytes, label.as_str(), mime_type);
+        let (bytes, label, mime_type) =
+            get_texture_bytes_info_from_gltf(texture, buffer_data, base_dir)?;
+        self.load_from_bytes(&bytes, label.as_str(), mime_type)
     }
 
     pub fn to_rgba8(&self) -> RgbaImage {
@@ -130,6 +146,11 @@ impl Image {
             .clone()
     }
 
+    /// Width and height of a loaded image
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.rgba8.as_ref().map(|rgba8| rgba8.dimensions())
+    }
+
     pub fn loaded(&self) -> bool {
         self.dynamic_image.is_some()
     }
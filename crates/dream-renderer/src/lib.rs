@@ -28,7 +28,7 @@ pub mod instance;
 pub mod lights;
 pub mod material;
 pub mod model;
-pub mod path_not_found_error;
+pub mod model_load_error;
 pub mod pbr_material_tech;
 pub mod render_map_key;
 pub mod render_storage;
@@ -2,6 +2,8 @@ use wgpu::util::DeviceExt;
 
 use dream_math::Vector3;
 
+use crate::render_storage::ALL_RENDER_LAYERS;
+
 #[derive(Debug)]
 pub struct RendererLight {
     pub(crate) position: Vector3<f32>,
@@ -10,6 +12,11 @@ pub struct RendererLight {
     pub(crate) light_type: u32,
     pub(crate) direction: Vector3<f32>,
     pub(crate) cast_shadow: bool,
+    /// Cone of spot lights, angles in radians from the direction
+    pub(crate) inner_cone_angle: f32,
+    pub(crate) outer_cone_angle: f32,
+    /// Render layers of the meshes this light casts shadows from
+    pub(crate) layer_mask: u32,
 }
 
 pub struct Lights {
@@ -82,6 +89,9 @@ pub struct LightData {
     pub _padding: u32,
     pub direction: [f32; 3],
     pub light_type: u32,
+    pub inner_cone_cos: f32,
+    pub outer_cone_cos: f32,
+    pub _padding2: [u32; 2],
 }
 
 #[repr(C)]
@@ -99,6 +109,9 @@ impl LightsUniform {
             light_type: 0,
             direction: Vector3::new(1.0, 0.0, 0.0),
             cast_shadow: false,
+            inner_cone_angle: 0.0,
+            outer_cone_angle: std::f32::consts::FRAC_PI_4,
+            layer_mask: ALL_RENDER_LAYERS,
         };
         for idx in 0..self.lights.len() {
             self.lights[idx].light_type = renderer_lights
@@ -121,6 +134,16 @@ impl LightsUniform {
                 .unwrap_or(&default_light)
                 .direction
                 .into();
+            self.lights[idx].inner_cone_cos = renderer_lights
+                .get(idx)
+                .unwrap_or(&default_light)
+                .inner_cone_angle
+                .cos();
+            self.lights[idx].outer_cone_cos = renderer_lights
+                .get(idx)
+                .unwrap_or(&default_light)
+                .outer_cone_angle
+                .cos();
         }
     }
 }
@@ -136,6 +159,9 @@ impl Default for LightsUniform {
                     color: [0., 0., 0.],
                     direction: [1., 0., 0.],
                     _padding: 0,
+                    inner_cone_cos: 1.0,
+                    outer_cone_cos: std::f32::consts::FRAC_1_SQRT_2,
+                    _padding2: [0, 0],
                 },
                 LightData {
                     light_type: 0,
@@ -144,6 +170,9 @@ impl Default for LightsUniform {
                     color: [0., 0., 0.],
                     direction: [1., 0., 0.],
                     _padding: 0,
+                    inner_cone_cos: 1.0,
+                    outer_cone_cos: std::f32::consts::FRAC_1_SQRT_2,
+                    _padding2: [0, 0],
                 },
                 LightData {
                     light_type: 0,
@@ -152,6 +181,9 @@ impl Default for LightsUniform {
                     color: [0., 0., 0.],
                     direction: [1., 0., 0.],
                     _padding: 0,
+                    inner_cone_cos: 1.0,
+                    outer_cone_cos: std::f32::consts::FRAC_1_SQRT_2,
+                    _padding2: [0, 0],
                 },
                 LightData {
                     light_type: 0,
@@ -160,6 +192,9 @@ impl Default for LightsUniform {
                     color: [0., 0., 0.],
                     direction: [1., 0., 0.],
                     _padding: 0,
+                    inner_cone_cos: 1.0,
+                    outer_cone_cos: std::f32::consts::FRAC_1_SQRT_2,
+                    _padding2: [0, 0],
                 },
             ],
         }
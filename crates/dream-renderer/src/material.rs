@@ -1,6 +1,7 @@
 use wgpu::util::DeviceExt;
 
 use crate::image::Image;
+use crate::model_load_error::ModelLoadError;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -54,6 +55,28 @@ impl From<gltf::material::AlphaMode> for AlphaBlendMode {
     }
 }
 
+/// Decode the image of a texture while the model is loaded, images that cannot be turned into a
+/// texture fail the load so that the model is drawn as the placeholder
+fn load_texture_image(
+    texture: gltf::Texture,
+    device: &wgpu::Device,
+    buffer_data: &[Vec<u8>],
+    base_dir: &std::path::Path,
+) -> Result<Image, ModelLoadError> {
+    let texture_name = texture.name().unwrap_or("No texture name").to_owned();
+    let mut image = Image::default();
+    image.load_from_gltf_texture(texture, buffer_data, base_dir)?;
+    let (width, height) = image.dimensions().unwrap_or_default();
+    let max_dimension = device.limits().max_texture_dimension_2d;
+    if width == 0 || height == 0 || width > max_dimension || height > max_dimension {
+        return Err(ModelLoadError::InvalidData(format!(
+            "image of texture {} is {}x{}, textures must be between 1x1 and {}x{}",
+            texture_name, width, height, max_dimension, max_dimension
+        )));
+    }
+    Ok(image)
+}
+
 pub struct Material {
     // pub pbr_material_factors_bind_group: wgpu::BindGroup,
     pub pbr_material_textures_bind_group: Option<wgpu::BindGroup>,
@@ -79,7 +102,8 @@ impl Material {
         device: &wgpu::Device,
         pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
         buffer_data: &[Vec<u8>],
-    ) -> Self {
+        base_dir: &std::path::Path,
+    ) -> Result<Self, ModelLoadError> {
         let pbr_properties = material.pbr_metallic_roughness();
 
         // get base color texture
@@ -90,8 +114,8 @@ impl Material {
                 base_color_image.load_from_bytes_threaded(bytes, "default", None);
             }
             Some(texture_info) => {
-                base_color_image
-                    .load_from_gltf_texture_threaded(texture_info.texture(), buffer_data);
+                base_color_image =
+                    load_texture_image(texture_info.texture(), device, buffer_data, base_dir)?;
             }
         }
 
@@ -103,8 +127,8 @@ impl Material {
                 metallic_roughness_image.load_from_bytes_threaded(bytes, "default", None);
             }
             Some(texture_info) => {
-                metallic_roughness_image
-                    .load_from_gltf_texture_threaded(texture_info.texture(), buffer_data);
+                metallic_roughness_image =
+                    load_texture_image(texture_info.texture(), device, buffer_data, base_dir)?;
             }
         }
 
@@ -116,8 +140,8 @@ impl Material {
                 normal_map_image.load_from_bytes_threaded(bytes, "default", None);
             }
             Some(texture_info) => {
-                normal_map_image
-                    .load_from_gltf_texture_threaded(texture_info.texture(), buffer_data);
+                normal_map_image =
+                    load_texture_image(texture_info.texture(), device, buffer_data, base_dir)?;
             }
         }
 
@@ -129,7 +153,8 @@ impl Material {
                 emissive_image.load_from_bytes_threaded(bytes, "default", None);
             }
             Some(texture_info) => {
-                emissive_image.load_from_gltf_texture_threaded(texture_info.texture(), buffer_data);
+                emissive_image =
+                    load_texture_image(texture_info.texture(), device, buffer_data, base_dir)?;
             }
         }
 
@@ -141,8 +166,8 @@ impl Material {
                 occlusion_image.load_from_bytes_threaded(bytes, "default", None);
             }
             Some(texture_info) => {
-                occlusion_image
-                    .load_from_gltf_texture_threaded(texture_info.texture(), buffer_data);
+                occlusion_image =
+                    load_texture_image(texture_info.texture(), device, buffer_data, base_dir)?;
             }
         }
 
@@ -175,7 +200,7 @@ impl Material {
         //     });
 
         // define this struct
-        Self {
+        Ok(Self {
             pbr_material_textures_bind_group: None,
             factor_base_color: material_factors_uniform.base_color.into(),
             factor_emissive: material_factors_uniform.emissive.into(),
@@ -191,7 +216,7 @@ impl Material {
             emissive_image,
             occlusion_image,
             pbr_mat_buffer,
-        }
+        })
     }
 
     pub fn update_textures(
@@ -199,29 +224,29 @@ impl Material {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         pbr_material_textures_bind_group_layout: &wgpu::BindGroupLayout,
-    ) {
+    ) -> Result<(), ModelLoadError> {
         if self.pbr_material_textures_bind_group.is_some() {
-            return;
+            return Ok(());
         }
 
         if !self.base_color_image.loaded() {
-            return;
+            return Ok(());
         }
 
         if !self.metallic_roughness_image.loaded() {
-            return;
+            return Ok(());
         }
 
         if !self.normal_map_image.loaded() {
-            return;
+            return Ok(());
         }
 
         if !self.emissive_image.loaded() {
-            return;
+            return Ok(());
         }
 
         if !self.occlusion_image.loaded() {
-            return;
+            return Ok(());
         }
 
         // load base color image
@@ -235,7 +260,9 @@ impl Material {
             Some(wgpu::FilterMode::Linear),
             Some(wgpu::TextureFormat::Rgba8UnormSrgb),
         )
-        .expect("Unable to load base color texture");
+        .map_err(|err| {
+            ModelLoadError::InvalidData(format!("unable to create base color texture: {}", err))
+        })?;
 
         // load metallic image
         let rgba_image = self.metallic_roughness_image.to_rgba8();
@@ -248,7 +275,12 @@ impl Material {
             Some(wgpu::FilterMode::Linear),
             Some(wgpu::TextureFormat::Rgba8Unorm),
         )
-        .expect("Unable to load metallic roughness texture");
+        .map_err(|err| {
+            ModelLoadError::InvalidData(format!(
+                "unable to create metallic roughness texture: {}",
+                err
+            ))
+        })?;
 
         // load normal map image
         let rgba_image = self.normal_map_image.to_rgba8();
@@ -261,7 +293,9 @@ impl Material {
             Some(wgpu::FilterMode::Linear),
             Some(wgpu::TextureFormat::Rgba8Unorm),
         )
-        .expect("Unable to load normal map texture");
+        .map_err(|err| {
+            ModelLoadError::InvalidData(format!("unable to create normal map texture: {}", err))
+        })?;
 
         // load emissive image
         let rgba_image = self.emissive_image.to_rgba8();
@@ -274,7 +308,9 @@ impl Material {
             Some(wgpu::FilterMode::Linear),
             Some(wgpu::TextureFormat::Rgba8UnormSrgb),
         )
-        .expect("Unable to load emissive texture");
+        .map_err(|err| {
+            ModelLoadError::InvalidData(format!("unable to create emissive texture: {}", err))
+        })?;
 
         // load occlusion image
         let rgba_image = self.occlusion_image.to_rgba8();
@@ -287,7 +323,9 @@ impl Material {
             Some(wgpu::FilterMode::Linear),
             Some(wgpu::TextureFormat::Rgba8Unorm),
         )
-        .expect("Unable to load occlusion texture");
+        .map_err(|err| {
+            ModelLoadError::InvalidData(format!("unable to create occlusion texture: {}", err))
+        })?;
 
         self.pbr_material_textures_bind_group =
             Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -344,6 +382,7 @@ impl Material {
                 ],
                 label: Some("pbr_textures_bind_group"),
             }));
+        Ok(())
     }
 
     pub fn update_images(&mut self) {
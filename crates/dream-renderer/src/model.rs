@@ -10,6 +10,16 @@ pub trait Vertex {
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PrimitiveInfo {
     pub(crate) num_vertices: u32,
+    pub(crate) num_morph_targets: u32,
+}
+
+/// Difference a morph target makes to a vertex, scaled by the weight of the target
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MorphTargetDelta {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 3],
 }
 
 #[repr(C)]
@@ -74,8 +84,11 @@ pub struct Primitive {
     pub primitive_info_buffer: wgpu::Buffer,
     pub primitive_info_bind_group: wgpu::BindGroup,
     pub vertex_buffer_bind_group: wgpu::BindGroup,
-    pub skinned_vertex_buffer: Option<wgpu::Buffer>,
-    pub skinned_vertices_buffer_bind_group: wgpu::BindGroup,
+    /// whether the vertices have joints, skinned vertices are computed per armature instance
+    pub is_skinned: bool,
+    /// deltas of the morph targets are computed per mesh entity with the weights of the entity
+    pub num_morph_targets: u32,
+    pub morph_target_buffer: wgpu::Buffer,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_elements: u32,
@@ -100,22 +113,27 @@ impl Model {
 }
 
 pub trait DrawModel<'a> {
-    fn draw_primitive_instanced(&mut self, primitive: &'a Primitive, instances: Range<u32>);
+    fn draw_primitive_instanced(
+        &mut self,
+        primitive: &'a Primitive,
+        skinned_vertices: Option<&'a wgpu::Buffer>,
+        instances: Range<u32>,
+    );
 }
 
 impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
 where
     'b: 'a,
 {
-    fn draw_primitive_instanced(&mut self, primitive: &'b Primitive, instances: Range<u32>) {
-        if primitive.skinned_vertex_buffer.is_some() {
-            self.set_vertex_buffer(
-                0,
-                primitive.skinned_vertex_buffer.as_ref().unwrap().slice(..),
-            );
-        } else {
-            self.set_vertex_buffer(0, primitive.vertex_buffer.slice(..));
-        }
+    fn draw_primitive_instanced(
+        &mut self,
+        primitive: &'b Primitive,
+        skinned_vertices: Option<&'b wgpu::Buffer>,
+        instances: Range<u32>,
+    ) {
+        // meshes without an armature are drawn in their bind pose
+        let vertex_buffer = skinned_vertices.unwrap_or(&primitive.vertex_buffer);
+        self.set_vertex_buffer(0, vertex_buffer.slice(..));
         self.set_index_buffer(primitive.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         self.draw_indexed(0..primitive.num_elements, 0, instances);
     }
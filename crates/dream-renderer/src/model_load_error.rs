@@ -0,0 +1,24 @@
+/// Reason the renderer was unable to load a model
+#[derive(Debug, Clone)]
+pub enum ModelLoadError {
+    /// The model file or one of the buffers or images it references could not be read
+    Io { path: String, message: String },
+    /// The file is not a valid glTF document
+    Parse(String),
+    /// The document is valid but its contents cannot be turned into a model
+    InvalidData(String),
+}
+
+impl std::fmt::Display for ModelLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ModelLoadError::Io { path, message } => {
+                write!(f, "unable to read {}: {}", path, message)
+            }
+            ModelLoadError::Parse(message) => write!(f, "unable to parse glTF: {}", message),
+            ModelLoadError::InvalidData(message) => write!(f, "invalid model data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ModelLoadError {}
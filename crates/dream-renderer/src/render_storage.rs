@@ -3,25 +3,92 @@ use wgpu::util::DeviceExt;
 use crate::gltf_loader;
 use crate::instance::Instance;
 use crate::model::Model;
-use crate::path_not_found_error::PathNotFoundError;
+use crate::model_load_error::ModelLoadError;
+use crate::skinning::SkinnedVertices;
+
+/// Guid under which the model drawn in place of models that failed to load is stored
+pub const PLACEHOLDER_MODEL_GUID: &str = "placeholder";
+
+/// Layer mask that matches every render layer, default mask of cameras and lights
+pub const ALL_RENDER_LAYERS: u32 = u32::MAX;
+
+/// Entity of one of the scenes that are drawn, runtime ids are only unique within their scene
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct EntityKey {
+    /// identity of the scene the entity belongs to, e.g. the address of the shared scene
+    pub scene: usize,
+    pub runtime_id: u64,
+}
 
 #[derive(Hash, PartialEq, Eq, Clone)]
 pub struct RenderMapKey {
     pub model_guid: String,
     pub mesh_index: i32,
+    /// Render layers of the instances, meshes on different layers are batched separately
+    pub layers: u32,
+    /// Root bone of the armature that poses the mesh, each armature instance gets its own
+    /// skinned vertices
+    pub armature: Option<EntityKey>,
+    /// Mesh entity whose morph weights deform the mesh
+    pub morph_weights: Option<EntityKey>,
 }
 
+impl RenderMapKey {
+    /// Whether any of the layers of this key are in a camera or light layer mask
+    pub fn is_in_layer_mask(&self, layer_mask: u32) -> bool {
+        self.layers & layer_mask != 0
+    }
+}
+
+#[derive(Hash, PartialEq, Eq, Clone)]
+pub struct SkinnedPrimitiveKey {
+    pub render_map_key: RenderMapKey,
+    pub primitive_index: usize,
+}
+
+#[derive(Default)]
 pub struct RenderStorage {
     pub model_guids: std::collections::HashMap<String, Box<Model>>,
     pub render_map: std::collections::HashMap<RenderMapKey, Vec<Instance>>,
     pub instance_buffer_map: std::collections::HashMap<RenderMapKey, wgpu::Buffer>,
+    pub skinned_vertices_map: std::collections::HashMap<SkinnedPrimitiveKey, SkinnedVertices>,
+    /// Models that failed to load, they are drawn as the placeholder model
+    pub failed_models: std::collections::HashMap<String, ModelLoadError>,
 }
 
 impl RenderStorage {
-    pub fn queue_for_drawing(&mut self, model_guid: &str, mesh_index: i32, model_mat: Instance) {
+    /// Meshes queued for drawing on any of the layers of a camera or light layer mask
+    pub fn meshes_in_layers(
+        &self,
+        layer_mask: u32,
+    ) -> impl Iterator<Item = (&RenderMapKey, &Vec<Instance>)> {
+        self.render_map
+            .iter()
+            .filter(move |(render_map_key, _)| render_map_key.is_in_layer_mask(layer_mask))
+    }
+
+    pub fn queue_for_drawing(
+        &mut self,
+        model_guid: &str,
+        mesh_index: i32,
+        layers: u32,
+        armature: Option<EntityKey>,
+        morph_weights: Option<EntityKey>,
+        model_mat: Instance,
+    ) {
+        // draw the placeholder in its bind pose for every mesh of a model that failed to load
+        let (model_guid, mesh_index, armature, morph_weights) =
+            if self.failed_models.contains_key(model_guid) {
+                (PLACEHOLDER_MODEL_GUID, 0, None, None)
+            } else {
+                (model_guid, mesh_index, armature, morph_weights)
+            };
         let key = RenderMapKey {
             model_guid: model_guid.parse().unwrap(),
             mesh_index,
+            layers,
+            armature,
+            morph_weights,
         };
         if let std::collections::hash_map::Entry::Vacant(e) = self.render_map.entry(key) {
             // create new array
@@ -30,6 +97,9 @@ impl RenderStorage {
             let key = RenderMapKey {
                 model_guid: model_guid.parse().unwrap(),
                 mesh_index,
+                layers,
+                armature,
+                morph_weights,
             };
             // add to existing array
             let current_vec = &mut self.render_map.get_mut(&key).unwrap();
@@ -37,8 +107,27 @@ impl RenderStorage {
         }
     }
 
+    /// Vertices of a primitive posed by the armature and morph weights of the key, None when it
+    /// is drawn in its bind pose
+    pub fn get_skinned_vertices(
+        &self,
+        render_map_key: &RenderMapKey,
+        primitive_index: usize,
+    ) -> Option<&wgpu::Buffer> {
+        if render_map_key.armature.is_none() && render_map_key.morph_weights.is_none() {
+            return None;
+        }
+        self.skinned_vertices_map
+            .get(&SkinnedPrimitiveKey {
+                render_map_key: render_map_key.clone(),
+                primitive_index,
+            })
+            .map(|skinned_vertices| &skinned_vertices.buffer)
+    }
+
+    /// Whether a model was loaded, or failed to load and should not be loaded again
     pub fn is_model_stored(&self, model_guid: &str) -> bool {
-        self.model_guids.contains_key(model_guid)
+        self.model_guids.contains_key(model_guid) || self.failed_models.contains_key(model_guid)
     }
 
     pub fn store_model(
@@ -47,26 +136,53 @@ impl RenderStorage {
         model_path: &str,
         device: &wgpu::Device,
         pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
-    ) -> Result<String, PathNotFoundError> {
-        let model_guid;
-        if model_guid_in.is_some() {
-            model_guid = model_guid_in.unwrap();
-        } else {
-            // TODO: auto-generate guid
-            todo!();
-        }
+    ) -> Result<String, ModelLoadError> {
+        // TODO: auto-generate guid
+        let model_guid = model_guid_in.ok_or_else(|| {
+            ModelLoadError::InvalidData(format!("no guid given for model {}", model_path))
+        })?;
         log::debug!("Storing model {} with guid {}", model_path, model_guid);
-        let model =
-            gltf_loader::read_gltf(model_path, device, pbr_material_factors_bind_group_layout);
-        self.model_guids
-            .insert(model_guid.parse().unwrap(), Box::new(model));
-        Ok(str::parse(model_guid).unwrap())
+        match gltf_loader::read_gltf(model_path, device, pbr_material_factors_bind_group_layout) {
+            Ok(model) => {
+                self.failed_models.remove(model_guid);
+                self.model_guids
+                    .insert(model_guid.parse().unwrap(), Box::new(model));
+                Ok(str::parse(model_guid).unwrap())
+            }
+            Err(err) => {
+                self.mark_model_failed(
+                    model_guid,
+                    err.clone(),
+                    device,
+                    pbr_material_factors_bind_group_layout,
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Draw a model as the placeholder from now on
+    fn mark_model_failed(
+        &mut self,
+        model_guid: &str,
+        err: ModelLoadError,
+        device: &wgpu::Device,
+        pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
+    ) {
+        if !self.model_guids.contains_key(PLACEHOLDER_MODEL_GUID) {
+            let placeholder =
+                gltf_loader::read_placeholder_gltf(device, pbr_material_factors_bind_group_layout);
+            self.model_guids
+                .insert(String::from(PLACEHOLDER_MODEL_GUID), Box::new(placeholder));
+        }
+        self.failed_models.insert(String::from(model_guid), err);
     }
 
     pub fn update_mesh_instance_buffer_and_materials(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        pbr_material_factors_bind_group_layout: &wgpu::BindGroupLayout,
         pbr_material_textures_bind_group_layout: &wgpu::BindGroupLayout,
     ) {
         // update internal meshes and materials
@@ -101,6 +217,7 @@ impl RenderStorage {
 
         // TODO: combine this with loop below to make things more concise
         // update materials
+        let mut failed_models = Vec::new();
         for (render_map_key, _transforms) in &self.render_map {
             let model_map = &mut self.model_guids;
             // TODO: use Arc<[T]> for faster clone https://www.youtube.com/watch?v=A4cKi7PTJSs&ab_channel=LoganSmith
@@ -122,13 +239,25 @@ impl RenderStorage {
                     .expect("No material at index");
                 if !material.loaded() {
                     material.update_images();
-                    material.update_textures(
+                    if let Err(err) = material.update_textures(
                         device,
                         queue,
                         pbr_material_textures_bind_group_layout,
-                    );
+                    ) {
+                        failed_models.push((model_guid.clone(), err));
+                    }
                 }
             }
         }
+        // the model is drawn as the placeholder from the next frame on
+        for (model_guid, err) in failed_models {
+            log::error!("Unable to load model {}: {}", model_guid, err);
+            self.mark_model_failed(
+                &model_guid,
+                err,
+                device,
+                pbr_material_factors_bind_group_layout,
+            );
+        }
     }
 }
@@ -32,9 +32,9 @@ use crate::hdr_tech::HdrTech;
 use crate::instance::Instance;
 use crate::lights::{Lights, RendererLight};
 use crate::material::Material;
-use crate::path_not_found_error::PathNotFoundError;
+use crate::model_load_error::ModelLoadError;
 use crate::pbr_material_tech::PbrMaterialTech;
-use crate::render_storage::RenderStorage;
+use crate::render_storage::{EntityKey, RenderStorage, ALL_RENDER_LAYERS};
 use crate::shadow_tech::ShadowTech;
 use crate::skinning::SkinningTech;
 use crate::{camera, texture};
@@ -268,11 +268,7 @@ impl RendererWgpu {
         let bloom_tech = BloomTech::new(&device, config.width, config.height, &frame_texture);
 
         // storage for all 3D mesh data and positions
-        let render_storage = RenderStorage {
-            model_guids: Default::default(),
-            render_map: Default::default(),
-            instance_buffer_map: Default::default(),
-        };
+        let render_storage = RenderStorage::default();
 
         Self {
             surface,
@@ -376,6 +372,10 @@ impl RendererWgpu {
             .update_mesh_instance_buffer_and_materials(
                 &self.device,
                 &self.queue,
+                // the same layout models are read with in store_model
+                &self
+                    .pbr_material_tech
+                    .pbr_material_textures_bind_group_layout,
                 &self
                     .pbr_material_tech
                     .pbr_material_textures_bind_group_layout,
@@ -385,11 +385,16 @@ impl RendererWgpu {
         self.lights.update_light_buffer(&self.device, &self.queue);
 
         // update bones buffer
-        self.skinning_tech.update_all_bones_buffer(&self.queue);
+        self.skinning_tech
+            .update_all_bones_buffer(&self.device, &self.queue);
 
         // use compute shader to calculate new vertices after animation transformations
-        self.skinning_tech
-            .compute_shader_update_vertices(&mut encoder, &mut self.render_storage);
+        self.skinning_tech.compute_shader_update_vertices(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &mut self.render_storage,
+        );
 
         // figure out shadows
         self.shadow_tech.render_shadow_depth_buffers(
@@ -409,6 +414,7 @@ impl RendererWgpu {
             &self.render_storage,
             &self.camera_light_bind_group,
             |material: &Material| material.factor_alpha >= 1.0,
+            self.camera.layer_mask,
         );
 
         // combine gbuffers into one final texture result
@@ -430,6 +436,7 @@ impl RendererWgpu {
             &self.camera_light_bind_group,
             &self.shadow_tech,
             |material: &Material| material.factor_alpha < 1.0,
+            self.camera.layer_mask,
         );
 
         // generate bloom texture
@@ -455,10 +462,27 @@ impl RendererWgpu {
     ///
     /// * `model_guid`
     /// * `mesh_index`
+    /// * `layers` - render layers of the mesh, see `set_camera_layer_mask`
+    /// * `armature` - root bone that poses the mesh, see `set_bone_transform`
+    /// * `morph_weights` - entity whose weights deform the mesh, see `set_morph_weights`
     /// * `model_mat`
-    pub fn draw_mesh(&mut self, model_guid: &str, mesh_index: i32, model_mat: Instance) {
-        self.render_storage
-            .queue_for_drawing(model_guid, mesh_index, model_mat);
+    pub fn draw_mesh(
+        &mut self,
+        model_guid: &str,
+        mesh_index: i32,
+        layers: u32,
+        armature: Option<EntityKey>,
+        morph_weights: Option<EntityKey>,
+        model_mat: Instance,
+    ) {
+        self.render_storage.queue_for_drawing(
+            model_guid,
+            mesh_index,
+            layers,
+            armature,
+            morph_weights,
+            model_mat,
+        );
     }
 
     /// User-facing API to draw a light at a specific position and color
@@ -467,6 +491,9 @@ impl RendererWgpu {
     ///
     /// * `position`
     /// * `color`
+    /// * `cone_angles` - inner and outer cone angles of spot lights in radians
+    /// * `layer_mask` - render layers of the meshes that cast shadows from this light
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_light(
         &mut self,
         light_type: u32,
@@ -475,6 +502,8 @@ impl RendererWgpu {
         radius: f32,
         direction: Vector3<f32>,
         cast_shadow: bool,
+        cone_angles: (f32, f32),
+        layer_mask: u32,
     ) {
         self.lights.renderer_lights.push(RendererLight {
             position,
@@ -483,10 +512,14 @@ impl RendererWgpu {
             light_type,
             direction,
             cast_shadow,
+            inner_cone_angle: cone_angles.0,
+            outer_cone_angle: cone_angles.1,
+            layer_mask,
         });
     }
 
-    /// User-facing API to store a model and associate it with a guid
+    /// User-facing API to store a model and associate it with a guid, a model that fails to load
+    /// is drawn as a placeholder and is not loaded again
     ///
     /// # Arguments
     ///
@@ -496,7 +529,7 @@ impl RendererWgpu {
         &mut self,
         model_guid_in: Option<&str>,
         model_path: &str,
-    ) -> Result<String, PathNotFoundError> {
+    ) -> Result<String, ModelLoadError> {
         self.render_storage.store_model(
             model_guid_in,
             model_path,
@@ -516,22 +549,56 @@ impl RendererWgpu {
         self.render_storage.is_model_stored(model_guid)
     }
 
+    /// User-facing API to get the error of a model that failed to load
+    ///
+    /// # Arguments
+    ///
+    /// * `model_guid`
+    pub fn get_model_load_error(&self, model_guid: &str) -> Option<&ModelLoadError> {
+        self.render_storage.failed_models.get(model_guid)
+    }
+
     /// User-facing API to remove all models, meshes, and instance buffers
     pub fn clear(&mut self) {
         self.render_storage.render_map.clear();
         self.lights.renderer_lights.clear();
+        // a frame without a scene camera sees every layer
+        self.camera.layer_mask = ALL_RENDER_LAYERS;
     }
 
-    pub fn set_bone_transform(&mut self, bone_id: u32, mat: dream_math::Matrix4<f32>) {
-        // phase 2 (this will allow u to model the same instance of a model in different ways)
-        // TODO: the entity ID of the root bone tells us which armature we are using
-        // TODO: associate bones for that armature
-        // todo!();
-        self.skinning_tech.update_bone(bone_id, mat);
+    /// User-facing API to pose a bone of an armature instance
+    ///
+    /// # Arguments
+    ///
+    /// * `armature` - root bone entity, each armature has its own bone palette
+    /// * `bone_id` - index of the joint in the skin
+    /// * `mat`
+    pub fn set_bone_transform(
+        &mut self,
+        armature: EntityKey,
+        bone_id: u32,
+        mat: dream_math::Matrix4<f32>,
+    ) {
+        self.skinning_tech.update_bone(armature, bone_id, mat);
+    }
+
+    /// User-facing API to set the weights of the morph targets of a mesh entity for this frame
+    ///
+    /// # Arguments
+    ///
+    /// * `entity` - mesh entity, passed to `draw_mesh` as `morph_weights`
+    /// * `weights` - one weight per morph target
+    pub fn set_morph_weights(&mut self, entity: EntityKey, weights: &[f32]) {
+        self.skinning_tech.update_morph_weights(entity, weights);
     }
 
     pub fn set_camera(&mut self, position: Point3<f32>, orientation: UnitQuaternion<f32>) {
         self.camera
             .set_position_and_orientation(&self.queue, position, orientation);
     }
+
+    /// Only draw meshes on these render layers from the camera
+    pub fn set_camera_layer_mask(&mut self, layer_mask: u32) {
+        self.camera.layer_mask = layer_mask;
+    }
 }
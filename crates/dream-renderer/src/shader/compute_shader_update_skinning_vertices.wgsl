@@ -1,7 +1,15 @@
 //include:model.wgsl
 
 struct PrimitiveInfo {
-  num_vertices: u32
+  num_vertices: u32,
+  num_morph_targets: u32
+};
+
+// position, normal and tangent deltas of a vertex for a morph target
+struct MorphTargetDelta {
+  px: f32, py: f32, pz: f32,
+  nx: f32, ny: f32, nz: f32,
+  tx: f32, ty: f32, tz: f32
 };
 
 // Vertex shader
@@ -11,14 +19,18 @@ var<uniform> primitiveInfo: PrimitiveInfo;
 var<storage, read> finalBonesMatrices: array<mat4x4<f32>>;
 @group(2) @binding(0)
 var<storage, read> vertices: array<f32>;
+@group(2) @binding(1)
+var<storage, read> morphTargets: array<MorphTargetDelta>;
 @group(3) @binding(0)
 var<storage, read_write> skinned_vertices: array<f32>;
+@group(3) @binding(1)
+var<storage, read> morphWeights: array<f32>;
 
 @compute @workgroup_size(64)
 fn cs_main(@builtin(global_invocation_id) global_invocation_id : vec3<u32>) {
     let idx = global_invocation_id.x;
 
-    if (idx > primitiveInfo.num_vertices) {
+    if (idx >= primitiveInfo.num_vertices) {
         return;
     }
 
@@ -94,6 +106,18 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id : vec3<u32>) {
     var nrm = vec3<f32>(nx, ny, nz);
     var tn  = vec3<f32>(tx, ty, tz);
 
+    // morph targets are applied before skinning
+    for (var t = u32(0); t < primitiveInfo.num_morph_targets; t++) {
+        let weight = morphWeights[t];
+        if (weight == 0.0) {
+            continue;
+        }
+        let delta = morphTargets[t * primitiveInfo.num_vertices + idx];
+        pos += vec4<f32>(delta.px, delta.py, delta.pz, 0.0) * weight;
+        nrm += vec3<f32>(delta.nx, delta.ny, delta.nz) * weight;
+        tn += vec3<f32>(delta.tx, delta.ty, delta.tz) * weight;
+    }
+
     var totalPosition = vec4<f32>(0.0);
     var totalNormal = vec3<f32>(0.0);
     var totalTangent = vec3<f32>(0.0);
@@ -110,13 +134,13 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id : vec3<u32>) {
             break;
         }
 
-        var localPosition: vec4<f32> = finalBonesMatrices[boneIds[i]] * vec4(px, py, pz, 1.0f) * weights[i];
+        var localPosition: vec4<f32> = finalBonesMatrices[boneIds[i]] * pos * weights[i];
         totalPosition += localPosition;
 
-        var localNormal: vec3<f32> = (finalBonesMatrices[boneIds[i]] * vec4(nx, ny, nz, 0.0f)).xyz * weights[i];
+        var localNormal: vec3<f32> = (finalBonesMatrices[boneIds[i]] * vec4(nrm, 0.0f)).xyz * weights[i];
         totalNormal += localNormal ;
 
-        var localTangent: vec3<f32> = (finalBonesMatrices[boneIds[i]] * vec4(tx, ty, tz, 0.0)).xyz * weights[i];
+        var localTangent: vec3<f32> = (finalBonesMatrices[boneIds[i]] * vec4(tn, 0.0)).xyz * weights[i];
         totalTangent += localTangent;
     }
 
@@ -1,6 +1,7 @@
 const PI: f32 = 3.14159265359;
 const LIGHT_TYPE_POINT: u32 = 0u;
 const LIGHT_TYPE_DIRECTIONAL: u32 = 1u;
+const LIGHT_TYPE_SPOT: u32 = 2u;
 
 struct MaterialFactors {
     base_color: vec3<f32>,
@@ -19,6 +20,9 @@ struct Light {
     _padding: u32,
     direction: vec3<f32>,
     light_type: u32,
+    inner_cone_cos: f32,
+    outer_cone_cos: f32,
+    _padding2: vec2<u32>,
 }
 
 struct LightsUniform {
@@ -80,7 +84,7 @@ fn compute_final_color(shadow_visibility: f32, world_position: vec3<f32>, camera
         // calculate per-light radiance
         let V: vec3<f32> = normalize(camera_position - world_position);
         var L: vec3<f32> = vec3(0.0);
-        if (light.light_type == LIGHT_TYPE_POINT) {
+        if (light.light_type == LIGHT_TYPE_POINT || light.light_type == LIGHT_TYPE_SPOT) {
             L = normalize(lightPosition - world_position);
         }
         if (light.light_type == LIGHT_TYPE_DIRECTIONAL) {
@@ -98,6 +102,14 @@ fn compute_final_color(shadow_visibility: f32, world_position: vec3<f32>, camera
             let attenuation: f32 = 1.0;
             radiance = lightColor * attenuation;
         }
+        if (light.light_type == LIGHT_TYPE_SPOT) {
+            let distance: f32 = length(lightPosition - world_position);
+            let attenuation: f32 = 1.0 / pow(distance / light.radius + 1.0, 2.0);
+            // fall off from the inner to the outer cone
+            let cos_angle: f32 = dot(normalize(light.direction), -L);
+            let cone: f32 = smoothstep(light.outer_cone_cos, light.inner_cone_cos, cos_angle);
+            radiance = lightColor * attenuation * cone;
+        }
 
         // Cook-Torrance BRDF
         let NDF: f32 = DistributionGGX(N, H, roughness);
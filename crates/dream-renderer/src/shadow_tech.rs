@@ -573,8 +573,11 @@ impl ShadowTech {
         camera_bones_lights_bind_group: &CameraLightBindGroup,
         camera: &Camera,
     ) {
+        // layers of the meshes that cast shadows into the cascades
+        let mut shadow_layer_mask = 0;
         for light in &lights.renderer_lights {
             if light.cast_shadow && light.light_type == LightType::DIRECTIONAL as u32 {
+                shadow_layer_mask |= light.layer_mask;
                 // update shadow cascade uniforms
                 let shadow_cascade_uniform_settings = vec![
                     ShadowCascadeSettingsUniform {
@@ -783,7 +786,7 @@ impl ShadowTech {
             // render_pass_write_shadow_buffer.set_bind_group(3, &skinning_bind_group.bind_group, &[]);
 
             // iterate through all meshes that should be instanced drawn
-            for (render_map_key, transforms) in render_storage.render_map.iter() {
+            for (render_map_key, transforms) in render_storage.meshes_in_layers(shadow_layer_mask) {
                 let model_map = &render_storage.model_guids;
                 // get the mesh to be instance drawn
                 let model_guid = render_map_key.model_guid.clone();
@@ -804,7 +807,7 @@ impl ShadowTech {
                     .get(render_map_key)
                     .expect("No instance buffer found in map");
                 render_pass_write_shadow_buffer.set_vertex_buffer(1, instance_buffer.slice(..));
-                for primitive in &mesh.primitives {
+                for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
                     // get the material and set it in the bind group
                     let material = model
                         .materials
@@ -817,8 +820,11 @@ impl ShadowTech {
                             material.pbr_material_textures_bind_group.as_ref().unwrap(),
                             &[],
                         );
-                        render_pass_write_shadow_buffer
-                            .draw_primitive_instanced(&primitive, 0..transforms.len() as u32);
+                        render_pass_write_shadow_buffer.draw_primitive_instanced(
+                            primitive,
+                            render_storage.get_skinned_vertices(render_map_key, primitive_index),
+                            0..transforms.len() as u32,
+                        );
                     }
                 }
             }
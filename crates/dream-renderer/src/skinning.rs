@@ -1,14 +1,38 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
 use wgpu::util::DeviceExt;
 
 use dream_math::Matrix4;
 
-use crate::render_storage::RenderStorage;
+use crate::model::Primitive;
+use crate::render_storage::{EntityKey, RenderStorage, SkinnedPrimitiveKey};
 use crate::shader::Shader;
 
+/// Bone palette of one armature instance, keyed by its root bone entity
+pub struct ArmaturePalette {
+    pub(crate) joints: Vec<[[f32; 4]; 4]>,
+    pub(crate) joints_buffer: Option<wgpu::Buffer>,
+    pub(crate) joints_bind_group: Option<wgpu::BindGroup>,
+    /// whether a bone was set since the last frame, armatures that are not drawn are dropped
+    pub(crate) posed: bool,
+}
+
+/// Output of the skinning compute pass for one primitive posed by one armature and deformed by
+/// the morph weights of one entity
+pub struct SkinnedVertices {
+    pub buffer: wgpu::Buffer,
+    pub morph_weights_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
 pub struct SkinningTech {
-    pub(crate) joints: [[[f32; 4]; 4]; 256],
-    pub(crate) skinning_buffer: wgpu::Buffer,
-    pub skinning_bind_group: wgpu::BindGroup,
+    pub(crate) armatures: HashMap<EntityKey, ArmaturePalette>,
+    /// morph weights set this frame, keyed by the mesh entity
+    pub(crate) morph_weights: HashMap<EntityKey, Vec<f32>>,
+    pub skinning_bind_group_layout: wgpu::BindGroupLayout,
+    /// single identity joint for meshes that are only deformed by morph targets
+    pub bind_pose_bind_group: wgpu::BindGroup,
     pub skinning_compute_pipeline_layout: wgpu::PipelineLayout,
     pub vertices_bind_group_layout: wgpu::BindGroupLayout,
     pub primitive_info_bind_group_layout: wgpu::BindGroupLayout,
@@ -18,17 +42,6 @@ pub struct SkinningTech {
 
 impl SkinningTech {
     pub fn new(device: &wgpu::Device) -> Self {
-        let joints = [Matrix4::identity().into(); 256];
-
-        let mut skinning_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Skinning buffer"),
-            contents: bytemuck::cast_slice(&[joints]),
-            usage: wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::COPY_SRC,
-        });
-
         let skinning_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
@@ -44,13 +57,18 @@ impl SkinningTech {
                 label: Some("skinning_bind_group_layout"),
             });
 
-        let skinning_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let bind_pose_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bind pose skinning buffer"),
+            contents: bytemuck::cast_slice(&[<[[f32; 4]; 4]>::from(Matrix4::<f32>::identity())]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let bind_pose_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &skinning_bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
-                resource: skinning_buffer.as_entire_binding(),
+                resource: bind_pose_buffer.as_entire_binding(),
             }],
-            label: Some("skinning_bind_group"),
+            label: Some("bind_pose_skinning_bind_group"),
         });
 
         let primitive_info_bind_group_layout =
@@ -68,34 +86,60 @@ impl SkinningTech {
                 label: Some("primitive_info_bind_group_layout"),
             });
 
+        // vertices and morph target deltas
         let vertices_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    count: None,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        },
                     },
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        },
+                    },
+                ],
             });
 
+        // skinned vertices and morph weights
         let skinned_vertices_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    count: None,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        },
                     },
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        },
+                    },
+                ],
             });
 
         let skinning_compute_pipeline_layout =
@@ -127,9 +171,10 @@ impl SkinningTech {
             });
 
         Self {
-            joints,
-            skinning_buffer,
-            skinning_bind_group,
+            armatures: HashMap::new(),
+            morph_weights: HashMap::new(),
+            skinning_bind_group_layout,
+            bind_pose_bind_group,
             skinning_compute_pipeline_layout,
             skinned_vertices_bind_group_layout,
             vertices_bind_group_layout,
@@ -140,81 +185,210 @@ impl SkinningTech {
 }
 
 impl SkinningTech {
-    pub fn update_bone(&mut self, idx: u32, mat: Matrix4<f32>) {
-        if (idx as usize) < self.joints.len() {
-            self.joints[idx as usize] = mat.into();
-        } else {
-            log::warn!("Skipping bone since its index is out of bounds");
+    /// Set the matrix of a bone in the palette of an armature, the palette grows to fit the bone
+    pub fn update_bone(&mut self, armature: EntityKey, idx: u32, mat: Matrix4<f32>) {
+        let palette = self
+            .armatures
+            .entry(armature)
+            .or_insert_with(|| ArmaturePalette {
+                joints: Vec::new(),
+                joints_buffer: None,
+                joints_bind_group: None,
+                posed: false,
+            });
+        let idx = idx as usize;
+        if idx >= palette.joints.len() {
+            palette
+                .joints
+                .resize(idx + 1, Matrix4::<f32>::identity().into());
         }
+        palette.joints[idx] = mat.into();
+        palette.posed = true;
     }
 
-    pub fn update_all_bones_buffer(&mut self, queue: &wgpu::Queue) {
-        queue.write_buffer(
-            &self.skinning_buffer,
-            0,
-            bytemuck::cast_slice(&[self.joints]),
-        );
+    /// Set the morph weights of a mesh entity for this frame
+    pub fn update_morph_weights(&mut self, entity: EntityKey, weights: &[f32]) {
+        self.morph_weights.insert(entity, weights.to_vec());
+    }
+
+    pub fn update_all_bones_buffer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // armatures that were not drawn this frame were deleted or hidden
+        self.armatures.retain(|_, palette| palette.posed);
+        for (armature, palette) in self.armatures.iter_mut() {
+            let joints_size = std::mem::size_of_val(palette.joints.as_slice()) as u64;
+            match &palette.joints_buffer {
+                Some(joints_buffer) if joints_buffer.size() >= joints_size => {
+                    queue.write_buffer(joints_buffer, 0, bytemuck::cast_slice(&palette.joints));
+                }
+                _ => {
+                    // (re)create the buffer when the armature is new or has more bones than before
+                    let joints_buffer =
+                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            label: Some(&format!(
+                                "Skinning buffer {}:{}",
+                                armature.scene, armature.runtime_id
+                            )),
+                            contents: bytemuck::cast_slice(&palette.joints),
+                            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                        });
+                    palette.joints_bind_group =
+                        Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            layout: &self.skinning_bind_group_layout,
+                            entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: joints_buffer.as_entire_binding(),
+                            }],
+                            label: Some("skinning_bind_group"),
+                        }));
+                    palette.joints_buffer = Some(joints_buffer);
+                }
+            }
+            palette.posed = false;
+        }
     }
 
     pub fn compute_shader_update_vertices(
         &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
         render_storage: &mut RenderStorage,
     ) {
-        // iterate through all meshes that should be instanced drawn
-        for (render_map_key, transforms) in render_storage.render_map.iter() {
-            let model_map = &mut render_storage.model_guids;
-            // get the mesh to be instance drawn
+        let RenderStorage {
+            model_guids,
+            render_map,
+            skinned_vertices_map,
+            ..
+        } = render_storage;
+        // drop the outputs of meshes, armatures and morph weights that are no longer drawn
+        skinned_vertices_map.retain(|skinned_primitive_key, _| {
+            render_map.contains_key(&skinned_primitive_key.render_map_key)
+        });
+        // iterate through all meshes that are posed by an armature or deformed by morph targets
+        for render_map_key in render_map.keys() {
+            let armature_bind_group = render_map_key
+                .armature
+                .and_then(|armature| self.armatures.get(&armature))
+                .and_then(|palette| palette.joints_bind_group.as_ref());
+            let morph_weights = render_map_key
+                .morph_weights
+                .and_then(|entity| self.morph_weights.get(&entity));
+            if armature_bind_group.is_none() && morph_weights.is_none() {
+                continue;
+            }
             let model_guid = render_map_key.model_guid.clone();
-            if model_map.get(&*model_guid).is_none() {
+            let Some(model) = model_guids.get(&*model_guid) else {
                 log::warn!("skipping drawing of model {model_guid}");
                 continue;
-            }
-            let model = model_map
-                .get_mut(&*model_guid)
-                .unwrap_or_else(|| panic!("no model loaded in renderer with guid {model_guid}"));
+            };
             let mesh_index = render_map_key.mesh_index;
-            let mesh = model
-                .meshes
-                .get_mut(mesh_index as usize)
-                .unwrap_or_else(|| {
-                    panic!("no mesh at index {mesh_index} for model with guid {model_guid}")
-                });
-            for primitive in &mut mesh.primitives {
+            let mesh = model.meshes.get(mesh_index as usize).unwrap_or_else(|| {
+                panic!("no mesh at index {mesh_index} for model with guid {model_guid}")
+            });
+            for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
                 // get the material and set it in the bind group
                 let material = model
                     .materials
                     .get(primitive.material)
                     .expect("No material at index");
-                // render all types of objects
-                if material.pbr_material_textures_bind_group.is_some()
-                    && primitive.skinned_vertex_buffer.is_some()
-                {
-                    let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                        label: Some("compute skinning pass"),
-                    });
-                    cpass.set_bind_group(0, &primitive.primitive_info_bind_group, &[]);
-                    cpass.set_bind_group(1, &self.skinning_bind_group, &[]);
-                    cpass.set_bind_group(2, &primitive.vertex_buffer_bind_group, &[]);
-                    cpass.set_bind_group(3, &primitive.skinned_vertices_buffer_bind_group, &[]);
-                    cpass.set_pipeline(&self.skinning_compute_pipeline);
-                    cpass.dispatch_workgroups(primitive.buffer_length / 64, 1, 1);
+                if material.pbr_material_textures_bind_group.is_none() {
+                    continue;
+                }
+                // skinned primitives without an armature are drawn in their bind pose
+                let joints_bind_group = match (primitive.is_skinned, armature_bind_group) {
+                    (true, Some(armature_bind_group)) => armature_bind_group,
+                    (true, None) => continue,
+                    (false, _) => &self.bind_pose_bind_group,
+                };
+                let morph_weights = morph_weights.filter(|_| primitive.num_morph_targets > 0);
+                if !primitive.is_skinned && morph_weights.is_none() {
+                    continue;
                 }
+                let skinned_primitive_key = SkinnedPrimitiveKey {
+                    render_map_key: render_map_key.clone(),
+                    primitive_index,
+                };
+                let skinned_vertices = match skinned_vertices_map.entry(skinned_primitive_key) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(self.create_skinned_vertices(device, encoder, primitive))
+                    }
+                };
+                if let Some(morph_weights) = morph_weights {
+                    // missing weights are 0 and extra weights are ignored
+                    let mut weights = morph_weights.clone();
+                    weights.resize(primitive.num_morph_targets as usize, 0.0);
+                    queue.write_buffer(
+                        &skinned_vertices.morph_weights_buffer,
+                        0,
+                        bytemuck::cast_slice(&weights),
+                    );
+                }
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("compute skinning pass"),
+                });
+                cpass.set_bind_group(0, &primitive.primitive_info_bind_group, &[]);
+                cpass.set_bind_group(1, joints_bind_group, &[]);
+                cpass.set_bind_group(2, &primitive.vertex_buffer_bind_group, &[]);
+                cpass.set_bind_group(3, &skinned_vertices.bind_group, &[]);
+                cpass.set_pipeline(&self.skinning_compute_pipeline);
+                cpass.dispatch_workgroups(primitive.buffer_length / 64, 1, 1);
             }
         }
+        // morph weights are set again every frame
+        self.morph_weights.clear();
     }
-}
 
-// #[repr(C)]
-// #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-// pub struct SkinningUniform {
-//     bone_transforms: [[[f32; 4]; 4]; 256],
-// }
-//
-// impl Default for SkinningUniform {
-//     fn default() -> Self {
-//         Self {
-//             bone_transforms: [Matrix4::identity().into(); 256],
-//         }
-//     }
-// }
+    /// Output buffer for a primitive posed by an armature, starts as a copy of the bind pose since
+    /// the compute shader only writes positions, normals and tangents
+    fn create_skinned_vertices(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        primitive: &Primitive,
+    ) -> SkinnedVertices {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Skinned Vertices"),
+            size: primitive.vertex_buffer.size(),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(
+            &primitive.vertex_buffer,
+            0,
+            &buffer,
+            0,
+            primitive.vertex_buffer.size(),
+        );
+        // one weight per morph target, all 0 until weights are set
+        let morph_weights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Morph Weights"),
+            contents: bytemuck::cast_slice(&vec![
+                0.0f32;
+                primitive.num_morph_targets.max(1) as usize
+            ]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skinning vertices buffer bind group"),
+            layout: &self.skinned_vertices_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: morph_weights_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        SkinnedVertices {
+            buffer,
+            morph_weights_buffer,
+            bind_group,
+        }
+    }
+}
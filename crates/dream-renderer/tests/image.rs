@@ -0,0 +1,39 @@
+use dream_renderer::image::Image;
+use dream_renderer::model_load_error::ModelLoadError;
+
+// a textured model whose only image is embedded as a data uri
+const MODEL_WITH_EMBEDDED_IMAGE: &str = r#"{
+    "asset": { "version": "2.0" },
+    "images": [{ "uri": "data:image/png;base64,IMAGE_BYTES" }],
+    "textures": [{ "source": 0, "name": "albedo" }]
+}"#;
+
+fn model_with_embedded_image(image_bytes: &str) -> gltf::Gltf {
+    let json = MODEL_WITH_EMBEDDED_IMAGE.replace("IMAGE_BYTES", image_bytes);
+    gltf::Gltf::from_slice(json.as_bytes()).unwrap()
+}
+
+#[test]
+fn corrupt_embedded_images_fail_the_model_load() {
+    // valid base64, but not a png
+    let model = model_with_embedded_image("bm90IGEgcG5nIGF0IGFsbA==");
+    let texture = model.textures().next().unwrap();
+    let mut image = Image::default();
+    let result = image.load_from_gltf_texture(texture, &[], &std::env::temp_dir());
+    assert!(matches!(result, Err(ModelLoadError::InvalidData(_))));
+    assert!(!image.loaded());
+}
+
+#[test]
+fn embedded_images_are_decoded_while_loading() {
+    // 1x1 rgba png
+    let model = model_with_embedded_image(
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==",
+    );
+    let texture = model.textures().next().unwrap();
+    let mut image = Image::default();
+    image
+        .load_from_gltf_texture(texture, &[], &std::env::temp_dir())
+        .unwrap();
+    assert_eq!(image.dimensions(), Some((1, 1)));
+}
@@ -0,0 +1,82 @@
+use dream_math::Matrix4;
+use dream_renderer::instance::Instance;
+use dream_renderer::render_storage::{EntityKey, RenderStorage, ALL_RENDER_LAYERS};
+
+fn instance() -> Instance {
+    Instance {
+        mat: Matrix4::identity(),
+    }
+}
+
+#[test]
+fn armatures_of_different_scenes_are_posed_separately() {
+    let mut render_storage = RenderStorage::default();
+    // both scenes gave the root bone of their armature the same runtime id
+    let first_armature = EntityKey {
+        scene: 1,
+        runtime_id: 7,
+    };
+    let second_armature = EntityKey {
+        scene: 2,
+        runtime_id: 7,
+    };
+    render_storage.queue_for_drawing("knight", 0, 1, Some(first_armature), None, instance());
+    render_storage.queue_for_drawing("knight", 0, 1, Some(second_armature), None, instance());
+    render_storage.queue_for_drawing("knight", 0, 1, Some(first_armature), None, instance());
+
+    assert_eq!(render_storage.render_map.len(), 2);
+    let mut armatures: Vec<_> = render_storage
+        .render_map
+        .iter()
+        .map(|(render_map_key, instances)| (render_map_key.armature.unwrap(), instances.len()))
+        .collect();
+    armatures.sort_by_key(|(armature, _)| armature.scene);
+    assert_eq!(armatures, vec![(first_armature, 2), (second_armature, 1)]);
+}
+
+#[test]
+fn morph_weights_of_different_scenes_are_applied_separately() {
+    let mut render_storage = RenderStorage::default();
+    // the morphed mesh entities of both scenes have the same runtime id
+    let first_mesh = EntityKey {
+        scene: 1,
+        runtime_id: 3,
+    };
+    let second_mesh = EntityKey {
+        scene: 2,
+        runtime_id: 3,
+    };
+    render_storage.queue_for_drawing("face", 0, 1, None, Some(first_mesh), instance());
+    render_storage.queue_for_drawing("face", 0, 1, None, Some(second_mesh), instance());
+
+    let mut morphed_meshes: Vec<_> = render_storage
+        .render_map
+        .keys()
+        .map(|render_map_key| render_map_key.morph_weights.unwrap())
+        .collect();
+    morphed_meshes.sort_by_key(|mesh| mesh.scene);
+    assert_eq!(morphed_meshes, vec![first_mesh, second_mesh]);
+}
+
+#[test]
+fn only_meshes_on_the_layers_of_a_mask_are_drawn() {
+    let mut render_storage = RenderStorage::default();
+    render_storage.queue_for_drawing("ground", 0, 0b001, None, None, instance());
+    render_storage.queue_for_drawing("minimap_icon", 0, 0b010, None, None, instance());
+    render_storage.queue_for_drawing("player", 0, 0b101, None, None, instance());
+    let drawn = |layer_mask: u32| {
+        let mut models: Vec<String> = render_storage
+            .meshes_in_layers(layer_mask)
+            .map(|(render_map_key, _)| render_map_key.model_guid.clone())
+            .collect();
+        models.sort();
+        models
+    };
+
+    assert_eq!(drawn(0b001), vec!["ground", "player"]);
+    assert_eq!(drawn(0b010), vec!["minimap_icon"]);
+    // a light casting shadows on the third layer only
+    assert_eq!(drawn(0b100), vec!["player"]);
+    assert_eq!(drawn(ALL_RENDER_LAYERS).len(), 3);
+    assert!(drawn(0b1000).is_empty());
+}
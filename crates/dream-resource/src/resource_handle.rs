@@ -1,13 +1,48 @@
 use std::path::PathBuf;
+use std::sync::Mutex;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// File of a resource, shared by the resource manager and the components using it
+#[derive(Debug)]
 pub struct ResourceHandle {
     pub key: String,
-    pub path: PathBuf,
+    /// Entities keep weak handles to the resource, so a moved file updates the path in place.
+    /// It used to be a plain pub field, read it with get_path instead.
+    path: Mutex<PathBuf>,
 }
 
 impl ResourceHandle {
     pub fn new(key: String, path: PathBuf) -> Self {
-        Self { key, path }
+        Self {
+            key,
+            path: Mutex::new(path),
+        }
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.path
+            .lock()
+            .expect("Unable to acquire resource handle path mutex")
+            .clone()
+    }
+
+    pub fn set_path(&self, path: PathBuf) {
+        *self
+            .path
+            .lock()
+            .expect("Unable to acquire resource handle path mutex") = path;
     }
 }
+
+impl Clone for ResourceHandle {
+    fn clone(&self) -> Self {
+        Self::new(self.key.clone(), self.get_path())
+    }
+}
+
+impl PartialEq for ResourceHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.get_path() == other.get_path()
+    }
+}
+
+impl Eq for ResourceHandle {}
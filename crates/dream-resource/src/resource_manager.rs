@@ -1,6 +1,6 @@
 use std::collections::{HashMap, VecDeque};
 use std::ops::Add;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Weak};
 
 use serde::{Deserialize, Serialize};
@@ -33,7 +33,8 @@ pub fn create_meta_file(file_path: PathBuf) {
     let meta_file_path = format!("{}{}", file_path.to_str().unwrap(), ".meta");
     let meta_file_path = PathBuf::from(meta_file_path);
     log::warn!("meta file path {}", meta_file_path.to_str().unwrap());
-    dream_fs::fs::write_binary(meta_file_path, res.into_bytes().to_vec());
+    dream_fs::fs::write_binary(meta_file_path, res.into_bytes().to_vec())
+        .expect("Unable to write meta file");
 }
 
 fn get_meta_data(file_path: PathBuf) -> MetaData {
@@ -103,4 +104,32 @@ impl ResourceManager {
     pub fn get_resource(&self, key: String) -> Option<Weak<ResourceHandle>> {
         self.guid_to_filepath.get(key.as_str()).map(Arc::downgrade)
     }
+
+    /// Guid of the resource stored at the given path
+    pub fn get_guid_for_path(&self, file_path: &Path) -> Option<String> {
+        self.guid_to_filepath
+            .values()
+            .find(|resource_handle| resource_handle.get_path().as_path() == file_path)
+            .map(|resource_handle| resource_handle.key.clone())
+    }
+
+    /// Track a file created after start up, creating its meta file if needed, and return its guid
+    pub fn register_resource(&mut self, file_path: PathBuf) -> String {
+        let meta_file_path = PathBuf::from(String::from(file_path.to_str().unwrap()).add(".meta"));
+        if !dream_fs::fs::exists(meta_file_path) {
+            create_meta_file(file_path.clone());
+        }
+        let guid = get_meta_data(file_path.clone()).guid;
+        match self.guid_to_filepath.get(&guid) {
+            // entities hold weak handles to the existing entry, replacing it would orphan them
+            Some(resource_handle) => resource_handle.set_path(file_path),
+            None => {
+                self.guid_to_filepath.insert(
+                    guid.clone(),
+                    Arc::new(ResourceHandle::new(guid.clone(), file_path)),
+                );
+            }
+        }
+        guid
+    }
 }